@@ -0,0 +1,175 @@
+// Milestones that unlock once and stay unlocked between sessions, stored as one `id date` line each for the
+// ones unlocked so far
+
+use std::path::{Path, PathBuf};
+
+use crate::files::write_atomically;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Achievement {
+    FirstBite,
+    Hungry,
+    Glutton,
+    Grown,
+    Giant,
+    Century,
+    Combo,
+    Survivor,
+    Splat,
+}
+
+impl Achievement {
+    pub const ALL: [Achievement; 9] = [
+        Achievement::FirstBite,
+        Achievement::Hungry,
+        Achievement::Glutton,
+        Achievement::Grown,
+        Achievement::Giant,
+        Achievement::Century,
+        Achievement::Combo,
+        Achievement::Survivor,
+        Achievement::Splat,
+    ];
+
+    // Name in the achievements file; never change one, or files that have it unlocked lose it
+    pub fn id(self) -> &'static str {
+        match self {
+            Achievement::FirstBite => "first_bite",
+            Achievement::Hungry => "hungry",
+            Achievement::Glutton => "glutton",
+            Achievement::Grown => "grown",
+            Achievement::Giant => "giant",
+            Achievement::Century => "century",
+            Achievement::Combo => "combo",
+            Achievement::Survivor => "survivor",
+            Achievement::Splat => "splat",
+        }
+    }
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Achievement::FirstBite => "First Bite",
+            Achievement::Hungry => "Hungry",
+            Achievement::Glutton => "Glutton",
+            Achievement::Grown => "Growing Up",
+            Achievement::Giant => "Giant",
+            Achievement::Century => "Century",
+            Achievement::Combo => "On Fire",
+            Achievement::Survivor => "Survivor",
+            Achievement::Splat => "Splat",
+        }
+    }
+
+    // What it takes, short enough for the terminal board
+    pub fn description(self) -> &'static str {
+        match self {
+            Achievement::FirstBite => "Eat your first food",
+            Achievement::Hungry => "Eat 10 food in one run",
+            Achievement::Glutton => "Eat 50 food in one run",
+            Achievement::Grown => "Reach length 25",
+            Achievement::Giant => "Reach length 50",
+            Achievement::Century => "Score 100 in one run",
+            Achievement::Combo => "Build a combo up to x5",
+            Achievement::Survivor => "Stay alive for 2 minutes",
+            Achievement::Splat => "Crash within 3 seconds",
+        }
+    }
+}
+
+// The achievements unlocked so far, each with the day it was unlocked, in the order they were
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct Achievements {
+    pub unlocked: Vec<(Achievement, String)>,
+}
+
+impl Achievements {
+    // Where interactive play keeps its achievements, or None when the platform has no data directory
+    pub fn path() -> Option<PathBuf> {
+        Some(dirs::data_dir()?.join("copperhead").join("achievements"))
+    }
+
+    // Read achievements from `path`. A missing file is a first run with nothing unlocked; a file that can't
+    // be read or has a malformed line is an error, which the caller can report before carrying on with none.
+    // Unknown ids are skipped, so files written by newer versions still load.
+    pub fn load(path: &Path) -> Result<Achievements, String> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Achievements::default()),
+            Err(err) => return Err(err.to_string()),
+        };
+        let mut achievements = Achievements::default();
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            let (id, date) = line.split_once(' ').ok_or_else(|| format!("malformed line {:?}", line))?;
+            if let Some(achievement) = Achievement::ALL.into_iter().find(|achievement| achievement.id() == id) {
+                achievements.unlock(achievement, date.trim());
+            }
+        }
+        Ok(achievements)
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        let text: String = self.unlocked.iter().map(|(achievement, date)| format!("{} {}\n", achievement.id(), date)).collect();
+        write_atomically(path, &text)
+    }
+
+    pub fn has(&self, achievement: Achievement) -> bool {
+        self.unlocked.iter().any(|&(unlocked, _)| unlocked == achievement)
+    }
+
+    // Mark `achievement` unlocked on `date`; returns false if it already was
+    pub fn unlock(&mut self, achievement: Achievement, date: &str) -> bool {
+        if self.has(achievement) {
+            return false;
+        }
+        self.unlocked.push((achievement, date.to_string()));
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("copperhead-achievements-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn ids_are_unique() {
+        for (i, achievement) in Achievement::ALL.iter().enumerate() {
+            assert!(Achievement::ALL[i + 1..].iter().all(|other| other.id() != achievement.id()));
+        }
+    }
+
+    #[test]
+    fn an_achievement_unlocks_once() {
+        let mut achievements = Achievements::default();
+        assert!(achievements.unlock(Achievement::Hungry, "2026-01-01"));
+        assert!(!achievements.unlock(Achievement::Hungry, "2026-01-02"));
+        assert_eq!(achievements.unlocked, [(Achievement::Hungry, "2026-01-01".to_string())]);
+    }
+
+    #[test]
+    fn saved_achievements_load_back() {
+        let path = temp_path("round-trip");
+        let mut achievements = Achievements::default();
+        achievements.unlock(Achievement::Splat, "2026-01-01");
+        achievements.unlock(Achievement::Giant, "2026-02-03");
+        achievements.save(&path).unwrap();
+        assert_eq!(Achievements::load(&path), Ok(achievements));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn a_missing_file_has_nothing_unlocked_and_unknown_ids_are_skipped() {
+        assert_eq!(Achievements::load(&temp_path("missing")), Ok(Achievements::default()));
+        let path = temp_path("unknown");
+        std::fs::write(&path, "speedrun 2026-01-01\ncentury 2026-01-02\n").unwrap();
+        let loaded = Achievements::load(&path);
+        std::fs::write(&path, "century\n").unwrap();
+        let malformed = Achievements::load(&path);
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(loaded.map(|loaded| loaded.unlocked), Ok(vec![(Achievement::Century, "2026-01-02".to_string())]));
+        assert!(malformed.is_err());
+    }
+}
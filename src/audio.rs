@@ -0,0 +1,214 @@
+// Background music: a loop while the snake is moving and a slower one after it dies, nothing on the start
+// screen. The tunes are synthesized as they play, so there are no audio files to ship. Without a sound
+// device everything quietly stays silent.
+
+use rodio::{OutputStream, Sink, Source};
+use std::time::Duration;
+use crate::game::{Game, GameState};
+
+const SAMPLE_RATE: u32 = 44_100;
+const FADE: f64 = 0.005; // Seconds each note takes to fade in and out, which keeps it from clicking
+const MELODY_LEVEL: f64 = 0.12;
+const BASS_LEVEL: f64 = 0.2;
+
+// A tune as MIDI note numbers, 0 being a rest. Each melody note lasts one step, each bass note four.
+struct Tune {
+    melody: &'static [u8],
+    bass: &'static [u8],
+    step: f64, // Seconds
+}
+
+// Brisk A minor run that loops every 4 seconds
+const RUNNING: Tune = Tune {
+    melody: &[
+        69, 0, 72, 76, 74, 72, 71, 0, 67, 0, 71, 74, 72, 71, 69, 0,
+        65, 0, 69, 72, 71, 69, 67, 0, 64, 67, 71, 74, 72, 0, 71, 0,
+    ],
+    bass: &[45, 45, 43, 43, 41, 41, 40, 40],
+    step: 0.125,
+};
+
+// Slow walk down to the tonic
+const GAME_OVER: Tune = Tune {
+    melody: &[76, 0, 74, 0, 72, 0, 71, 0, 69, 0, 0, 0, 68, 0, 69, 0],
+    bass: &[45, 41, 40, 45],
+    step: 0.3,
+};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Track {
+    Running,
+    GameOver,
+}
+
+impl Track {
+    fn tune(self) -> &'static Tune {
+        match self {
+            Track::Running => &RUNNING,
+            Track::GameOver => &GAME_OVER,
+        }
+    }
+}
+
+// Endless mono samples of a tune: a square wave melody over a triangle wave bass
+struct Synth {
+    tune: &'static Tune,
+    sample: u64, // Position within the loop
+}
+
+impl Synth {
+    fn new(tune: &'static Tune) -> Self {
+        Synth { tune, sample: 0 }
+    }
+}
+
+impl Iterator for Synth {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let tune = self.tune;
+        let step_samples = (tune.step * SAMPLE_RATE as f64) as u64;
+        let step = (self.sample / step_samples) as usize;
+        let since_step = (self.sample % step_samples) as f64 / SAMPLE_RATE as f64;
+        let since_bass = (self.sample % (4 * step_samples)) as f64 / SAMPLE_RATE as f64;
+        let value = MELODY_LEVEL * tone(tune.melody[step], since_step, tune.step, square)
+            + BASS_LEVEL * tone(tune.bass[step / 4 % tune.bass.len()], since_bass, 4.0 * tune.step, triangle);
+        self.sample = (self.sample + 1) % (tune.melody.len() as u64 * step_samples);
+        Some(value as f32)
+    }
+}
+
+impl Source for Synth {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+// One sample of `note`, `since` seconds into a note lasting `length`; plucked, so it dies away as it goes
+fn tone(note: u8, since: f64, length: f64, wave: fn(f64) -> f64) -> f64 {
+    if note == 0 {
+        return 0.0;
+    }
+    let frequency = 440.0 * 2f64.powf((note as f64 - 69.0) / 12.0);
+    let envelope = (since / FADE).min((length - since) / FADE).min(1.0) * (-since * 3.0).exp();
+    wave((frequency * since).fract()) * envelope
+}
+
+fn square(phase: f64) -> f64 {
+    if phase < 0.5 { 1.0 } else { -1.0 }
+}
+
+fn triangle(phase: f64) -> f64 {
+    4.0 * (phase - 0.5).abs() - 1.0
+}
+
+// Plays the track that goes with the game's screen. Lives next to `Game` in the frontend's loop.
+pub struct Music {
+    output: Option<(OutputStream, Sink)>, // The stream has to be kept alive for the sink to play
+    track: Option<Track>,
+    volume: u32, // Percent, as last set on the sink
+    muted: bool,
+}
+
+impl Music {
+    pub fn new(volume: u32, muted: bool) -> Self {
+        let output = OutputStream::try_default().ok().and_then(|(stream, handle)| Some((stream, Sink::try_new(&handle).ok()?)));
+        let music = Music { output, track: None, volume: volume.min(100), muted };
+        music.apply_volume();
+        music
+    }
+
+    // Switch tracks when the game has moved to another screen, hold the music while the game is paused or
+    // the help is open, and play it as loud as the settings say. Meant to be called on every update.
+    pub fn follow(&mut self, game: &Game) {
+        if (self.volume, self.muted) != (game.settings.volume, game.settings.muted) {
+            self.volume = game.settings.volume;
+            self.muted = game.settings.muted;
+            self.apply_volume();
+        }
+        let Some((_, sink)) = &self.output else {
+            return;
+        };
+        let track = match game.state {
+            GameState::Menu | GameState::LevelComplete => None,
+            GameState::Running | GameState::Paused => Some(Track::Running),
+            GameState::GameOver => Some(Track::GameOver),
+        };
+        if track != self.track {
+            sink.clear();
+            if let Some(track) = track {
+                sink.append(Synth::new(track.tune()));
+            }
+            self.track = track;
+        }
+        if track.is_none() || game.state == GameState::Paused || game.help_shown {
+            sink.pause();
+        } else {
+            sink.play();
+        }
+    }
+
+    fn apply_volume(&self) {
+        if let Some((_, sink)) = &self.output {
+            sink.set_volume(if self.muted { 0.0 } else { self.volume as f32 / 100.0 });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Settings;
+
+    // One full loop of a track
+    fn one_loop(tune: &'static Tune) -> Vec<f32> {
+        let step_samples = (tune.step * SAMPLE_RATE as f64) as usize;
+        Synth::new(tune).take(tune.melody.len() * step_samples).collect()
+    }
+
+    #[test]
+    fn the_tunes_stay_in_range() {
+        for tune in [&RUNNING, &GAME_OVER] {
+            let samples = one_loop(tune);
+            assert!(samples.iter().all(|sample| sample.is_finite()));
+            let peak = samples.iter().fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+            assert!(peak > 0.1 && peak <= (MELODY_LEVEL + BASS_LEVEL) as f32, "peak {}", peak);
+        }
+    }
+
+    #[test]
+    fn the_loops_join_without_a_click() {
+        for tune in [&RUNNING, &GAME_OVER] {
+            let samples = one_loop(tune);
+            assert_eq!(samples[0], 0.0);
+            assert!(samples.last().unwrap().abs() < 1e-3);
+        }
+    }
+
+    // Music without a sound device, which keeps track of the volume all the same
+    fn silent(volume: u32) -> Music {
+        Music { output: None, track: None, volume, muted: false }
+    }
+
+    #[test]
+    fn the_volume_follows_the_settings() {
+        let mut music = silent(50);
+        let mut game = Game::new(&Settings::default());
+        game.change_volume(2);
+        game.toggle_mute();
+        music.follow(&game);
+        assert_eq!((music.volume, music.muted), (70, true));
+    }
+}
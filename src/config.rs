@@ -0,0 +1,502 @@
+// Options kept in `config.toml` between sessions: board size, timing, the color theme, the window, key bindings
+// and music.
+// The file is written with the defaults when there is none yet, so there is something to edit; command line
+// options still win over it. Options changed in game are written back to it when the game closes.
+
+use serde::{Deserialize, Deserializer};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use crate::files::write_atomically;
+use crate::game::{Action, BoardAlign, KeyBindings, Settings, SettingsBuilder, DEFAULT_VOLUME};
+use crate::input::key_from_name;
+use crate::online::{Server, MAX_NAME_LEN, valid_name};
+use crate::snake::Direction;
+use crate::theme::{Theme, DEFAULT_THEME, THEME_NAMES};
+
+pub const DEFAULT_UPS: u64 = 100; // Game logic updates per second
+pub const DEFAULT_MAX_FPS: u64 = 120; // Frame cap while something is animating
+
+#[derive(Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub board: BoardConfig,
+    pub timing: TimingConfig,
+    pub colors: ColorConfig,
+    pub window: WindowConfig,
+    pub keys: KeyBindings,
+    pub audio: AudioConfig,
+    pub online: OnlineConfig,
+}
+
+#[derive(Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BoardConfig {
+    pub columns: i32,
+    pub rows: i32,
+    pub cell_size: i32, // Pixels per cell
+    #[serde(deserialize_with = "start_dir")]
+    pub start_dir: Direction, // Heading the snake starts off in
+}
+
+#[derive(Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimingConfig {
+    pub updates_per_second: u64,
+    pub move_interval: f64, // Seconds between snake moves
+}
+
+// A built-in theme, with any of its colors changed by name. Unknown names can't be turned away while the file
+// is parsed, since they all land in `overrides`, so `Config::load` checks them through `theme`.
+#[derive(Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct ColorConfig {
+    pub theme: String, // One of THEME_NAMES
+    #[serde(flatten)]
+    pub overrides: BTreeMap<String, String>, // Hex colors by their names in `Theme::names`
+}
+
+#[derive(Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WindowConfig {
+    #[serde(deserialize_with = "board_align")]
+    pub board_align: BoardAlign,
+    pub max_fps: u64,
+    pub smooth_movement: bool,
+    pub fullscreen: bool, // Start in fullscreen
+    pub position: Option<[i32; 2]>, // Outer top left corner on the desktop when the game last closed, if it ran in a window
+    pub size: Option<[u32; 2]>, // Inner size when the game last closed; never made smaller than the board
+}
+
+#[derive(Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AudioConfig {
+    pub volume: u32, // Music volume in percent
+    pub muted: bool,
+}
+
+#[derive(Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OnlineConfig {
+    pub endpoint: String, // Address of a global leaderboard, or empty for none
+    pub name: String, // Player name the scores go in under
+}
+
+// The defaults are the game's own, so an untouched file plays exactly like no file at all
+impl Default for Config {
+    fn default() -> Self {
+        let settings = Settings::default();
+        Config {
+            board: BoardConfig { columns: settings.grid.0, rows: settings.grid.1, cell_size: settings.cell_size, start_dir: settings.start_dir },
+            timing: TimingConfig { updates_per_second: DEFAULT_UPS, move_interval: settings.move_interval },
+            colors: ColorConfig { theme: DEFAULT_THEME.to_string(), overrides: BTreeMap::new() },
+            window: WindowConfig {
+                board_align: settings.board_align,
+                max_fps: DEFAULT_MAX_FPS,
+                smooth_movement: settings.smooth_movement,
+                fullscreen: settings.fullscreen,
+                position: None,
+                size: None,
+            },
+            keys: settings.key_bindings,
+            audio: AudioConfig { volume: DEFAULT_VOLUME, muted: false },
+            online: OnlineConfig { endpoint: String::new(), name: settings.player_name },
+        }
+    }
+}
+
+impl Default for BoardConfig {
+    fn default() -> Self {
+        Config::default().board
+    }
+}
+
+impl Default for TimingConfig {
+    fn default() -> Self {
+        Config::default().timing
+    }
+}
+
+impl Default for ColorConfig {
+    fn default() -> Self {
+        Config::default().colors
+    }
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Config::default().window
+    }
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        Config::default().audio
+    }
+}
+
+impl Default for OnlineConfig {
+    fn default() -> Self {
+        Config::default().online
+    }
+}
+
+impl ColorConfig {
+    // The theme with the changed colors in it, or what is wrong with them
+    pub fn theme(&self) -> Result<Theme, String> {
+        let mut theme: Theme = self.theme.parse().map_err(|_| format!("colors.theme must be one of {}", THEME_NAMES.join(", ")))?;
+        for (name, hex) in &self.overrides {
+            let color = parse_hex_color(hex).ok_or_else(|| format!("colors.{} needs a hex color like \"1a1a1a\", got {:?}", name, hex))?;
+            if !theme.set(name, color) {
+                return Err(format!("unknown color colors.{}", name));
+            }
+        }
+        Ok(theme)
+    }
+}
+
+impl OnlineConfig {
+    // The global leaderboard, if an address is set; `Config::load` has made sure it parses
+    pub fn server(&self) -> Option<Server> {
+        self.endpoint.parse().ok()
+    }
+}
+
+impl Config {
+    // Where the config lives, or None when the platform has no config directory
+    pub fn path() -> Option<PathBuf> {
+        Some(dirs::config_dir()?.join("copperhead").join("config.toml"))
+    }
+
+    // Read the config from `path`. A missing file gives the defaults. Anything left out of the file keeps
+    // its default, but a file that can't be read, doesn't parse or has an unknown key is an error, so typos
+    // don't go unnoticed. Board size and move interval are checked along with the other settings.
+    pub fn load(path: &Path) -> Result<Config, String> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Config::default()),
+            Err(err) => return Err(err.to_string()),
+        };
+        let config: Config = toml::from_str(&text).map_err(|err| err.to_string())?;
+        if config.timing.updates_per_second == 0 {
+            return Err("timing.updates_per_second must be at least 1".to_string());
+        }
+        config.colors.theme()?;
+        if config.window.max_fps == 0 {
+            return Err("window.max_fps must be at least 1".to_string());
+        }
+        if config.audio.volume > 100 {
+            return Err("audio.volume must be between 0 and 100".to_string());
+        }
+        if !config.online.endpoint.is_empty() {
+            config.online.endpoint.parse::<Server>().map_err(|err| format!("online.endpoint: {}", err))?;
+        }
+        if !valid_name(&config.online.name) {
+            return Err(format!("online.name must be 1 to {} letters, digits, - or _", MAX_NAME_LEN));
+        }
+        for action in Action::ALL {
+            let names = config.keys.keys(action);
+            if names.is_empty() {
+                return Err(format!("keys.{} needs at least one key", format!("{:?}", action).to_lowercase()));
+            }
+            if let Some(name) = names.iter().find(|name| key_from_name(name).is_none()) {
+                return Err(format!("unknown key {:?}; use up, down, left, right, space, enter, tab, or a letter or digit", name));
+            }
+        }
+        Ok(config)
+    }
+
+    // Write the config as a commented TOML file, creating its directory if needed
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        write_atomically(path, &self.to_toml())
+    }
+
+    // Settings as configured here, for the command line to override
+    pub fn builder(&self) -> SettingsBuilder {
+        Settings::builder()
+            .grid(self.board.columns, self.board.rows)
+            .cell_size(self.board.cell_size)
+            .start_dir(self.board.start_dir.clone())
+            .move_interval(self.timing.move_interval)
+            .theme(self.colors.theme().unwrap_or_default())
+            .board_align(self.window.board_align)
+            .fullscreen(self.window.fullscreen)
+            .smooth_movement(self.window.smooth_movement)
+            .key_bindings(self.keys.clone())
+            .volume(self.audio.volume, self.audio.muted)
+            .player_name(self.online.name.clone())
+    }
+
+    // This config with the options that were changed in game, from `launched` to `settings`: on the options
+    // page or with the music keys. Only those are taken over, so options given on the command line don't end
+    // up in the file.
+    pub fn remember(&self, launched: &Settings, settings: &Settings) -> Config {
+        let mut config = self.clone();
+        if settings.grid != launched.grid {
+            (config.board.columns, config.board.rows) = settings.grid;
+        }
+        if settings.move_interval != launched.move_interval {
+            config.timing.move_interval = settings.move_interval;
+        }
+        // The colors the config changed are kept, on top of the new theme
+        if settings.theme.name != launched.theme.name {
+            config.colors.theme = settings.theme.name.to_string();
+        }
+        if settings.key_bindings != launched.key_bindings {
+            config.keys = settings.key_bindings.clone();
+        }
+        if (settings.volume, settings.muted) != (launched.volume, launched.muted) {
+            config.audio = AudioConfig { volume: settings.volume, muted: settings.muted };
+        }
+        config
+    }
+
+    fn to_toml(&self) -> String {
+        format!(
+            "# Copperhead settings. Delete this file to get the defaults back; command line options override it.\n\
+             # The options page in game changes the speed, board size, theme, keys and music here as well.\n\
+             \n\
+             [board]\n\
+             columns = {} # 10 to 60\n\
+             rows = {} # 10 to 60\n\
+             cell_size = {} # Pixels per cell, 16 to 64\n\
+             start_dir = \"{}\" # Heading the snake starts off in: up, down, left or right\n\
+             \n\
+             [timing]\n\
+             updates_per_second = {} # How often the game checks for moves\n\
+             move_interval = {:?} # Seconds between snake moves\n\
+             \n\
+             [colors]\n\
+             theme = {:?} # {}\n\
+             # Any color of the theme can be changed by name, as a hex color like board = \"1a1a1a\". The names are:\n\
+             {}{}\
+             \n\
+             [window]\n\
+             board_align = \"{}\" # top-left, top, top-right, left, center, right, bottom-left, bottom or bottom-right\n\
+             max_fps = {} # Frame cap while something is animating\n\
+             smooth_movement = {} # Slide the snake between cells; false to step a cell at a time and redraw less\n\
+             fullscreen = {} # Start in fullscreen; F11 switches in game\n\
+             {}\n\
+             {}\n\
+             \n\
+             [keys] # up, down, left, right, space, enter, tab, or a letter or digit\n\
+             up = {}\n\
+             down = {}\n\
+             left = {}\n\
+             right = {}\n\
+             pause = {}\n\
+             restart = {} # Start, resume and restart\n\
+             \n\
+             [audio] # Changed in game with M, - and +, or on the options page\n\
+             volume = {} # Music volume, 0 to 100\n\
+             muted = {}\n\
+             \n\
+             [online] # A global leaderboard that finished runs are sent to\n\
+             endpoint = {:?} # Like \"http://scores.example.com/copperhead\", or empty for none\n\
+             name = {:?} # Up to {} letters, digits, - or _\n",
+            self.board.columns,
+            self.board.rows,
+            self.board.cell_size,
+            self.board.start_dir,
+            self.timing.updates_per_second,
+            self.timing.move_interval,
+            self.colors.theme,
+            THEME_NAMES.join(", "),
+            color_names(),
+            self.colors.overrides.iter().map(|(name, hex)| format!("{} = {:?}\n", name, hex)).collect::<String>(),
+            self.window.board_align,
+            self.window.max_fps,
+            self.window.smooth_movement,
+            self.window.fullscreen,
+            pair("position", self.window.position, "Where the window was when the game last closed"),
+            pair("size", self.window.size, "Window size when the game last closed; never smaller than the board"),
+            names(&self.keys.up),
+            names(&self.keys.down),
+            names(&self.keys.left),
+            names(&self.keys.right),
+            names(&self.keys.pause),
+            names(&self.keys.restart),
+            self.audio.volume,
+            self.audio.muted,
+            self.online.endpoint,
+            self.online.name,
+            MAX_NAME_LEN,
+        )
+    }
+}
+
+// A `name = [a, b]` line, or the same line commented out while there is no value yet
+fn pair<T: std::fmt::Display>(name: &str, value: Option<[T; 2]>, comment: &str) -> String {
+    match value {
+        Some([a, b]) => format!("{} = [{}, {}] # {}", name, a, b, comment),
+        None => format!("# {} = [0, 0] # {}", name, comment),
+    }
+}
+
+// Parse a color like "1a1a1a" or "#1a1a1a" (fully opaque)
+pub fn parse_hex_color(hex: &str) -> Option<[f32; 4]> {
+    let hex = hex.strip_prefix('#').unwrap_or(hex);
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok().map(|v| v as f32 / 255.0);
+    Some([channel(0)?, channel(2)?, channel(4)?, 1.0])
+}
+
+// The names of the theme's colors as comment lines, a few to a line
+fn color_names() -> String {
+    let names: Vec<&str> = Theme::default().names().into_iter().map(|(name, _)| name).collect();
+    names.chunks(8).map(|line| format!("# {}\n", line.join(", "))).collect()
+}
+
+// A list of key names as a TOML array
+fn names(names: &[String]) -> String {
+    format!("[{}]", names.iter().map(|name| format!("{:?}", name)).collect::<Vec<_>>().join(", "))
+}
+
+fn board_align<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BoardAlign, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    name.parse().map_err(|_| serde::de::Error::custom(format!("expected a placement like \"center\" or \"top-left\", got {:?}", name)))
+}
+
+fn start_dir<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Direction, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    name.parse().map_err(|_| serde::de::Error::custom(format!("expected up, down, left or right, got {:?}", name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<Config, String> {
+        toml::from_str(text).map_err(|err| err.to_string())
+    }
+
+    #[test]
+    fn the_written_defaults_read_back_as_the_defaults() {
+        let text = Config::default().to_toml();
+        let config = parse(&text).unwrap();
+        assert_eq!(config.to_toml(), text);
+        assert!(config == Config::default());
+    }
+
+    #[test]
+    fn keys_left_out_keep_their_defaults() {
+        let config = parse("[board]\ncolumns = 30\nstart_dir = \"up\"\n").unwrap();
+        assert_eq!((config.board.columns, config.board.rows), (30, Config::default().board.rows));
+        assert!(config.board.start_dir == Direction::Up);
+        assert_eq!(config.timing.updates_per_second, DEFAULT_UPS);
+        assert!(config.window.position.is_none());
+    }
+
+    #[test]
+    fn unknown_keys_and_bad_values_are_errors() {
+        assert!(parse("[board]\ncolums = 30\n").is_err());
+        // Colors are only known by name once the theme is made from them
+        assert!(parse("[colors]\nboard = \"green\"\n").unwrap().colors.theme().is_err());
+        assert!(parse("[window]\nboard_align = \"middle\"\n").is_err());
+        assert!(parse("[board]\nstart_dir = \"north\"\n").is_err());
+        assert!(parse("[window]\nfullscreen = \"yes\"\n").is_err());
+    }
+
+    #[test]
+    fn the_window_position_and_size_are_kept() {
+        let path = std::env::temp_dir().join(format!("copperhead-config-{}.toml", std::process::id()));
+        let mut config = Config::default();
+        config.window.position = Some([-1200, 40]);
+        config.window.size = Some([900, 700]);
+        config.save(&path).unwrap();
+        let loaded = Config::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!((loaded.window.position, loaded.window.size), (Some([-1200, 40]), Some([900, 700])));
+    }
+
+    #[test]
+    fn starting_fullscreen_leaves_the_window_position_and_size_alone() {
+        let mut config = parse("[window]\nfullscreen = true\nposition = [10, 20]\nsize = [800, 600]\n").unwrap();
+        assert!(config.builder().build().unwrap().fullscreen);
+        config = parse(&config.to_toml()).unwrap();
+        assert!(config.window.fullscreen);
+        assert_eq!((config.window.position, config.window.size), (Some([10, 20]), Some([800, 600])));
+    }
+
+    #[test]
+    fn the_colors_pick_a_theme_and_change_some_of_it() {
+        let config = parse("[colors]\ntheme = \"deuteranopia\"\nfood = \"#102030\"\n").unwrap();
+        let theme = config.colors.theme().unwrap();
+        assert!(theme.food == parse_hex_color("102030").unwrap());
+        assert!(theme.poison == Theme::DEUTERANOPIA.poison);
+        assert!(config.builder().build().unwrap().theme == theme);
+        assert!(parse(&config.to_toml()).unwrap().colors == config.colors, "the changes are written back");
+    }
+
+    #[test]
+    fn unknown_themes_and_color_names_are_errors() {
+        let path = std::env::temp_dir().join(format!("copperhead-config-colors-{}.toml", std::process::id()));
+        let load = |text: &str| {
+            std::fs::write(&path, text).unwrap();
+            Config::load(&path)
+        };
+        assert!(load("[colors]\ntheme = \"high-contrast\"\n").is_ok());
+        assert!(load("[colors]\ntheme = \"neon\"\n").is_err());
+        assert!(load("[colors]\nfod = \"102030\"\n").is_err());
+        assert!(load("[colors]\nfood = \"1a2b3\"\n").is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn every_action_needs_known_keys() {
+        let path = std::env::temp_dir().join(format!("copperhead-config-keys-{}.toml", std::process::id()));
+        let load = |text: &str| {
+            std::fs::write(&path, text).unwrap();
+            Config::load(&path)
+        };
+        let rebound = load("[keys]\nup = [\"i\", \"up\"]\n").unwrap();
+        assert_eq!(rebound.keys.up, ["i", "up"]);
+        assert_eq!(rebound.keys.down, Config::default().keys.down);
+        assert!(load("[keys]\npause = []\n").is_err());
+        assert!(load("[keys]\nleft = [\"escape\"]\n").is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn the_volume_is_a_percentage() {
+        let path = std::env::temp_dir().join(format!("copperhead-config-audio-{}.toml", std::process::id()));
+        std::fs::write(&path, "[audio]\nvolume = 30\nmuted = true\n").unwrap();
+        let config = Config::load(&path).unwrap();
+        assert!(config.audio == AudioConfig { volume: 30, muted: true });
+        std::fs::write(&path, "[audio]\nvolume = 101\n").unwrap();
+        assert!(Config::load(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn only_what_changed_in_game_is_remembered() {
+        let config = parse("[window]\nposition = [10, 20]\nsize = [800, 600]\n").unwrap();
+        // The board size came from the command line, the speed and music changed in game
+        let launched = Settings { grid: (30, 30), ..config.builder().build().unwrap() };
+        let settings = Settings { move_interval: 0.25, volume: 80, ..launched.clone() };
+        let remembered = config.remember(&launched, &settings);
+        assert_eq!((remembered.board.columns, remembered.board.rows), (config.board.columns, config.board.rows));
+        assert_eq!(remembered.timing.move_interval, 0.25);
+        assert!(remembered.audio == AudioConfig { volume: 80, muted: false });
+        assert_eq!((remembered.window.position, remembered.window.size), (Some([10, 20]), Some([800, 600])));
+        assert!(config.remember(&launched, &launched) == config);
+    }
+
+    #[test]
+    fn the_online_section_needs_an_http_address_and_a_plain_name() {
+        let path = std::env::temp_dir().join(format!("copperhead-config-online-{}.toml", std::process::id()));
+        let load = |text: &str| {
+            std::fs::write(&path, text).unwrap();
+            Config::load(&path)
+        };
+        let config = load("[online]\nendpoint = \"http://localhost:8080/scores\"\nname = \"ada_99\"\n").unwrap();
+        assert!(config.online.server().is_some());
+        assert_eq!(config.builder().build().unwrap().player_name, "ada_99");
+        assert!(load("[online]\nname = \"ada\"\n").unwrap().online.server().is_none());
+        assert!(load("[online]\nendpoint = \"https://localhost/scores\"\n").is_err());
+        assert!(load("[online]\nname = \"two words\"\n").is_err());
+        assert!(load("[online]\nname = \"\"\n").is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
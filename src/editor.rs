@@ -0,0 +1,236 @@
+// Level editor, opened from the start screen. It paints walls and places the snake on a board the size of
+// the game's, then saves the result as a campaign level. Everything happens at a grid cursor, which the
+// arrow keys move and the mouse points at, so it works in the terminal as well as in the window.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use crate::level::{Level, LEVELS_DIR};
+use crate::snake::{Direction, Snake};
+
+pub const EDITOR_FILE: &str = "custom.lvl"; // In LEVELS_DIR, so a saved level joins the campaign after the numbered ones
+const DEFAULT_TARGET: u32 = 10;
+const MAX_TARGET: u32 = 999;
+
+pub struct Editor {
+    pub level: Level,
+    pub cursor: (i32, i32),
+    pub painting: Option<bool>, // While a mouse button is held: whether dragging puts walls down (true) or takes them away
+    pub notice: Option<String>, // Result of the last save or load, or why the last edit didn't happen
+    path: PathBuf,
+}
+
+impl Editor {
+    // Open the level saved last time, or an empty board if there is none yet or it no longer fits the board
+    pub fn open(grid: (i32, i32)) -> Self {
+        let path = Path::new(LEVELS_DIR).join(EDITOR_FILE);
+        let mut editor = Editor { level: empty_level(grid), cursor: (grid.0 / 2, grid.1 / 2), painting: None, notice: None, path };
+        if editor.path.exists() {
+            editor.load();
+        }
+        editor
+    }
+
+    // The snake as it would spawn, for drawing
+    pub fn snake(&self) -> Snake {
+        Snake::at(self.level.start_dir.clone(), self.level.start)
+    }
+
+    pub fn move_cursor(&mut self, dir: Direction) {
+        let (dx, dy) = dir.delta();
+        let (columns, rows) = self.level.grid;
+        self.cursor = ((self.cursor.0 + dx).clamp(0, columns - 1), (self.cursor.1 + dy).clamp(0, rows - 1));
+        self.apply_paint();
+    }
+
+    // The mouse moved onto `cell`; a held button paints or erases along the way
+    pub fn point_at(&mut self, cell: (i32, i32)) {
+        if cell == self.cursor {
+            return;
+        }
+        self.cursor = cell;
+        self.apply_paint();
+    }
+
+    // Start painting walls (or erasing them) at the cursor, until `stop_painting`
+    pub fn start_painting(&mut self, walls: bool) {
+        self.painting = Some(walls);
+        self.apply_paint();
+    }
+
+    pub fn stop_painting(&mut self) {
+        self.painting = None;
+    }
+
+    // Put a wall at the cursor, or take the one there away
+    pub fn toggle_wall(&mut self) {
+        let wall = !self.level.walls.contains(&self.cursor);
+        self.set_wall(self.cursor, wall);
+    }
+
+    // Move the snake's head to the cursor. On the head already, turn it a quarter clockwise instead.
+    pub fn place_snake(&mut self) {
+        let mut placed = self.level.clone();
+        if placed.start == self.cursor {
+            placed.start_dir = placed.start_dir.turn_right();
+        } else {
+            placed.start = self.cursor;
+        }
+        self.update_snake(placed);
+    }
+
+    // Change the score that clears the level by `steps`
+    pub fn change_target(&mut self, steps: i32) {
+        self.level.target = (self.level.target as i32 + steps).clamp(1, MAX_TARGET as i32) as u32;
+    }
+
+    // Take away every wall. Portals from a hand-written level file stay.
+    pub fn clear(&mut self) {
+        self.level.walls.clear();
+        self.notice = None;
+    }
+
+    pub fn save(&mut self) {
+        self.notice = Some(match self.level.save(&self.path) {
+            Ok(()) => format!("Saved {}", self.path.display()),
+            Err(err) => format!("Could not save {}: {}", self.path.display(), err),
+        });
+    }
+
+    // Go back to the level as last saved, if it fits the board
+    pub fn load(&mut self) {
+        let grid = self.level.grid;
+        let result = Level::load(&self.path).and_then(|level| {
+            if level.grid == grid {
+                Ok(level)
+            } else {
+                Err(format!("it is {}x{}, the board is {}x{}", level.grid.0, level.grid.1, grid.0, grid.1))
+            }
+        });
+        self.notice = Some(match result {
+            Ok(level) => {
+                self.level = level;
+                format!("Loaded {}", self.path.display())
+            },
+            Err(err) => format!("Could not load {}: {}", self.path.display(), err),
+        });
+    }
+
+    fn apply_paint(&mut self) {
+        if let Some(walls) = self.painting {
+            self.set_wall(self.cursor, walls);
+        }
+    }
+
+    // Walls can't go on the snake, right ahead of it or on a portal; erasing always works
+    fn set_wall(&mut self, cell: (i32, i32), wall: bool) {
+        if !wall {
+            self.level.walls.remove(&cell);
+        } else if self.level.snake_cells().contains(&cell) {
+            self.notice = Some("The snake is in the way".to_string());
+        } else if cell == self.level.ahead() {
+            self.notice = Some("The snake needs this cell for its first move".to_string());
+        } else if !self.level.is_floor(cell) && !self.level.walls.contains(&cell) {
+            self.notice = Some("A portal is in the way".to_string());
+        } else {
+            self.level.walls.insert(cell);
+        }
+    }
+
+    // Move or turn the snake, as long as all of it still lands on open floor and its first move doesn't kill it
+    fn update_snake(&mut self, placed: Level) {
+        if !placed.snake_fits() {
+            self.notice = Some("The snake needs open floor behind its head".to_string());
+        } else if !placed.first_move_is_open() {
+            self.notice = Some("The snake needs open floor ahead of its head".to_string());
+        } else {
+            self.level = placed;
+            self.notice = None;
+        }
+    }
+}
+
+// A board without walls, with the snake in the middle heading right, the way a run without a level starts
+fn empty_level(grid: (i32, i32)) -> Level {
+    Level {
+        name: "Custom".to_string(),
+        grid,
+        walls: HashSet::new(),
+        portals: Vec::new(),
+        start: (grid.0 / 2, grid.1 / 2),
+        start_dir: Direction::Right,
+        target: DEFAULT_TARGET,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::DEFAULT_GRID;
+
+    // An editor on an empty default-sized board that saves to a file of its own under the temp directory
+    fn editor(name: &str) -> Editor {
+        let path = std::env::temp_dir().join(format!("copperhead-editor-{}-{}.lvl", std::process::id(), name));
+        Editor { level: empty_level(DEFAULT_GRID), cursor: (0, 0), painting: None, notice: None, path }
+    }
+
+    #[test]
+    fn walls_stay_off_the_snake_and_the_cell_ahead_of_it() {
+        let mut editor = editor("walls");
+        let ahead = editor.level.ahead();
+        editor.point_at(ahead);
+        editor.toggle_wall();
+        editor.point_at(editor.level.start);
+        editor.toggle_wall();
+        assert!(editor.level.walls.is_empty());
+        editor.point_at((0, 0));
+        editor.toggle_wall();
+        assert!(editor.level.walls.contains(&(0, 0)));
+    }
+
+    #[test]
+    fn the_snake_wont_turn_to_face_a_wall() {
+        let mut editor = editor("turn");
+        let (x, y) = editor.level.start;
+        editor.level.walls.insert((x, y + 1));
+        editor.point_at(editor.level.start);
+        editor.place_snake();
+        assert!(editor.level.start_dir == Direction::Right);
+        assert!(editor.notice.is_some());
+    }
+
+    #[test]
+    fn a_saved_level_loads_back_and_passes_the_loader() {
+        let mut editor = editor("save");
+        editor.start_painting(true);
+        editor.move_cursor(Direction::Right);
+        editor.stop_painting();
+        editor.change_target(5);
+        editor.save();
+        let saved = Level::load(&editor.path).expect("the saved level loads");
+        assert!(saved.walls == HashSet::from([(0, 0), (1, 0)]));
+        assert_eq!(saved.target, DEFAULT_TARGET + 5);
+
+        editor.clear();
+        editor.load();
+        std::fs::remove_file(&editor.path).unwrap();
+        assert!(editor.level.walls == saved.walls);
+    }
+
+    #[test]
+    fn a_level_for_another_board_size_is_not_loaded() {
+        let mut editor = editor("size");
+        empty_level((DEFAULT_GRID.0 + 5, DEFAULT_GRID.1)).save(&editor.path).unwrap();
+        editor.load();
+        std::fs::remove_file(&editor.path).unwrap();
+        assert_eq!(editor.level.grid, DEFAULT_GRID);
+        assert!(editor.notice.as_ref().is_some_and(|notice| notice.starts_with("Could not load")));
+    }
+
+    #[test]
+    fn walls_stay_off_portals() {
+        let mut editor = editor("portal");
+        editor.level.portals = vec![[(0, 0), (5, 5)]];
+        editor.toggle_wall();
+        assert!(editor.level.walls.is_empty());
+    }
+}
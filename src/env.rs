@@ -0,0 +1,241 @@
+// A reinforcement learning environment over the headless game, in the style of OpenAI Gym: `reset` starts a
+// run and returns what the agent sees, and `step` plays one move and returns what it sees then, the reward
+// and whether the run is done. It plays the same `Game` as the window, so an agent learns the real rules.
+//
+// The agent steers relative to the snake's heading (straight on, left or right), so it can never pick the
+// one move that reverses into its own neck. The reward is the points scored on the move, less DEATH_PENALTY
+// when the snake dies on it.
+
+use crate::game::{FoodKind, Game, GameState, Players, Settings, TickResult};
+use crate::snake::Direction;
+
+// Codes of the cells in an observation's grid
+pub const EMPTY: u8 = 0;
+pub const WALL: u8 = 1; // The edge of a shrunken arena, a rock, or a wall of the level
+pub const BODY: u8 = 2;
+pub const HEAD: u8 = 3;
+pub const FOOD: u8 = 4; // Any food that scores
+pub const POISON: u8 = 5;
+pub const PORTAL: u8 = 6;
+
+pub const DEATH_PENALTY: f64 = 10.0;
+const MAX_MOVES: u32 = 10_000; // A run that gets this long is done, in case the agent circles forever
+
+// A move of the agent's, relative to where the snake is heading
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Action {
+    Straight,
+    Left,
+    Right,
+}
+
+impl Action {
+    pub const ALL: [Action; 3] = [Action::Straight, Action::Left, Action::Right];
+
+    // The action numbered `index` in ALL, for agents that pick a number
+    pub fn from_index(index: usize) -> Option<Action> {
+        Action::ALL.get(index).copied()
+    }
+
+    // Where the snake heads when taking this action while heading `dir`
+    fn direction(self, dir: &Direction) -> Direction {
+        match self {
+            Action::Straight => dir.clone(),
+            Action::Left => dir.turn_left(),
+            Action::Right => dir.turn_right(),
+        }
+    }
+}
+
+// What the agent sees after a move: the board as one cell code per cell, row by row, and the snake's
+// heading, which the grid alone doesn't show
+#[derive(Clone, PartialEq)]
+pub struct Observation {
+    pub grid: Vec<u8>,
+    pub columns: i32,
+    pub rows: i32,
+    pub direction: Direction,
+    pub score: u32,
+}
+
+impl Observation {
+    fn of(game: &Game) -> Observation {
+        let (columns, rows) = game.settings.grid;
+        let layout = game.layout();
+        let mut grid = vec![EMPTY; (columns * rows) as usize];
+        let mut set = |(x, y): (i32, i32), code: u8| {
+            if (0..columns).contains(&x) && (0..rows).contains(&y) {
+                grid[(y * columns + x) as usize] = code;
+            }
+        };
+        for y in 0..rows {
+            for x in 0..columns {
+                if !layout.contains((x, y)) || game.obstacles.contains(&(x, y)) {
+                    set((x, y), WALL);
+                }
+            }
+        }
+        for &cell in game.portals.iter().flatten() {
+            set(cell, PORTAL);
+        }
+        for food in &game.foods {
+            set(food.pos, if food.kind == FoodKind::Poison { POISON } else { FOOD });
+        }
+        for &cell in game.snake.body() {
+            set(cell, BODY);
+        }
+        set(game.snake.head(), HEAD);
+        Observation { grid, columns, rows, direction: game.snake.dir.clone(), score: game.score }
+    }
+
+    // The code of the cell at `(x, y)`, or WALL off the board
+    pub fn cell(&self, (x, y): (i32, i32)) -> u8 {
+        if (0..self.columns).contains(&x) && (0..self.rows).contains(&y) {
+            self.grid[(y * self.columns + x) as usize]
+        } else {
+            WALL
+        }
+    }
+}
+
+pub struct CopperheadEnv {
+    settings: Settings,
+    game: Game,
+    next_seed: Option<u64>, // Seed of the next run, counting up from the settings' seed; None for random runs
+}
+
+impl CopperheadEnv {
+    // An environment playing one-player runs with `settings`. Nothing is kept between sessions. With a seed
+    // in the settings, the runs are seeded with it and the numbers after it, one per reset, so training
+    // can be repeated exactly.
+    pub fn new(settings: &Settings) -> CopperheadEnv {
+        let settings = Settings {
+            players: Players::One,
+            stats_file: None,
+            leaderboard_file: None,
+            achievements_file: None,
+            save_file: None,
+            online_server: None,
+            json_results: false,
+            export_heatmap: false,
+            ..settings.clone()
+        };
+        let game = Game::new(&settings);
+        CopperheadEnv { next_seed: settings.seed, settings, game }
+    }
+
+    // Start a new run and return what the agent sees at its start
+    pub fn reset(&mut self) -> Observation {
+        let settings = Settings { seed: self.next_seed, ..self.settings.clone() };
+        self.next_seed = self.next_seed.map(|seed| seed.wrapping_add(1));
+        self.game = Game::new(&settings);
+        self.game.state = GameState::Running;
+        Observation::of(&self.game)
+    }
+
+    // Play `action` for one move. A run that is done stays done, with no reward, until the next reset.
+    pub fn step(&mut self, action: Action) -> (Observation, f64, bool) {
+        let score = self.game.score;
+        let dir = action.direction(&self.game.snake.dir);
+        let result = self.game.tick(Some(dir));
+        let mut reward = f64::from(self.game.score) - f64::from(score);
+        if let TickResult::Over(Some(_)) = result {
+            reward -= DEATH_PENALTY;
+        }
+        let done = !matches!(result, TickResult::Moved | TickResult::Ate) || self.game.moves >= MAX_MOVES;
+        (Observation::of(&self.game), reward, done)
+    }
+
+    // The game being played, for anything the observation leaves out
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env() -> CopperheadEnv {
+        CopperheadEnv::new(&Settings { seed: Some(7), ..Settings::default() })
+    }
+
+    // The cell just ahead of the snake's head
+    fn ahead(game: &Game) -> (i32, i32) {
+        let (x, y) = game.snake.head();
+        let (dx, dy) = game.snake.dir.delta();
+        (x + dx, y + dy)
+    }
+
+    #[test]
+    fn actions_turn_from_the_heading() {
+        assert!(Action::Straight.direction(&Direction::Right) == Direction::Right);
+        assert!(Action::Left.direction(&Direction::Right) == Direction::Up);
+        assert!(Action::Right.direction(&Direction::Right) == Direction::Down);
+        assert_eq!(Action::from_index(2), Some(Action::Right));
+        assert_eq!(Action::from_index(3), None);
+    }
+
+    #[test]
+    fn the_observation_shows_the_board() {
+        let mut env = env();
+        let observation = env.reset();
+        let game = env.game();
+        assert_eq!((observation.columns, observation.rows), game.settings.grid);
+        assert_eq!(observation.cell(game.snake.head()), HEAD);
+        let count = |code| observation.grid.iter().filter(|&&cell| cell == code).count();
+        assert_eq!(count(BODY), game.snake.body().len() - 1);
+        assert_eq!(count(FOOD) + count(POISON), game.foods.len());
+        assert_eq!(observation.cell((-1, 0)), WALL);
+        assert!(observation.direction == game.snake.dir);
+    }
+
+    #[test]
+    fn eating_scores_the_points_as_the_reward() {
+        let mut env = env();
+        env.reset();
+        let food = ahead(&env.game);
+        env.game.foods[0].pos = food;
+        env.game.foods[0].kind = FoodKind::Normal;
+        let (observation, reward, done) = env.step(Action::Straight);
+        assert!(reward > 0.0 && !done);
+        assert_eq!(reward, f64::from(observation.score));
+    }
+
+    #[test]
+    fn dying_costs_the_penalty_and_the_run_stays_done() {
+        let mut env = env();
+        env.reset();
+        env.game.foods.clear();
+        let mut last = env.step(Action::Straight);
+        while !last.2 {
+            last = env.step(Action::Straight);
+        }
+        assert_eq!(last.1, -DEATH_PENALTY);
+        let (_, reward, done) = env.step(Action::Left);
+        assert!(reward == 0.0 && done);
+    }
+
+    #[test]
+    fn the_same_seed_plays_the_same_runs() {
+        let play = || {
+            let mut env = env();
+            (0..2)
+                .map(|_| {
+                    let mut observations = vec![env.reset()];
+                    for i in 0..50 {
+                        let (observation, _, done) = env.step(Action::ALL[i % 3]);
+                        observations.push(observation);
+                        if done {
+                            break;
+                        }
+                    }
+                    observations
+                })
+                .collect::<Vec<_>>()
+        };
+        let (one, two) = (play(), play());
+        assert!(one == two);
+        assert!(one[0][0] != one[1][0], "each reset starts a run with the next seed");
+    }
+}
@@ -0,0 +1,30 @@
+// Small file helpers shared by the modules that keep data between sessions
+
+use std::path::Path;
+
+// Write `text` to `path`, creating its directory if needed. The text goes to a temporary file first, which is
+// then moved into place, so a crash halfway never leaves a damaged file behind.
+pub fn write_atomically(path: &Path, text: &str) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let temp = path.with_extension("tmp");
+    std::fs::write(&temp, text)?;
+    std::fs::rename(temp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_atomically_creates_the_directory_and_leaves_no_temporary_file() {
+        let dir = std::env::temp_dir().join(format!("copperhead-files-{}", std::process::id()));
+        let path = dir.join("nested").join("data");
+        write_atomically(&path, "first\n").unwrap();
+        write_atomically(&path, "second\n").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "second\n");
+        assert!(!path.with_extension("tmp").exists());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,5161 @@
+// Game rules and state: the board, settings, scoring, food, game modes and the run itself. Nothing in
+// here draws or reads input, so it runs the same in the window, the terminal and the benchmark.
+
+use rand::{Rng, RngCore, SeedableRng};
+use rand::seq::SliceRandom;
+use std::collections::{HashMap, HashSet, VecDeque};
+use crate::achievements::{Achievement, Achievements};
+use crate::editor::Editor;
+use crate::files::write_atomically;
+use crate::leaderboard::{Entry, Leaderboard, INITIALS_LEN, today};
+use crate::level::Level;
+use crate::menu::{Menu, MenuItem, MenuPage};
+use crate::mods::Mod;
+use crate::net::Side;
+use crate::online::{OnlineBoard, Server, MAX_NAME_LEN};
+use crate::particles::Particles;
+use crate::snake::{Direction, Snake, START_DIRECTION, START_LENGTH};
+use crate::stats::Stats;
+use crate::theme::{Theme, THEME_NAMES};
+
+pub const DEFAULT_GRID: (i32, i32) = (20, 20); // 20x20 grid
+pub const DEFAULT_CELL_SIZE: i32 = 32; // Each cell is 32x32 pixels
+const GRID_RANGE: std::ops::RangeInclusive<i32> = 10..=60; // Columns and rows; smaller boards leave no room for the start screen
+const CELL_SIZE_RANGE: std::ops::RangeInclusive<i32> = 16..=64; // Pixels per cell
+const GROWTH_PER_FOOD: u32 = 1; // Segments gained per food
+const MOVE_INTERVAL: f64 = 0.10; // Snake moves every 100ms (10Hz)
+const SPEED_RANGE: std::ops::RangeInclusive<i32> = 4..=20; // Moves per second the options page goes between
+const GRID_STEP: i32 = 5; // Columns and rows the options page adds or takes off the board at a time
+pub const DEFAULT_VOLUME: u32 = 50; // Percent
+pub const VOLUME_STEP: u32 = 10; // Percent per key press
+pub const INPUT_BUFFER_LEN: usize = 3; // Turns that can wait for the moves to come, so quick taps between two moves all count
+const MAX_WALL_DENSITY: f64 = 0.5; // Denser random walls leave too little room to play
+const CELEBRATION_MOVES: u32 = 15; // How long the "new high score" banner flashes, in moves
+const PHASE_PICKUP_EVERY: u32 = 5; // A phase charge shows up on the board after every this many foods
+pub const PHASE_FLASH_MOVES: u32 = 4; // How long the playfield flashes after phasing through a wall, in moves
+pub const DEFAULT_FONT: &str = "assets/JetBrainsMono-Regular.ttf";
+pub const SPRITE_ATLAS: &str = "assets/sprites.png"; // Head, body, corner and tail pieces, then the foods
+const UI_SCALE_RANGE: std::ops::RangeInclusive<f64> = 0.5..=2.0; // Beyond this, text no longer fits the layout
+const BONUS_FOOD_MULTIPLIER: u32 = 3; // Bonus food is worth this many normal foods
+const GOLDEN_FOOD_MULTIPLIER: u32 = 5; // Golden food is worth this many normal foods
+const GOLDEN_FOOD_EVERY: u32 = 7; // A golden food shows up after every this many foods
+pub const GOLDEN_FOOD_SECONDS: f64 = 5.0; // How long a golden food stays before it disappears
+const POWER_UP_EVERY: u32 = 100; // A power-up shows up after every this many moves, unless the last one is still lying there
+pub const POWER_UP_MOVES: u32 = 50; // How long a power-up's effect lasts
+const SPEED_BOOST: f64 = 1.5; // Move rate multiplier while the speed boost lasts
+const SLOW_MOTION: f64 = 0.6; // Move rate multiplier while slow motion lasts
+const POWER_UP_SHRINK: u32 = 3; // Segments the shrink power-up takes off, never going below the starting length
+const LARGE_FOOD_MULTIPLIER: u32 = 3; // Large food grows the snake this many times as much
+const POISON_SHRINK: u32 = 2; // Segments lost to poison, never going below the starting length
+pub const REWIND_SECONDS: f64 = 3.0; // How far back a rewind after death goes
+const DEFAULT_WRAP_PENALTY: (u32, u32) = (1, 1); // Points and segments per crossing when penalty wrapping is picked on the start screen
+const MAX_SNAPSHOTS: usize = 600; // Upper bound on the rewind history, whatever the move interval
+pub const TIME_ATTACK_SECONDS: f64 = 120.0; // Time limit of a time attack run picked on the start screen
+pub const COUNTDOWN_SECONDS: u32 = 3; // Counted down before the snake moves, when a run starts or resumes
+const DEMO_AFTER: f64 = 10.0; // Seconds on the start screen without a key press before the demo starts
+const MIN_ARENA_SPAN: i32 = 6; // A shrinking arena stops closing in once it is this many cells across
+pub const SHRINK_WARNING_SECONDS: f64 = 2.0; // How long the ring about to close flashes beforehand
+const PORTAL_CLEARANCE: i32 = 3; // Random portals stay off this many cells straight ahead of the spawn
+const FLEE_RADIUS: i32 = 3; // Wandering food this many steps or fewer from a head runs away from it
+pub const MAX_COMBO: u32 = 5; // Highest score multiplier a combo builds up to
+pub const TOAST_SECONDS: f64 = 3.0; // How long the notice of an unlocked achievement stays up
+const SURVIVOR_SECONDS: f64 = 120.0; // Run time that unlocks the Survivor achievement
+const SPLAT_SECONDS: f64 = 3.0; // Crashing sooner than this into a run unlocks the Splat achievement
+const DEFAULT_PLAYER_NAME: &str = "Player"; // Name on the global leaderboard until the config gives one
+
+// Make the window big enough to show the border outside the playfield
+const BORDER_THICKNESS: f64 = 16.0;
+const BORDER_FULL: f64 = BORDER_THICKNESS * 2.0;
+
+// Which cells the snake may be in, and where that area's edge is drawn. Collisions and the drawn border
+// both come from here, so the edge that kills is always the edge on screen. With a wall ring, the outermost
+// ring of cells is wall too, drawn as such, and the deadly edge moves in by one cell.
+#[derive(Clone, Copy, PartialEq)]
+pub struct BoardLayout {
+    pub grid: (i32, i32),
+    pub cell: i32, // Pixels per cell
+    ring: i32, // Cells of wall along each side of the grid
+}
+
+impl BoardLayout {
+    pub fn new(grid: (i32, i32), cell: i32, wall_ring: bool) -> Self {
+        BoardLayout { grid, cell, ring: if wall_ring { 1 } else { 0 } }
+    }
+
+    // Whether the snake can be in `cell` without hitting the border or the wall ring
+    pub fn contains(&self, (x, y): (i32, i32)) -> bool {
+        x >= self.ring && x < self.grid.0 - self.ring && y >= self.ring && y < self.grid.1 - self.ring
+    }
+
+    // Whether `cell` is part of the wall ring
+    pub fn in_ring(&self, (x, y): (i32, i32)) -> bool {
+        let in_grid = x >= 0 && x < self.grid.0 && y >= 0 && y < self.grid.1;
+        in_grid && !self.contains((x, y))
+    }
+
+    // The cell a move off one edge comes back in at, on the opposite edge
+    pub fn wrap(&self, (x, y): (i32, i32)) -> (i32, i32) {
+        let (w, h) = (self.grid.0 - 2 * self.ring, self.grid.1 - 2 * self.ring);
+        (self.ring + (x - self.ring).rem_euclid(w), self.ring + (y - self.ring).rem_euclid(h))
+    }
+
+    // The same board with `rings` more rings of cells walled off along each side
+    pub fn shrunk(self, rings: i32) -> Self {
+        BoardLayout { ring: self.ring + rings, ..self }
+    }
+
+    // Cells across the open area, the narrower way
+    pub fn open_span(&self) -> i32 {
+        (self.grid.0 - 2 * self.ring).min(self.grid.1 - 2 * self.ring)
+    }
+
+    pub fn open_cells(&self) -> usize {
+        ((self.grid.0 - 2 * self.ring) * (self.grid.1 - 2 * self.ring)) as usize
+    }
+
+    // Row-by-row index of `cell` into per-cell data, or None for cells outside the grid (e.g. a head in the wall)
+    pub fn index(&self, (x, y): (i32, i32)) -> Option<usize> {
+        if x < 0 || x >= self.grid.0 || y < 0 || y >= self.grid.1 {
+            return None;
+        }
+        Some((y * self.grid.0 + x) as usize)
+    }
+
+    // Size of the whole board in pixels, border included; the window opens at this size
+    pub fn window_size(&self) -> [u32; 2] {
+        let [ox, oy] = self.playfield_origin();
+        [(self.grid.0 * self.cell) as u32 + (ox as u32) * 2, (self.grid.1 * self.cell) as u32 + (oy as u32) * 2]
+    }
+
+    // Pixel square of `cell`, relative to the playfield origin
+    pub fn square(&self, (x, y): (i32, i32)) -> [f64; 4] {
+        let cell = self.cell as f64;
+        [x as f64 * cell, y as f64 * cell, cell, cell]
+    }
+
+    // Top left corner of cell (0, 0) on the board, inside the border
+    pub fn playfield_origin(&self) -> [f64; 2] {
+        [BORDER_FULL, BORDER_FULL]
+    }
+
+    // The area the snake can move in, relative to the playfield origin. Its outline is the deadly edge.
+    pub fn open_rect(&self) -> [f64; 4] {
+        let cell = self.cell as f64;
+        let ring = self.ring as f64 * cell;
+        [ring, ring, (self.grid.0 - 2 * self.ring) as f64 * cell, (self.grid.1 - 2 * self.ring) as f64 * cell]
+    }
+
+    // Top, bottom, left and right border bars on the board, framing the grid
+    pub fn border_rects(&self) -> [[f64; 4]; 4] {
+        let [ox, oy] = self.playfield_origin();
+        let (w, h) = ((self.grid.0 * self.cell) as f64, (self.grid.1 * self.cell) as f64);
+        [
+            [0.0, 0.0, w + ox * 2.0, oy],
+            [0.0, oy + h, w + ox * 2.0, oy],
+            [0.0, 0.0, ox, h + oy * 2.0],
+            [ox + w, 0.0, ox, h + oy * 2.0],
+        ]
+    }
+}
+
+// How the steering keys steer
+#[derive(Clone, Copy, PartialEq)]
+pub enum ControlScheme {
+    Absolute, // Each steering key points the snake that way
+    Relative, // Left and right turn the snake a quarter to its own left or right; up and down do nothing
+}
+
+// What a key can be bound to
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Action {
+    Up,
+    Down,
+    Left,
+    Right,
+    Pause, // Pause a run, or resume it
+    Restart, // Start a run from the start screen, resume a paused one or leave the game over screen
+}
+
+impl Action {
+    // Steering first, so a key bound to both steers during a run
+    pub const ALL: [Action; 6] = [Action::Up, Action::Down, Action::Left, Action::Right, Action::Pause, Action::Restart];
+}
+
+// Keys for each action, by name ("up", "space", "w", ...). Names rather than key codes keep the rules free
+// of Piston; `input` turns them into keys. An action can have several keys; the first one is shown in the help.
+#[derive(Clone, PartialEq, Debug, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeyBindings {
+    pub up: Vec<String>,
+    pub down: Vec<String>,
+    pub left: Vec<String>,
+    pub right: Vec<String>,
+    pub pause: Vec<String>,
+    pub restart: Vec<String>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        let names = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+        KeyBindings {
+            up: names(&["up", "w"]),
+            down: names(&["down", "s"]),
+            left: names(&["left", "a"]),
+            right: names(&["right", "d"]),
+            pause: names(&["p"]),
+            restart: names(&["space"]),
+        }
+    }
+}
+
+impl KeyBindings {
+    pub fn keys(&self, action: Action) -> &[String] {
+        match action {
+            Action::Up => &self.up,
+            Action::Down => &self.down,
+            Action::Left => &self.left,
+            Action::Right => &self.right,
+            Action::Pause => &self.pause,
+            Action::Restart => &self.restart,
+        }
+    }
+
+    fn keys_mut(&mut self, action: Action) -> &mut Vec<String> {
+        match action {
+            Action::Up => &mut self.up,
+            Action::Down => &mut self.down,
+            Action::Left => &mut self.left,
+            Action::Right => &mut self.right,
+            Action::Pause => &mut self.pause,
+            Action::Restart => &mut self.restart,
+        }
+    }
+
+    // Make the key called `name` the first key of `action`, in place of the one it had, and take it off every
+    // other action. An action that would be left without a key gets the replaced one, so the two swap.
+    pub fn bind(&mut self, action: Action, name: &str) {
+        let replaced = self.keys(action).first().cloned().filter(|key| key != name);
+        for other in Action::ALL.into_iter().filter(|&other| other != action) {
+            let keys = self.keys_mut(other);
+            keys.retain(|key| key != name);
+            if keys.is_empty() {
+                keys.extend(replaced.clone());
+            }
+        }
+        let keys = self.keys_mut(action);
+        keys.retain(|key| key != name && Some(key) != replaced.as_ref());
+        keys.insert(0, name.to_string());
+    }
+}
+
+// How to judge the head moving into the cell the tail is leaving on the same move
+#[derive(Clone, Copy, PartialEq)]
+pub enum TailChase {
+    Classic, // Allowed when not growing: the tail is already gone by the time the head arrives
+    Strict, // Fatal: any overlap with where the body was counts as a bite
+}
+
+// Where new food may appear
+#[derive(Clone, Copy, PartialEq)]
+pub enum FoodSpawn {
+    Uniform, // Any free, reachable cell
+    NearHead { radius: u32 }, // Assist: prefer free, reachable cells within `radius` steps of the head
+}
+
+// What happens when the head runs off the edge of the board
+#[derive(Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum WallMode {
+    Lethal, // The border is a wall
+    Wrap, // The head comes back in at the opposite edge
+    WrapPenalty { point_cost: u32, shrink: u32 }, // Wraps, but every crossing costs points and segments
+}
+
+impl WallMode {
+    // The mode after this one when cycling through them on the start screen; penalty wrapping uses `penalty`
+    fn next(self, (point_cost, shrink): (u32, u32)) -> WallMode {
+        match self {
+            WallMode::Lethal => WallMode::Wrap,
+            WallMode::Wrap => WallMode::WrapPenalty { point_cost, shrink },
+            WallMode::WrapPenalty { .. } => WallMode::Lethal,
+        }
+    }
+
+    pub fn describe(self) -> String {
+        match self {
+            WallMode::Lethal => "Edges: deadly".to_string(),
+            WallMode::Wrap => "Edges: wrap around".to_string(),
+            WallMode::WrapPenalty { point_cost, shrink } => format!("Edges: wrap for -{} points, -{} length", point_cost, shrink),
+        }
+    }
+}
+
+// How board cells (food, rocks, the snake's head) are drawn
+#[derive(Clone, Copy, PartialEq)]
+pub enum CellShape {
+    Sharp, // Plain rectangles, the cheapest to draw
+    Rounded { round_food: bool }, // Rounded corners, optionally with circular food
+}
+
+// What a piece of food does when eaten
+#[derive(Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum FoodKind {
+    Normal,
+    Bonus, // Worth several normal foods
+    Poison, // No points, and the snake shrinks
+    Large, // Normal points, but the snake grows a lot more
+    Golden, // Worth even more, but only lies there for a few seconds
+}
+
+impl std::str::FromStr for FoodKind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "normal" => Ok(FoodKind::Normal),
+            "bonus" => Ok(FoodKind::Bonus),
+            "poison" => Ok(FoodKind::Poison),
+            "large" => Ok(FoodKind::Large),
+            _ => Err(()),
+        }
+    }
+}
+
+impl FoodKind {
+    // Points for eating this kind of food, when a normal one is worth `per_food`
+    fn points(self, per_food: u32) -> u32 {
+        match self {
+            FoodKind::Normal | FoodKind::Large => per_food,
+            FoodKind::Bonus => per_food * BONUS_FOOD_MULTIPLIER,
+            FoodKind::Golden => per_food * GOLDEN_FOOD_MULTIPLIER,
+            FoodKind::Poison => 0,
+        }
+    }
+
+    // Segments gained by eating this kind of food, when a normal one adds `growth`
+    fn growth(self, growth: u32) -> u32 {
+        match self {
+            FoodKind::Large => growth * LARGE_FOOD_MULTIPLIER,
+            FoodKind::Poison => 0,
+            _ => growth,
+        }
+    }
+}
+
+// A piece of food lying on the board
+#[derive(Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Food {
+    pub pos: (i32, i32),
+    pub kind: FoodKind,
+    respawns: bool, // Replaced by a fresh one when eaten; pre-placed items aren't
+    spawned_at: u32, // Move count when it appeared, for food that loses value with age
+    pub moves_left: Option<u32>, // Moves until it disappears, None for food that stays until eaten
+}
+
+// Pickups that change the rules for a while. Shrink works at once; the others last POWER_UP_MOVES moves.
+#[derive(Clone, Copy, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+pub enum PowerUp {
+    Speed, // The snake moves faster
+    SlowMotion, // The snake moves slower
+    Ghost, // The snake can pass through itself
+    Shrink, // The tail loses a few segments
+}
+
+impl PowerUp {
+    const ALL: [PowerUp; 4] = [PowerUp::Speed, PowerUp::SlowMotion, PowerUp::Ghost, PowerUp::Shrink];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PowerUp::Speed => "Speed",
+            PowerUp::SlowMotion => "Slow-mo",
+            PowerUp::Ghost => "Ghost",
+            PowerUp::Shrink => "Shrink",
+        }
+    }
+}
+
+// Where the board sits when the window is larger than it
+#[derive(Clone, Copy, PartialEq)]
+pub enum BoardAlign {
+    TopLeft, Top, TopRight,
+    Left, Center, Right,
+    BottomLeft, Bottom, BottomRight,
+}
+
+impl BoardAlign {
+    // Fraction of the spare room that goes to the left of and above the board
+    pub fn fractions(&self) -> (f64, f64) {
+        match self {
+            BoardAlign::TopLeft => (0.0, 0.0),
+            BoardAlign::Top => (0.5, 0.0),
+            BoardAlign::TopRight => (1.0, 0.0),
+            BoardAlign::Left => (0.0, 0.5),
+            BoardAlign::Center => (0.5, 0.5),
+            BoardAlign::Right => (1.0, 0.5),
+            BoardAlign::BottomLeft => (0.0, 1.0),
+            BoardAlign::Bottom => (0.5, 1.0),
+            BoardAlign::BottomRight => (1.0, 1.0),
+        }
+    }
+}
+
+impl std::str::FromStr for BoardAlign {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "top-left" => Ok(BoardAlign::TopLeft),
+            "top" => Ok(BoardAlign::Top),
+            "top-right" => Ok(BoardAlign::TopRight),
+            "left" => Ok(BoardAlign::Left),
+            "center" => Ok(BoardAlign::Center),
+            "right" => Ok(BoardAlign::Right),
+            "bottom-left" => Ok(BoardAlign::BottomLeft),
+            "bottom" => Ok(BoardAlign::Bottom),
+            "bottom-right" => Ok(BoardAlign::BottomRight),
+            _ => Err(()),
+        }
+    }
+}
+
+impl std::fmt::Display for BoardAlign {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            BoardAlign::TopLeft => "top-left",
+            BoardAlign::Top => "top",
+            BoardAlign::TopRight => "top-right",
+            BoardAlign::Left => "left",
+            BoardAlign::Center => "center",
+            BoardAlign::Right => "right",
+            BoardAlign::BottomLeft => "bottom-left",
+            BoardAlign::Bottom => "bottom",
+            BoardAlign::BottomRight => "bottom-right",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// How points are earned; every change to the score goes through `Game::award`
+#[derive(Clone, Copy, PartialEq)]
+pub struct Scoring {
+    pub per_food: u32, // Points for every food eaten
+    pub survival_every: u32, // One point per this many moves survived, or 0 for none
+    pub length_bonus: u32, // Points per segment grown, added at game over
+    pub food_decay: Option<FoodDecay>, // Anti-grind: food loses value while it waits, replacing `per_food`
+    pub combo_window: u32, // Moves after eating in which the next food raises the multiplier, or 0 for no combos
+}
+
+impl Default for Scoring {
+    fn default() -> Self {
+        Scoring {
+            per_food: 1,
+            survival_every: 0,
+            length_bonus: 0,
+            food_decay: None,
+            combo_window: 0,
+        }
+    }
+}
+
+// Food worth `max` points when fresh, losing half of what it's worth above `floor` every `half_life` moves
+#[derive(Clone, Copy, PartialEq)]
+pub struct FoodDecay {
+    pub max: u32,
+    pub floor: u32,
+    pub half_life: u32,
+}
+
+impl FoodDecay {
+    // Points for food eaten after waiting `age` moves
+    pub fn value(&self, age: u32) -> u32 {
+        let fresh = (self.max - self.floor) as f64;
+        self.floor + (fresh * 0.5f64.powf(age as f64 / self.half_life as f64)).round() as u32
+    }
+}
+
+// Where points came from
+#[derive(Clone, Copy, PartialEq)]
+pub enum ScoreSource {
+    Food,
+    Survival,
+    Length,
+    Mod, // Awarded by a mod's script
+}
+
+// The run's score split by source; the parts always add up to `Game::score`
+#[derive(Clone, Copy, Default, serde::Serialize, serde::Deserialize)]
+pub struct ScoreBreakdown {
+    food: u32,
+    survival: u32,
+    length: u32,
+    penalty: u32, // Points lost to penalty wraps
+    #[serde(default)]
+    mods: u32,
+}
+
+// What ended the run, recorded at the moment of death
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DeathCause {
+    Wall,
+    SelfBite,
+    Obstacle,
+    TimeUp,
+    Trapped,
+    Rival, // Ran into the other player's snake
+}
+
+impl DeathCause {
+    pub fn message(&self) -> &'static str {
+        match self {
+            DeathCause::Wall => "You hit the wall",
+            DeathCause::SelfBite => "You bit yourself",
+            DeathCause::Obstacle => "You crashed into a rock",
+            DeathCause::TimeUp => "Time's up",
+            DeathCause::Trapped => "You boxed yourself in",
+            DeathCause::Rival => "You ran into the other snake",
+        }
+    }
+}
+
+// What one move made with `Game::tick` did
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TickResult {
+    Moved, // The snake moved and the run goes on
+    Ate, // The snake ate on this move and the run goes on
+    Over(Option<DeathCause>), // The run ended on this move: how the snake died, or None if the board ran out of room
+    LevelComplete, // The score reached the campaign level's target on this move
+    Stopped, // No run was going, so nothing happened
+}
+
+// How fast the frontend draws and updates, as it measured over the last second, for the debug overlay
+#[derive(Clone, Copy, Default)]
+pub struct FrameTimes {
+    pub fps: f64, // Frames drawn per second
+    pub ups: f64, // Logic updates per second
+    pub tick_ms: f64, // Milliseconds an update took on average
+}
+
+#[derive(PartialEq)]
+pub enum GameState {
+    Menu, // The title screen, with its menu
+    Running,
+    Paused, // A run on hold; nothing moves until it's resumed
+    GameOver,
+    LevelComplete, // A campaign level was cleared; the next one starts from here
+}
+
+// Who plays a run
+#[derive(Clone, Copy, PartialEq)]
+pub enum Players {
+    One, // A single snake
+    Two, // A second snake, steered by another player with W, A, S and D
+    Computer, // A second snake, steered by the computer
+}
+
+impl Players {
+    // The next choice when cycling through them on the start screen
+    fn next(self) -> Players {
+        match self {
+            Players::One => Players::Two,
+            Players::Two => Players::Computer,
+            Players::Computer => Players::One,
+        }
+    }
+
+    pub fn describe(self) -> &'static str {
+        match self {
+            Players::One => "One player",
+            Players::Two => "Two players: arrows and WASD",
+            Players::Computer => "Against the computer",
+        }
+    }
+}
+
+// Every tunable needed to construct a game, independent of where the values came from
+#[derive(Clone)]
+pub struct Settings {
+    pub grid: (i32, i32), // Columns and rows of the board
+    pub cell_size: i32, // Pixels per cell, which with the grid decides the window size
+    pub start_dir: Direction, // Heading the snake spawns with on every (re)start
+    pub growth_per_food: u32, // Segments gained for every food eaten
+    pub grow_every: u32, // Only every this many foods make the snake grow (1 for all of them); scoring is unaffected
+    pub max_length: Option<usize>, // The snake stops growing at this length, or None for no limit
+    pub move_interval: f64, // Seconds between snake moves
+    pub time_scale: f64, // Game speed relative to real time: below 1 is slow motion, above 1 fast-forward
+    pub rumble: bool, // Gamepad force feedback on eating and dying
+    pub countdown: u32, // Seconds counted down before the snake moves, when a run starts or resumes; 0 to go at once
+    pub tail_chase: TailChase, // Whether chasing your own tail is forgiven
+    pub export_heatmap: bool, // Save a heatmap of every run automatically on game over
+    pub json_results: bool, // Print one JSON line per finished run to stdout, for scripts
+    pub time_limit: Option<f64>, // Time attack: seconds per run, or None to play until death
+    pub arena_shrink: Option<f64>, // Shrinking arena: seconds between the walls closing in by a ring, or None to keep the board whole
+    pub food_wander: Option<u32>, // Wandering food: moves between each food's steps, or None for food that stays put
+    pub wall_density: f64, // Fraction of the grid covered by random walls each run (0 for none)
+    pub portal_pairs: u32, // Pairs of portals placed at random each run, on top of a campaign level's own
+    pub assist_arrows: bool, // Beginner aid: show the heading and any queued turn ahead of the head
+    pub seed: Option<u64>, // Seed for food, walls and pickups, for reproducible games; None for a random one
+    pub food_spawn: FoodSpawn, // How far from the head new food may land
+    pub wall_mode: WallMode, // Whether the border kills, wraps, or wraps at a cost
+    pub wall_ring: bool, // Wall off the outermost ring of cells, so the deadly edge is drawn as cells of wall
+    pub cell_shape: CellShape, // Sharp or rounded cells
+    pub end_when_trapped: bool, // End the run as soon as every move would be fatal, instead of making the player pick one
+    pub rewinds: u32, // Forgiving mode: how often per run a death can be undone by rewinding (0 for never)
+    pub power_ups: bool, // Power-ups show up on the board now and then
+    pub players: Players, // Whether there is a second snake, and who steers it
+    pub net_side: Option<Side>, // Which snake this machine steers in a two-player run over the network, None for a local run
+    pub scoring: Scoring, // Points per food, for surviving, and for length
+    pub food_table: Vec<(FoodKind, f64)>, // Relative odds of each kind of food spawning
+    pub food_count: usize, // How many foods are on the board at once; each one eaten is replaced
+    pub placed_food: Vec<(FoodKind, (i32, i32))>, // Extra items lying on the board at the start of every run, not replaced once eaten
+    pub board_align: BoardAlign, // Placement of the board in a window that is larger than it
+    pub fullscreen: bool, // The window fills the screen, and the board is scaled up to fill as much of it as fits
+    pub theme: Theme, // Every color the game is drawn in
+    pub volume: u32, // Music volume in percent
+    pub muted: bool,
+    pub font_path: Option<std::path::PathBuf>, // Custom TTF font for all text, or None for the bundled one
+    pub ui_scale: f64, // Multiplier for every text size, for HiDPI screens or readability
+    // Input feel. Turns normally wait in a buffer and are applied one per move, so quick combos
+    // (e.g. up-then-left within one move) play out predictably. Applying them on the frame they are
+    // pressed feels snappier, but only the last press before a move counts.
+    pub input_buffer_len: usize, // How many turns can be queued ahead of the snake (at least 1)
+    pub apply_turns_on_frame: bool, // Steer as soon as a key is pressed instead of buffering until the next move
+    pub control_scheme: ControlScheme, // Absolute arrow directions, or two-key turning relative to the heading
+    pub key_bindings: KeyBindings, // Keys for steering, pausing and restarting
+    pub shadows: bool, // Soft drop shadows under the snake and the food
+    pub smooth_movement: bool, // Slide the snake from cell to cell in between moves, instead of jumping a whole cell
+    pub sprites: bool, // Draw the snake and the food from the sprite atlas instead of plain shapes
+    pub stats_file: Option<std::path::PathBuf>, // Where the high score is kept between sessions, or None to start from 0 every time
+    pub leaderboard_file: Option<std::path::PathBuf>, // Where the top 10 is kept, or None for no leaderboard
+    pub achievements_file: Option<std::path::PathBuf>, // Where unlocked achievements are kept, or None for no achievements
+    pub save_file: Option<std::path::PathBuf>, // Where a run left mid-way is kept to be continued, or None to never save one
+    pub online_server: Option<Server>, // Global leaderboard that finished runs are sent to, or None to keep scores local
+    pub player_name: String, // Name on the global leaderboard
+    pub campaign: Vec<Level>, // Levels to play through in order, or empty for the open board
+    pub mods: Vec<Mod>, // Scripted rules playing along, in the order their hooks run
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            grid: DEFAULT_GRID,
+            cell_size: DEFAULT_CELL_SIZE,
+            start_dir: START_DIRECTION,
+            growth_per_food: GROWTH_PER_FOOD,
+            grow_every: 1,
+            max_length: None,
+            move_interval: MOVE_INTERVAL,
+            time_scale: 1.0,
+            rumble: true,
+            countdown: COUNTDOWN_SECONDS,
+            tail_chase: TailChase::Classic,
+            export_heatmap: false,
+            json_results: false,
+            time_limit: None,
+            arena_shrink: None,
+            food_wander: None,
+            wall_density: 0.0,
+            portal_pairs: 0,
+            assist_arrows: false,
+            seed: None,
+            food_spawn: FoodSpawn::Uniform,
+            wall_mode: WallMode::Lethal,
+            wall_ring: false,
+            cell_shape: CellShape::Sharp,
+            end_when_trapped: false,
+            rewinds: 0,
+            power_ups: false,
+            players: Players::One,
+            net_side: None,
+            scoring: Scoring::default(),
+            food_table: vec![(FoodKind::Normal, 1.0)],
+            food_count: 1,
+            placed_food: Vec::new(),
+            board_align: BoardAlign::TopLeft,
+            fullscreen: false,
+            theme: Theme::default(),
+            volume: DEFAULT_VOLUME,
+            muted: false,
+            font_path: None,
+            ui_scale: 1.0,
+            input_buffer_len: INPUT_BUFFER_LEN,
+            apply_turns_on_frame: false,
+            control_scheme: ControlScheme::Absolute,
+            key_bindings: KeyBindings::default(),
+            shadows: false,
+            smooth_movement: true,
+            sprites: false,
+            stats_file: None,
+            leaderboard_file: None,
+            achievements_file: None,
+            save_file: None,
+            online_server: None,
+            player_name: DEFAULT_PLAYER_NAME.to_string(),
+            campaign: Vec::new(),
+            mods: Vec::new(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn builder() -> SettingsBuilder {
+        SettingsBuilder { settings: Settings::default() }
+    }
+
+    pub fn layout(&self) -> BoardLayout {
+        BoardLayout::new(self.grid, self.cell_size, self.wall_ring)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SettingsError {
+    GridSize((i32, i32)),
+    CellSize(i32),
+    MoveInterval(f64),
+    TimeLimit(f64),
+    ArenaShrink(f64),
+    FoodWander,
+    WallDensity(f64),
+    MagnetRadius,
+    InputBuffer,
+    FoodTable,
+    FoodCount(usize),
+    PlacedFood((i32, i32)),
+    GrowEvery,
+    MaxLength(usize),
+    UiScale(f64),
+    TimeScale(f64),
+    FoodDecay,
+    LevelSize(String, (i32, i32), (i32, i32)), // Level name, its size and the board's
+    CampaignPlayers,
+}
+
+impl std::fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SettingsError::GridSize((columns, rows)) => write!(f, "the board must have between {} and {} columns and rows, got {}x{}", GRID_RANGE.start(), GRID_RANGE.end(), columns, rows),
+            SettingsError::CellSize(size) => write!(f, "cell size must be between {} and {} pixels, got {}", CELL_SIZE_RANGE.start(), CELL_SIZE_RANGE.end(), size),
+            SettingsError::MoveInterval(secs) => write!(f, "move interval must be a positive number of seconds, got {}", secs),
+            SettingsError::TimeLimit(secs) => write!(f, "time limit must be a positive number of seconds, got {}", secs),
+            SettingsError::ArenaShrink(secs) => write!(f, "the arena must shrink every positive number of seconds, got {}", secs),
+            SettingsError::FoodWander => write!(f, "wandering food must step every 1 or more moves"),
+            SettingsError::WallDensity(density) => write!(f, "wall density must be between 0 and {}, got {}", MAX_WALL_DENSITY, density),
+            SettingsError::MagnetRadius => write!(f, "food magnet radius must be at least 1"),
+            SettingsError::InputBuffer => write!(f, "input buffer must hold at least 1 turn"),
+            SettingsError::FoodTable => write!(f, "food weights must not be negative and must add up to more than 0"),
+            SettingsError::FoodCount(count) => write!(f, "food count must be at least 1 and fit on the board next to the snake and any placed food, got {}", count),
+            SettingsError::PlacedFood((x, y)) => write!(f, "placed food at {}:{} must be on the board (inside the wall ring, if any), clear of the snake's starting position and of other placed food", x, y),
+            SettingsError::FoodDecay => write!(f, "decaying food must start at or above its floor and halve over at least 1 move"),
+            SettingsError::GrowEvery => write!(f, "the snake must grow every 1 or more foods"),
+            SettingsError::TimeScale(scale) => write!(f, "time scale must be a positive number, got {}", scale),
+            SettingsError::UiScale(scale) => write!(f, "UI scale must be between {} and {}, got {}", UI_SCALE_RANGE.start(), UI_SCALE_RANGE.end(), scale),
+            SettingsError::LevelSize(name, level, board) => write!(f, "level {:?} is {}x{}, but the board is {}x{}; every level must be the size of the board", name, level.0, level.1, board.0, board.1),
+            SettingsError::CampaignPlayers => write!(f, "the campaign is for one player"),
+            SettingsError::MaxLength(len) => write!(f, "maximum length must be at least the starting length of {}, got {}", START_LENGTH, len),
+        }
+    }
+}
+
+// Fluent construction of `Settings`, starting from the defaults and validated on `build`
+pub struct SettingsBuilder {
+    settings: Settings,
+}
+
+impl SettingsBuilder {
+    pub fn grid(mut self, columns: i32, rows: i32) -> Self {
+        self.settings.grid = (columns, rows);
+        self
+    }
+
+    pub fn cell_size(mut self, pixels: i32) -> Self {
+        self.settings.cell_size = pixels;
+        self
+    }
+
+    pub fn start_dir(mut self, dir: Direction) -> Self {
+        self.settings.start_dir = dir;
+        self
+    }
+
+    pub fn growth_per_food(mut self, segments: u32) -> Self {
+        self.settings.growth_per_food = segments;
+        self
+    }
+
+    pub fn grow_every(mut self, foods: u32) -> Self {
+        self.settings.grow_every = foods;
+        self
+    }
+
+    pub fn max_length(mut self, segments: Option<usize>) -> Self {
+        self.settings.max_length = segments;
+        self
+    }
+
+    pub fn move_interval(mut self, secs: f64) -> Self {
+        self.settings.move_interval = secs;
+        self
+    }
+
+    pub fn time_scale(mut self, scale: f64) -> Self {
+        self.settings.time_scale = scale;
+        self
+    }
+
+    pub fn countdown(mut self, seconds: u32) -> Self {
+        self.settings.countdown = seconds;
+        self
+    }
+
+    pub fn rumble(mut self, enabled: bool) -> Self {
+        self.settings.rumble = enabled;
+        self
+    }
+
+    pub fn tail_chase(mut self, rule: TailChase) -> Self {
+        self.settings.tail_chase = rule;
+        self
+    }
+
+    pub fn export_heatmap(mut self, enabled: bool) -> Self {
+        self.settings.export_heatmap = enabled;
+        self
+    }
+
+    pub fn json_results(mut self, enabled: bool) -> Self {
+        self.settings.json_results = enabled;
+        self
+    }
+
+    pub fn time_limit(mut self, secs: Option<f64>) -> Self {
+        self.settings.time_limit = secs;
+        self
+    }
+
+    pub fn arena_shrink(mut self, secs: Option<f64>) -> Self {
+        self.settings.arena_shrink = secs;
+        self
+    }
+
+    pub fn food_wander(mut self, every: Option<u32>) -> Self {
+        self.settings.food_wander = every;
+        self
+    }
+
+    pub fn wall_density(mut self, density: f64) -> Self {
+        self.settings.wall_density = density;
+        self
+    }
+
+    pub fn portal_pairs(mut self, pairs: u32) -> Self {
+        self.settings.portal_pairs = pairs;
+        self
+    }
+
+    pub fn assist_arrows(mut self, enabled: bool) -> Self {
+        self.settings.assist_arrows = enabled;
+        self
+    }
+
+    pub fn seed(mut self, seed: Option<u64>) -> Self {
+        self.settings.seed = seed;
+        self
+    }
+
+    pub fn food_spawn(mut self, strategy: FoodSpawn) -> Self {
+        self.settings.food_spawn = strategy;
+        self
+    }
+
+    pub fn wall_mode(mut self, mode: WallMode) -> Self {
+        self.settings.wall_mode = mode;
+        self
+    }
+
+    pub fn control_scheme(mut self, scheme: ControlScheme) -> Self {
+        self.settings.control_scheme = scheme;
+        self
+    }
+
+    pub fn key_bindings(mut self, bindings: KeyBindings) -> Self {
+        self.settings.key_bindings = bindings;
+        self
+    }
+
+    pub fn shadows(mut self, enabled: bool) -> Self {
+        self.settings.shadows = enabled;
+        self
+    }
+
+    pub fn smooth_movement(mut self, enabled: bool) -> Self {
+        self.settings.smooth_movement = enabled;
+        self
+    }
+
+    pub fn sprites(mut self, enabled: bool) -> Self {
+        self.settings.sprites = enabled;
+        self
+    }
+
+    pub fn wall_ring(mut self, enabled: bool) -> Self {
+        self.settings.wall_ring = enabled;
+        self
+    }
+
+    pub fn cell_shape(mut self, shape: CellShape) -> Self {
+        self.settings.cell_shape = shape;
+        self
+    }
+
+    pub fn end_when_trapped(mut self, enabled: bool) -> Self {
+        self.settings.end_when_trapped = enabled;
+        self
+    }
+
+    pub fn power_ups(mut self, enabled: bool) -> Self {
+        self.settings.power_ups = enabled;
+        self
+    }
+
+    pub fn players(mut self, players: Players) -> Self {
+        self.settings.players = players;
+        self
+    }
+
+    pub fn net_side(mut self, side: Option<Side>) -> Self {
+        self.settings.net_side = side;
+        self
+    }
+
+    pub fn stats_file(mut self, path: Option<std::path::PathBuf>) -> Self {
+        self.settings.stats_file = path;
+        self
+    }
+
+    pub fn leaderboard_file(mut self, path: Option<std::path::PathBuf>) -> Self {
+        self.settings.leaderboard_file = path;
+        self
+    }
+
+    pub fn achievements_file(mut self, path: Option<std::path::PathBuf>) -> Self {
+        self.settings.achievements_file = path;
+        self
+    }
+
+    pub fn save_file(mut self, path: Option<std::path::PathBuf>) -> Self {
+        self.settings.save_file = path;
+        self
+    }
+
+    pub fn online_server(mut self, server: Option<Server>) -> Self {
+        self.settings.online_server = server;
+        self
+    }
+
+    pub fn player_name(mut self, name: String) -> Self {
+        self.settings.player_name = name;
+        self
+    }
+
+    pub fn campaign(mut self, levels: Vec<Level>) -> Self {
+        self.settings.campaign = levels;
+        self
+    }
+
+    pub fn mods(mut self, mods: Vec<Mod>) -> Self {
+        self.settings.mods = mods;
+        self
+    }
+
+    pub fn rewinds(mut self, count: u32) -> Self {
+        self.settings.rewinds = count;
+        self
+    }
+
+    pub fn scoring(mut self, scoring: Scoring) -> Self {
+        self.settings.scoring = scoring;
+        self
+    }
+
+    pub fn food_count(mut self, count: usize) -> Self {
+        self.settings.food_count = count;
+        self
+    }
+
+    pub fn placed_food(mut self, items: Vec<(FoodKind, (i32, i32))>) -> Self {
+        self.settings.placed_food = items;
+        self
+    }
+
+    pub fn food_table(mut self, table: Vec<(FoodKind, f64)>) -> Self {
+        self.settings.food_table = table;
+        self
+    }
+
+    pub fn board_align(mut self, align: BoardAlign) -> Self {
+        self.settings.board_align = align;
+        self
+    }
+
+    pub fn fullscreen(mut self, enabled: bool) -> Self {
+        self.settings.fullscreen = enabled;
+        self
+    }
+
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.settings.theme = theme;
+        self
+    }
+
+    pub fn volume(mut self, volume: u32, muted: bool) -> Self {
+        self.settings.volume = volume.min(100);
+        self.settings.muted = muted;
+        self
+    }
+
+    pub fn font_path(mut self, path: Option<std::path::PathBuf>) -> Self {
+        self.settings.font_path = path;
+        self
+    }
+
+    pub fn ui_scale(mut self, scale: f64) -> Self {
+        self.settings.ui_scale = scale;
+        self
+    }
+
+    pub fn input_buffer_len(mut self, turns: usize) -> Self {
+        self.settings.input_buffer_len = turns;
+        self
+    }
+
+    pub fn apply_turns_on_frame(mut self, enabled: bool) -> Self {
+        self.settings.apply_turns_on_frame = enabled;
+        self
+    }
+
+    pub fn build(self) -> Result<Settings, SettingsError> {
+        let (columns, rows) = self.settings.grid;
+        if !GRID_RANGE.contains(&columns) || !GRID_RANGE.contains(&rows) {
+            return Err(SettingsError::GridSize(self.settings.grid));
+        }
+        if !CELL_SIZE_RANGE.contains(&self.settings.cell_size) {
+            return Err(SettingsError::CellSize(self.settings.cell_size));
+        }
+        let interval = self.settings.move_interval;
+        if !interval.is_finite() || interval <= 0.0 {
+            return Err(SettingsError::MoveInterval(interval));
+        }
+        if let Some(secs) = self.settings.time_limit
+            && (!secs.is_finite() || secs <= 0.0)
+        {
+            return Err(SettingsError::TimeLimit(secs));
+        }
+        if let Some(secs) = self.settings.arena_shrink
+            && (!secs.is_finite() || secs <= 0.0)
+        {
+            return Err(SettingsError::ArenaShrink(secs));
+        }
+        if self.settings.food_wander == Some(0) {
+            return Err(SettingsError::FoodWander);
+        }
+        let density = self.settings.wall_density;
+        if !(0.0..=MAX_WALL_DENSITY).contains(&density) {
+            return Err(SettingsError::WallDensity(density));
+        }
+        if self.settings.food_spawn == (FoodSpawn::NearHead { radius: 0 }) {
+            return Err(SettingsError::MagnetRadius);
+        }
+        // Stopping time altogether would be a pause in disguise, which the game can't get out of
+        let scale = self.settings.time_scale;
+        if !scale.is_finite() || scale <= 0.0 {
+            return Err(SettingsError::TimeScale(scale));
+        }
+        if !UI_SCALE_RANGE.contains(&self.settings.ui_scale) {
+            return Err(SettingsError::UiScale(self.settings.ui_scale));
+        }
+        if self.settings.grow_every == 0 {
+            return Err(SettingsError::GrowEvery);
+        }
+        if let Some(decay) = self.settings.scoring.food_decay
+            && (decay.max < decay.floor || decay.half_life == 0)
+        {
+            return Err(SettingsError::FoodDecay);
+        }
+        if let Some(len) = self.settings.max_length
+            && len < START_LENGTH
+        {
+            return Err(SettingsError::MaxLength(len));
+        }
+        if self.settings.input_buffer_len == 0 {
+            return Err(SettingsError::InputBuffer);
+        }
+        if let Some(level) = self.settings.campaign.iter().find(|level| level.grid != self.settings.grid) {
+            return Err(SettingsError::LevelSize(level.name.clone(), level.grid, self.settings.grid));
+        }
+        if !self.settings.campaign.is_empty() && self.settings.players != Players::One {
+            return Err(SettingsError::CampaignPlayers);
+        }
+        let weights = self.settings.food_table.iter().map(|&(_, weight)| weight);
+        if weights.clone().any(|weight| !weight.is_finite() || weight < 0.0) || weights.sum::<f64>() <= 0.0 {
+            return Err(SettingsError::FoodTable);
+        }
+        let start = Snake::new(self.settings.start_dir.clone(), self.settings.grid);
+        let layout = self.settings.layout();
+        let mut placed = HashSet::new();
+        for &(_, pos) in &self.settings.placed_food {
+            if !layout.contains(pos) || start.occupies(pos) || !placed.insert(pos) {
+                return Err(SettingsError::PlacedFood(pos));
+            }
+        }
+        let room = layout.open_cells() - START_LENGTH - placed.len();
+        if self.settings.food_count == 0 || self.settings.food_count > room {
+            return Err(SettingsError::FoodCount(self.settings.food_count));
+        }
+        Ok(self.settings)
+    }
+}
+
+// Extension point for game modes. Each hook runs at a fixed point of the game loop and may change the
+// game freely; every hook does nothing by default. Classic play is no modes at all. A fork adds a mode
+// by implementing this and pushing it in `modes_for`.
+pub trait GameMode {
+    // Every move, right after the snake moved and before anything it ran into is resolved
+    fn on_tick(&mut self, _game: &mut Game) {}
+    // Every frame while running, with the game seconds that passed
+    fn on_clock(&mut self, _game: &mut Game, _dt: f64) {}
+    // Before new food is placed; remove cells from `candidates` to keep food off them. If none are left,
+    // no food is added.
+    fn on_spawn_food(&mut self, _game: &Game, _candidates: &mut Vec<(i32, i32)>) {}
+    // After food was eaten and scored
+    fn on_food_eaten(&mut self, _game: &mut Game, _food: Food) {}
+    // When the run is about to end with `cause`; return false to keep it going. The mode must then have
+    // moved the snake out of harm's way itself.
+    fn on_death(&mut self, _game: &mut Game, _cause: DeathCause) -> bool {
+        true
+    }
+}
+
+// Edges lead to the opposite edge, optionally at a cost. Score can't go below 0, and the snake doesn't
+// shrink below its starting length.
+struct Wrap {
+    penalty: Option<(u32, u32)>, // Points and segments lost per crossing
+}
+
+impl GameMode for Wrap {
+    fn on_tick(&mut self, game: &mut Game) {
+        let layout = game.layout();
+        if let Some(rival) = game.rival.as_mut()
+            && wrap_head(&mut rival.snake, layout)
+            && let Some((point_cost, shrink)) = self.penalty
+        {
+            rival.score -= point_cost.min(rival.score);
+            rival.snake.shrink(shrink);
+            game.wrap_flash = PHASE_FLASH_MOVES;
+        }
+        if !wrap_head(&mut game.snake, layout) {
+            return;
+        }
+
+        if let Some((point_cost, shrink)) = self.penalty {
+            let lost = point_cost.min(game.score);
+            game.score -= lost;
+            game.breakdown.penalty += lost;
+            game.snake.shrink(shrink);
+            game.wrap_flash = PHASE_FLASH_MOVES;
+        }
+    }
+}
+
+// Bring a head that went off the edge back in at the opposite edge; returns whether it did
+fn wrap_head(snake: &mut Snake, layout: BoardLayout) -> bool {
+    let head = snake.head();
+    if layout.contains(head) {
+        return false;
+    }
+    snake.move_head(layout.wrap(head));
+    true
+}
+
+// Score as much as possible before the clock in `Game::time_left` runs out
+struct TimeAttack;
+
+impl GameMode for TimeAttack {
+    fn on_clock(&mut self, game: &mut Game, dt: f64) {
+        let Some(left) = game.time_left.as_mut() else {
+            return;
+        };
+        // Only redraw when the clock shows a different tenth of a second
+        let shown = |secs: f64| (secs * 10.0).round();
+        let before = shown(*left);
+        *left = (*left - dt).max(0.0);
+        game.dirty |= shown(*left) != before;
+        if *left == 0.0 {
+            game.game_over(Some(DeathCause::TimeUp));
+        }
+    }
+}
+
+// The walls close in by a ring of cells every so often, until the arena is MIN_ARENA_SPAN cells across.
+// A head caught in the ring as it closes runs into the wall.
+struct ShrinkingArena {
+    every: u32, // Moves between rings
+}
+
+impl GameMode for ShrinkingArena {
+    fn on_tick(&mut self, game: &mut Game) {
+        let Some(moves) = game.shrink_in.as_mut() else {
+            return;
+        };
+        *moves -= 1;
+        if *moves > 0 {
+            return;
+        }
+        game.close_ring();
+        let room_left = game.layout().shrunk(1).open_span() >= MIN_ARENA_SPAN;
+        game.shrink_in = room_left.then_some(self.every);
+    }
+}
+
+// Food steps to a neighboring cell every so many moves, and runs from a snake's head that gets close
+struct WanderingFood {
+    every: u32, // Moves between steps
+}
+
+impl GameMode for WanderingFood {
+    fn on_tick(&mut self, game: &mut Game) {
+        if game.moves.is_multiple_of(self.every) {
+            game.wander_food();
+        }
+    }
+}
+
+// Moves between the rings of a shrinking arena closing, or None when the arena stays put. The schedule counts
+// moves rather than frame time, so a seeded run shrinks the same way every time.
+fn moves_per_shrink(settings: &Settings) -> Option<u32> {
+    let secs = settings.arena_shrink?;
+    let room = settings.layout().shrunk(1).open_span() >= MIN_ARENA_SPAN;
+    room.then(|| (secs / settings.move_interval).ceil().max(1.0) as u32)
+}
+
+// The modes a run with these settings plays by, fresh for every run
+fn modes_for(settings: &Settings) -> Vec<Box<dyn GameMode>> {
+    let mut modes: Vec<Box<dyn GameMode>> = Vec::new();
+    match settings.wall_mode {
+        WallMode::Lethal => {},
+        WallMode::Wrap => modes.push(Box::new(Wrap { penalty: None })),
+        WallMode::WrapPenalty { point_cost, shrink } => modes.push(Box::new(Wrap { penalty: Some((point_cost, shrink)) })),
+    }
+    if settings.time_limit.is_some() {
+        modes.push(Box::new(TimeAttack));
+    }
+    if let Some(every) = moves_per_shrink(settings) {
+        modes.push(Box::new(ShrinkingArena { every }));
+    }
+    if let Some(every) = settings.food_wander {
+        modes.push(Box::new(WanderingFood { every }));
+    }
+    modes.extend(settings.mods.iter().map(Mod::mode));
+    modes
+}
+
+// Player two in a two-player run, steered with W, A, S and D or by the computer. The food is shared; phase charges, power-ups
+// and rewinds are left out of two-player runs, and only one-player runs count for the high score and the
+// leaderboard.
+#[derive(Clone)]
+pub struct Rival {
+    pub snake: Snake,
+    pub score: u32,
+    pub foods_eaten: u32,
+    pub queued_turns: VecDeque<Direction>, // Turns pressed ahead of the snake, one applied per move
+    pub death_cause: Option<DeathCause>, // None while player two is still alive
+}
+
+// Steers a snake: the players at the keys, the computer opponent, the demo's autopilot or a bot. Asked once
+// per move, before the snakes move; None keeps the snake going the way it is.
+pub trait Controller {
+    fn next_move(&mut self, view: &GameView) -> Option<Direction>;
+}
+
+// The game as a controller sees it when asked for a move, from the side of the snake it steers
+pub struct GameView<'a> {
+    pub game: &'a Game,
+    rival: bool, // Steering player two's snake rather than player one's
+}
+
+impl<'a> GameView<'a> {
+    // The snake being steered
+    pub fn snake(&self) -> &'a Snake {
+        match (&self.game.rival, self.rival) {
+            (Some(rival), true) => &rival.snake,
+            _ => &self.game.snake,
+        }
+    }
+
+    // The other player's snake, in a two-player run
+    pub fn other(&self) -> Option<&'a Snake> {
+        if self.rival {
+            Some(&self.game.snake)
+        } else {
+            self.game.rival.as_ref().map(|rival| &rival.snake)
+        }
+    }
+
+    // Turns pressed ahead of the steered snake; the first one is due on this move
+    pub fn queued_turns(&self) -> &'a VecDeque<Direction> {
+        match (&self.game.rival, self.rival) {
+            (Some(rival), true) => &rival.queued_turns,
+            _ => &self.game.queued_turns,
+        }
+    }
+
+    // Whether the steered head moving into `cell` on this move would be fatal
+    pub fn is_fatal(&self, cell: (i32, i32)) -> bool {
+        if self.rival {
+            self.game.is_fatal_for_rival(cell)
+        } else {
+            self.game.is_fatal(cell)
+        }
+    }
+
+    // Towards the nearest food along a shortest path that keeps out of `blocked` cells, or else towards
+    // the most open space
+    pub fn seek_food(&self, blocked: impl Fn((i32, i32)) -> bool + Copy) -> Direction {
+        seek_food(self.game, self.snake(), blocked)
+    }
+}
+
+// A player at the keyboard, a gamepad or the other end of a network run: plays the turns they pressed, one
+// per move
+pub struct Keys;
+
+impl Controller for Keys {
+    fn next_move(&mut self, view: &GameView) -> Option<Direction> {
+        view.queued_turns().front().cloned()
+    }
+}
+
+// Plays by itself, the way `Game::autopilot` does: the start screen's demo and the benchmark
+pub struct Autopilot;
+
+impl Controller for Autopilot {
+    fn next_move(&mut self, view: &GameView) -> Option<Direction> {
+        Some(view.seek_food(|cell| view.is_fatal(cell)))
+    }
+}
+
+// The computer opponent: races player one to the nearest food along a shortest path, the same way the
+// autopilot plays, and otherwise heads for the most open space. It keeps out of the cells player one's
+// head could move into next, unless there is no other way, so the two don't keep meeting head-on.
+pub struct Chaser;
+
+impl Controller for Chaser {
+    fn next_move(&mut self, view: &GameView) -> Option<Direction> {
+        let other = view.other().map(|snake| snake.head());
+        let near_head = |(x, y): (i32, i32)| other.is_some_and(|head| (x - head.0).abs() + (y - head.1).abs() == 1);
+        let wary = view.seek_food(|cell| view.is_fatal(cell) || near_head(cell));
+        let (dx, dy) = wary.delta();
+        let next = (view.snake().head().0 + dx, view.snake().head().1 + dy);
+        if !view.is_fatal(next) && !near_head(next) {
+            return Some(wary);
+        }
+        Some(view.seek_food(|cell| view.is_fatal(cell)))
+    }
+}
+
+pub struct Game {
+    pub snake: Snake, // Player one's snake, the only one outside two-player runs
+    pub rival: Option<Rival>, // Player two, in a two-player run
+    controller: Box<dyn Controller>, // Steers player one: the keys, or the autopilot in the demo
+    rival_controller: Box<dyn Controller>, // Steers player two: the keys, or the computer
+    pub foods: Vec<Food>, // Food on the board, in the order it was placed
+    pub score: u32,
+    breakdown: ScoreBreakdown, // Where this run's score came from
+    pub combo: u32, // Multiplier on food points, 1 without a combo going
+    pub combo_moves: u32, // Moves left to eat the next food and raise the multiplier; when none are left it drops by one
+    pub foods_eaten: u32,
+    pub moves: u32, // Moves made this run
+    pub high_score: u32, // Best score so far in this kind of run: time attack or not
+    pub stats: Stats, // High scores of every kind of run and lifetime totals, as kept between sessions
+    pub state: GameState,
+    pub settings: Settings,
+    wrap_penalty: (u32, u32), // Point cost and shrink used when the start screen switches to penalty wrapping
+    time_attack: f64, // Seconds a time attack run lasts when the start screen switches to it
+    pub death_cause: Option<DeathCause>, // None while alive, or when the run ended without a collision
+    reported: bool, // This run was already wrapped up: length bonus awarded, high score raised, lifetime stats counted and JSON line queued
+    pub beat_high: bool, // Set once per run, the moment the score passes the previous high score
+    pub celebration_moves: u32, // Moves left to show the "new high score" banner
+    visits: Vec<u32>, // How often the head entered each cell this run, row by row
+    pub notice: Option<String>, // Status line for the game over screen (e.g. heatmap export result)
+    pub time_left: Option<f64>, // Seconds remaining in time attack, None when there is no limit
+    pub obstacles: HashSet<(i32, i32)>, // Lethal wall cells inside the playfield
+    pub portals: Vec<[(i32, i32); 2]>, // Pairs of portal cells; a head going into either end comes out of the other
+    pub closed_rings: i32, // Rings of cells a shrinking arena has walled off so far this run
+    pub shrink_in: Option<u32>, // Moves until the next ring closes, None when the arena is done shrinking or doesn't
+    pub queued_turns: VecDeque<Direction>, // Turns pressed ahead of the snake, one applied per move
+    pub phase_pickup: Option<(i32, i32)>, // Collectible phase charge lying on the board, if any
+    pub phase_charges: u32, // Collected phase charges not used yet
+    pub phase_armed: bool, // The next wall or rock contact spends a charge instead of killing the snake
+    pub phase_flash: u32, // Moves left to flash the playfield after a successful phase
+    pub wrap_flash: u32, // Moves left to flash the border after a penalty wrap
+    pub power_up: Option<(PowerUp, (i32, i32))>, // Power-up lying on the board, if any
+    pub effects: Vec<(PowerUp, u32)>, // Power-ups in effect, with the moves each has left
+    pub particles: Particles, // Sparks from the food eaten lately
+    pub run_seed: u64, // Seed of this run: the one from the settings, or a fresh random one per run
+    rng: rand_chacha::ChaCha12Rng, // Source of all of the game's randomness, seeded with `run_seed`. The same generator as rand's StdRng, but one whose place in its stream can be saved.
+    pub elapsed: f64, // Seconds this run has been going
+    #[cfg(not(target_arch = "wasm32"))]
+    clipboard: Option<arboard::Clipboard>, // Opened on first use; on some platforms copied text only lasts as long as this does
+    pub time_scale: f64, // Current game speed, starting at the configured one and changed with the number keys
+    pub move_progress: f64, // How far the frontend's clock is towards the next move, from 0 to 1, for smooth movement
+    pub countdown: f64, // Real seconds left before the snake moves, after the run started or resumed; 0 while it moves
+    json_lines: Vec<String>, // Finished-run JSON lines waiting for the frontend to print them
+    pub dirty: bool, // Something visible changed since the last frame was drawn
+    history: VecDeque<RunSnapshot>, // Run state before each of the last few seconds of moves, oldest first
+    pub rewinds_left: u32, // Rewinds still available this run
+    pub help_shown: bool, // The controls overlay is open, which pauses the game
+    pub debug_shown: bool, // The F3 debug overlay is open
+    pub frame_times: FrameTimes, // Filled in by the frontend for the debug overlay
+    modes: Vec<Box<dyn GameMode>>, // Rules on top of classic play, from the settings
+    pub leaderboard: Leaderboard, // Best runs so far, empty when the settings keep no leaderboard
+    pub leaderboard_shown: bool, // The top 10 is open over the start screen
+    pub online: Option<OnlineBoard>, // The global leaderboard, when the settings name one
+    pub global_shown: bool, // The open top 10 is the global one
+    pub achievements: Achievements, // Unlocked so far, empty when the settings keep no achievements
+    pub achievements_shown: bool, // The list of achievements is open over the start screen
+    pub stats_shown: bool, // The lifetime stats are open over the start screen
+    pub menu: Menu<MenuItem>, // The title screen's menu, on the page that's open
+    pub menu_page: MenuPage, // The menu's page that's open
+    pub rebinding: Option<Action>, // On the keys page, the action waiting for its new key
+    pub quit_requested: bool, // Quit was picked on the menu; the frontend closes the game
+    pub resized: bool, // The board changed size on the options page; the frontend fits its window to it and clears this
+    pub toasts: VecDeque<Achievement>, // Achievements unlocked lately, oldest first, each announced for TOAST_SECONDS
+    pub toast_time: f64, // Seconds the oldest one has been announced
+    pub initials: Option<String>, // Letters typed so far while this run is being entered on the leaderboard
+    signed: bool, // This run was already offered its place on the leaderboard
+    pub demo: Option<Box<Game>>, // Autopilot game playing behind the title after a while on the start screen
+    pub level: usize, // Index of the campaign level being played; restarting replays it
+    pub editor: Option<Editor>, // The level editor, while it's open over the start screen
+    pub idle: f64, // Seconds spent on the start screen since the last key press
+    pub outbox: Vec<Direction>, // In a network run, turns pressed on this machine that the other one hasn't been sent yet
+    pub restart_requested: bool, // In a network run, restart was pressed on the game over screen; both games restart together
+    saved_run: Option<SavedRun>, // Run left mid-way last time, which the start screen offers to continue
+}
+
+// Outcome of one run. Benchmark games that hit the move cap are "Unfinished".
+pub struct RunStats {
+    pub seed: u64,
+    pub score: u32,
+    pub length: usize,
+    pub moves: u32,
+    pub cause: String,
+}
+
+impl RunStats {
+    // The run as a single line of JSON
+    pub fn json(&self) -> String {
+        format!(
+            "{{\"score\":{},\"length\":{},\"ticks\":{},\"cause\":\"{}\",\"seed\":{}}}",
+            self.score, self.length, self.moves, self.cause, self.seed,
+        )
+    }
+}
+
+// Everything a move can change, captured so a death can be rewound or a run saved to be continued later
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+struct RunSnapshot {
+    snake: Snake,
+    foods: Vec<Food>,
+    score: u32,
+    breakdown: ScoreBreakdown,
+    combo: u32,
+    combo_moves: u32,
+    foods_eaten: u32,
+    moves: u32,
+    beat_high: bool,
+    celebration_moves: u32,
+    visits: Vec<u32>,
+    time_left: Option<f64>,
+    closed_rings: i32,
+    shrink_in: Option<u32>,
+    portals: Vec<[(i32, i32); 2]>,
+    elapsed: f64,
+    queued_turns: VecDeque<Direction>,
+    phase_pickup: Option<(i32, i32)>,
+    phase_charges: u32,
+    phase_armed: bool,
+    phase_flash: u32,
+    wrap_flash: u32,
+    power_up: Option<(PowerUp, (i32, i32))>,
+    effects: Vec<(PowerUp, u32)>,
+    #[serde(skip, default = "unplaced_rng")]
+    rng: rand_chacha::ChaCha12Rng, // Place in the random numbers, so food spawns the same way after a rewind. A saved run keeps it as a seed and word position instead.
+}
+
+// A run left mid-way, kept on disk until it's continued from the start screen. Besides what a rewind puts
+// back, it holds the start screen's choices and what stays the same all run: the walls, the place in the
+// random numbers and the rewinds left. The rewind history itself isn't kept.
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct SavedRun {
+    grid: (i32, i32), // Board it was played on; a run can only be continued on a board of the same size
+    wall_mode: WallMode,
+    time_limit: Option<f64>,
+    level: usize,
+    run_seed: i64, // The bits of the u64 seed, since TOML integers are signed
+    rng_seed: [u8; 32],
+    rng_word: u64, // Place in the random number stream
+    obstacles: Vec<(i32, i32)>,
+    rewinds_left: u32,
+    run: RunSnapshot,
+}
+
+impl SavedRun {
+    // Where interactive play keeps a run left mid-way, or None when the platform has no data directory
+    pub fn path() -> Option<std::path::PathBuf> {
+        Some(dirs::data_dir()?.join("copperhead").join("saved-run.toml"))
+    }
+
+    // Read the run saved at `path`, or None when there is none
+    fn load(path: &std::path::Path) -> Result<Option<SavedRun>, String> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.to_string()),
+        };
+        toml::from_str(&text).map(Some).map_err(|err| err.to_string())
+    }
+
+    fn save(&self, path: &std::path::Path) -> std::io::Result<()> {
+        let text = toml::to_string(self).map_err(std::io::Error::other)?;
+        write_atomically(path, &text)
+    }
+}
+
+// Stands in for the snapshot's generator while a saved run is read back, until `continue_run` puts the saved one in
+fn unplaced_rng() -> rand_chacha::ChaCha12Rng {
+    rand_chacha::ChaCha12Rng::seed_from_u64(0)
+}
+
+impl Game {
+    pub fn new(settings: &Settings) -> Self {
+        let run_seed = settings.seed.unwrap_or_else(rand::random);
+        let stats = settings.stats_file.as_deref().map_or_else(Stats::default, load_stats);
+        let mut game = Game {
+            snake: Snake::new(settings.start_dir.clone(), settings.grid),
+            rival: None,
+            controller: Box::new(Keys),
+            rival_controller: Box::new(Keys),
+            foods: Vec::new(),
+            score: 0,
+            breakdown: ScoreBreakdown::default(),
+            combo: 1,
+            combo_moves: 0,
+            foods_eaten: 0,
+            moves: 0,
+            high_score: stats.best(settings.time_limit.is_some()),
+            stats,
+            state: GameState::Menu,
+            settings: settings.clone(),
+            wrap_penalty: match settings.wall_mode {
+                WallMode::WrapPenalty { point_cost, shrink } => (point_cost, shrink),
+                _ => DEFAULT_WRAP_PENALTY,
+            },
+            time_attack: settings.time_limit.unwrap_or(TIME_ATTACK_SECONDS),
+            death_cause: None,
+            reported: false,
+            beat_high: false,
+            celebration_moves: 0,
+            visits: vec![0; (settings.grid.0 * settings.grid.1) as usize],
+            notice: None,
+            time_left: settings.time_limit,
+            obstacles: HashSet::new(),
+            portals: Vec::new(),
+            closed_rings: 0,
+            shrink_in: moves_per_shrink(settings),
+            queued_turns: VecDeque::new(),
+            phase_pickup: None,
+            phase_charges: 0,
+            phase_armed: false,
+            phase_flash: 0,
+            wrap_flash: 0,
+            power_up: None,
+            effects: Vec::new(),
+            particles: Particles::new(run_seed),
+            run_seed,
+            rng: rand_chacha::ChaCha12Rng::seed_from_u64(run_seed),
+            elapsed: 0.0,
+            #[cfg(not(target_arch = "wasm32"))]
+            clipboard: None,
+            time_scale: settings.time_scale,
+            move_progress: 0.0,
+            countdown: 0.0,
+            json_lines: Vec::new(),
+            dirty: true,
+            history: VecDeque::new(),
+            rewinds_left: settings.rewinds,
+            help_shown: false,
+            debug_shown: false,
+            frame_times: FrameTimes::default(),
+            leaderboard: settings.leaderboard_file.as_deref().map_or_else(Leaderboard::default, load_leaderboard),
+            leaderboard_shown: false,
+            online: settings.online_server.clone().map(|server| OnlineBoard::new(server, settings.player_name.clone())),
+            global_shown: false,
+            achievements: settings.achievements_file.as_deref().map_or_else(Achievements::default, load_achievements),
+            achievements_shown: false,
+            stats_shown: false,
+            menu: Menu::new(Vec::new()),
+            menu_page: MenuPage::Main,
+            rebinding: None,
+            quit_requested: false,
+            resized: false,
+            toasts: VecDeque::new(),
+            toast_time: 0.0,
+            initials: None,
+            signed: false,
+            modes: modes_for(settings),
+            demo: None,
+            level: 0,
+            editor: None,
+            idle: 0.0,
+            outbox: Vec::new(),
+            restart_requested: false,
+            saved_run: settings.save_file.as_deref().and_then(|path| load_saved_run(path, settings)),
+        };
+        game.refresh_menu();
+        game.spawn_snakes();
+        game.place_obstacles();
+        game.place_portals();
+        game.lay_out_food();
+        game
+    }
+
+    // Headline and explanation for how the run ended
+    pub fn outcome(&self) -> (&'static str, &'static str) {
+        // Two players: whoever is left standing wins, and when time or room runs out, the higher score does
+        if let Some(rival) = &self.rival {
+            let (one_wins, two_wins, one_crashed, two_crashed) = match (self.settings.players, self.settings.net_side) {
+                (Players::Computer, _) => ("YOU WIN!", "COMPUTER WINS!", "You crashed", "The computer crashed"),
+                (_, Some(Side::Host)) => ("YOU WIN!", "THEY WIN!", "You crashed", "They crashed"),
+                (_, Some(Side::Guest)) => ("THEY WIN!", "YOU WIN!", "They crashed", "You crashed"),
+                _ => ("PLAYER 1 WINS!", "PLAYER 2 WINS!", "Player 1 crashed", "Player 2 crashed"),
+            };
+            let one_lost = self.death_cause.is_some_and(|cause| cause != DeathCause::TimeUp);
+            return match (one_lost, rival.death_cause.is_some()) {
+                (true, true) => ("DRAW!", "Both snakes crashed"),
+                (true, false) => (two_wins, one_crashed),
+                (false, true) => (one_wins, two_crashed),
+                (false, false) => {
+                    let cause = if self.death_cause.is_some() { "Time's up" } else { "Nowhere left to go" };
+                    match self.score.cmp(&rival.score) {
+                        std::cmp::Ordering::Greater => (one_wins, cause),
+                        std::cmp::Ordering::Less => (two_wins, cause),
+                        std::cmp::Ordering::Equal => ("DRAW!", cause),
+                    }
+                },
+            };
+        }
+        // Filling the whole board is a win; anything else means the snake got itself stuck
+        let cleared = self.snake.body().len() >= self.layout().open_cells();
+        let over = if cleared { "CLEARED!" } else { "COILED!" };
+        let cause = match self.death_cause {
+            Some(cause) => cause.message(),
+            None if cleared => "You filled the board",
+            None => "Nowhere left to go",
+        };
+        (over, cause)
+    }
+
+    // The run at a glance, for the results screen and the copied summary. Lines for features that are
+    // turned off are left out.
+    pub fn results(&self) -> Vec<(&'static str, String)> {
+        // Time attack runs are compared by pace as well as total
+        let score = match (self.settings.time_limit, self.time_left) {
+            (Some(limit), Some(left)) if limit > left => {
+                format!("{} ({:.1}/min)", self.score, self.score as f64 * 60.0 / (limit - left))
+            },
+            _ => self.score.to_string(),
+        };
+        let mut lines = match &self.rival {
+            Some(rival) if self.settings.players == Players::Computer => vec![("Your score", score), ("Computer score", rival.score.to_string())],
+            Some(rival) => vec![("Player 1 score", score), ("Player 2 score", rival.score.to_string())],
+            None => vec![("Score", score)],
+        };
+        let scoring = self.settings.scoring;
+        if scoring.survival_every > 0 || scoring.length_bonus > 0 {
+            let breakdown = format!("{} + {} + {}", self.breakdown.food, self.breakdown.survival, self.breakdown.length);
+            lines.push(("Food+time+size", breakdown));
+        }
+        if self.breakdown.penalty > 0 {
+            lines.push(("Wrap penalties", format!("-{}", self.breakdown.penalty)));
+        }
+        if self.breakdown.mods > 0 {
+            lines.push(("From mods", self.breakdown.mods.to_string()));
+        }
+        lines.push(("Highest", self.high_score.to_string()));
+        lines.push(("Length", self.snake.body().len().to_string()));
+        lines.push(("Food eaten", self.foods_eaten.to_string()));
+        lines.push(("Time survived", format!("{:.1}s", self.elapsed)));
+        lines.push(("Seed", self.run_seed.to_string()));
+        lines
+    }
+
+    // Put a plain text summary of the run on the clipboard, to paste and share
+    pub fn copy_results(&mut self) {
+        let (over, cause) = self.outcome();
+        let mut summary = format!("Copperhead: {} {}\n", over, cause);
+        for (label, value) in self.results() {
+            summary += &format!("{}: {}\n", label, value);
+        }
+
+        self.notice = Some(match self.set_clipboard(summary) {
+            Ok(()) => "Results copied to the clipboard".to_string(),
+            Err(err) => format!("Could not copy results: {}", err),
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn set_clipboard(&mut self, text: String) -> Result<(), String> {
+        let clipboard = match self.clipboard.take() {
+            Some(clipboard) => Ok(clipboard),
+            None => arboard::Clipboard::new(),
+        };
+        let result = clipboard.and_then(|mut clipboard| {
+            let copied = clipboard.set_text(text);
+            self.clipboard = Some(clipboard);
+            copied
+        });
+        result.map_err(|err| err.to_string())
+    }
+
+    // The browser only hands out its clipboard to page scripts, not to the game
+    #[cfg(target_arch = "wasm32")]
+    fn set_clipboard(&mut self, _text: String) -> Result<(), String> {
+        Err("the browser version has no clipboard".to_string())
+    }
+
+    // Make one move without a frontend: steer player one towards `input`, if given, the way `turn` does,
+    // run the clock for as long as the move takes and move. A run that hasn't started yet starts, without a
+    // countdown. Bots, benchmarks and anything replaying a run only need this, with None for `input` when a
+    // controller set with `set_controller` steers; none of it depends on Piston.
+    pub fn tick(&mut self, input: Option<Direction>) -> TickResult {
+        if self.state == GameState::Menu {
+            self.state = GameState::Running;
+        }
+        self.countdown = 0.0;
+        if self.state != GameState::Running {
+            return TickResult::Stopped;
+        }
+        if let Some(dir) = input {
+            self.turn(dir);
+        }
+        let foods_eaten = self.foods_eaten;
+        self.tick_clock(self.settings.move_interval / self.move_speed());
+        self.update();
+        match self.state {
+            GameState::GameOver => TickResult::Over(self.death_cause),
+            GameState::LevelComplete => TickResult::LevelComplete,
+            _ if self.foods_eaten > foods_eaten => TickResult::Ate,
+            _ => TickResult::Moved,
+        }
+    }
+
+    pub fn update(&mut self) {
+        // On the start screen only the demo moves, if one is playing
+        if self.state == GameState::Menu {
+            self.step_demo();
+        }
+        // Don't update if game is not running, or the countdown before it goes isn't over
+        if self.state != GameState::Running || self.countdown > 0.0 {
+            return;
+        }
+        self.dirty = true;
+
+        if self.rewinds_left > 0 && self.rival.is_none() {
+            self.record_snapshot();
+        }
+        // Each queued turn is used up on its move, whether the snake's controller plays it or not
+        let one = self.next_move(false);
+        let two = if self.rival.is_some() { self.next_move(true) } else { None };
+        self.queued_turns.pop_front();
+        if let Some(dir) = one {
+            self.turn(dir);
+        }
+        if let Some(rival) = self.rival.as_mut() {
+            rival.queued_turns.pop_front();
+            if let Some(dir) = two {
+                rival.snake.steer(dir);
+            }
+        }
+        self.celebration_moves = self.celebration_moves.saturating_sub(1);
+        self.phase_flash = self.phase_flash.saturating_sub(1);
+        self.wrap_flash = self.wrap_flash.saturating_sub(1);
+        // Timed food runs out before the snake gets to move onto it
+        for food in &mut self.foods {
+            food.moves_left = food.moves_left.map(|moves| moves.saturating_sub(1));
+        }
+        self.foods.retain(|food| food.moves_left != Some(0));
+        for (_, moves) in &mut self.effects {
+            *moves -= 1;
+        }
+        self.effects.retain(|&(_, moves)| moves > 0);
+
+        self.snake.update();
+        if let Some(rival) = self.rival.as_mut() {
+            rival.snake.update();
+        }
+        self.each_mode(|mode, game| mode.on_tick(game));
+        // A head that went into a portal comes out of its twin, still heading the same way
+        if let Some(exit) = self.portal_exit(self.snake.head()) {
+            self.snake.move_head(exit);
+        }
+        let rival_exit = self.rival.as_ref().and_then(|rival| self.portal_exit(rival.snake.head()));
+        if let (Some(rival), Some(exit)) = (self.rival.as_mut(), rival_exit) {
+            rival.snake.move_head(exit);
+        }
+        if self.phase_armed && matches!(self.collision(), Some(DeathCause::Wall | DeathCause::Obstacle)) {
+            self.phase();
+        }
+        if self.phase_pickup == Some(self.snake.head()) {
+            self.phase_pickup = None;
+            self.phase_charges += 1;
+        }
+        if let Some((power_up, pos)) = self.power_up
+            && pos == self.snake.head()
+        {
+            self.power_up = None;
+            self.apply_power_up(power_up);
+        }
+        if let Some(i) = self.settings.layout().index(self.snake.head()) {
+            self.visits[i] += 1;
+        }
+        self.moves += 1;
+        if self.settings.power_ups && self.rival.is_none() && self.moves.is_multiple_of(POWER_UP_EVERY) && self.power_up.is_none() {
+            let spots = self.free_cells();
+            let power_up = *PowerUp::ALL.choose(&mut self.rng).unwrap_or(&PowerUp::Speed);
+            self.power_up = spots.choose(&mut self.rng).map(|&pos| (power_up, pos));
+        }
+        let survival_every = self.settings.scoring.survival_every;
+        if survival_every > 0 && self.moves.is_multiple_of(survival_every) {
+            self.award(ScoreSource::Survival, 1);
+        }
+        // Food
+        if let Some(i) = self.foods.iter().position(|food| food.pos == self.snake.head()) {
+            let food = self.foods.remove(i);
+            self.foods_eaten += 1;
+            self.particles.burst(food.pos);
+            // Worth what it showed before this move, which is already counted
+            let (per_food, growth) = (self.food_value(&food, self.moves - 1), self.settings.growth_per_food);
+            self.feed_combo(food.kind);
+            if food.kind == FoodKind::Poison {
+                self.snake.shrink(POISON_SHRINK);
+            } else {
+                self.award(ScoreSource::Food, food.kind.points(per_food) * self.combo);
+                self.grow_snake(food.kind.growth(growth));
+            }
+            self.each_mode(|mode, game| mode.on_food_eaten(game, food));
+
+            if food.respawns && !self.spawn_food() && self.foods.is_empty() {
+                // No free cell is reachable anymore (board cleared or sealed off), so end the run fairly
+                self.game_over(None);
+                return;
+            }
+            if self.foods_eaten.is_multiple_of(PHASE_PICKUP_EVERY) && self.phase_pickup.is_none() && self.rival.is_none() {
+                let spots = self.free_cells();
+                self.phase_pickup = spots.choose(&mut self.rng).copied();
+            }
+            self.bring_out_golden_food();
+        } else {
+            self.cool_combo();
+        }
+        if !self.feed_rival() {
+            return;
+        }
+
+        let rival_cause = self.rival_collision();
+        if let Some(rival) = self.rival.as_mut() {
+            rival.death_cause = rival_cause;
+        }
+        if let Some(cause) = self.collision() {
+            self.game_over(Some(cause));
+        } else if rival_cause.is_some() {
+            self.game_over(None);
+        } else if self.settings.end_when_trapped && self.is_trapped() {
+            self.game_over(Some(DeathCause::Trapped));
+        } else if self.current_level().is_some_and(|level| self.score >= level.target) {
+            self.state = GameState::LevelComplete;
+        }
+        self.check_achievements();
+    }
+
+    // The campaign level being played, if this is a campaign
+    pub fn current_level(&self) -> Option<&Level> {
+        self.settings.campaign.get(self.level)
+    }
+
+    // Whether the level just cleared was the campaign's last
+    pub fn campaign_won(&self) -> bool {
+        self.state == GameState::LevelComplete && self.level + 1 >= self.settings.campaign.len()
+    }
+
+    // Go on from a cleared level to the next one, or after the last one back to the first, and start playing
+    pub fn next_level(&mut self) {
+        if self.state != GameState::LevelComplete {
+            return;
+        }
+        self.level = (self.level + 1) % self.settings.campaign.len();
+        self.reset();
+        self.play();
+    }
+
+    // The number the countdown shows, from the settings' seconds down to 1, while a run waits for it
+    pub fn countdown_number(&self) -> Option<u32> {
+        (self.state == GameState::Running && self.countdown > 0.0).then(|| self.countdown.ceil() as u32)
+    }
+
+    // Start the run, or resume it from the pause, after counting down. A network run goes at once: its two
+    // machines move in lockstep, and the clock that counts down doesn't run in it.
+    pub fn play(&mut self) {
+        self.state = GameState::Running;
+        self.countdown = if self.settings.net_side.is_none() { self.settings.countdown as f64 } else { 0.0 };
+        self.dirty = true;
+    }
+
+    // Eating within the combo window raises the multiplier, up to MAX_COMBO, and opens a fresh window.
+    // Poison ends the combo. Two-player runs have no combos, since only player one could build them.
+    fn feed_combo(&mut self, kind: FoodKind) {
+        let window = self.settings.scoring.combo_window;
+        if window == 0 || self.rival.is_some() {
+            return;
+        }
+        if kind == FoodKind::Poison {
+            self.combo = 1;
+            self.combo_moves = 0;
+            return;
+        }
+        if self.combo_moves > 0 {
+            self.combo = (self.combo + 1).min(MAX_COMBO);
+        }
+        self.combo_moves = window;
+    }
+
+    // A move without food runs the combo window down. Each time it runs out, the multiplier drops by one
+    // and a new window opens to build it back up.
+    fn cool_combo(&mut self) {
+        if self.combo_moves == 0 {
+            return;
+        }
+        self.combo_moves -= 1;
+        if self.combo_moves == 0 && self.combo > 1 {
+            self.combo -= 1;
+            self.combo_moves = self.settings.scoring.combo_window;
+        }
+    }
+
+    // Player two eats the food its head is on, scoring and growing like player one would. Returns false if
+    // that ended the run.
+    fn feed_rival(&mut self) -> bool {
+        let Some(head) = self.rival.as_ref().map(|rival| rival.snake.head()) else {
+            return true;
+        };
+        let Some(i) = self.foods.iter().position(|food| food.pos == head) else {
+            return true;
+        };
+        let food = self.foods.remove(i);
+        self.particles.burst(food.pos);
+        let per_food = self.food_value(&food, self.moves - 1);
+        let (growth, grow_every, max_length) = (self.settings.growth_per_food, self.settings.grow_every, self.settings.max_length);
+        if let Some(rival) = self.rival.as_mut() {
+            rival.foods_eaten += 1;
+            if food.kind == FoodKind::Poison {
+                rival.snake.shrink(POISON_SHRINK);
+            } else {
+                rival.score += food.kind.points(per_food);
+                if rival.foods_eaten.is_multiple_of(grow_every) {
+                    rival.snake.grow(food.kind.growth(growth), max_length);
+                }
+            }
+        }
+        if food.respawns && !self.spawn_food() && self.foods.is_empty() {
+            self.game_over(None);
+            return false;
+        }
+        self.bring_out_golden_food();
+        true
+    }
+
+    // Put out a golden food after every GOLDEN_FOOD_EVERY foods, counting both players' in a two-player run,
+    // unless there is one lying on the board already
+    fn bring_out_golden_food(&mut self) {
+        let eaten = self.foods_eaten + self.rival.as_ref().map_or(0, |rival| rival.foods_eaten);
+        if eaten.is_multiple_of(GOLDEN_FOOD_EVERY) && !self.foods.iter().any(|food| food.kind == FoodKind::Golden) {
+            self.spawn_golden_food();
+        }
+    }
+
+    // Grow after eating, if this food is one that counts towards growth and the snake is below its cap
+    fn grow_snake(&mut self, amount: u32) {
+        if self.foods_eaten.is_multiple_of(self.settings.grow_every) {
+            self.snake.grow(amount, self.settings.max_length);
+        }
+    }
+
+    // Run `f` for every mode in turn. The modes are taken out of the game meanwhile, so they can change
+    // any of it.
+    fn each_mode(&mut self, mut f: impl FnMut(&mut dyn GameMode, &mut Game)) {
+        let mut modes = std::mem::take(&mut self.modes);
+        for mode in &mut modes {
+            f(mode.as_mut(), self);
+        }
+        self.modes = modes;
+    }
+
+    // The part of the board still in play: the configured one, minus the rings a shrinking arena has closed
+    pub fn layout(&self) -> BoardLayout {
+        self.settings.layout().shrunk(self.closed_rings)
+    }
+
+    // Whether the next ring of a shrinking arena closes within SHRINK_WARNING_SECONDS
+    pub fn ring_closing(&self) -> bool {
+        self.shrink_in.is_some_and(|moves| moves as f64 * self.settings.move_interval <= SHRINK_WARNING_SECONDS)
+    }
+
+    // Wall off the outermost ring of the arena. Pickups and portals lying there are gone; food that would have been
+    // replaced after eating is put back inside the smaller arena.
+    fn close_ring(&mut self) {
+        self.closed_rings += 1;
+        let layout = self.layout();
+        let lost = self.foods.iter().filter(|food| food.respawns && !layout.contains(food.pos)).count();
+        self.foods.retain(|food| layout.contains(food.pos));
+        self.phase_pickup = self.phase_pickup.filter(|&pos| layout.contains(pos));
+        self.power_up = self.power_up.filter(|&(_, pos)| layout.contains(pos));
+        self.portals.retain(|ends| ends.iter().all(|&end| layout.contains(end)));
+        for _ in 0..lost {
+            if !self.spawn_food() {
+                break;
+            }
+        }
+    }
+
+    // Move every food one step onto a free neighboring cell. Food within FLEE_RADIUS of a head steps as far
+    // from the nearest head as it can, and stays put rather than step closer; the rest picks a step at
+    // random. Food a head has just reached stays to be eaten.
+    fn wander_food(&mut self) {
+        let heads: Vec<(i32, i32)> = std::iter::once(self.snake.head()).chain(self.rival.as_ref().map(|rival| rival.snake.head())).collect();
+        let distance = |(x, y): (i32, i32)| heads.iter().map(|&(hx, hy)| (x - hx).abs() + (y - hy).abs()).min().unwrap_or(i32::MAX);
+        for i in 0..self.foods.len() {
+            let pos = self.foods[i].pos;
+            if heads.contains(&pos) {
+                continue;
+            }
+            let mut steps: Vec<(i32, i32)> = [Direction::Up, Direction::Down, Direction::Left, Direction::Right]
+                .iter()
+                .map(|dir| (pos.0 + dir.delta().0, pos.1 + dir.delta().1))
+                .filter(|&cell| self.is_free(cell))
+                .collect();
+            if distance(pos) <= FLEE_RADIUS {
+                let farthest = steps.iter().map(|&cell| distance(cell)).max().filter(|&far| far >= distance(pos));
+                steps.retain(|&cell| Some(distance(cell)) == farthest);
+            }
+            if let Some(&cell) = steps.choose(&mut self.rng) {
+                self.foods[i].pos = cell;
+            }
+        }
+    }
+
+    // Spend the armed charge to hop over the wall or rock the head just ran into. Past the border is the
+    // opposite edge; past a rock is the next cell along. Leaves the head where it is to die if that cell is
+    // blocked as well; the charge is gone either way.
+    fn phase(&mut self) {
+        self.phase_armed = false;
+        self.phase_charges = self.phase_charges.saturating_sub(1);
+
+        let (x, y) = self.snake.head();
+        let (dx, dy) = self.snake.dir.delta();
+        let layout = self.layout();
+        let target = if layout.contains((x, y)) {
+            (x + dx, y + dy)
+        } else {
+            layout.wrap((x, y))
+        };
+        if !layout.contains(target) || self.obstacles.contains(&target) || self.snake.occupies_behind_head(target) {
+            return;
+        }
+        self.snake.move_head(target);
+        self.phase_flash = PHASE_FLASH_MOVES;
+    }
+
+    // Take the effect of a power-up the head just ran over. Speed and slow motion cancel out, so either one
+    // ends the other; picking up one that is already in effect starts it over.
+    fn apply_power_up(&mut self, power_up: PowerUp) {
+        if power_up == PowerUp::Shrink {
+            self.snake.shrink(POWER_UP_SHRINK);
+            return;
+        }
+        let pace = [PowerUp::Speed, PowerUp::SlowMotion];
+        self.effects.retain(|&(active, _)| active != power_up && !(pace.contains(&active) && pace.contains(&power_up)));
+        self.effects.push((power_up, POWER_UP_MOVES));
+    }
+
+    // Moves left on a power-up's effect, 0 when it isn't in effect
+    pub fn effect_moves(&self, power_up: PowerUp) -> u32 {
+        self.effects.iter().find(|&&(active, _)| active == power_up).map_or(0, |&(_, moves)| moves)
+    }
+
+    // How much faster than usual the snake moves because of power-ups; the frontend's move clock is
+    // sped up or slowed down by this
+    pub fn move_speed(&self) -> f64 {
+        if self.effect_moves(PowerUp::Speed) > 0 {
+            SPEED_BOOST
+        } else if self.effect_moves(PowerUp::SlowMotion) > 0 {
+            SLOW_MOTION
+        } else {
+            1.0
+        }
+    }
+
+    // Ready a collected phase charge for the next wall or rock contact
+    pub fn arm_phase(&mut self) {
+        if self.phase_charges > 0 {
+            self.phase_armed = true;
+        }
+    }
+
+    // The run as it stands, for a rewind or a saved run
+    fn snapshot(&self) -> RunSnapshot {
+        RunSnapshot {
+            snake: self.snake.clone(),
+            foods: self.foods.clone(),
+            score: self.score,
+            breakdown: self.breakdown,
+            combo: self.combo,
+            combo_moves: self.combo_moves,
+            foods_eaten: self.foods_eaten,
+            moves: self.moves,
+            beat_high: self.beat_high,
+            celebration_moves: self.celebration_moves,
+            visits: self.visits.clone(),
+            time_left: self.time_left,
+            closed_rings: self.closed_rings,
+            shrink_in: self.shrink_in,
+            portals: self.portals.clone(),
+            elapsed: self.elapsed,
+            queued_turns: self.queued_turns.clone(),
+            phase_pickup: self.phase_pickup,
+            phase_charges: self.phase_charges,
+            phase_armed: self.phase_armed,
+            phase_flash: self.phase_flash,
+            wrap_flash: self.wrap_flash,
+            power_up: self.power_up,
+            effects: self.effects.clone(),
+            rng: self.rng.clone(),
+        }
+    }
+
+    // Put the run back the way `snapshot` caught it
+    fn restore(&mut self, snapshot: RunSnapshot) {
+        self.snake = snapshot.snake;
+        self.foods = snapshot.foods;
+        self.score = snapshot.score;
+        self.breakdown = snapshot.breakdown;
+        self.combo = snapshot.combo;
+        self.combo_moves = snapshot.combo_moves;
+        self.foods_eaten = snapshot.foods_eaten;
+        self.moves = snapshot.moves;
+        self.beat_high = snapshot.beat_high;
+        self.celebration_moves = snapshot.celebration_moves;
+        self.visits = snapshot.visits;
+        self.time_left = snapshot.time_left;
+        self.closed_rings = snapshot.closed_rings;
+        self.shrink_in = snapshot.shrink_in;
+        self.portals = snapshot.portals;
+        self.elapsed = snapshot.elapsed;
+        self.queued_turns = snapshot.queued_turns;
+        self.phase_pickup = snapshot.phase_pickup;
+        self.phase_charges = snapshot.phase_charges;
+        self.phase_armed = snapshot.phase_armed;
+        self.phase_flash = snapshot.phase_flash;
+        self.wrap_flash = snapshot.wrap_flash;
+        self.power_up = snapshot.power_up;
+        self.effects = snapshot.effects;
+        self.rng = snapshot.rng;
+    }
+
+    // Remember the state before this move, dropping whatever is older than the rewind window
+    fn record_snapshot(&mut self) {
+        let capacity = ((REWIND_SECONDS / self.settings.move_interval).ceil() as usize).clamp(1, MAX_SNAPSHOTS);
+        while self.history.len() >= capacity {
+            self.history.pop_front();
+        }
+        let snapshot = self.snapshot();
+        self.history.push_back(snapshot);
+    }
+
+    // Whether the game over screen may offer a rewind. Running out of time can't be undone.
+    pub fn can_rewind(&self) -> bool {
+        self.state == GameState::GameOver
+            && self.rival.is_none()
+            && self.rewinds_left > 0
+            && !self.history.is_empty()
+            && self.death_cause.is_some_and(|cause| cause != DeathCause::TimeUp)
+    }
+
+    // Undo the death by resuming from the oldest snapshot, about REWIND_SECONDS before it
+    pub fn rewind(&mut self) {
+        if !self.can_rewind() {
+            return;
+        }
+        let Some(snapshot) = self.history.pop_front() else {
+            return;
+        };
+        self.history.clear();
+        self.rewinds_left -= 1;
+
+        self.restore(snapshot);
+        self.death_cause = None;
+        self.notice = None;
+        self.state = GameState::Running;
+    }
+
+    // Keep a one-player run that is still going as the game closes, so the start screen can offer to
+    // continue it next time
+    pub fn save_run(&self) {
+        let Some(path) = &self.settings.save_file else {
+            return;
+        };
+        if !matches!(self.state, GameState::Running | GameState::Paused) || self.rival.is_some() {
+            return;
+        }
+        let saved = SavedRun {
+            grid: self.settings.grid,
+            wall_mode: self.settings.wall_mode,
+            time_limit: self.settings.time_limit,
+            level: self.level,
+            run_seed: self.run_seed as i64,
+            rng_seed: self.rng.get_seed(),
+            rng_word: self.rng.get_word_pos() as u64,
+            obstacles: self.obstacles.iter().copied().collect(),
+            rewinds_left: self.rewinds_left,
+            run: self.snapshot(),
+        };
+        if let Err(err) = saved.save(path) {
+            eprintln!("copperhead: could not save the run to {}: {}", path.display(), err);
+        }
+    }
+
+    // Score of the run the start screen offers to continue, if there is one
+    pub fn saved_score(&self) -> Option<u32> {
+        self.saved_run.as_ref().map(|saved| saved.run.score)
+    }
+
+    // Pick the saved run up where it was left, paused so the player can get ready. The file goes away, so the
+    // same run can't be continued twice.
+    pub fn continue_run(&mut self) {
+        let Some(saved) = self.saved_run.take() else {
+            return;
+        };
+        if let Some(path) = &self.settings.save_file
+            && let Err(err) = std::fs::remove_file(path)
+        {
+            eprintln!("copperhead: could not remove the saved run {}: {}", path.display(), err);
+        }
+        self.settings.players = Players::One;
+        self.settings.wall_mode = saved.wall_mode;
+        self.settings.time_limit = saved.time_limit;
+        self.level = saved.level;
+        self.high_score = self.stats.best(self.settings.time_limit.is_some());
+        self.modes = modes_for(&self.settings);
+        self.spawn_snakes();
+        self.run_seed = saved.run_seed as u64;
+        self.particles = Particles::new(self.run_seed);
+        self.obstacles = saved.obstacles.into_iter().collect();
+        self.rewinds_left = saved.rewinds_left;
+        self.history.clear();
+        self.restore(saved.run);
+        self.rng = rand_chacha::ChaCha12Rng::from_seed(saved.rng_seed);
+        self.rng.set_word_pos(saved.rng_word as u128);
+        self.death_cause = None;
+        self.notice = None;
+        self.demo = None;
+        self.state = GameState::Paused;
+    }
+
+    // Advance the run's clock and the modes that keep time by elapsed game seconds
+    pub fn tick_clock(&mut self, dt: f64) {
+        // Answers from the global leaderboard come in on every screen
+        if let Some(online) = self.online.as_mut()
+            && online.poll()
+        {
+            self.dirty = true;
+        }
+        // Unlock notices count down on every screen, and fade out as they go
+        if !self.toasts.is_empty() {
+            self.toast_time += dt;
+            if self.toast_time >= TOAST_SECONDS {
+                self.toasts.pop_front();
+                self.toast_time = 0.0;
+            }
+            self.dirty = true;
+        }
+        if self.state == GameState::Menu {
+            self.tick_title(dt);
+        }
+        if self.state != GameState::Running {
+            return;
+        }
+        // The countdown goes by in real seconds, whatever the game speed, and the run's clock waits for it
+        if self.countdown > 0.0 {
+            self.countdown = (self.countdown - dt / self.time_scale).max(0.0);
+            self.dirty = true;
+            return;
+        }
+        self.elapsed += dt;
+        // Keep drawing until the last spark is gone, even when the snake itself only steps
+        if !self.particles.is_empty() {
+            self.particles.update(dt);
+            self.dirty = true;
+        }
+        // Portals swirl all the time
+        if !self.portals.is_empty() {
+            self.dirty = true;
+        }
+        self.each_mode(|mode, game| {
+            // A mode may have ended the run already
+            if game.state == GameState::Running {
+                mode.on_clock(game, dt);
+            }
+        });
+    }
+
+    // Time on the start screen: after a while without a key press the demo starts playing, and from then on
+    // its clock runs along. Nothing counts while the help, the editor or a list (leaderboard, achievements,
+    // stats) is open, or while a key is being bound.
+    fn tick_title(&mut self, dt: f64) {
+        if self.help_shown || self.leaderboard_shown || self.achievements_shown || self.stats_shown || self.editor.is_some() || self.rebinding.is_some() {
+            return;
+        }
+        self.idle += dt;
+        if self.demo.is_none() && self.idle >= DEMO_AFTER {
+            self.demo = Some(Box::new(Game::demo(&self.settings)));
+            self.dirty = true;
+        }
+        if let Some(demo) = self.demo.as_mut() {
+            demo.tick_clock(dt);
+            // The frontend only sets this on the game it knows about; a frame late is close enough
+            demo.move_progress = self.move_progress;
+            if demo.is_dirty() {
+                demo.clear_dirty();
+                self.dirty = true;
+            }
+        }
+    }
+
+    // One move of the demo, steered by the autopilot. A demo that ends makes way for a new one.
+    fn step_demo(&mut self) {
+        let Some(demo) = self.demo.as_mut() else {
+            return;
+        };
+        if demo.state != GameState::Running {
+            **demo = Game::demo(&self.settings);
+        }
+        demo.update();
+        self.dirty = true;
+    }
+
+    // A game for the start screen's demo: the same board and rules for one player, but nothing it does is
+    // kept or reported
+    fn demo(settings: &Settings) -> Game {
+        let settings = Settings {
+            players: Players::One,
+            seed: None,
+            time_limit: None,
+            rewinds: 0,
+            assist_arrows: false,
+            export_heatmap: false,
+            json_results: false,
+            stats_file: None,
+            leaderboard_file: None,
+            achievements_file: None,
+            save_file: None,
+            online_server: None,
+            ..settings.clone()
+        };
+        let mut demo = Game::new(&settings);
+        demo.set_controller(Box::new(Autopilot));
+        demo.state = GameState::Running;
+        demo
+    }
+
+    // Check whether the head's current cell is fatal, and why
+    fn collision(&self) -> Option<DeathCause> {
+        let (x, y) = self.snake.head();
+        if !self.layout().contains((x, y)) {
+            Some(DeathCause::Wall)
+        } else if self.obstacles.contains(&(x, y)) {
+            Some(DeathCause::Obstacle)
+        } else if self.effect_moves(PowerUp::Ghost) == 0 && self.snake.self_collision(self.settings.tail_chase) {
+            Some(DeathCause::SelfBite)
+        } else if self.rival.as_ref().is_some_and(|rival| rival.snake.occupies((x, y))) {
+            Some(DeathCause::Rival)
+        } else {
+            None
+        }
+    }
+
+    // The same for player two's head, with player one's snake in the way
+    fn rival_collision(&self) -> Option<DeathCause> {
+        let rival = self.rival.as_ref()?;
+        let head = rival.snake.head();
+        if !self.layout().contains(head) {
+            Some(DeathCause::Wall)
+        } else if self.obstacles.contains(&head) {
+            Some(DeathCause::Obstacle)
+        } else if rival.snake.self_collision(self.settings.tail_chase) {
+            Some(DeathCause::SelfBite)
+        } else if self.snake.occupies(head) {
+            Some(DeathCause::Rival)
+        } else {
+            None
+        }
+    }
+
+    // Base points for eating `food` on the move after `moves`, before the kind's multiplier
+    pub fn food_value(&self, food: &Food, moves: u32) -> u32 {
+        match self.settings.scoring.food_decay {
+            Some(decay) => decay.value(moves.saturating_sub(food.spawned_at)),
+            None => self.settings.scoring.per_food,
+        }
+    }
+
+    // Add points to the score, keeping track of their source
+    pub(crate) fn award(&mut self, source: ScoreSource, points: u32) {
+        match source {
+            ScoreSource::Food => self.breakdown.food += points,
+            ScoreSource::Survival => self.breakdown.survival += points,
+            ScoreSource::Length => self.breakdown.length += points,
+            ScoreSource::Mod => self.breakdown.mods += points,
+        }
+        self.score += points;
+
+        // Celebrate the crossover once; the score keeps climbing past the old record afterwards
+        if !self.beat_high && self.rival.is_none() && self.high_score > 0 && self.score > self.high_score {
+            self.beat_high = true;
+            self.celebration_moves = CELEBRATION_MOVES;
+        }
+    }
+
+    fn game_over(&mut self, cause: Option<DeathCause>) {
+        if let Some(cause) = cause {
+            let mut allow = true;
+            self.each_mode(|mode, game| allow &= mode.on_death(game, cause));
+            if !allow {
+                return;
+            }
+        }
+        self.death_cause = cause;
+        self.state = GameState::GameOver;
+        if self.settings.export_heatmap {
+            self.export_heatmap();
+        }
+        if !self.can_rewind() {
+            self.finish_run();
+            self.offer_initials();
+        }
+    }
+
+    // Ask for initials if this run makes the top 10; returns whether it did. Signing ends the run for good,
+    // so a run that could still be rewound only gets here once the player gives up on it.
+    pub fn offer_initials(&mut self) -> bool {
+        if self.signed || self.rival.is_some() || self.settings.leaderboard_file.is_none() || !self.leaderboard.qualifies(self.score) {
+            return false;
+        }
+        self.signed = true;
+        self.rewinds_left = 0;
+        self.finish_run();
+        self.initials = Some(String::new());
+        true
+    }
+
+    // Move on from the game over screen: to the initials entry if this run makes the top 10 and wasn't
+    // entered yet, otherwise to a new run
+    pub fn leave_game_over(&mut self) {
+        if !self.offer_initials() {
+            self.reset();
+        }
+    }
+
+    // Add a letter to the initials being entered, ignoring anything but letters and anything past the limit
+    pub fn type_initial(&mut self, letter: char) {
+        if let Some(initials) = self.initials.as_mut()
+            && letter.is_ascii_alphabetic()
+            && initials.len() < INITIALS_LEN
+        {
+            initials.push(letter.to_ascii_uppercase());
+            self.dirty = true;
+        }
+    }
+
+    pub fn erase_initial(&mut self) {
+        if let Some(initials) = self.initials.as_mut() {
+            initials.pop();
+            self.dirty = true;
+        }
+    }
+
+    // Put this run on the leaderboard under the initials entered so far, and save it. Needs at least one letter.
+    pub fn sign_leaderboard(&mut self) {
+        let Some(name) = self.initials.take_if(|initials| !initials.is_empty()) else {
+            return;
+        };
+        self.dirty = true;
+        let place = self.leaderboard.insert(Entry { name, score: self.score, date: today() });
+        if let Some(place) = place {
+            self.notice = Some(format!("Number {} on the leaderboard!", place + 1));
+        }
+        if let Some(path) = &self.settings.leaderboard_file
+            && let Err(err) = self.leaderboard.save(path)
+        {
+            self.notice = Some(format!("Could not save the leaderboard: {}", err));
+        }
+    }
+
+    // Leave this run off the leaderboard
+    pub fn skip_initials(&mut self) {
+        self.initials = None;
+        self.dirty = true;
+    }
+
+    // The leaderboard as text lines, best first, for the leaderboard views
+    pub fn leaderboard_lines(&self) -> Vec<String> {
+        if self.leaderboard.entries.is_empty() {
+            return vec!["No runs yet. Make it count!".to_string()];
+        }
+        self.leaderboard
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| format!("{:>2}. {:<3} {:>6}  {}", i + 1, entry.name, entry.score, entry.date))
+            .collect()
+    }
+
+    // The global top 10 as text lines, best first, with how the last request went underneath
+    pub fn global_lines(&self) -> Vec<String> {
+        let Some(online) = &self.online else {
+            return Vec::new();
+        };
+        let mut lines: Vec<String> = match &online.top {
+            Some(top) if top.entries.is_empty() => vec!["No runs yet. Be the first!".to_string()],
+            Some(top) => top
+                .entries
+                .iter()
+                .enumerate()
+                .map(|(i, entry)| format!("{:>2}. {:<width$} {:>6}  {}", i + 1, entry.name, entry.score, entry.date, width = MAX_NAME_LEN))
+                .collect(),
+            None => Vec::new(),
+        };
+        if let Some(status) = &online.status {
+            if !lines.is_empty() {
+                lines.push(String::new());
+            }
+            lines.push(status.clone());
+        }
+        lines
+    }
+
+    // Remember the high score and the lifetime totals for the next session, if this game keeps them
+    fn save_stats(&mut self) {
+        self.stats.set_best(self.settings.time_limit.is_some(), self.high_score);
+        let Some(path) = &self.settings.stats_file else {
+            return;
+        };
+        if let Err(err) = self.stats.save(path) {
+            eprintln!("copperhead: could not save stats to {}: {}", path.display(), err);
+        }
+    }
+
+    // The lifetime stats as text lines, for the stats views
+    pub fn stats_lines(&self) -> Vec<String> {
+        let stats = &self.stats;
+        let minutes = (stats.seconds_played / 60.0).floor();
+        vec![
+            format!("Games played      {}", stats.games_played),
+            format!("Average score     {:.1}", stats.average_score()),
+            format!("Best score        {}", stats.high_score),
+            format!("Time attack best  {}", stats.time_attack_high_score),
+            format!("Food eaten        {}", stats.food_eaten),
+            format!("Longest snake     {}", stats.longest_snake),
+            format!("Time played       {}m {:02}s", minutes, (stats.seconds_played - minutes * 60.0).floor()),
+        ]
+    }
+
+    // Unlock what this run has earned so far, announcing each one and saving them right away. Only
+    // one-player runs count, as with the leaderboard.
+    fn check_achievements(&mut self) {
+        if self.settings.achievements_file.is_none() || self.rival.is_some() {
+            return;
+        }
+        let length = self.snake.body().len();
+        let crashed = self.death_cause.is_some_and(|cause| cause != DeathCause::TimeUp);
+        let earned: Vec<Achievement> = Achievement::ALL
+            .into_iter()
+            .filter(|&achievement| !self.achievements.has(achievement))
+            .filter(|&achievement| match achievement {
+                Achievement::FirstBite => self.foods_eaten >= 1,
+                Achievement::Hungry => self.foods_eaten >= 10,
+                Achievement::Glutton => self.foods_eaten >= 50,
+                Achievement::Grown => length >= 25,
+                Achievement::Giant => length >= 50,
+                Achievement::Century => self.score >= 100,
+                Achievement::Combo => self.combo >= MAX_COMBO,
+                Achievement::Survivor => self.elapsed >= SURVIVOR_SECONDS,
+                Achievement::Splat => crashed && self.elapsed < SPLAT_SECONDS,
+            })
+            .collect();
+        if earned.is_empty() {
+            return;
+        }
+        let date = today();
+        for achievement in earned {
+            self.achievements.unlock(achievement, &date);
+            self.toasts.push_back(achievement);
+        }
+        if let Some(path) = &self.settings.achievements_file
+            && let Err(err) = self.achievements.save(path)
+        {
+            eprintln!("copperhead: could not save achievements to {}: {}", path.display(), err);
+        }
+    }
+
+    // The achievements as text lines, two per achievement: whether and when it was unlocked, and what it takes
+    pub fn achievement_lines(&self) -> Vec<String> {
+        let mut lines = vec![format!("{} of {} unlocked", self.achievements.unlocked.len(), Achievement::ALL.len())];
+        for achievement in Achievement::ALL {
+            let unlocked = self.achievements.unlocked.iter().find(|&&(unlocked, _)| unlocked == achievement);
+            lines.push(match unlocked {
+                Some((_, date)) => format!("[x] {:<12} {}", achievement.title(), date),
+                None => format!("[ ] {}", achievement.title()),
+            });
+            lines.push(format!("    {}", achievement.description()));
+        }
+        lines
+    }
+
+    // Wrap up the run once it is over for good, once per run: unlock achievements, award the length bonus and
+    // raise the high score, add one-player runs to the lifetime stats and save them, and queue the JSON result
+    // line. A death that can still be rewound isn't final, so then this waits until the player restarts or
+    // quits, and a rewound death leaves no trace. A cleared campaign level counts in the stats, but it is
+    // scored as it stands and has no result line.
+    pub fn finish_run(&mut self) {
+        if !matches!(self.state, GameState::GameOver | GameState::LevelComplete) || self.reported {
+            return;
+        }
+        self.reported = true;
+        self.check_achievements();
+        let over = self.state == GameState::GameOver;
+        if over {
+            let grown = self.snake.body().len().saturating_sub(START_LENGTH) as u32;
+            self.award(ScoreSource::Length, grown * self.settings.scoring.length_bonus);
+        }
+        if let Some(rival) = self.rival.as_mut() {
+            let grown = rival.snake.body().len().saturating_sub(START_LENGTH) as u32;
+            rival.score += grown * self.settings.scoring.length_bonus;
+        } else {
+            self.high_score = self.high_score.max(self.score);
+            self.stats.count_run(self.score, self.foods_eaten, self.elapsed, self.snake.body().len() as u32);
+            self.save_stats();
+            if let Some(online) = self.online.as_mut()
+                && self.state == GameState::GameOver
+                && self.score > 0
+            {
+                online.submit(self.score, &today());
+            }
+        }
+        if over && self.settings.json_results {
+            let line = self.run_stats().json();
+            self.json_lines.push(line);
+        }
+    }
+
+    // How the run stands, for the result lines of --json and --benchmark
+    pub fn run_stats(&self) -> RunStats {
+        let cause = match (&self.state, self.death_cause) {
+            (GameState::GameOver, Some(cause)) => format!("{:?}", cause),
+            (GameState::GameOver, None) if self.snake.body().len() >= self.layout().open_cells() => "Cleared".to_string(),
+            (GameState::GameOver, None) => "NoRoom".to_string(),
+            _ => "Unfinished".to_string(),
+        };
+        RunStats {
+            seed: self.run_seed,
+            score: self.score,
+            length: self.snake.body().len(),
+            moves: self.moves,
+            cause,
+        }
+    }
+
+    // Give scores still on their way to the global leaderboard the chance to get there before the game closes
+    pub fn finish_uploads(&mut self) {
+        if let Some(online) = self.online.as_mut() {
+            online.finish();
+        }
+    }
+
+    // Print and flush queued JSON result lines
+    pub fn print_json_lines(&mut self) {
+        use std::io::Write;
+
+        if self.json_lines.is_empty() {
+            return;
+        }
+        let mut out = std::io::stdout().lock();
+        for line in self.json_lines.drain(..) {
+            writeln!(out, "{}", line).ok();
+        }
+        out.flush().ok();
+    }
+
+    // Save where the head went this run as `heatmap-<score>.png`, one cell-sized block per grid cell
+    pub fn export_heatmap(&mut self) {
+        let path = format!("heatmap-{}.png", self.score);
+        let result = heatmap_image(self.settings.layout(), &self.visits).save(&path);
+        self.notice = Some(match result {
+            Ok(()) => format!("Heatmap saved to {}", path),
+            Err(err) => format!("Could not save heatmap: {}", err),
+        });
+    }
+
+    // Switch to the next edge behavior. Only offered before a run starts, so a run is played under one rule
+    // from start to finish; the choice sticks for the runs after it.
+    pub fn cycle_wall_mode(&mut self) {
+        self.settings.wall_mode = self.settings.wall_mode.next(self.wrap_penalty);
+        self.modes = modes_for(&self.settings);
+    }
+
+    // Switch between classic play and time attack, also only before a run starts. Each keeps its own high
+    // score. Time attack lasts as long as --time-attack said, or two minutes.
+    pub fn toggle_time_attack(&mut self) {
+        self.settings.time_limit = match self.settings.time_limit {
+            Some(_) => None,
+            None => Some(self.time_attack),
+        };
+        self.time_left = self.settings.time_limit;
+        self.high_score = self.stats.best(self.settings.time_limit.is_some());
+        self.modes = modes_for(&self.settings);
+    }
+
+    // Speed up or slow down by `steps` moves a second, within SPEED_RANGE. The frontend's move timer follows.
+    pub fn change_speed(&mut self, steps: i32) {
+        let speed = (1.0 / self.settings.move_interval).round() as i32;
+        self.settings.move_interval = 1.0 / (speed + steps).clamp(*SPEED_RANGE.start(), *SPEED_RANGE.end()) as f64;
+    }
+
+    // Make the board `steps` times GRID_STEP columns and rows bigger, or smaller, within GRID_RANGE. The game
+    // starts over on the new board with the same settings and the menu as it was, so the high score, the
+    // saved run and the rest are looked up again for it.
+    pub fn change_grid(&mut self, steps: i32) {
+        let resize = |cells: i32| (cells + steps * GRID_STEP).clamp(*GRID_RANGE.start(), *GRID_RANGE.end());
+        let grid = (resize(self.settings.grid.0), resize(self.settings.grid.1));
+        if grid == self.settings.grid {
+            return;
+        }
+        // Placed food and the food count were only checked against the old board
+        let Ok(settings) = (SettingsBuilder { settings: Settings { grid, ..self.settings.clone() } }).build() else {
+            return;
+        };
+        let mut game = Game::new(&settings);
+        game.time_scale = self.time_scale;
+        game.debug_shown = self.debug_shown;
+        game.menu_page = self.menu_page;
+        game.refresh_menu();
+        game.menu.select(MenuItem::Board);
+        game.resized = true;
+        *self = game;
+    }
+
+    // Switch to the built-in theme `steps` along THEME_NAMES, with the colors changed from the current one
+    // changed the same way
+    pub fn cycle_theme(&mut self, steps: i32) {
+        let index = THEME_NAMES.iter().position(|&name| name == self.settings.theme.name).unwrap_or(0) as i32;
+        let name = THEME_NAMES[(index + steps).rem_euclid(THEME_NAMES.len() as i32) as usize];
+        if let Some(theme) = self.settings.theme.switch_to(name) {
+            self.settings.theme = theme;
+        }
+    }
+
+    // Louder or quieter by `steps` volume steps; changing the volume also unmutes. The frontend's music
+    // follows the settings.
+    pub fn change_volume(&mut self, steps: i32) {
+        self.settings.volume = (self.settings.volume as i32 + steps * VOLUME_STEP as i32).clamp(0, 100) as u32;
+        self.settings.muted = false;
+        self.dirty = true;
+    }
+
+    pub fn toggle_mute(&mut self) {
+        self.settings.muted = !self.settings.muted;
+        self.dirty = true;
+    }
+
+    // Open the level editor in place of the start screen
+    pub fn open_editor(&mut self) {
+        self.editor = Some(Editor::open(self.settings.grid));
+        self.leaderboard_shown = false;
+        self.global_shown = false;
+        self.achievements_shown = false;
+        self.stats_shown = false;
+    }
+
+    // Switch to the next choice of players, also only before a run starts. The snakes start elsewhere with
+    // two of them, so the board is laid out again around them.
+    pub fn cycle_players(&mut self) {
+        // Campaign levels are laid out for one snake
+        if !self.settings.campaign.is_empty() {
+            return;
+        }
+        self.settings.players = self.settings.players.next();
+        self.spawn_snakes();
+        self.place_obstacles();
+        self.place_portals();
+        self.lay_out_food();
+    }
+
+    // Put the snakes in place for a new run: player one in the middle of the board, or where the campaign
+    // level says, or with two players, both a third of the board apart across their heading and facing
+    // opposite ways
+    fn spawn_snakes(&mut self) {
+        if let Some(level) = self.current_level() {
+            self.snake = Snake::at(level.start_dir.clone(), level.start);
+            self.rival = None;
+            return;
+        }
+        let (dir, grid) = (self.settings.start_dir.clone(), self.settings.grid);
+        self.rival_controller = match self.settings.players {
+            Players::Computer => Box::new(Chaser),
+            _ => Box::new(Keys),
+        };
+        if self.settings.players == Players::One {
+            self.snake = Snake::new(dir, grid);
+            self.rival = None;
+            return;
+        }
+        let (dx, dy) = dir.delta();
+        let (ox, oy) = (dy.abs() * grid.0 / 6, dx.abs() * grid.1 / 6);
+        let (cx, cy) = (grid.0 / 2, grid.1 / 2);
+        self.snake = Snake::at(dir.clone(), (cx - ox, cy - oy));
+        self.rival = Some(Rival {
+            snake: Snake::at(dir.opposite(), (cx + ox, cy + oy)),
+            score: 0,
+            foods_eaten: 0,
+            queued_turns: VecDeque::new(),
+            death_cause: None,
+        });
+    }
+
+    // Convert real seconds into game seconds. Everything that advances with time (moves, the time attack
+    // clock, animations) must be fed game seconds, so they all speed up and slow down together.
+    pub fn game_dt(&self, real_dt: f64) -> f64 {
+        if self.help_shown || self.state == GameState::Paused {
+            return 0.0;
+        }
+        real_dt * self.time_scale
+    }
+
+    // Whether anything on screen moves on its own; static screens can be redrawn far less often
+    pub fn is_animating(&self) -> bool {
+        (self.state == GameState::Running && !self.help_shown) || self.demo.is_some()
+    }
+
+    // Whether the last drawn frame is out of date. Every change that affects the picture sets this,
+    // and the frame loop only draws when it's set.
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    pub fn clear_dirty(&mut self) {
+        self.dirty = false;
+    }
+
+    // Force a redraw for reasons outside the game, like the window being resized
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+
+    // Handle a direction key: steer right away, or buffer it for the moves to come while there is room.
+    // Presses that wouldn't change the direction the snake will be going in by then are dropped,
+    // so they can't take up buffer slots.
+    pub fn queue_turn(&mut self, dir: Direction) {
+        if self.state != GameState::Running || self.help_shown {
+            return;
+        }
+        self.dirty = true;
+        if self.settings.apply_turns_on_frame {
+            self.turn(dir);
+            return;
+        }
+        buffer_turn(&mut self.queued_turns, &self.snake.dir, dir, self.settings.input_buffer_len);
+    }
+
+    // Buffer the turns both players pressed before the next move of a network run, the same way on both
+    // machines whatever either one's screen shows
+    pub fn apply_turns(&mut self, one: &[Direction], two: &[Direction]) {
+        for dir in one {
+            if self.settings.apply_turns_on_frame {
+                self.turn(dir.clone());
+            } else {
+                buffer_turn(&mut self.queued_turns, &self.snake.dir, dir.clone(), self.settings.input_buffer_len);
+            }
+        }
+        let Some(rival) = self.rival.as_mut() else {
+            return;
+        };
+        for dir in two {
+            if self.settings.apply_turns_on_frame {
+                rival.snake.steer(dir.clone());
+            } else {
+                buffer_turn(&mut rival.queued_turns, &rival.snake.dir, dir.clone(), self.settings.input_buffer_len);
+            }
+        }
+    }
+
+    // The same for player two's steering keys
+    pub fn queue_rival_turn(&mut self, dir: Direction) {
+        if self.state != GameState::Running || self.help_shown {
+            return;
+        }
+        self.dirty = true;
+        let Some(rival) = self.rival.as_mut() else {
+            return;
+        };
+        if self.settings.apply_turns_on_frame {
+            rival.snake.steer(dir);
+            return;
+        }
+        buffer_turn(&mut rival.queued_turns, &rival.snake.dir, dir, self.settings.input_buffer_len);
+    }
+
+    // Steer player one with `controller` from the next move on, in place of the keys
+    pub fn set_controller(&mut self, controller: Box<dyn Controller>) {
+        self.controller = controller;
+    }
+
+    // Ask player one's controller, or player two's, for the next move
+    fn next_move(&mut self, rival: bool) -> Option<Direction> {
+        let slot = if rival { &mut self.rival_controller } else { &mut self.controller };
+        let mut controller = std::mem::replace(slot, Box::new(Keys));
+        let dir = controller.next_move(&GameView { game: self, rival });
+        *(if rival { &mut self.rival_controller } else { &mut self.controller }) = controller;
+        dir
+    }
+
+    // Steer the snake, ignoring attempts to reverse straight into its own neck
+    pub fn turn(&mut self, dir: Direction) {
+        self.snake.steer(dir);
+    }
+
+    // Put the pre-placed items and the configured number of foods on the board for a new run
+    fn lay_out_food(&mut self) {
+        self.foods = self.settings.placed_food.iter().map(|&(kind, pos)| Food { pos, kind, respawns: false, spawned_at: 0, moves_left: None }).collect();
+        for _ in 0..self.settings.food_count {
+            if !self.spawn_food() {
+                break;
+            }
+        }
+    }
+
+    // Add food of a kind drawn from the spawn table on a random free cell the snake can reach;
+    // returns false if there is none
+    fn spawn_food(&mut self) -> bool {
+        let mut candidates = self.free_cells();
+        self.each_mode(|mode, game| mode.on_spawn_food(game, &mut candidates));
+        if candidates.is_empty() {
+            return false;
+        }
+
+        let kind = match self.settings.food_table.choose_weighted(&mut self.rng, |&(_, weight)| weight) {
+            Ok(&(kind, _)) => kind,
+            Err(_) => FoodKind::Normal,
+        };
+        if let FoodSpawn::NearHead { radius } = self.settings.food_spawn {
+            // Favor cells closer to the head; fall back to anywhere when nothing is within the radius
+            let (hx, hy) = self.snake.head();
+            let nearby: Vec<((i32, i32), u32)> = candidates
+                .iter()
+                .map(|&(x, y)| ((x, y), (x - hx).unsigned_abs() + (y - hy).unsigned_abs()))
+                .filter(|&(_, distance)| distance <= radius)
+                .map(|(pos, distance)| (pos, radius + 1 - distance))
+                .collect();
+            if let Ok(&(pos, _)) = nearby.choose_weighted(&mut self.rng, |&(_, weight)| weight) {
+                self.foods.push(Food { pos, kind, respawns: true, spawned_at: self.moves, moves_left: None });
+                return true;
+            }
+        }
+        let pos = candidates[self.rng.gen_range(0..candidates.len())];
+        self.foods.push(Food { pos, kind, respawns: true, spawned_at: self.moves, moves_left: None });
+        true
+    }
+
+    // Put a golden food on a random free cell the snake can reach, to vanish again after GOLDEN_FOOD_SECONDS
+    fn spawn_golden_food(&mut self) {
+        if let Some(&pos) = self.free_cells().choose(&mut self.rng) {
+            let moves_left = Some(self.golden_food_moves());
+            self.foods.push(Food { pos, kind: FoodKind::Golden, respawns: false, spawned_at: self.moves, moves_left });
+        }
+    }
+
+    // How many moves a golden food lasts at the current move interval
+    pub fn golden_food_moves(&self) -> u32 {
+        (GOLDEN_FOOD_SECONDS / self.settings.move_interval).ceil().max(1.0) as u32
+    }
+
+    // Steer towards the nearest food along a shortest path. Without one, head for the most open space and
+    // wait for the body to clear a way.
+    pub fn autopilot(&self) -> Direction {
+        seek_food(self, &self.snake, |cell| self.is_fatal(cell))
+    }
+
+    // Whether the head moving into `cell` on the next move would be fatal, by the same rules as `collision`.
+    // The tail's cell is safe when the tail moves out of it on that same move.
+    pub fn is_fatal(&self, cell: (i32, i32)) -> bool {
+        // A ghost passes through itself, as long as the effect lasts through the next move
+        let ghost = self.effect_moves(PowerUp::Ghost) > 1;
+        self.is_fatal_for(&self.snake, self.rival.as_ref().map(|rival| &rival.snake), ghost, cell)
+    }
+
+    // The same for player two's head, with player one's snake in the way
+    pub fn is_fatal_for_rival(&self, cell: (i32, i32)) -> bool {
+        match &self.rival {
+            Some(rival) => self.is_fatal_for(&rival.snake, Some(&self.snake), false, cell),
+            None => true,
+        }
+    }
+
+    // Whether `snake`'s head moving into `(x, y)` would be fatal with `other` on the board as well. All of
+    // the other snake counts, since its tail may stay put. A portal sends the head on, so what counts is
+    // the cell at its other end.
+    fn is_fatal_for(&self, snake: &Snake, other: Option<&Snake>, ghost: bool, (x, y): (i32, i32)) -> bool {
+        let (x, y) = self.warp((x, y));
+        let in_grid = self.layout().contains((x, y));
+        let tail_leaves = snake.pending_growth == 0 && self.settings.tail_chase == TailChase::Classic;
+        let tail = Some(snake.tail()).filter(|_| tail_leaves);
+        let in_other = other.is_some_and(|other| other.occupies((x, y)));
+        !in_grid || self.obstacles.contains(&(x, y)) || (!ghost && snake.occupies((x, y)) && Some((x, y)) != tail) || in_other
+    }
+
+    // Whether every move the snake can make next is fatal. An unused phase charge might still get it out.
+    pub fn is_trapped(&self) -> bool {
+        let (x, y) = self.snake.head();
+        self.state == GameState::Running
+            && self.phase_charges == 0
+            && [Direction::Up, Direction::Down, Direction::Left, Direction::Right].iter().all(|dir| {
+                let (dx, dy) = dir.delta();
+                self.is_fatal((x + dx, y + dy))
+            })
+    }
+
+    // Empty cells the snake can reach, in grid order so a random pick only depends on the RNG
+    fn free_cells(&self) -> Vec<(i32, i32)> {
+        let reachable = self.reachable_cells();
+        let (columns, rows) = self.settings.grid;
+        (0..rows)
+            .flat_map(|y| (0..columns).map(move |x| (x, y)))
+            .filter(|&pos| reachable.contains(&pos) && self.is_free(pos))
+            .collect()
+    }
+
+    // Whether `pos` is in the arena with nothing on it: no snake, rock, food, pickup or portal
+    fn is_free(&self, pos: (i32, i32)) -> bool {
+        self.layout().contains(pos)
+            && !self.snake.occupies(pos)
+            && !self.obstacles.contains(&pos)
+            && !self.rival.as_ref().is_some_and(|rival| rival.snake.occupies(pos))
+            && !self.foods.iter().any(|food| food.pos == pos)
+            && Some(pos) != self.phase_pickup
+            && Some(pos) != self.power_up.map(|(_, pos)| pos)
+            && self.portal_exit(pos).is_none()
+    }
+
+    // Where a head stepping into `cell` ends up: back in on the opposite edge when the edges wrap, and out of
+    // the other end when it is a portal
+    fn warp(&self, cell: (i32, i32)) -> (i32, i32) {
+        let cell = match self.settings.wall_mode {
+            WallMode::Lethal => cell,
+            WallMode::Wrap | WallMode::WrapPenalty { .. } => self.layout().wrap(cell),
+        };
+        self.portal_exit(cell).unwrap_or(cell)
+    }
+
+    // Where a head entering `cell` comes out, if `cell` is one end of a portal
+    pub fn portal_exit(&self, cell: (i32, i32)) -> Option<(i32, i32)> {
+        self.portals.iter().find_map(|&[a, b]| match cell {
+            _ if cell == a => Some(b),
+            _ if cell == b => Some(a),
+            _ => None,
+        })
+    }
+
+    // Flood fill from the head over cells inside the grid that are not covered by the body, across wrapping
+    // edges and through portals the way the head would go
+    fn reachable_cells(&self) -> HashSet<(i32, i32)> {
+        let rival = |cell: &(i32, i32)| self.rival.as_ref().is_some_and(|rival| rival.snake.occupies(*cell));
+        let blocked = |cell| self.snake.occupies(cell) || self.obstacles.contains(&cell) || rival(&cell);
+        flood_fill(self.layout(), self.snake.head(), |cell| self.warp(cell), blocked)
+    }
+
+    // Scatter fresh random walls for this run, keeping the spawn area and the pre-placed food clear. A
+    // campaign level brings its own walls instead.
+    fn place_obstacles(&mut self) {
+        self.obstacles = if let Some(level) = self.current_level() {
+            level.walls.clone()
+        } else if self.settings.wall_density > 0.0 {
+            let placed = self.settings.placed_food.iter().map(|&(_, pos)| pos);
+            let rival = self.rival.iter().flat_map(|rival| rival.snake.body().iter().copied());
+            let spawn: Vec<(i32, i32)> = self.snake.body().iter().copied().chain(rival).chain(placed).collect();
+            generate_obstacles(self.settings.layout(), self.settings.wall_density, self.rng.next_u64(), &spawn)
+        } else {
+            HashSet::new()
+        };
+    }
+
+    // Set out this run's portals: the campaign level's own, then the settings' pairs on random free cells.
+    // Cells just ahead of the spawn and the pre-placed food stay clear, so no run starts by being thrown
+    // across the board.
+    fn place_portals(&mut self) {
+        self.portals = self.current_level().map_or_else(Vec::new, |level| level.portals.clone());
+        if self.settings.portal_pairs == 0 {
+            return;
+        }
+        let (x, y) = self.snake.head();
+        let (dx, dy) = self.snake.dir.delta();
+        let ahead: Vec<(i32, i32)> = (1..=PORTAL_CLEARANCE).map(|i| (x + dx * i, y + dy * i)).collect();
+        let placed: Vec<(i32, i32)> = self.settings.placed_food.iter().map(|&(_, pos)| pos).collect();
+        let spots: Vec<(i32, i32)> = self.free_cells().into_iter().filter(|pos| !ahead.contains(pos) && !placed.contains(pos)).collect();
+        let ends: Vec<(i32, i32)> = spots.choose_multiple(&mut self.rng, self.settings.portal_pairs as usize * 2).copied().collect();
+        self.portals.extend(ends.chunks_exact(2).map(|pair| [pair[0], pair[1]]));
+    }
+
+    pub fn reset(&mut self) {
+        self.finish_run();
+        self.reported = false;
+        self.spawn_snakes();
+        self.score = 0;
+        self.breakdown = ScoreBreakdown::default();
+        self.combo = 1;
+        self.combo_moves = 0;
+        self.foods_eaten = 0;
+        self.moves = 0;
+        self.death_cause = None;
+        self.beat_high = false;
+        self.celebration_moves = 0;
+        self.visits = vec![0; (self.settings.grid.0 * self.settings.grid.1) as usize];
+        self.notice = None;
+        self.time_left = self.settings.time_limit;
+        self.closed_rings = 0;
+        self.shrink_in = moves_per_shrink(&self.settings);
+        self.queued_turns.clear();
+        self.phase_pickup = None;
+        self.phase_charges = 0;
+        self.phase_armed = false;
+        self.phase_flash = 0;
+        self.wrap_flash = 0;
+        self.power_up = None;
+        self.effects.clear();
+        self.history.clear();
+        self.rewinds_left = self.settings.rewinds;
+        self.initials = None;
+        self.signed = false;
+        self.demo = None;
+        self.idle = 0.0;
+        self.outbox.clear();
+        self.restart_requested = false;
+        self.countdown = 0.0;
+        self.modes = modes_for(&self.settings);
+        self.elapsed = 0.0;
+        self.run_seed = self.settings.seed.unwrap_or_else(rand::random);
+        self.rng = rand_chacha::ChaCha12Rng::seed_from_u64(self.run_seed);
+        self.particles = Particles::new(self.run_seed);
+        self.state = GameState::Menu;
+        self.menu_page = MenuPage::Main;
+        self.rebinding = None;
+        self.refresh_menu();
+        self.place_obstacles();
+        self.place_portals();
+        self.lay_out_food();
+    }
+}
+
+// Leaderboard stored at `path`. A damaged file is reported and counts as an empty leaderboard; it gets
+// replaced the next time a run makes the list.
+fn load_leaderboard(path: &std::path::Path) -> Leaderboard {
+    Leaderboard::load(path).unwrap_or_else(|err| {
+        eprintln!("copperhead: could not read the leaderboard from {}: {}", path.display(), err);
+        Leaderboard::default()
+    })
+}
+
+// Achievements stored at `path`. A damaged file is reported and counts as nothing unlocked yet; it gets
+// replaced the next time one is unlocked.
+fn load_achievements(path: &std::path::Path) -> Achievements {
+    Achievements::load(path).unwrap_or_else(|err| {
+        eprintln!("copperhead: could not read achievements from {}: {}", path.display(), err);
+        Achievements::default()
+    })
+}
+
+// The run saved at `path`, if it can be continued with `settings`. A damaged file is reported and counts as
+// no saved run; it gets replaced the next time a run is left mid-way.
+fn load_saved_run(path: &std::path::Path, settings: &Settings) -> Option<SavedRun> {
+    let saved = SavedRun::load(path).unwrap_or_else(|err| {
+        eprintln!("copperhead: could not read the saved run from {}: {}", path.display(), err);
+        None
+    })?;
+    (saved.grid == settings.grid && saved.level < settings.campaign.len().max(1)).then_some(saved)
+}
+
+// High scores and lifetime totals stored at `path`. A damaged file is reported and counts as no runs yet; it
+// gets replaced once the next run is over.
+fn load_stats(path: &std::path::Path) -> Stats {
+    Stats::load(path).unwrap_or_else(|err| {
+        eprintln!("copperhead: could not read stats from {}: {}", path.display(), err);
+        Stats::default()
+    })
+}
+
+// Add `dir` to a snake's turn buffer while there is room, unless it wouldn't change the direction the snake
+// will be going in by then (the last buffered turn, or its heading `dir_now`)
+fn buffer_turn(turns: &mut VecDeque<Direction>, dir_now: &Direction, dir: Direction, room: usize) {
+    let heading = turns.back().unwrap_or(dir_now);
+    if turns.len() < room && dir != *heading && dir != heading.opposite() {
+        turns.push_back(dir);
+    }
+}
+
+// Every cell reachable from `start` through 4-neighbors inside the grid, without entering `blocked` cells.
+// `warp` says where a step into a cell really ends up, which is elsewhere across a wrapping edge or through a
+// portal; `blocked` is asked about where it ends up.
+fn flood_fill(layout: BoardLayout, start: (i32, i32), warp: impl Fn((i32, i32)) -> (i32, i32), blocked: impl Fn((i32, i32)) -> bool) -> HashSet<(i32, i32)> {
+    let mut seen = HashSet::from([start]);
+    let mut queue = VecDeque::from([start]);
+    while let Some((x, y)) = queue.pop_front() {
+        for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+            let next = warp((x + dx, y + dy));
+            if layout.contains(next) && !blocked(next) && seen.insert(next) {
+                queue.push_back(next);
+            }
+        }
+    }
+    seen
+}
+
+// Where `snake` should go next: towards the nearest food along a shortest path that avoids `blocked` cells,
+// or failing that, into the direction with the most room to move around in
+fn seek_food(game: &Game, snake: &Snake, blocked: impl Fn((i32, i32)) -> bool + Copy) -> Direction {
+    let head = snake.head();
+    let layout = game.layout();
+    let nearest = game.foods.iter().map(|food| food.pos).min_by_key(|&(x, y)| (x - head.0).abs() + (y - head.1).abs());
+    let warp = |cell| game.portal_exit(cell).unwrap_or(cell);
+    if let Some(dir) = nearest.and_then(|food| first_step(layout, head, food, blocked, warp)) {
+        return dir;
+    }
+
+    [Direction::Up, Direction::Down, Direction::Left, Direction::Right]
+        .into_iter()
+        .filter_map(|dir| {
+            let (dx, dy) = dir.delta();
+            let next = warp((head.0 + dx, head.1 + dy));
+            (!blocked(next)).then(|| (flood_fill(layout, next, warp, blocked).len(), dir))
+        })
+        .max_by_key(|(room, _)| *room)
+        .map_or_else(|| snake.dir.clone(), |(_, dir)| dir)
+}
+
+// Direction of the first step on a shortest path from `start` to `goal` through 4-neighbors inside the grid,
+// without entering `blocked` cells; None if the goal can't be reached. `warp` says where a step into a cell
+// really ends up, which is elsewhere for a portal.
+fn first_step(layout: BoardLayout, start: (i32, i32), goal: (i32, i32), blocked: impl Fn((i32, i32)) -> bool, warp: impl Fn((i32, i32)) -> (i32, i32)) -> Option<Direction> {
+    // Every reached cell remembers which first step led to it
+    let mut first: HashMap<(i32, i32), Direction> = HashMap::from([(start, Direction::Right)]);
+    let mut queue = VecDeque::from([start]);
+    while let Some((x, y)) = queue.pop_front() {
+        for dir in [Direction::Up, Direction::Down, Direction::Left, Direction::Right] {
+            let (dx, dy) = dir.delta();
+            let next = (x + dx, y + dy);
+            if !layout.contains(next) || blocked(next) {
+                continue;
+            }
+            let next = warp(next);
+            if first.contains_key(&next) {
+                continue;
+            }
+            let step = if (x, y) == start { dir } else { first[&(x, y)].clone() };
+            if next == goal {
+                return Some(step);
+            }
+            first.insert(next, step);
+            queue.push_back(next);
+        }
+    }
+    None
+}
+
+// Scatter walls over roughly `density` of the grid. A wall is only kept if the open area stays in one piece,
+// so there are never pockets the snake can't get to. Cells within two steps of `spawn` always stay open.
+fn generate_obstacles(layout: BoardLayout, density: f64, seed: u64, spawn: &[(i32, i32)]) -> HashSet<(i32, i32)> {
+    const SPAWN_MARGIN: i32 = 2;
+    let near_spawn = |(x, y): (i32, i32)| spawn.iter().any(|&(sx, sy)| (x - sx).abs() <= SPAWN_MARGIN && (y - sy).abs() <= SPAWN_MARGIN);
+    let mut candidates: Vec<(i32, i32)> = (0..layout.grid.1)
+        .flat_map(|y| (0..layout.grid.0).map(move |x| (x, y)))
+        .filter(|&cell| layout.contains(cell) && !near_spawn(cell))
+        .collect();
+    let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+    candidates.shuffle(&mut rng);
+
+    let target = (layout.open_cells() as f64 * density).round() as usize;
+    let mut walls = HashSet::new();
+    let Some(&start) = spawn.first() else {
+        return walls;
+    };
+    for cell in candidates {
+        if walls.len() >= target {
+            break;
+        }
+        walls.insert(cell);
+        let open_cells = layout.open_cells() - walls.len();
+        if flood_fill(layout, start, |c| c, |c| walls.contains(&c)).len() != open_cells {
+            walls.remove(&cell); // Would cut the board in two
+        }
+    }
+    walls
+}
+
+// Render visit counts as an image, from the dark border color (never visited) through copper to pale gold (most visited)
+fn heatmap_image(layout: BoardLayout, visits: &[u32]) -> ::image::RgbImage {
+    let stops: [[f32; 3]; 3] = [[0.25, 0.13, 0.05], [0.85, 0.55, 0.22], [1.0, 0.95, 0.80]];
+    let max = visits.iter().copied().max().unwrap_or(0).max(1) as f32;
+    let width = (layout.grid.0 * layout.cell) as u32;
+    let height = (layout.grid.1 * layout.cell) as u32;
+    ::image::RgbImage::from_fn(width, height, |px, py| {
+        let cell = (px as i32 / layout.cell, py as i32 / layout.cell);
+        let count = layout.index(cell).map_or(0, |i| visits[i]);
+        let t = count as f32 / max * 2.0; // Position along the two gradient segments
+        let (from, to, f) = if t <= 1.0 { (stops[0], stops[1], t) } else { (stops[1], stops[2], t - 1.0) };
+        let channel = |i: usize| ((from[i] + (to[i] - from[i]) * f) * 255.0).round() as u8;
+        ::image::Rgb([channel(0), channel(1), channel(2)])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use piston_input::Key;
+
+    // A running game with the snake laid out along `body`, head first, heading `dir`
+    fn game(body: &[(i32, i32)], dir: Direction) -> Game {
+        let mut game = Game::new(&Settings::default());
+        game.snake = Snake::along(dir, body);
+        game.foods = food((19, 19));
+        game.state = GameState::Running;
+        game
+    }
+
+    // A board with just one plain food on it, at `pos`
+    fn food(pos: (i32, i32)) -> Vec<Food> {
+        vec![Food { pos, kind: FoodKind::Normal, respawns: true, spawned_at: 0, moves_left: None }]
+    }
+
+    // A snake that walls off the 2x2 pocket in the top-left corner with its own body, head outside it
+    const WALL_SNAKE: [(i32, i32); 6] = [(0, 3), (0, 2), (1, 2), (2, 2), (2, 1), (2, 0)];
+
+    const POCKET: [(i32, i32); 4] = [(0, 0), (1, 0), (0, 1), (1, 1)];
+
+    #[test]
+    fn food_never_spawns_in_a_walled_off_pocket() {
+        let mut game = game(&WALL_SNAKE, Direction::Down);
+        for _ in 0..500 {
+            game.foods.clear();
+            assert!(game.spawn_food());
+            assert!(!POCKET.contains(&game.foods[0].pos), "food spawned at {:?}", game.foods[0].pos);
+            assert!(!game.snake.body().contains(&game.foods[0].pos));
+        }
+    }
+
+    #[test]
+    fn the_pocket_is_out_of_reach() {
+        let game = game(&WALL_SNAKE, Direction::Down);
+        let reachable = game.reachable_cells();
+        assert!(POCKET.iter().all(|cell| !reachable.contains(cell)));
+        assert!(reachable.contains(&(19, 19)) && reachable.contains(&(3, 0)));
+    }
+
+    #[test]
+    fn no_food_when_the_head_is_boxed_in() {
+        // The head in the corner with its own body on both sides
+        let mut game = game(&[(0, 0), (1, 0), (1, 1), (0, 1)], Direction::Up);
+        game.foods.clear();
+        assert!(!game.spawn_food());
+        assert!(game.foods.is_empty());
+    }
+
+    #[test]
+    fn eating_with_nowhere_left_for_food_ends_the_run() {
+        // Eating the food in the corner closes the head in behind its own body
+        let mut game = game(&[(1, 0), (2, 0), (2, 1), (1, 1), (0, 1), (0, 2)], Direction::Left);
+        game.foods = food((0, 0));
+        game.update();
+        assert!(game.state == GameState::GameOver);
+        assert!(game.death_cause.is_none());
+        assert_eq!(game.score, 1);
+    }
+
+    #[test]
+    fn running_off_the_board_hits_the_wall() {
+        let mut game = game(&[(19, 5), (18, 5), (17, 5)], Direction::Right);
+        game.update();
+        assert!(game.state == GameState::GameOver);
+        assert!(game.death_cause == Some(DeathCause::Wall));
+        assert_eq!(game.death_cause.unwrap().message(), "You hit the wall");
+    }
+
+    #[test]
+    fn turning_back_into_the_body_is_a_self_bite() {
+        let mut game = game(&[(5, 5), (6, 5), (6, 6), (5, 6), (4, 6)], Direction::Down);
+        game.update();
+        assert!(game.state == GameState::GameOver);
+        assert!(game.death_cause == Some(DeathCause::SelfBite));
+        assert_eq!(game.death_cause.unwrap().message(), "You bit yourself");
+    }
+
+    #[test]
+    fn a_safe_move_has_no_collision() {
+        let mut game = game(&[(5, 5), (4, 5), (3, 5)], Direction::Right);
+        game.update();
+        assert!(game.state == GameState::Running);
+        assert!(game.collision().is_none());
+    }
+
+    #[test]
+    fn growth_per_food_sets_how_much_one_food_grows_the_snake() {
+        let mut game = game(&[(5, 5), (4, 5), (3, 5)], Direction::Right);
+        game.settings.growth_per_food = 3;
+        game.foods = food((6, 5));
+        game.update();
+        assert_eq!(game.score, 1);
+        game.foods = food((0, 19)); // Keep the next food out of the way
+        let lengths: Vec<usize> = (0..4)
+            .map(|_| {
+                game.update();
+                game.snake.body().len()
+            })
+            .collect();
+        assert_eq!(lengths, [4, 5, 6, 6]);
+    }
+
+    #[test]
+    fn the_defaults_build() {
+        let settings = Settings::builder().build().expect("the defaults are valid");
+        assert!(settings.start_dir == START_DIRECTION);
+        assert_eq!(settings.growth_per_food, GROWTH_PER_FOOD);
+    }
+
+    #[test]
+    fn a_move_interval_must_be_a_positive_number() {
+        for secs in [0.0, -0.1, f64::NAN, f64::INFINITY] {
+            let err = Settings::builder().move_interval(secs).build().err();
+            assert!(matches!(err, Some(SettingsError::MoveInterval(_))));
+        }
+        assert!(Settings::builder().move_interval(0.05).build().is_ok());
+    }
+
+    #[test]
+    fn time_limits_and_wall_densities_are_checked() {
+        assert_eq!(Settings::builder().time_limit(Some(0.0)).build().err(), Some(SettingsError::TimeLimit(0.0)));
+        assert_eq!(Settings::builder().wall_density(0.9).build().err(), Some(SettingsError::WallDensity(0.9)));
+        assert!(Settings::builder().time_limit(Some(60.0)).wall_density(MAX_WALL_DENSITY).build().is_ok());
+    }
+
+    #[test]
+    fn a_game_is_made_from_its_settings() {
+        let settings = Settings::builder().start_dir(Direction::Up).growth_per_food(3).build().expect("valid settings");
+        let game = Game::new(&settings);
+        assert_eq!(game.snake.head(), (DEFAULT_GRID.0 / 2, DEFAULT_GRID.1 / 2));
+        assert!(game.snake.dir == Direction::Up);
+        assert_eq!(game.settings.growth_per_food, 3);
+        assert!(game.state == GameState::Menu);
+    }
+
+    // A four-long snake curled into the 2x2 square at (5, 5)-(6, 6), head at (5, 6) with the tail above it
+    const CURLED: [(i32, i32); 4] = [(5, 6), (6, 6), (6, 5), (5, 5)];
+
+    #[test]
+    fn chasing_the_tail_is_forgiven_in_classic() {
+        let mut game = game(&CURLED, Direction::Up);
+        game.update();
+        assert_eq!(game.snake.head(), (5, 5));
+        assert!(game.state == GameState::Running);
+    }
+
+    #[test]
+    fn chasing_the_tail_is_a_bite_in_strict() {
+        let mut game = game(&CURLED, Direction::Up);
+        game.settings.tail_chase = TailChase::Strict;
+        game.update();
+        assert!(game.state == GameState::GameOver);
+        assert!(game.death_cause == Some(DeathCause::SelfBite));
+    }
+
+    #[test]
+    fn chasing_a_tail_that_stays_put_is_a_bite_either_way() {
+        for rule in [TailChase::Classic, TailChase::Strict] {
+            let mut game = game(&CURLED, Direction::Up);
+            game.settings.tail_chase = rule;
+            game.snake.grow(1, None);
+            game.update();
+            assert!(game.death_cause == Some(DeathCause::SelfBite));
+        }
+    }
+
+    #[test]
+    fn random_walls_never_cut_off_part_of_the_board() {
+        let spawn = [(10, 10), (9, 10), (8, 10)];
+        for density in [0.05, 0.15, 0.3, 0.5] {
+            for seed in 0..10 {
+                let walls = generate_obstacles(BoardLayout::new(DEFAULT_GRID, DEFAULT_CELL_SIZE, false), density, seed, &spawn);
+                let open = flood_fill(BoardLayout::new(DEFAULT_GRID, DEFAULT_CELL_SIZE, false), spawn[0], |cell| cell, |cell| walls.contains(&cell));
+                assert_eq!(open.len(), (DEFAULT_GRID.0 * DEFAULT_GRID.1) as usize - walls.len(), "seed {} at density {} left a pocket", seed, density);
+                assert!(walls.len() <= ((DEFAULT_GRID.0 * DEFAULT_GRID.1) as f64 * density).round() as usize);
+            }
+        }
+    }
+
+    #[test]
+    fn random_walls_keep_clear_of_the_spawn() {
+        let spawn = [(10, 10), (9, 10), (8, 10)];
+        for seed in 0..10 {
+            let walls = generate_obstacles(BoardLayout::new(DEFAULT_GRID, DEFAULT_CELL_SIZE, false), 0.5, seed, &spawn);
+            for (x, y) in walls {
+                // Two cells around any of (8..=10, 10)
+                assert!(!(6..=12).contains(&x) || (y - 10).abs() > 2, "seed {} walled ({}, {})", seed, x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn random_walls_follow_the_seed() {
+        let walls = |seed| generate_obstacles(BoardLayout::new(DEFAULT_GRID, DEFAULT_CELL_SIZE, false), 0.2, seed, &[(10, 10)]);
+        assert_eq!(walls(3), walls(3));
+        assert_ne!(walls(3), walls(4));
+        assert!(!walls(3).is_empty());
+    }
+
+    #[test]
+    fn running_into_a_rock_is_an_obstacle_death() {
+        let mut game = game(&[(5, 5), (4, 5), (3, 5)], Direction::Right);
+        game.obstacles.insert((6, 5));
+        game.update();
+        assert!(game.death_cause == Some(DeathCause::Obstacle));
+        assert_eq!(game.death_cause.unwrap().message(), "You crashed into a rock");
+    }
+
+    // Steps from the snake's head to `pos`
+    fn from_head(game: &Game, (x, y): (i32, i32)) -> u32 {
+        let (hx, hy) = game.snake.head();
+        (x - hx).unsigned_abs() + (y - hy).unsigned_abs()
+    }
+
+    #[test]
+    fn the_food_magnet_keeps_food_near_the_head() {
+        let mut game = game(&[(10, 10), (9, 10), (8, 10)], Direction::Right);
+        game.settings.food_spawn = FoodSpawn::NearHead { radius: 2 };
+        for _ in 0..500 {
+            game.foods.clear();
+            assert!(game.spawn_food());
+            assert!(from_head(&game, game.foods[0].pos) <= 2, "food spawned at {:?}", game.foods[0].pos);
+        }
+    }
+
+    #[test]
+    fn the_food_magnet_still_skips_taken_cells() {
+        // Block all but one free cell within reach; the food has to land on that one
+        let mut game = game(&[(10, 10), (9, 10), (8, 10)], Direction::Right);
+        game.settings.food_spawn = FoodSpawn::NearHead { radius: 1 };
+        game.obstacles = HashSet::from([(10, 9), (10, 11)]);
+        for _ in 0..100 {
+            game.foods.clear();
+            assert!(game.spawn_food());
+            assert_eq!(game.foods[0].pos, (11, 10));
+        }
+    }
+
+    #[test]
+    fn uniform_food_goes_all_over() {
+        let mut game = game(&[(10, 10), (9, 10), (8, 10)], Direction::Right);
+        let far = (0..200).any(|_| {
+            game.foods.clear();
+            game.spawn_food();
+            from_head(&game, game.foods[0].pos) > 5
+        });
+        assert!(far);
+    }
+
+    #[test]
+    fn a_magnet_needs_some_radius() {
+        let built = Settings::builder().food_spawn(FoodSpawn::NearHead { radius: 0 }).build();
+        assert_eq!(built.err(), Some(SettingsError::MagnetRadius));
+    }
+
+    #[test]
+    fn an_armed_phase_hops_over_a_rock() {
+        let mut game = game(&[(10, 10), (9, 10), (8, 10)], Direction::Right);
+        game.obstacles = HashSet::from([(11, 10)]);
+        game.phase_charges = 1;
+        game.arm_phase();
+        game.update();
+        assert!(game.state == GameState::Running);
+        assert_eq!(game.snake.head(), (12, 10));
+        assert_eq!(game.phase_charges, 0);
+        assert!(!game.phase_armed);
+        assert!(game.phase_flash > 0);
+    }
+
+    #[test]
+    fn an_armed_phase_crosses_the_border() {
+        let mut game = game(&[(19, 10), (18, 10), (17, 10)], Direction::Right);
+        game.phase_charges = 1;
+        game.arm_phase();
+        game.update();
+        assert!(game.state == GameState::Running);
+        assert_eq!(game.snake.head(), (0, 10));
+    }
+
+    #[test]
+    fn a_phase_into_a_blocked_cell_still_kills() {
+        let mut game = game(&[(10, 10), (9, 10), (8, 10)], Direction::Right);
+        game.obstacles = HashSet::from([(11, 10), (12, 10)]);
+        game.phase_charges = 1;
+        game.arm_phase();
+        game.update();
+        assert!(game.death_cause == Some(DeathCause::Obstacle));
+        assert_eq!(game.phase_charges, 0, "the charge is spent all the same");
+    }
+
+    #[test]
+    fn each_phase_spends_one_charge() {
+        let mut game = game(&[(10, 10), (9, 10), (8, 10)], Direction::Right);
+        game.obstacles = HashSet::from([(11, 10), (13, 10)]);
+        game.phase_charges = 2;
+        game.arm_phase();
+        game.update();
+        assert_eq!(game.phase_charges, 1);
+        // Not armed again, so the second rock is fatal
+        game.update();
+        assert!(game.death_cause == Some(DeathCause::Obstacle));
+        assert_eq!(game.phase_charges, 1);
+    }
+
+    #[test]
+    fn charges_are_picked_up_before_they_can_be_armed() {
+        let mut game = game(&[(10, 10), (9, 10), (8, 10)], Direction::Right);
+        game.arm_phase();
+        assert!(!game.phase_armed);
+        game.phase_pickup = Some((11, 10));
+        game.update();
+        assert_eq!(game.phase_charges, 1);
+        assert_eq!(game.phase_pickup, None);
+    }
+
+    // Everything a rewind puts back that the next moves depend on, the place in the random numbers included
+    fn state(game: &Game) -> String {
+        let body: Vec<(i32, i32)> = game.snake.body().iter().copied().collect();
+        format!(
+            "{:?} {:?} {:?} {} {} {:?} {}",
+            body,
+            game.snake.dir.delta(),
+            game.foods[0].pos,
+            game.score,
+            game.snake.pending_growth,
+            game.time_left,
+            game.rng.clone().next_u64(),
+        )
+    }
+
+    // A seeded run with one rewind; seed 12 feeds the snake on the way round (see `scripted_games`)
+    fn rewindable() -> Game {
+        // 20 snapshots fit in the rewind window
+        let settings = Settings::builder().seed(Some(12)).rewinds(1).move_interval(0.15).build().expect("valid settings");
+        let mut game = Game::new(&settings);
+        game.state = GameState::Running;
+        game
+    }
+
+    const ROUTE: [(usize, Key); 5] = [(7, Key::Down), (8, Key::Left), (10, Key::Up), (19, Key::Right), (21, Key::Down)];
+
+    // Make moves from `from` on, pressing the keys of ROUTE on the way, until the run ends. Returns the
+    // state before every move.
+    fn follow_route(game: &mut Game, from: usize) -> Vec<String> {
+        let mut timeline = Vec::new();
+        for moves in from..100 {
+            for &(_, key) in ROUTE.iter().filter(|&&(at, _)| at == moves) {
+                game.press_key(key);
+            }
+            timeline.push(state(game));
+            game.update();
+            if game.state == GameState::GameOver {
+                return timeline;
+            }
+        }
+        panic!("the snake never crashed");
+    }
+
+    #[test]
+    fn a_rewind_puts_back_the_exact_earlier_state() {
+        let mut game = rewindable();
+        let timeline = follow_route(&mut game, 0);
+        assert_eq!(timeline.len(), 39);
+
+        assert!(game.can_rewind());
+        game.rewind();
+        assert!(game.state == GameState::Running);
+        let rewound_to = timeline.len() - 20;
+        assert_eq!(state(&game), timeline[rewound_to]);
+
+        // And the same moves from there play out the same, random food included
+        let replayed = follow_route(&mut game, rewound_to);
+        assert_eq!(replayed, timeline[rewound_to..]);
+        assert!(game.death_cause == Some(DeathCause::Wall));
+    }
+
+    #[test]
+    fn a_rewound_death_leaves_the_high_score_alone() {
+        let mut game = rewindable();
+        follow_route(&mut game, 0);
+        assert_eq!(game.score, 3);
+        assert_eq!(game.high_score, 0, "not while the death can be rewound");
+
+        game.rewind();
+        game.score = 1; // As if the run went worse the second time round
+        game.update();
+        while game.state == GameState::Running {
+            game.update();
+        }
+        assert!(!game.can_rewind());
+        assert_eq!(game.high_score, game.score);
+    }
+
+    #[test]
+    fn restarting_instead_of_rewinding_keeps_the_score() {
+        let mut game = rewindable();
+        follow_route(&mut game, 0);
+        assert!(game.can_rewind());
+        game.press_key(Key::Space);
+        assert!(game.state == GameState::Menu);
+        assert_eq!(game.high_score, 3);
+    }
+
+    #[test]
+    fn the_breakdown_adds_up_with_survival_and_length_points() {
+        let scoring = Scoring { per_food: 5, survival_every: 2, length_bonus: 3, ..Scoring::default() };
+        let settings = Settings::builder().seed(Some(12)).scoring(scoring).build().expect("valid settings");
+        let mut game = Game::new(&settings);
+        game.state = GameState::Running;
+        follow_route(&mut game, 0);
+
+        assert_eq!(game.foods_eaten, 3);
+        assert_eq!(game.moves, 39);
+        assert_eq!(game.breakdown.food, 15);
+        assert_eq!(game.breakdown.survival, 19);
+        assert_eq!(game.breakdown.length, 9, "three segments grown");
+        assert_eq!(game.score, 43);
+    }
+
+    #[test]
+    fn the_length_bonus_is_awarded_once() {
+        let scoring = Scoring { length_bonus: 3, ..Scoring::default() };
+        let settings = Settings::builder().seed(Some(12)).scoring(scoring).build().expect("valid settings");
+        let mut game = Game::new(&settings);
+        game.state = GameState::Running;
+        follow_route(&mut game, 0);
+        assert_eq!(game.score, 3 + 9);
+        game.finish_run();
+        game.press_key(Key::Space);
+        assert_eq!(game.high_score, 12);
+    }
+
+    const SPAWN: [(i32, i32); 3] = [(10, 10), (9, 10), (8, 10)];
+
+    #[test]
+    fn buffered_turns_wait_for_the_move() {
+        let mut game = game(&SPAWN, Direction::Right);
+        game.settings.input_buffer_len = 2;
+        game.queue_turn(Direction::Up);
+        assert!(game.snake.dir == Direction::Right, "the snake keeps going until its next move");
+        game.update();
+        assert_eq!(game.snake.head(), (10, 9));
+    }
+
+    #[test]
+    fn the_buffer_keeps_as_many_turns_as_it_has_room_for() {
+        for (room, heads) in [(1, vec![(10, 9), (10, 8)]), (2, vec![(10, 9), (9, 9)])] {
+            let mut game = game(&SPAWN, Direction::Right);
+            game.settings.input_buffer_len = room;
+            for dir in [Direction::Up, Direction::Left, Direction::Down] {
+                game.queue_turn(dir);
+            }
+            assert_eq!(game.queued_turns.len(), room);
+            let mut seen = Vec::new();
+            for _ in 0..2 {
+                game.update();
+                seen.push(game.snake.head());
+            }
+            assert_eq!(seen, heads, "with room for {}", room);
+        }
+    }
+
+    #[test]
+    fn presses_that_change_nothing_take_no_room() {
+        let mut game = game(&SPAWN, Direction::Right);
+        game.settings.input_buffer_len = 2;
+        for dir in [Direction::Right, Direction::Left, Direction::Up, Direction::Up, Direction::Down] {
+            game.queue_turn(dir);
+        }
+        assert!(game.queued_turns == [Direction::Up]);
+    }
+
+    #[test]
+    fn frame_turns_steer_at_once() {
+        let mut game = game(&SPAWN, Direction::Right);
+        game.settings.apply_turns_on_frame = true;
+        game.queue_turn(Direction::Up);
+        assert!(game.snake.dir == Direction::Up);
+        assert!(game.queued_turns.is_empty());
+        game.update();
+        assert_eq!(game.snake.head(), (10, 9));
+    }
+
+    #[test]
+    fn frame_turns_between_moves_keep_only_the_last() {
+        let mut game = game(&SPAWN, Direction::Right);
+        game.settings.apply_turns_on_frame = true;
+        game.queue_turn(Direction::Up);
+        // Still heading right since the last move, so left would reverse into the neck
+        game.queue_turn(Direction::Left);
+        assert!(game.snake.dir == Direction::Up);
+        game.queue_turn(Direction::Down);
+        game.update();
+        assert_eq!(game.snake.head(), (10, 11));
+    }
+
+    #[test]
+    fn spawned_kinds_follow_the_table() {
+        let table = vec![(FoodKind::Normal, 6.0), (FoodKind::Bonus, 3.0), (FoodKind::Poison, 1.0), (FoodKind::Large, 0.0)];
+        let mut game = game(&SPAWN, Direction::Right);
+        game.settings.food_table = table.clone();
+        const SPAWNS: usize = 2000;
+        let mut counts = [0; 4];
+        for _ in 0..SPAWNS {
+            game.foods.clear();
+            game.spawn_food();
+            let row = table.iter().position(|&(kind, _)| kind == game.foods[0].kind).expect("a kind from the table");
+            counts[row] += 1;
+        }
+        for (row, (_, weight)) in table.iter().enumerate() {
+            let share = counts[row] as f64 / SPAWNS as f64;
+            assert!((share - weight / 10.0).abs() < 0.04, "row {} came up {:.3} of the time", row, share);
+        }
+    }
+
+    #[test]
+    fn a_one_kind_table_spawns_only_that_kind() {
+        let mut game = game(&SPAWN, Direction::Right);
+        game.settings.food_table = vec![(FoodKind::Bonus, 1.0)];
+        for _ in 0..100 {
+            game.foods.clear();
+            game.spawn_food();
+            assert!(game.foods[0].kind == FoodKind::Bonus);
+        }
+    }
+
+    #[test]
+    fn food_tables_need_positive_weights() {
+        let build = |table| Settings::builder().food_table(table).build().err();
+        assert_eq!(build(vec![(FoodKind::Normal, 1.0), (FoodKind::Poison, -1.0)]), Some(SettingsError::FoodTable));
+        assert_eq!(build(vec![(FoodKind::Normal, 0.0), (FoodKind::Bonus, 0.0)]), Some(SettingsError::FoodTable));
+        assert_eq!(build(Vec::new()), Some(SettingsError::FoodTable));
+    }
+
+    #[test]
+    fn each_kind_has_its_own_effect() {
+        // Points and length after eating one of `kind` just ahead of the snake
+        let eat = |kind| {
+            let mut game = game(&SPAWN, Direction::Right);
+            game.foods = food((11, 10));
+            game.foods[0].kind = kind;
+            game.snake.grow(2, None);
+            game.update();
+            game.foods = food((0, 19)); // Keep the next food out of the way
+            game.update();
+            (game.score, game.snake.body().len() + game.snake.pending_growth as usize)
+        };
+        assert_eq!(eat(FoodKind::Normal), (1, 6));
+        assert_eq!(eat(FoodKind::Bonus), (BONUS_FOOD_MULTIPLIER, 6));
+        assert_eq!(eat(FoodKind::Large), (1, 5 + LARGE_FOOD_MULTIPLIER as usize));
+        assert_eq!(eat(FoodKind::Poison), (0, 5 - POISON_SHRINK as usize));
+    }
+
+    #[test]
+    fn autopilot_takes_the_shortest_way_to_the_food() {
+        let mut game = game(&SPAWN, Direction::Right);
+        game.foods = food((10, 4));
+        assert!(game.autopilot() == Direction::Up);
+    }
+
+    #[test]
+    fn autopilot_stays_alive_when_the_food_is_cut_off() {
+        let mut game = game(&WALL_SNAKE, Direction::Down);
+        game.foods = food((0, 0));
+        let (dx, dy) = game.autopilot().delta();
+        let next = (dx, 3 + dy);
+        assert!(next.0 >= 0 && next.1 < DEFAULT_GRID.1 && !game.snake.body().contains(&next), "{next:?}");
+    }
+
+    #[test]
+    fn board_alignments_parse_by_name() {
+        assert!("bottom-right".parse::<BoardAlign>() == Ok(BoardAlign::BottomRight));
+        assert!("center".parse::<BoardAlign>() == Ok(BoardAlign::Center));
+        assert!("middle".parse::<BoardAlign>().is_err());
+    }
+
+    #[test]
+    fn only_visible_changes_ask_for_a_redraw() {
+        let mut game = game(&SPAWN, Direction::Right);
+        game.clear_dirty();
+        game.tick_clock(0.05);
+        assert!(!game.is_dirty(), "no clock on screen");
+        game.update();
+        assert!(game.is_dirty(), "the snake moved");
+
+        game.clear_dirty();
+        game.state = GameState::GameOver;
+        game.update();
+        game.queue_turn(Direction::Up);
+        assert!(!game.is_dirty(), "nothing moves once the run is over");
+    }
+
+    #[test]
+    fn the_clock_asks_for_a_redraw_when_its_tenth_changes() {
+        let mut game = game(&SPAWN, Direction::Right);
+        game.settings.time_limit = Some(10.0);
+        game.modes = modes_for(&game.settings);
+        game.time_left = Some(10.0);
+        game.clear_dirty();
+        game.tick_clock(0.01);
+        assert!(!game.is_dirty(), "still shows 10.0");
+        game.tick_clock(0.1);
+        assert!(game.is_dirty());
+    }
+
+    // Length counting growth still to come after each of `moves` moves to the right, with food waiting
+    // in the next cell for the first six of them
+    fn lengths_eating_a_row(game: &mut Game, moves: usize) -> Vec<usize> {
+        (0..moves)
+            .map(|i| {
+                let (x, y) = game.snake.head();
+                game.foods = food(if i < 6 { (x + 1, y) } else { (0, 19) });
+                game.update();
+                game.snake.body().len() + game.snake.pending_growth as usize
+            })
+            .collect()
+    }
+
+    #[test]
+    fn the_length_cap_stops_growth_but_not_scoring() {
+        let mut game = game(&SPAWN, Direction::Right);
+        game.settings.max_length = Some(5);
+        assert_eq!(lengths_eating_a_row(&mut game, 8), [4, 5, 5, 5, 5, 5, 5, 5]);
+        assert_eq!(game.foods_eaten, 6);
+        assert_eq!(game.score, 6);
+    }
+
+    #[test]
+    fn growing_every_third_food() {
+        let mut game = game(&SPAWN, Direction::Right);
+        game.settings.grow_every = 3;
+        assert_eq!(lengths_eating_a_row(&mut game, 6), [3, 3, 4, 4, 4, 5]);
+        assert_eq!(game.score, 6);
+    }
+
+    #[test]
+    fn the_cap_and_the_cadence_work_together() {
+        let mut game = game(&SPAWN, Direction::Right);
+        game.settings.grow_every = 2;
+        game.settings.max_length = Some(4);
+        assert_eq!(lengths_eating_a_row(&mut game, 6), [3, 4, 4, 4, 4, 4]);
+    }
+
+    #[test]
+    fn growth_settings_are_validated() {
+        assert!(matches!(Settings::builder().grow_every(0).build().err(), Some(SettingsError::GrowEvery)));
+        let too_short = Settings::builder().max_length(Some(START_LENGTH - 1)).build().err();
+        assert!(matches!(too_short, Some(SettingsError::MaxLength(_))));
+        assert!(Settings::builder().max_length(Some(START_LENGTH)).build().is_ok());
+    }
+
+    #[test]
+    fn the_ui_scale_is_validated() {
+        for scale in [0.4, 2.1, f64::NAN] {
+            assert!(matches!(Settings::builder().ui_scale(scale).build().err(), Some(SettingsError::UiScale(_))));
+        }
+        assert!(Settings::builder().ui_scale(2.0).build().is_ok());
+    }
+
+    #[test]
+    fn a_head_boxed_in_by_rocks_is_trapped() {
+        let mut game = game(&SPAWN, Direction::Right);
+        assert!(!game.is_trapped());
+        // The neck is behind the head, rocks on the other three sides
+        game.obstacles = HashSet::from([(11, 10), (10, 9), (10, 11)]);
+        assert!(game.is_trapped());
+        game.phase_charges = 1;
+        assert!(!game.is_trapped(), "a phase charge is a way out");
+    }
+
+    #[test]
+    fn a_trap_ends_the_run_when_asked_to() {
+        for end in [true, false] {
+            let mut game = game(&SPAWN, Direction::Right);
+            game.settings.end_when_trapped = end;
+            game.obstacles = HashSet::from([(12, 10), (11, 9), (11, 11)]);
+            game.update();
+            assert!((game.state == GameState::GameOver) == end);
+            assert!(game.is_trapped() != end, "a run that is over isn't trapped any more");
+            if end {
+                assert!(game.death_cause == Some(DeathCause::Trapped));
+            }
+        }
+    }
+
+    #[test]
+    fn a_tail_about_to_leave_is_a_way_out() {
+        for (rule, trapped) in [(TailChase::Classic, false), (TailChase::Strict, true)] {
+            let mut game = game(&CURLED, Direction::Left);
+            game.settings.tail_chase = rule;
+            // Body on one side, the tail just above the head, and rocks on the rest
+            game.obstacles = HashSet::from([(4, 6), (5, 7)]);
+            assert_eq!(game.is_trapped(), trapped);
+        }
+    }
+
+    #[test]
+    fn the_results_leave_out_features_that_are_off() {
+        let mut game = game(&SPAWN, Direction::Right);
+        let labels: Vec<&str> = game.results().iter().map(|&(label, _)| label).collect();
+        assert_eq!(labels, ["Score", "Highest", "Length", "Food eaten", "Time survived", "Seed"]);
+
+        game.settings.time_limit = Some(60.0);
+        game.time_left = Some(30.0);
+        game.score = 10;
+        game.settings.scoring.length_bonus = 1;
+        let results = game.results();
+        assert_eq!(results[0], ("Score", "10 (20.0/min)".to_string()));
+        assert_eq!(results[1].0, "Food+time+size");
+    }
+
+    #[test]
+    fn a_seed_replays_every_run_not_just_the_first() {
+        let mut game = Game::new(&Settings::builder().seed(Some(7)).build().unwrap());
+        let first = (game.run_seed, game.foods[0].pos);
+        game.state = GameState::GameOver;
+        game.reset();
+        assert_eq!((game.run_seed, game.foods[0].pos), first);
+    }
+
+    #[test]
+    fn the_time_attack_clock_runs_at_game_speed() {
+        let mut game = game(&SPAWN, Direction::Right);
+        game.settings.time_limit = Some(10.0);
+        game.modes = modes_for(&game.settings);
+        game.time_left = Some(10.0);
+        game.time_scale = 0.5;
+        for _ in 0..60 {
+            game.tick_clock(game.game_dt(1.0 / 60.0));
+        }
+        assert!((game.time_left.unwrap() - 9.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn a_time_scale_must_be_a_positive_number() {
+        for scale in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(matches!(Settings::builder().time_scale(scale).build().err(), Some(SettingsError::TimeScale(_))));
+        }
+        assert!(Settings::builder().time_scale(0.25).build().is_ok());
+    }
+
+    #[test]
+    fn a_finished_run_queues_one_json_line() {
+        let mut game = game(&SPAWN, Direction::Right);
+        game.settings.json_results = true;
+        game.run_seed = 5;
+        game.game_over(Some(DeathCause::SelfBite));
+        game.finish_run();
+        game.finish_run();
+        assert_eq!(game.json_lines, [r#"{"score":0,"length":3,"ticks":0,"cause":"SelfBite","seed":5}"#]);
+    }
+
+    // A game under `walls` with the snake about to cross the right edge, having scored `score` and with
+    // `grown` segments still to grow
+    fn at_the_edge(walls: WallMode, score: u32, grown: u32) -> Game {
+        let mut game = game(&[(19, 10), (18, 10), (17, 10)], Direction::Right);
+        game.settings.wall_mode = walls;
+        game.modes = modes_for(&game.settings);
+        game.snake.grow(grown, None);
+        game.score = score;
+        game
+    }
+
+    #[test]
+    fn a_lethal_edge_kills() {
+        let mut game = at_the_edge(WallMode::Lethal, 5, 0);
+        game.update();
+        assert!(game.death_cause == Some(DeathCause::Wall));
+    }
+
+    #[test]
+    fn a_wrapping_edge_is_free() {
+        let mut game = at_the_edge(WallMode::Wrap, 5, 0);
+        game.update();
+        assert_eq!(game.snake.head(), (0, 10));
+        assert_eq!(game.score, 5);
+        assert_eq!(game.snake.body().len(), START_LENGTH);
+        assert_eq!(game.wrap_flash, 0);
+    }
+
+    #[test]
+    fn a_penalty_edge_costs_points_and_length() {
+        let mut game = at_the_edge(WallMode::WrapPenalty { point_cost: 2, shrink: 1 }, 5, 2);
+        game.update();
+        assert!(game.state == GameState::Running);
+        assert_eq!(game.snake.head(), (0, 10));
+        assert_eq!(game.score, 3);
+        assert_eq!(game.snake.body().len() + game.snake.pending_growth as usize, START_LENGTH + 1);
+        assert!(game.wrap_flash > 0);
+    }
+
+    #[test]
+    fn a_penalty_never_goes_below_nothing() {
+        let mut game = at_the_edge(WallMode::WrapPenalty { point_cost: 3, shrink: 2 }, 1, 0);
+        game.update();
+        assert!(game.state == GameState::Running);
+        assert_eq!(game.score, 0);
+        assert_eq!(game.snake.body().len(), START_LENGTH);
+    }
+
+    // A full-height wall in column 5 cuts the board in two, with the snake on the right
+    fn split_board(mode: WallMode) -> Game {
+        let mut game = game(&SPAWN, Direction::Right);
+        game.settings.wall_mode = mode;
+        game.obstacles = (0..20).map(|y| (5, y)).collect();
+        game
+    }
+
+    #[test]
+    fn lethal_edges_keep_the_far_side_out_of_reach() {
+        let game = split_board(WallMode::Lethal);
+        let free = game.free_cells();
+        assert!(!free.iter().any(|&(x, _)| x < 5));
+        assert!(free.contains(&(19, 10)));
+    }
+
+    #[test]
+    fn wrapping_edges_reach_the_far_side() {
+        for mode in [WallMode::Wrap, WallMode::WrapPenalty { point_cost: 1, shrink: 0 }] {
+            let game = split_board(mode);
+            let free = game.free_cells();
+            assert!(free.contains(&(0, 10)) && free.contains(&(4, 0)));
+        }
+    }
+
+    // The snake about to eat the last food in the right half, with every other cell there a rock: only a way
+    // across the edge leaves room for more food
+    fn last_bite(mode: WallMode) -> Game {
+        let mut game = split_board(mode);
+        game.snake = Snake::along(Direction::Right, &[(18, 10), (17, 10), (16, 10)]);
+        let open = |cell: (i32, i32)| cell.0 < 5 || cell == (19, 10) || game.snake.body().contains(&cell);
+        game.obstacles = (0..20).flat_map(|y| (0..20).map(move |x| (x, y))).filter(|&cell| !open(cell)).collect();
+        game.foods = food((19, 10));
+        game
+    }
+
+    #[test]
+    fn a_sealed_off_last_bite_ends_the_run() {
+        let mut game = last_bite(WallMode::Lethal);
+        game.update();
+        assert!(game.state == GameState::GameOver && game.death_cause.is_none());
+    }
+
+    #[test]
+    fn a_last_bite_next_to_a_wrapping_edge_does_not() {
+        let mut game = last_bite(WallMode::Wrap);
+        game.update();
+        assert!(game.state == GameState::Running);
+        assert_eq!(game.score, 1);
+        assert!(game.foods[0].pos.0 < 5);
+    }
+
+    // Every food cell on the board is its own and off the snake and the rocks
+    fn assert_food_apart(game: &Game) {
+        let cells: HashSet<(i32, i32)> = game.foods.iter().map(|food| food.pos).collect();
+        assert_eq!(cells.len(), game.foods.len(), "two foods share a cell");
+        for (x, y) in cells {
+            assert!(!game.snake.body().contains(&(x, y)) && !game.obstacles.contains(&(x, y)), "food lies under something at {:?}", (x, y));
+            assert!(x >= 0 && x < DEFAULT_GRID.0 && y >= 0 && y < DEFAULT_GRID.1);
+        }
+    }
+
+    #[test]
+    fn a_run_starts_with_the_configured_food() {
+        for count in [1, 5, 40] {
+            for seed in 0..5 {
+                let settings = Settings::builder().seed(Some(seed)).food_count(count).wall_density(0.1).build().expect("valid settings");
+                let game = Game::new(&settings);
+                assert_eq!(game.foods.len(), count);
+                assert_food_apart(&game);
+            }
+        }
+    }
+
+    #[test]
+    fn placed_items_are_where_they_were_put() {
+        let placed = vec![(FoodKind::Bonus, (2, 2)), (FoodKind::Large, (17, 3)), (FoodKind::Poison, (5, 15))];
+        let settings = Settings::builder().food_count(3).placed_food(placed.clone()).build().expect("valid settings");
+        let mut game = Game::new(&settings);
+        for _ in 0..2 {
+            assert_eq!(game.foods.len(), 6);
+            for (&(kind, pos), food) in placed.iter().zip(&game.foods) {
+                assert!(food.kind == kind && food.pos == pos);
+                assert!(!food.respawns, "a placed item is there once");
+            }
+            assert_food_apart(&game);
+            // The next run starts with the same items
+            game.reset();
+        }
+    }
+
+    #[test]
+    fn the_board_fills_up_with_food() {
+        // 400 cells, 3 of them taken by the snake
+        let game = Game::new(&Settings::builder().food_count(397).build().expect("valid settings"));
+        assert_eq!(game.foods.len(), 397);
+        assert_food_apart(&game);
+        assert!(matches!(Settings::builder().food_count(398).build().err(), Some(SettingsError::FoodCount(398))));
+        assert!(matches!(Settings::builder().food_count(0).build().err(), Some(SettingsError::FoodCount(0))));
+    }
+
+    #[test]
+    fn placed_items_must_be_clear_and_on_the_board() {
+        for cell in [(20, 0), (-1, 5), (10, 10)] {
+            let err = Settings::builder().placed_food(vec![(FoodKind::Normal, cell)]).build().err();
+            assert!(matches!(err, Some(SettingsError::PlacedFood(c)) if c == cell), "{:?}", cell);
+        }
+        let twice = vec![(FoodKind::Normal, (3, 3)), (FoodKind::Bonus, (3, 3))];
+        assert!(matches!(Settings::builder().placed_food(twice).build().err(), Some(SettingsError::PlacedFood((3, 3)))));
+    }
+
+    #[test]
+    fn the_border_frames_exactly_the_deadly_edge() {
+        let layout = BoardLayout::new(DEFAULT_GRID, DEFAULT_CELL_SIZE, false);
+        let [ox, oy] = layout.playfield_origin();
+        let [top, bottom, left, right] = layout.border_rects();
+        // The inner edges of the four bars
+        let inner = [left[0] + left[2], top[1] + top[3], right[0], bottom[1]];
+        let [x, y, w, h] = layout.open_rect();
+        assert_eq!(inner, [ox + x, oy + y, ox + x + w, oy + y + h]);
+    }
+
+    #[test]
+    fn a_cell_is_deadly_exactly_when_it_lies_outside_the_open_area() {
+        let cell = DEFAULT_CELL_SIZE as f64;
+        for wall_ring in [false, true] {
+            let layout = BoardLayout::new(DEFAULT_GRID, DEFAULT_CELL_SIZE, wall_ring);
+            let [x, y, w, h] = layout.open_rect();
+            for cy in -1..=DEFAULT_GRID.1 {
+                for cx in -1..=DEFAULT_GRID.0 {
+                    let (mx, my) = ((cx as f64 + 0.5) * cell, (cy as f64 + 0.5) * cell);
+                    let drawn_open = mx > x && mx < x + w && my > y && my < y + h;
+                    assert_eq!(layout.contains((cx, cy)), drawn_open, "cell ({}, {}) with the wall ring {}", cx, cy, wall_ring);
+                    let on_grid = (0..DEFAULT_GRID.0).contains(&cx) && (0..DEFAULT_GRID.1).contains(&cy);
+                    assert_eq!(layout.in_ring((cx, cy)), wall_ring && on_grid && !drawn_open);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn the_wall_ring_is_deadly() {
+        let mut game = game(&[(17, 10), (16, 10), (15, 10)], Direction::Right);
+        game.settings.wall_ring = true;
+        game.update();
+        assert!(game.state == GameState::Running);
+        game.update();
+        assert!(game.death_cause == Some(DeathCause::Wall));
+        assert_eq!(game.snake.head(), (19, 10));
+    }
+
+    const DECAY: FoodDecay = FoodDecay { max: 10, floor: 2, half_life: 4 };
+
+    #[test]
+    fn decaying_food_halves_down_to_its_floor() {
+        assert_eq!(DECAY.value(0), 10);
+        assert_eq!(DECAY.value(4), 6);
+        assert_eq!(DECAY.value(8), 4);
+        assert_eq!(DECAY.value(1000), 2);
+        assert!((0..40).all(|age| DECAY.value(age + 1) <= DECAY.value(age)), "never gains value");
+    }
+
+    // A game with decaying food, and one food right ahead of the snake
+    fn decaying() -> Game {
+        let mut game = game(&SPAWN, Direction::Right);
+        game.settings.scoring.food_decay = Some(DECAY);
+        game.foods = food((11, 10));
+        game
+    }
+
+    #[test]
+    fn food_eaten_at_once_is_worth_the_most() {
+        let mut game = decaying();
+        game.update();
+        assert_eq!(game.foods_eaten, 1);
+        assert_eq!(game.score, 10);
+    }
+
+    #[test]
+    fn food_left_waiting_is_worth_the_floor() {
+        let mut game = decaying();
+        // As if the snake had taken a long way round to the food
+        game.moves = 500;
+        assert_eq!(game.food_value(&game.foods[0], game.moves), 2);
+        game.update();
+        assert_eq!(game.score, 2);
+    }
+
+    #[test]
+    fn decay_needs_a_floor_below_the_start_and_a_half_life() {
+        let build = |decay| Settings::builder().scoring(Scoring { food_decay: Some(decay), ..Scoring::default() }).build().err();
+        assert!(build(FoodDecay { max: 2, floor: 5, half_life: 4 }) == Some(SettingsError::FoodDecay));
+        assert!(build(FoodDecay { max: 10, floor: 2, half_life: 0 }) == Some(SettingsError::FoodDecay));
+        assert!(build(DECAY).is_none());
+    }
+
+    // An example of a mode written against the hooks alone, the way a fork would add one: food stays off the
+    // board's edge, each food is worth MOD_BONUS more, and the first few crashes into a wall only put the
+    // snake back in the middle
+    const MOD_BONUS: u32 = 2;
+
+    struct Sanctuary {
+        lives: u32,
+    }
+
+    impl GameMode for Sanctuary {
+        fn on_spawn_food(&mut self, _game: &Game, candidates: &mut Vec<(i32, i32)>) {
+            let (columns, rows) = DEFAULT_GRID;
+            candidates.retain(|&(x, y)| x > 0 && y > 0 && x < columns - 1 && y < rows - 1);
+        }
+
+        fn on_food_eaten(&mut self, game: &mut Game, _food: Food) {
+            game.award(ScoreSource::Food, MOD_BONUS);
+        }
+
+        fn on_death(&mut self, game: &mut Game, cause: DeathCause) -> bool {
+            if cause != DeathCause::Wall || self.lives == 0 {
+                return true;
+            }
+            self.lives -= 1;
+            game.snake = Snake::new(game.settings.start_dir.clone(), DEFAULT_GRID);
+            false
+        }
+    }
+
+    fn sanctuary(lives: u32) -> Game {
+        let mut game = game(&SPAWN, Direction::Right);
+        game.modes.push(Box::new(Sanctuary { lives }));
+        game
+    }
+
+    #[test]
+    fn a_custom_mode_keeps_food_off_the_edge() {
+        let mut game = sanctuary(0);
+        for _ in 0..500 {
+            game.foods.clear();
+            assert!(game.spawn_food());
+            let (x, y) = game.foods[0].pos;
+            assert!(x > 0 && y > 0 && x < 19 && y < 19, "food spawned at {:?}", (x, y));
+        }
+    }
+
+    #[test]
+    fn a_custom_mode_adds_to_the_score() {
+        let mut game = sanctuary(0);
+        game.foods = food((11, 10));
+        game.update();
+        assert_eq!(game.foods_eaten, 1);
+        assert_eq!(game.score, 1 + MOD_BONUS);
+    }
+
+    #[test]
+    fn a_custom_mode_can_refuse_a_death() {
+        let mut game = sanctuary(1);
+        game.foods.clear();
+        game.turn(Direction::Up);
+        // Ten moves up reach the top row, the eleventh runs into the wall
+        for _ in 0..11 {
+            game.update();
+        }
+        assert_eq!(game.snake.head(), (10, 10), "the first crash puts the snake back");
+        assert!(game.state == GameState::Running);
+
+        game.turn(Direction::Up);
+        for _ in 0..11 {
+            game.update();
+        }
+        assert!(game.death_cause == Some(DeathCause::Wall));
+    }
+
+    #[test]
+    fn eating_throws_sparks_until_they_burn_out() {
+        let mut game = game(&[(5, 5), (4, 5), (3, 5)], Direction::Right);
+        game.foods = food((6, 5));
+        game.update();
+        assert!(!game.particles.is_empty());
+        game.dirty = false;
+        game.tick_clock(0.1);
+        assert!(game.dirty, "sparks in the air keep the frame changing");
+        game.tick_clock(1.0);
+        assert!(game.particles.is_empty());
+    }
+
+    #[test]
+    fn every_seventh_food_brings_a_golden_one() {
+        let mut game = game(&[(5, 5), (4, 5), (3, 5)], Direction::Right);
+        game.foods = food((6, 5));
+        game.foods_eaten = GOLDEN_FOOD_EVERY - 1;
+        game.update();
+        let golden: Vec<&Food> = game.foods.iter().filter(|food| food.kind == FoodKind::Golden).collect();
+        assert_eq!(golden.len(), 1);
+        assert_eq!(golden[0].moves_left, Some(game.golden_food_moves()));
+        assert!(!game.snake.body().contains(&golden[0].pos));
+    }
+
+    #[test]
+    fn golden_food_disappears_when_its_time_is_up() {
+        let mut game = game(&[(5, 5), (4, 5), (3, 5)], Direction::Right);
+        game.foods.push(Food { pos: (15, 15), kind: FoodKind::Golden, respawns: false, spawned_at: 0, moves_left: Some(2) });
+        game.update();
+        assert!(game.foods.iter().any(|food| food.kind == FoodKind::Golden && food.moves_left == Some(1)));
+        game.update();
+        assert!(!game.foods.iter().any(|food| food.kind == FoodKind::Golden));
+    }
+
+    #[test]
+    fn golden_food_is_worth_five_normal_ones() {
+        let mut game = game(&[(5, 5), (4, 5), (3, 5)], Direction::Right);
+        game.foods = vec![Food { pos: (6, 5), kind: FoodKind::Golden, respawns: false, spawned_at: 0, moves_left: Some(10) }];
+        game.update();
+        assert_eq!(game.score, GOLDEN_FOOD_MULTIPLIER);
+        assert!(game.foods.is_empty(), "a golden food isn't replaced");
+    }
+
+    #[test]
+    fn golden_food_lasts_the_same_time_at_any_speed() {
+        let mut game = Game::new(&Settings::default());
+        game.settings.move_interval = 0.1;
+        assert_eq!(game.golden_food_moves(), 50);
+        game.settings.move_interval = 0.25;
+        assert_eq!(game.golden_food_moves(), 20);
+    }
+
+    #[test]
+    fn power_ups_show_up_every_hundred_moves() {
+        let mut game = game(&[(5, 5), (4, 5), (3, 5)], Direction::Right);
+        game.settings.power_ups = true;
+        game.moves = POWER_UP_EVERY - 1;
+        game.update();
+        let (_, pos) = game.power_up.expect("a power-up on the board");
+        assert!(!game.snake.body().contains(&pos) && !game.foods.iter().any(|food| food.pos == pos));
+    }
+
+    #[test]
+    fn a_ghost_passes_through_itself() {
+        // Heading down into its own body, which would bite it
+        let body = [(5, 5), (6, 5), (6, 6), (5, 6), (4, 6)];
+        let mut game = game(&body, Direction::Down);
+        assert!(game.is_fatal((5, 6)));
+        game.update();
+        assert!(game.death_cause == Some(DeathCause::SelfBite));
+
+        let mut game = self::game(&body, Direction::Down);
+        game.effects = vec![(PowerUp::Ghost, 5)];
+        assert!(!game.is_fatal((5, 6)));
+        game.update();
+        assert!(game.state == GameState::Running);
+        assert_eq!(game.effect_moves(PowerUp::Ghost), 4);
+    }
+
+    #[test]
+    fn running_over_a_power_up_takes_its_effect() {
+        let mut game = game(&[(5, 5), (4, 5), (3, 5)], Direction::Right);
+        game.power_up = Some((PowerUp::Ghost, (6, 5)));
+        game.update();
+        assert!(game.power_up.is_none());
+        assert_eq!(game.effect_moves(PowerUp::Ghost), POWER_UP_MOVES);
+    }
+
+    #[test]
+    fn speed_and_slow_motion_cancel_each_other_and_run_out() {
+        let mut game = game(&[(5, 5), (4, 5), (3, 5)], Direction::Right);
+        assert_eq!(game.move_speed(), 1.0);
+        game.apply_power_up(PowerUp::Speed);
+        assert_eq!(game.move_speed(), SPEED_BOOST);
+        game.apply_power_up(PowerUp::SlowMotion);
+        assert_eq!(game.move_speed(), SLOW_MOTION);
+        assert_eq!(game.effects.len(), 1);
+        game.effects[0].1 = 1;
+        game.update();
+        assert_eq!(game.move_speed(), 1.0);
+        assert!(game.effects.is_empty());
+    }
+
+    #[test]
+    fn shrink_takes_segments_off_at_once() {
+        let mut game = game(&[(8, 5), (7, 5), (6, 5), (5, 5), (4, 5), (3, 5)], Direction::Right);
+        game.apply_power_up(PowerUp::Shrink);
+        assert_eq!(game.snake.body().len(), START_LENGTH);
+        assert!(game.effects.is_empty());
+    }
+
+    // A two-player run with player two laid out as `rival`, the food out of the way
+    fn two_player_game(rival: Snake) -> Game {
+        versus(Players::Two, rival)
+    }
+
+    // A run with a second snake laid out as `rival`, steered by `players`, the food out of the way
+    fn versus(players: Players, rival: Snake) -> Game {
+        let mut game = Game::new(&Settings { players, ..Settings::default() });
+        if let Some(player_two) = game.rival.as_mut() {
+            player_two.snake = rival;
+        }
+        game.foods = food((0, 0));
+        game.state = GameState::Running;
+        game
+    }
+
+    #[test]
+    fn two_players_start_apart_facing_opposite_ways() {
+        let game = Game::new(&Settings { players: Players::Two, ..Settings::default() });
+        let rival = game.rival.as_ref().expect("a player two");
+        assert!(rival.snake.dir == game.snake.dir.opposite());
+        assert!(!rival.snake.body().iter().any(|cell| game.snake.body().contains(cell)));
+        assert!(!game.foods.iter().any(|food| game.snake.body().contains(&food.pos) || rival.snake.body().contains(&food.pos)));
+    }
+
+    #[test]
+    fn running_into_the_other_snake_loses() {
+        // Player one heads right from (10, 7) into player two's body coming down through (11, 7)
+        let mut game = two_player_game(Snake::at(Direction::Down, (11, 8)));
+        game.update();
+        assert!(game.state == GameState::GameOver);
+        assert!(game.death_cause == Some(DeathCause::Rival));
+        assert!(game.rival.as_ref().is_some_and(|rival| rival.death_cause.is_none()));
+        assert_eq!(game.outcome(), ("PLAYER 2 WINS!", "Player 1 crashed"));
+    }
+
+    #[test]
+    fn a_head_on_crash_is_a_draw() {
+        let mut game = two_player_game(Snake::at(Direction::Left, (12, 7)));
+        game.update();
+        assert!(game.death_cause == Some(DeathCause::Rival));
+        assert!(game.rival.as_ref().is_some_and(|rival| rival.death_cause == Some(DeathCause::Rival)));
+        assert_eq!(game.outcome(), ("DRAW!", "Both snakes crashed"));
+    }
+
+    #[test]
+    fn two_player_runs_leave_the_high_score_alone() {
+        let mut game = two_player_game(Snake::at(Direction::Left, (12, 7)));
+        game.score = 50;
+        game.update();
+        game.finish_run();
+        assert_eq!(game.high_score, 0);
+    }
+
+    #[test]
+    fn the_computer_goes_for_the_food() {
+        let mut game = versus(Players::Computer, Snake::at(Direction::Left, (10, 13)));
+        game.foods = food((10, 16));
+        game.update();
+        assert!(game.rival.as_ref().is_some_and(|rival| rival.snake.head() == (10, 14)));
+    }
+
+    #[test]
+    fn the_computer_keeps_away_from_the_players_head() {
+        // Up through (10, 8) is as short a way to the food as left through (9, 9), but (10, 8) is next to
+        // player one's head at (10, 7)
+        let mut game = versus(Players::Computer, Snake::at(Direction::Up, (10, 9)));
+        game.foods = food((9, 8));
+        game.update();
+        assert!(game.rival.as_ref().is_some_and(|rival| rival.snake.head() == (9, 9)));
+    }
+
+    #[test]
+    fn the_game_over_screen_talks_to_the_player_against_the_computer() {
+        let mut game = versus(Players::Computer, Snake::at(Direction::Up, (5, 0)));
+        game.rival_controller = Box::new(Keys); // Let it run into the wall
+        game.update();
+        assert!(game.state == GameState::GameOver);
+        assert_eq!(game.outcome(), ("YOU WIN!", "The computer crashed"));
+    }
+
+    #[test]
+    fn the_start_screen_cycles_through_who_plays() {
+        let mut game = Game::new(&Settings::default());
+        game.cycle_players();
+        // With nothing pressed, only the computer has a move of its own for player two
+        assert!(game.settings.players == Players::Two && game.rival.is_some() && game.next_move(true).is_none());
+        game.cycle_players();
+        assert!(game.settings.players == Players::Computer && game.rival.is_some() && game.next_move(true).is_some());
+        game.cycle_players();
+        assert!(game.settings.players == Players::One && game.rival.is_none());
+    }
+
+    // Always heads one way, to tell a controller's moves from the keys'
+    struct Heading(Direction);
+
+    impl Controller for Heading {
+        fn next_move(&mut self, _: &GameView) -> Option<Direction> {
+            Some(self.0.clone())
+        }
+    }
+
+    #[test]
+    fn a_view_sees_the_game_from_the_side_of_its_snake() {
+        let mut game = two_player_game(Snake::at(Direction::Left, (12, 9)));
+        game.rival.as_mut().unwrap().queued_turns.push_back(Direction::Down);
+        let one = GameView { game: &game, rival: false };
+        let two = GameView { game: &game, rival: true };
+        assert_eq!(one.snake().head(), game.snake.head());
+        assert_eq!(one.other().map(Snake::head), Some((12, 9)));
+        assert_eq!(two.snake().head(), (12, 9));
+        assert_eq!(two.other().map(Snake::head), Some(game.snake.head()));
+        assert!(one.queued_turns().is_empty());
+        assert!(two.queued_turns().iter().eq([&Direction::Down]));
+        // Each snake may run into its own tail as it moves away, but not into the other's
+        let tail = game.snake.body().back().cloned().unwrap();
+        assert!(!one.is_fatal(tail) && two.is_fatal(tail));
+        assert!(one.is_fatal((-1, 9)) && two.is_fatal((-1, 9)));
+    }
+
+    #[test]
+    fn the_keys_play_the_queued_turns_one_per_move() {
+        let mut game = game(&SPAWN, Direction::Right);
+        let (x, y) = game.snake.head();
+        game.queued_turns.extend([Direction::Up, Direction::Left]);
+        game.update();
+        assert_eq!(game.snake.head(), (x, y - 1));
+        game.update();
+        assert_eq!(game.snake.head(), (x - 1, y - 1));
+        assert!(game.queued_turns.is_empty());
+    }
+
+    #[test]
+    fn a_controller_steers_player_one_in_place_of_the_keys() {
+        let mut game = game(&SPAWN, Direction::Right);
+        let (x, y) = game.snake.head();
+        game.set_controller(Box::new(Heading(Direction::Down)));
+        game.queued_turns.push_back(Direction::Up);
+        game.tick(None);
+        assert_eq!(game.snake.head(), (x, y + 1));
+        assert!(game.queued_turns.is_empty(), "the pressed turn is used up all the same");
+    }
+
+    #[test]
+    fn the_autopilot_plays_through_tick() {
+        let mut game = game(&SPAWN, Direction::Right);
+        game.foods = food((10, 4));
+        let (x, y) = game.snake.head();
+        game.set_controller(Box::new(Autopilot));
+        game.tick(None);
+        assert_eq!(game.snake.head(), (x, y - 1));
+    }
+
+    #[test]
+    fn the_demo_starts_after_a_while_on_the_start_screen() {
+        let mut game = Game::new(&Settings::default());
+        game.tick_clock(DEMO_AFTER / 2.0);
+        assert!(game.demo.is_none());
+        game.help_shown = true;
+        game.tick_clock(DEMO_AFTER);
+        assert!(game.demo.is_none(), "reading the help isn't sitting idle");
+        game.help_shown = false;
+        game.tick_clock(DEMO_AFTER / 2.0);
+        let demo = game.demo.as_ref().expect("a demo playing");
+        assert!(demo.state == GameState::Running);
+        assert!(demo.settings.stats_file.is_none() && demo.settings.leaderboard_file.is_none());
+    }
+
+    #[test]
+    fn the_demo_plays_itself_and_starts_over() {
+        let mut game = Game::new(&Settings::default());
+        game.tick_clock(DEMO_AFTER);
+        let start = game.demo.as_ref().map(|demo| demo.snake.head());
+        game.update();
+        assert!(game.state == GameState::Menu, "the real game stays on the start screen");
+        assert!(game.demo.as_ref().map(|demo| demo.snake.head()) != start);
+        if let Some(demo) = game.demo.as_mut() {
+            demo.state = GameState::GameOver;
+        }
+        game.update();
+        assert!(game.demo.as_ref().is_some_and(|demo| demo.state == GameState::Running && demo.moves == 1));
+    }
+
+    #[test]
+    fn tick_starts_the_run_and_reports_each_move() {
+        let mut game = Game::new(&Settings::default());
+        game.foods = food((12, 10));
+        assert!(game.tick(None) == TickResult::Moved);
+        assert!(game.state == GameState::Running);
+        assert!((game.elapsed - game.settings.move_interval).abs() < 1e-9);
+        assert!(game.tick(None) == TickResult::Ate);
+        // Up to the top wall from row 10
+        let mut result = game.tick(Some(Direction::Up));
+        while result == TickResult::Moved || result == TickResult::Ate {
+            result = game.tick(None);
+        }
+        assert!(result == TickResult::Over(Some(DeathCause::Wall)));
+        assert!(game.tick(None) == TickResult::Stopped);
+    }
+
+    #[test]
+    fn time_attack_is_picked_on_the_start_screen_with_its_own_high_score() {
+        let mut game = Game::new(&Settings::default());
+        game.stats = Stats { high_score: 50, time_attack_high_score: 20, ..Stats::default() };
+        game.high_score = 50;
+        game.toggle_time_attack();
+        assert_eq!((game.settings.time_limit, game.time_left, game.high_score), (Some(TIME_ATTACK_SECONDS), Some(TIME_ATTACK_SECONDS), 20));
+        game.toggle_time_attack();
+        assert_eq!((game.settings.time_limit, game.high_score), (None, 50));
+    }
+
+    #[test]
+    fn a_shrinking_arena_closes_a_ring_on_schedule_until_it_is_small() {
+        let settings = Settings::builder().arena_shrink(Some(1.0)).move_interval(0.25).build().unwrap();
+        assert_eq!(moves_per_shrink(&settings), Some(4));
+        let mut game = Game::new(&settings);
+        let mut mode = ShrinkingArena { every: 4 };
+        for _ in 0..3 {
+            mode.on_tick(&mut game);
+        }
+        assert_eq!((game.closed_rings, game.shrink_in), (0, Some(1)));
+        assert!(game.ring_closing());
+        for _ in 0..100 {
+            mode.on_tick(&mut game);
+        }
+        assert_eq!((game.closed_rings, game.shrink_in), ((DEFAULT_GRID.0 - MIN_ARENA_SPAN) / 2, None));
+        assert_eq!(game.layout().open_span(), MIN_ARENA_SPAN);
+    }
+
+    #[test]
+    fn a_closing_ring_takes_pickups_with_it_but_puts_food_back() {
+        let mut game = game(&SPAWN, Direction::Right);
+        game.foods = food((0, 0));
+        game.phase_pickup = Some((5, 0));
+        game.close_ring();
+        assert_eq!(game.foods.len(), 1);
+        assert!(game.layout().contains(game.foods[0].pos));
+        assert!(game.phase_pickup.is_none());
+    }
+
+    #[test]
+    fn a_closed_ring_is_a_wall() {
+        let mut game = game(&[(2, 5), (3, 5), (4, 5)], Direction::Left);
+        game.closed_rings = 1;
+        assert!(game.tick(None) == TickResult::Moved);
+        assert!(game.tick(None) == TickResult::Over(Some(DeathCause::Wall)));
+    }
+
+    #[test]
+    fn the_arena_must_shrink_every_positive_number_of_seconds() {
+        for secs in [0.0, -2.0, f64::NAN] {
+            assert!(matches!(Settings::builder().arena_shrink(Some(secs)).build().err(), Some(SettingsError::ArenaShrink(_))));
+        }
+    }
+
+    // A game playing the shipped campaign
+    fn campaign() -> Game {
+        let levels = crate::level::load_campaign(std::path::Path::new(crate::level::LEVELS_DIR)).expect("the campaign loads");
+        Game::new(&Settings::builder().grid(levels[0].grid.0, levels[0].grid.1).campaign(levels).build().unwrap())
+    }
+
+    #[test]
+    fn a_campaign_level_places_the_snake_and_brings_its_walls() {
+        let mut game = campaign();
+        game.tick(None);
+        let level = game.current_level().expect("a level").clone();
+        assert!(game.snake.dir == level.start_dir && game.snake.body()[1] == level.start);
+        assert!(game.obstacles == level.walls);
+    }
+
+    #[test]
+    fn reaching_the_target_clears_the_level_and_space_goes_on_to_the_next() {
+        let mut game = campaign();
+        game.tick(None);
+        game.score = game.current_level().expect("a level").target;
+        assert!(game.tick(None) == TickResult::LevelComplete);
+        assert!(!game.campaign_won());
+        game.next_level();
+        assert!(game.state == GameState::Running);
+        assert_eq!((game.level, game.score), (1, 0));
+        assert!(game.obstacles == game.settings.campaign[1].walls);
+
+        // After the last level comes the first again
+        game.level = game.settings.campaign.len() - 1;
+        game.state = GameState::LevelComplete;
+        assert!(game.campaign_won());
+        game.next_level();
+        assert_eq!(game.level, 0);
+    }
+
+    #[test]
+    fn a_campaign_needs_levels_the_size_of_the_board_and_one_player() {
+        let levels = crate::level::load_campaign(std::path::Path::new(crate::level::LEVELS_DIR)).expect("the campaign loads");
+        let built = Settings::builder().grid(30, 30).campaign(levels.clone()).build();
+        assert!(matches!(built.err(), Some(SettingsError::LevelSize(..))));
+        let built = Settings::builder().grid(levels[0].grid.0, levels[0].grid.1).campaign(levels).players(Players::Two).build();
+        assert!(matches!(built.err(), Some(SettingsError::CampaignPlayers)));
+    }
+
+    #[test]
+    fn a_head_in_a_portal_comes_out_of_its_twin_still_going_straight() {
+        let mut game = game(&SPAWN, Direction::Right);
+        game.portals = vec![[(11, 10), (3, 4)]];
+        assert!(game.tick(None) == TickResult::Moved);
+        assert_eq!(game.snake.head(), (3, 4));
+        assert!(game.snake.dir == Direction::Right);
+        // The body reads as the straight move it was, not a turn towards where it came from
+        let joints = game.snake.joints();
+        assert!(joints[0] == (Some(Direction::Right), Direction::Right) && joints[1] == (Some(Direction::Right), Direction::Right));
+        assert!(game.tick(None) == TickResult::Moved);
+        assert_eq!(game.snake.head(), (4, 4));
+    }
+
+    #[test]
+    fn what_lies_past_a_portal_decides_whether_entering_it_is_fatal() {
+        let mut game = game(&SPAWN, Direction::Right);
+        game.portals = vec![[(11, 10), (3, 4)]];
+        assert!(!game.is_fatal((11, 10)));
+        game.obstacles.insert((3, 4));
+        assert!(game.is_fatal((11, 10)));
+        assert!(game.tick(None) == TickResult::Over(Some(DeathCause::Obstacle)));
+    }
+
+    #[test]
+    fn room_behind_a_wall_counts_when_a_portal_leads_there() {
+        let mut game = game(&SPAWN, Direction::Right);
+        game.obstacles = (0..DEFAULT_GRID.1).map(|y| (5, y)).collect();
+        assert!(!game.reachable_cells().contains(&(0, 0)));
+        game.portals = vec![[(15, 15), (2, 2)]];
+        assert!(game.reachable_cells().contains(&(0, 0)));
+    }
+
+    #[test]
+    fn random_portals_keep_clear_of_the_way_ahead_and_of_food() {
+        for seed in 0..20 {
+            let mut game = Game::new(&Settings::builder().portal_pairs(4).seed(Some(seed)).build().unwrap());
+            game.tick(None);
+            assert_eq!(game.portals.len(), 4);
+            let ends: Vec<(i32, i32)> = game.portals.iter().flatten().copied().collect();
+            let (x, y) = game.snake.body()[1];
+            assert!((1..=PORTAL_CLEARANCE).all(|i| !ends.contains(&(x + i, y))));
+            assert!(game.foods.iter().all(|food| !ends.contains(&food.pos)));
+        }
+    }
+
+    #[test]
+    fn far_off_food_wanders_a_step_at_a_time() {
+        let mut game = game(&SPAWN, Direction::Right);
+        game.foods = food((15, 15));
+        for _ in 0..3 {
+            let (x, y) = game.foods[0].pos;
+            game.wander_food();
+            let (nx, ny) = game.foods[0].pos;
+            assert_eq!((nx - x).abs() + (ny - y).abs(), 1);
+        }
+    }
+
+    #[test]
+    fn food_near_a_head_runs_from_it_but_never_closer() {
+        let mut game = game(&SPAWN, Direction::Right);
+        game.foods = food((12, 10));
+        game.wander_food();
+        let (x, y) = game.foods[0].pos;
+        assert_eq!((x - 10).abs() + (y - 10).abs(), 3);
+
+        // Cornered by rocks and a portal, the only step left is towards the head, so it stays
+        game.foods = food((12, 10));
+        game.obstacles = HashSet::from([(13, 10), (12, 9)]);
+        game.portals = vec![[(12, 11), (0, 0)]];
+        game.wander_food();
+        assert_eq!(game.foods[0].pos, (12, 10));
+    }
+
+    #[test]
+    fn food_a_head_has_reached_stays_to_be_eaten() {
+        let mut game = game(&SPAWN, Direction::Right);
+        game.foods = food(SPAWN[0]);
+        game.wander_food();
+        assert_eq!(game.foods[0].pos, SPAWN[0]);
+        assert!(matches!(Settings::builder().food_wander(Some(0)).build().err(), Some(SettingsError::FoodWander)));
+    }
+
+    #[test]
+    fn quick_eats_build_a_combo_that_cools_off_a_step_at_a_time() {
+        let mut game = game(&SPAWN, Direction::Right);
+        game.settings.scoring.combo_window = 3;
+        for _ in 0..7 {
+            game.feed_combo(FoodKind::Normal);
+        }
+        assert_eq!((game.combo, game.combo_moves), (MAX_COMBO, 3));
+        for _ in 0..3 {
+            game.cool_combo();
+        }
+        assert_eq!((game.combo, game.combo_moves), (MAX_COMBO - 1, 3));
+        game.feed_combo(FoodKind::Poison);
+        assert_eq!((game.combo, game.combo_moves), (1, 0));
+    }
+
+    #[test]
+    fn the_combo_multiplies_food_points() {
+        let mut game = game(&SPAWN, Direction::Right);
+        game.settings.scoring.combo_window = 3;
+        game.foods = food((11, 10));
+        game.tick(None);
+        let first = game.score;
+        game.foods = food((12, 10));
+        game.tick(None);
+        assert_eq!(game.combo, 2);
+        assert_eq!(game.score - first, first * 2);
+    }
+
+    #[test]
+    fn two_player_runs_have_no_combos() {
+        let mut game = two_player_game(Snake::at(Direction::Left, (15, 5)));
+        game.settings.scoring.combo_window = 3;
+        game.feed_combo(FoodKind::Normal);
+        game.feed_combo(FoodKind::Normal);
+        assert_eq!((game.combo, game.combo_moves), (1, 0));
+    }
+
+    // A running game that keeps its achievements in a file of its own under the temp directory
+    fn achiever(name: &str) -> (Game, std::path::PathBuf) {
+        let path = std::env::temp_dir().join(format!("copperhead-game-achievements-{}-{}", std::process::id(), name));
+        let mut game = game(&SPAWN, Direction::Right);
+        game.settings.achievements_file = Some(path.clone());
+        (game, path)
+    }
+
+    #[test]
+    fn the_first_food_unlocks_an_achievement_and_saves_it() {
+        let (mut game, path) = achiever("first-bite");
+        game.foods = food((11, 10));
+        game.tick(None);
+        let saved = Achievements::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(game.toasts == [Achievement::FirstBite]);
+        assert!(saved.is_ok_and(|saved| saved.has(Achievement::FirstBite)));
+    }
+
+    #[test]
+    fn an_early_crash_is_a_splat() {
+        let (mut game, path) = achiever("splat");
+        game.snake = Snake::along(Direction::Left, &[(1, 5), (2, 5), (3, 5)]);
+        game.tick(None);
+        assert!(game.tick(None) == TickResult::Over(Some(DeathCause::Wall)));
+        std::fs::remove_file(&path).unwrap();
+        assert!(game.achievements.has(Achievement::Splat));
+    }
+
+    #[test]
+    fn only_games_with_an_achievements_file_unlock_anything() {
+        let mut game = game(&SPAWN, Direction::Right);
+        game.foods = food((11, 10));
+        game.tick(None);
+        assert!(game.toasts.is_empty() && game.achievements.unlocked.is_empty());
+    }
+
+    #[test]
+    fn a_finished_run_counts_once_in_the_lifetime_stats() {
+        let mut game = game(&[(1, 5), (2, 5), (3, 5)], Direction::Left);
+        game.foods = food((0, 5));
+        game.tick(None);
+        assert!(game.tick(None) == TickResult::Over(Some(DeathCause::Wall)));
+        game.finish_run();
+        assert_eq!((game.stats.games_played, game.stats.food_eaten, game.stats.longest_snake), (1, 1, START_LENGTH as u32 + 1));
+        assert_eq!(game.stats.total_score, game.score as u64);
+    }
+
+    #[test]
+    fn a_cleared_level_counts_in_the_lifetime_stats_without_a_result_line() {
+        let mut game = campaign();
+        game.settings.json_results = true;
+        game.tick(None);
+        game.score = game.current_level().expect("a level").target;
+        game.tick(None);
+        game.next_level();
+        assert_eq!(game.stats.games_played, 1);
+        assert!(game.json_lines.is_empty());
+    }
+
+    #[test]
+    fn two_player_runs_stay_out_of_the_lifetime_stats() {
+        let mut game = two_player_game(Snake::at(Direction::Left, (15, 5)));
+        game.game_over(Some(DeathCause::Wall));
+        game.finish_run();
+        assert_eq!(game.stats.games_played, 0);
+    }
+
+    // Settings for a seeded run on a wrapping board that saves to a temp file named after `name`
+    fn saving(name: &str) -> (Settings, std::path::PathBuf) {
+        let path = std::env::temp_dir().join(format!("copperhead-game-saved-run-{}-{}.toml", std::process::id(), name));
+        let _ = std::fs::remove_file(&path);
+        let settings = Settings::builder().seed(Some(11)).wall_mode(WallMode::Wrap).save_file(Some(path.clone())).build().unwrap();
+        (settings, path)
+    }
+
+    // A game of `settings` played `moves` moves in and then left mid-way
+    fn left_mid_way(settings: &Settings, moves: usize) -> Game {
+        let mut game = Game::new(settings);
+        game.state = GameState::Running;
+        for i in 0..moves {
+            game.tick(if i % 7 == 3 { Some(Direction::Down) } else if i % 7 == 5 { Some(Direction::Right) } else { None });
+        }
+        game.save_run();
+        game
+    }
+
+    #[test]
+    fn a_continued_run_plays_on_as_if_it_was_never_left() {
+        let (settings, path) = saving("continue");
+        let mut game = left_mid_way(&settings, 40);
+        let mut continued = Game::new(&settings);
+        assert_eq!(continued.saved_score(), Some(game.score));
+        continued.continue_run();
+        assert!(!path.exists(), "a run can only be continued once");
+        assert!(continued.state == GameState::Paused);
+        continued.state = GameState::Running;
+        assert!(continued.snake.body() == game.snake.body() && continued.snake.steps() == game.snake.steps());
+        assert!(continued.foods == game.foods && continued.obstacles == game.obstacles);
+
+        // The next food lands where it would have without the break, and so does everything after it
+        assert!(game.spawn_food() && continued.spawn_food());
+        assert!(continued.foods.last() == game.foods.last());
+        for _ in 0..200 {
+            game.tick(None);
+            continued.tick(None);
+            assert!(continued.state == game.state);
+            assert!(continued.foods == game.foods && continued.snake.body() == game.snake.body());
+            assert_eq!(continued.score, game.score);
+        }
+    }
+
+    #[test]
+    fn a_saved_run_keeps_its_place_in_the_random_numbers() {
+        let (settings, path) = saving("rng-word");
+        let game = left_mid_way(&settings, 25);
+        let saved = SavedRun::load(&path).unwrap().expect("the run was saved");
+        std::fs::remove_file(&path).unwrap();
+        assert!(saved.rng_word > 0);
+        assert_eq!(saved.rng_word as u128, game.rng.get_word_pos());
+        assert_eq!(saved.rng_seed, game.rng.get_seed());
+        assert_eq!(saved.run_seed as u64, game.run_seed);
+        assert!(!path.with_extension("tmp").exists(), "the save goes through a temporary file");
+    }
+
+    #[test]
+    fn a_run_saved_on_another_board_size_is_not_offered() {
+        let (settings, path) = saving("board-size");
+        left_mid_way(&settings, 10);
+        let bigger = Settings { grid: (DEFAULT_GRID.0 + 4, DEFAULT_GRID.1 + 4), ..settings.clone() };
+        assert_eq!(Game::new(&bigger).saved_score(), None);
+        assert!(Game::new(&settings).saved_score().is_some());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn only_a_one_player_run_in_progress_is_saved() {
+        let (settings, path) = saving("over");
+        let mut game = Game::new(&settings);
+        game.save_run();
+        assert!(!path.exists(), "nothing to save from the start screen");
+        game.state = GameState::GameOver;
+        game.save_run();
+        assert!(!path.exists(), "a finished run isn't saved");
+    }
+
+    #[test]
+    fn the_snake_waits_for_the_countdown_while_turns_queue_up() {
+        let mut game = Game::new(&Settings::default());
+        game.foods.clear();
+        let head = game.snake.head();
+        game.play();
+        assert_eq!(game.countdown_number(), Some(COUNTDOWN_SECONDS));
+        game.queue_turn(Direction::Up);
+        game.tick_clock(0.5);
+        game.update();
+        assert_eq!(game.countdown_number(), Some(COUNTDOWN_SECONDS));
+        assert!(game.snake.head() == head && game.elapsed == 0.0);
+        game.tick_clock(COUNTDOWN_SECONDS as f64);
+        assert_eq!(game.countdown_number(), None);
+        game.update();
+        assert_eq!(game.snake.head(), (head.0, head.1 - 1));
+    }
+
+    #[test]
+    fn the_countdown_goes_by_in_real_seconds() {
+        let mut game = Game::new(&Settings { time_scale: 0.5, ..Settings::default() });
+        game.play();
+        // The frontend hands over game time: half a real second at half speed
+        game.tick_clock(game.game_dt(1.0));
+        assert_eq!(game.countdown, COUNTDOWN_SECONDS as f64 - 1.0);
+    }
+
+    #[test]
+    fn some_runs_go_at_once() {
+        let mut game = Game::new(&Settings { countdown: 0, ..Settings::default() });
+        game.play();
+        assert_eq!(game.countdown_number(), None);
+        let mut game = Game::new(&Settings { net_side: Some(Side::Host), players: Players::Two, ..Settings::default() });
+        game.play();
+        assert_eq!(game.countdown_number(), None, "the two machines of a network run move in lockstep");
+        let mut game = Game::new(&Settings::default());
+        assert!(game.tick(None) == TickResult::Moved, "tick is for bots, which don't need to get ready");
+    }
+
+    #[test]
+    fn a_key_bound_to_another_action_swaps_with_it() {
+        let mut keys = KeyBindings::default();
+        keys.bind(Action::Up, "d");
+        assert_eq!(keys.up, ["d", "w"]);
+        assert_eq!(keys.right, ["right"]);
+        // Pause's only key is taken, so it gets the restart key it replaced
+        keys.bind(Action::Restart, "p");
+        assert_eq!(keys.restart, ["p"]);
+        assert_eq!(keys.pause, ["space"]);
+        keys.bind(Action::Restart, "p");
+        assert_eq!(keys.restart, ["p"], "binding a key again changes nothing");
+    }
+
+    #[test]
+    fn the_speed_and_volume_change_in_steps_within_range() {
+        let mut game = Game::new(&Settings::default());
+        game.change_speed(-20);
+        assert_eq!(game.settings.move_interval, 1.0 / *SPEED_RANGE.start() as f64);
+        game.change_speed(3);
+        assert_eq!(game.settings.move_interval, 1.0 / 7.0);
+        game.change_volume(6);
+        assert_eq!(game.settings.volume, 100);
+        game.toggle_mute();
+        game.change_volume(-3);
+        assert_eq!((game.settings.volume, game.settings.muted), (70, false), "changing the volume unmutes");
+        game.change_volume(-10);
+        assert_eq!(game.settings.volume, 0);
+    }
+}
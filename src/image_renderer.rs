@@ -0,0 +1,288 @@
+// A `Renderer` that draws into an image in memory instead of a window, for screenshots: the frame is drawn
+// again, the same way `Game::render` draws it on screen. Shapes are filled without anti-aliasing, and text is
+// drawn with rusttype at the sizes Piston's glyph cache uses, so the picture matches the window closely.
+
+use image::{Rgba, RgbaImage};
+use rusttype::{point, Font, Scale};
+use crate::game::{Game, DEFAULT_FONT, SPRITE_ATLAS};
+use crate::renderer::{transform_pos, Color, Renderer, Transform};
+
+const ELLIPSE_RESOLUTION: usize = 64; // Corners of the polygon an ellipse is drawn as
+const POINTS_TO_PIXELS: f64 = 1.333; // Text sizes are in points, as in Piston's glyph cache
+
+pub struct ImageRenderer<'a> {
+    image: RgbaImage,
+    font: &'a Font<'static>,
+}
+
+impl<'a> ImageRenderer<'a> {
+    // A blank picture `size` pixels big, with its text in `font`
+    pub fn new([width, height]: [u32; 2], font: &'a Font<'static>) -> Self {
+        ImageRenderer { image: RgbaImage::new(width, height), font }
+    }
+
+    pub fn into_image(self) -> RgbaImage {
+        self.image
+    }
+
+    // Blend `color` over the pixel at `(x, y)`, with its alpha scaled by `coverage`; pixels off the image
+    // are left alone
+    fn blend(&mut self, x: i64, y: i64, color: Color, coverage: f32) {
+        if x < 0 || y < 0 || x >= self.image.width() as i64 || y >= self.image.height() as i64 {
+            return;
+        }
+        let alpha = color[3] * coverage;
+        let Rgba(dst) = self.image.get_pixel_mut(x as u32, y as u32);
+        if alpha >= 1.0 {
+            *dst = color.map(|value| (value * 255.0).round() as u8);
+            return;
+        }
+        for (channel, value) in dst.iter_mut().zip(color).take(3) {
+            *channel = (value * 255.0 * alpha + *channel as f32 * (1.0 - alpha)).round() as u8;
+        }
+        dst[3] = (alpha * 255.0 + dst[3] as f32 * (1.0 - alpha)).round() as u8;
+    }
+
+    // Fill the convex polygon `corners`, given in image pixels, covering the pixels whose middles are inside
+    // it. Each row is filled between where the row's middle line crosses the outline. Filling the polygon
+    // whole rather than as a fan of triangles keeps see-through colors from doubling up along the edges the
+    // triangles would share.
+    fn fill_convex(&mut self, color: Color, corners: &[[f64; 2]]) {
+        let height = self.image.height() as f64;
+        let top = corners.iter().map(|p| p[1]).fold(f64::INFINITY, f64::min).floor().max(0.0) as i64;
+        let bottom = corners.iter().map(|p| p[1]).fold(f64::NEG_INFINITY, f64::max).ceil().min(height) as i64;
+        for y in top..bottom {
+            let middle = y as f64 + 0.5;
+            let (mut left, mut right) = (f64::INFINITY, f64::NEG_INFINITY);
+            for (i, &p) in corners.iter().enumerate() {
+                // Each edge is followed from its upper end, so two shapes sharing it find the very same crossings
+                let q = corners[(i + 1) % corners.len()];
+                let (p, q) = if p[1] <= q[1] { (p, q) } else { (q, p) };
+                if (p[1] <= middle) != (q[1] <= middle) {
+                    let x = p[0] + (middle - p[1]) / (q[1] - p[1]) * (q[0] - p[0]);
+                    left = left.min(x);
+                    right = right.max(x);
+                }
+            }
+            // Pixels whose middles lie from the left crossing up to the right one; one right on the right
+            // crossing belongs to the next shape over, so see-through shapes side by side don't show their seams
+            let first = (left - 0.5).ceil().max(0.0) as i64;
+            let end = (right - 0.5).ceil().min(self.image.width() as f64) as i64;
+            for x in first..end {
+                self.blend(x, y, color, 1.0);
+            }
+        }
+    }
+}
+
+impl Renderer for ImageRenderer<'_> {
+    type Texture = RgbaImage;
+
+    fn transform(&self) -> Transform {
+        [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]
+    }
+
+    fn view_size(&self) -> [f64; 2] {
+        [self.image.width() as f64, self.image.height() as f64]
+    }
+
+    fn clear(&mut self, color: Color) {
+        let pixel = Rgba(color.map(|value| (value * 255.0).round() as u8));
+        for dst in self.image.pixels_mut() {
+            *dst = pixel;
+        }
+    }
+
+    fn rectangle(&mut self, color: Color, [x, y, w, h]: [f64; 4], transform: Transform) {
+        self.polygon(color, &[[x, y], [x + w, y], [x + w, y + h], [x, y + h]], transform);
+    }
+
+    fn ellipse(&mut self, color: Color, [x, y, w, h]: [f64; 4], transform: Transform) {
+        let (rx, ry) = (w / 2.0, h / 2.0);
+        let outline: Vec<[f64; 2]> = (0..ELLIPSE_RESOLUTION)
+            .map(|i| {
+                let angle = std::f64::consts::TAU * i as f64 / ELLIPSE_RESOLUTION as f64;
+                [x + rx + rx * angle.cos(), y + ry + ry * angle.sin()]
+            })
+            .collect();
+        self.polygon(color, &outline, transform);
+    }
+
+    fn polygon(&mut self, color: Color, points: &[[f64; 2]], transform: Transform) {
+        let points: Vec<[f64; 2]> = points.iter().map(|&point| transform_pos(transform, point)).collect();
+        self.fill_convex(color, &points);
+    }
+
+    fn triangles(&mut self, color: Color, vertices: &[[f32; 2]]) {
+        for corners in vertices.chunks_exact(3) {
+            self.fill_convex(color, &[0, 1, 2].map(|i| [corners[i][0] as f64, corners[i][1] as f64]));
+        }
+    }
+
+    fn draw_text(&mut self, color: Color, size: u32, text: &str, transform: Transform) {
+        let scale = transform[0][1].hypot(transform[1][1]);
+        let [x, y] = transform_pos(transform, [0.0, 0.0]);
+        let scale = Scale::uniform(((size as f64 * POINTS_TO_PIXELS).round() * scale) as f32);
+        // Glyph after glyph by their advance widths, without kerning, the way Piston lays text out
+        let mut caret = x as f32;
+        for ch in text.chars() {
+            let glyph = self.font.glyph(ch).scaled(scale);
+            let advance = glyph.h_metrics().advance_width;
+            let glyph = glyph.positioned(point(caret, y as f32));
+            caret += advance;
+            let Some(bounds) = glyph.pixel_bounding_box() else {
+                continue;
+            };
+            glyph.draw(|gx, gy, coverage| self.blend(bounds.min.x as i64 + gx as i64, bounds.min.y as i64 + gy as i64, color, coverage));
+        }
+    }
+
+    fn text_width(&mut self, size: u32, text: &str) -> f64 {
+        let scale = Scale::uniform((size as f64 * POINTS_TO_PIXELS).round() as f32);
+        text.chars().map(|ch| self.font.glyph(ch).scaled(scale).h_metrics().advance_width as f64).sum()
+    }
+
+    // Every pixel whose middle lands inside `rect` takes the texture pixel it maps back to
+    fn image(&mut self, texture: &RgbaImage, [sx, sy, sw, sh]: [f64; 4], [x, y, w, h]: [f64; 4], transform: Transform) {
+        let [[a, b, tx], [c, d, ty]] = transform;
+        let det = a * d - b * c;
+        if det == 0.0 || w == 0.0 || h == 0.0 {
+            return;
+        }
+        let corners = [[x, y], [x + w, y], [x + w, y + h], [x, y + h]].map(|corner| transform_pos(transform, corner));
+        let (width, height) = (self.image.width() as f64, self.image.height() as f64);
+        let left = corners.iter().map(|p| p[0]).fold(f64::INFINITY, f64::min).floor().max(0.0) as i64;
+        let right = corners.iter().map(|p| p[0]).fold(f64::NEG_INFINITY, f64::max).ceil().min(width) as i64;
+        let top = corners.iter().map(|p| p[1]).fold(f64::INFINITY, f64::min).floor().max(0.0) as i64;
+        let bottom = corners.iter().map(|p| p[1]).fold(f64::NEG_INFINITY, f64::max).ceil().min(height) as i64;
+        for py in top..bottom {
+            for px in left..right {
+                // Back from the image to the rectangle, through the inverse of the transform
+                let (dx, dy) = (px as f64 + 0.5 - tx, py as f64 + 0.5 - ty);
+                let (u, v) = ((d * dx - b * dy) / det, (a * dy - c * dx) / det);
+                if u < x || u >= x + w || v < y || v >= y + h {
+                    continue;
+                }
+                let tu = (sx + (u - x) / w * sw).floor() as u32;
+                let tv = (sy + (v - y) / h * sh).floor() as u32;
+                if let Some(Rgba([r, g, b, alpha])) = texture.get_pixel_checked(tu, tv) {
+                    let color = [*r, *g, *b, *alpha].map(|value| value as f32 / 255.0);
+                    self.blend(px, py, color, 1.0);
+                }
+            }
+        }
+    }
+
+    fn present(&mut self) {}
+}
+
+// The font the game's text is drawn in: the configured one, or the bundled one if that can't be loaded
+pub fn load_font(game: &Game) -> Result<Font<'static>, String> {
+    let custom = game.settings.font_path.as_ref().and_then(|path| std::fs::read(path).ok()).and_then(Font::try_from_vec);
+    if let Some(font) = custom {
+        return Ok(font);
+    }
+    let bytes = std::fs::read(DEFAULT_FONT).map_err(|err| format!("could not load font {}: {}", DEFAULT_FONT, err))?;
+    Font::try_from_vec(bytes).ok_or_else(|| format!("could not load font {}", DEFAULT_FONT))
+}
+
+// The sprite atlas, when the game draws with sprites
+pub fn load_sprites(game: &Game) -> Option<RgbaImage> {
+    if !game.settings.sprites {
+        return None;
+    }
+    image::open(SPRITE_ATLAS).ok().map(|atlas| atlas.into_rgba8())
+}
+
+// `game` as the window shows it now, drawn again into a picture `view` pixels big
+pub fn snapshot(game: &Game, view: [u32; 2]) -> Result<RgbaImage, String> {
+    let font = load_font(game)?;
+    let mut renderer = ImageRenderer::new(view, &font);
+    game.render(&mut renderer, load_sprites(game).as_ref());
+    Ok(renderer.into_image())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{GameState, Settings, DEFAULT_CELL_SIZE, DEFAULT_GRID};
+    use crate::snake::{Direction, Snake};
+
+    const RED: Color = [1.0, 0.0, 0.0, 1.0];
+    const IDENTITY: Transform = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+
+    fn font() -> Font<'static> {
+        load_font(&Game::new(&Settings::default())).expect("the bundled font loads")
+    }
+
+    // The pixels of `image` that aren't transparent black, as (x, y)
+    fn drawn(image: &RgbaImage) -> Vec<(u32, u32)> {
+        image.enumerate_pixels().filter(|(_, _, pixel)| pixel.0 != [0, 0, 0, 0]).map(|(x, y, _)| (x, y)).collect()
+    }
+
+    #[test]
+    fn shapes_cover_the_pixels_whose_middles_are_inside() {
+        let font = font();
+        let mut renderer = ImageRenderer::new([8, 8], &font);
+        renderer.rectangle(RED, [2.0, 1.0, 3.0, 2.0], IDENTITY);
+        let cells: Vec<(u32, u32)> = (1..3).flat_map(|y| (2..5).map(move |x| (x, y))).collect();
+        assert_eq!(drawn(&renderer.into_image()), cells);
+    }
+
+    #[test]
+    fn see_through_colors_blend_with_what_is_under_them() {
+        let font = font();
+        let mut renderer = ImageRenderer::new([2, 2], &font);
+        renderer.clear([0.0, 0.0, 1.0, 1.0]);
+        renderer.rectangle([1.0, 0.0, 0.0, 0.5], [0.0, 0.0, 1.0, 1.0], IDENTITY);
+        let image = renderer.into_image();
+        assert_eq!(image.get_pixel(0, 0).0, [128, 0, 128, 255]);
+        assert_eq!(image.get_pixel(1, 1).0, [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn see_through_shapes_side_by_side_dont_overlap() {
+        let font = font();
+        let mut renderer = ImageRenderer::new([4, 2], &font);
+        renderer.rectangle([1.0, 0.0, 0.0, 0.5], [0.0, 0.0, 2.5, 2.0], IDENTITY);
+        renderer.rectangle([1.0, 0.0, 0.0, 0.5], [2.5, 0.0, 1.5, 2.0], IDENTITY);
+        renderer.triangles([1.0, 0.0, 0.0, 0.5], &[[0.0, 0.0], [4.0, 0.0], [4.0, 2.0], [0.0, 0.0], [4.0, 2.0], [0.0, 2.0]]);
+        // Every pixel is covered once by the rectangles and once by the triangles: half red, then half again
+        let image = renderer.into_image();
+        assert!(image.pixels().all(|pixel| pixel.0 == [192, 0, 0, 192]), "{:?}", image.pixels().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn textures_stretch_over_their_rectangle() {
+        let font = font();
+        let mut texture = RgbaImage::new(2, 1);
+        texture.put_pixel(1, 0, Rgba([255, 0, 0, 255]));
+        let mut renderer = ImageRenderer::new([4, 2], &font);
+        renderer.image(&texture, [0.0, 0.0, 2.0, 1.0], [0.0, 0.0, 4.0, 2.0], IDENTITY);
+        let cells: Vec<(u32, u32)> = (0..2).flat_map(|y| (2..4).map(move |x| (x, y))).collect();
+        assert_eq!(drawn(&renderer.into_image()), cells);
+    }
+
+    #[test]
+    fn text_is_drawn_within_its_measured_width() {
+        let font = font();
+        let mut renderer = ImageRenderer::new([200, 40], &font);
+        let width = renderer.text_width(16, "Score");
+        renderer.draw_text(RED, 16, "Score", [[1.0, 0.0, 10.0], [0.0, 1.0, 30.0]]);
+        let pixels = drawn(&renderer.into_image());
+        assert!(!pixels.is_empty());
+        assert!(pixels.iter().all(|&(x, y)| (10..=10 + width.ceil() as u32).contains(&x) && y <= 30), "{pixels:?}");
+    }
+
+    #[test]
+    fn a_snapshot_is_the_window_drawn_again() {
+        let mut game = Game::new(&Settings::default());
+        game.state = GameState::Running;
+        let size = [(DEFAULT_GRID.0 * DEFAULT_CELL_SIZE) as u32, (DEFAULT_GRID.1 * DEFAULT_CELL_SIZE) as u32];
+        let before = snapshot(&game, size).expect("the snapshot is drawn");
+        assert_eq!(before.dimensions(), (size[0], size[1]));
+        assert!(snapshot(&game, size).expect("the snapshot is drawn") == before, "the same game draws the same picture");
+        game.snake = Snake::at(Direction::Down, (3, 3));
+        assert!(snapshot(&game, size).expect("the snapshot is drawn") != before, "the snake was moved");
+    }
+}
@@ -0,0 +1,846 @@
+// Turning key presses and gamepad buttons into game actions
+
+use piston_input::{Button, Key, MouseButton};
+use std::collections::HashSet;
+use crate::game::{Action, ControlScheme, Game, GameState, Players};
+use crate::menu::MenuPage;
+use crate::net::Side;
+use crate::snake::Direction;
+
+#[cfg(not(target_arch = "wasm32"))]
+const STICK_DEAD_ZONE: f32 = 0.5; // How far the left stick must be pushed before it steers, out of 1
+
+impl Game {
+    // Handle key presses
+    pub fn pressed(&mut self, btn: &Button) {
+        self.dirty = true;
+
+        // The debug overlay can be switched on and off on any screen, without disturbing anything else
+        if *btn == Button::Keyboard(Key::F3) {
+            self.debug_shown = !self.debug_shown;
+            return;
+        }
+
+        // Any key stops the demo, and does nothing else but bring back the title
+        self.idle = 0.0;
+        if self.demo.take().is_some() {
+            return;
+        }
+
+        // Entering initials for the leaderboard takes every key, letters included
+        if self.initials.is_some() {
+            match *btn {
+                Button::Keyboard(Key::Return) => self.sign_leaderboard(),
+                Button::Keyboard(Key::Backspace) => self.erase_initial(),
+                Button::Keyboard(Key::Escape) => self.skip_initials(),
+                Button::Keyboard(key) => {
+                    if let Some(letter) = key_letter(key) {
+                        self.type_initial(letter);
+                    }
+                },
+                _ => {},
+            }
+            return;
+        }
+
+        // While a key is being bound on the menu, the next key pressed is the one, if it can be bound at all.
+        // Escape gives up.
+        if let Some(action) = self.rebinding {
+            match *btn {
+                Button::Keyboard(Key::Escape) => self.rebinding = None,
+                Button::Keyboard(key) => {
+                    if let Some(name) = key_name(key) {
+                        self.settings.key_bindings.bind(action, &name);
+                        self.rebinding = None;
+                    }
+                },
+                _ => {},
+            }
+            return;
+        }
+
+        // Help can be opened from any screen, and swallows every other key while it's open
+        match *btn {
+            Button::Keyboard(Key::H | Key::F1) => {
+                self.help_shown = !self.help_shown;
+                return;
+            },
+            Button::Keyboard(Key::Escape) if self.help_shown => {
+                self.help_shown = false;
+                return;
+            },
+            _ if self.help_shown => return,
+            _ => {},
+        }
+
+        // The level editor takes every other key while it's open
+        if let Some(editor) = self.editor.as_mut() {
+            match *btn {
+                Button::Keyboard(Key::Escape) => self.editor = None,
+                Button::Keyboard(Key::Up) => editor.move_cursor(Direction::Up),
+                Button::Keyboard(Key::Down) => editor.move_cursor(Direction::Down),
+                Button::Keyboard(Key::Left) => editor.move_cursor(Direction::Left),
+                Button::Keyboard(Key::Right) => editor.move_cursor(Direction::Right),
+                Button::Keyboard(Key::Space) => editor.toggle_wall(),
+                Button::Keyboard(Key::P) => editor.place_snake(),
+                Button::Keyboard(Key::LeftBracket) => editor.change_target(-1),
+                Button::Keyboard(Key::RightBracket) => editor.change_target(1),
+                Button::Keyboard(Key::C) => editor.clear(),
+                Button::Keyboard(Key::S) => editor.save(),
+                Button::Keyboard(Key::L) => editor.load(),
+                _ => {},
+            }
+            return;
+        }
+
+        // Game speed can be changed at any time
+        let time_scale = match *btn {
+            Button::Keyboard(Key::D1) => Some(0.25),
+            Button::Keyboard(Key::D2) => Some(0.5),
+            Button::Keyboard(Key::D3) => Some(1.0),
+            Button::Keyboard(Key::D4) => Some(2.0),
+            _ => None,
+        };
+        if let Some(scale) = time_scale {
+            self.time_scale = scale;
+            return;
+        }
+
+        let action = match *btn {
+            Button::Keyboard(key) => self.key_action(key),
+            _ => None,
+        };
+        match self.state {
+            GameState::Menu => {
+                // Up and down choose an item, left and right change its value, and restart or Enter picks it.
+                // While a list is open over the menu, only picking its item again or Escape does anything.
+                let lists_shown = self.leaderboard_shown || self.achievements_shown || self.stats_shown;
+                match *btn {
+                    _ if action == Some(Action::Restart) => self.pick_menu_item(),
+                    Button::Keyboard(Key::Return) => self.pick_menu_item(),
+                    Button::Keyboard(Key::Escape) if lists_shown => self.close_lists(),
+                    Button::Keyboard(Key::Escape) => self.menu_back(),
+                    _ if lists_shown => {},
+                    _ if action == Some(Action::Up) => self.menu.move_by(-1),
+                    _ if action == Some(Action::Down) => self.menu.move_by(1),
+                    _ if action == Some(Action::Left) => self.change_menu_item(-1),
+                    _ if action == Some(Action::Right) => self.change_menu_item(1),
+                    _ => {},
+                }
+            },
+            GameState::GameOver => {
+                match *btn {
+                    _ if action == Some(Action::Restart) && self.settings.net_side.is_some() => self.restart_requested = true,
+                    _ if action == Some(Action::Restart) => self.leave_game_over(),
+                    Button::Keyboard(Key::E) => self.export_heatmap(),
+                    Button::Keyboard(Key::R) => self.rewind(),
+                    Button::Keyboard(Key::C) => self.copy_results(),
+                    _ => {},
+                }
+            },
+            GameState::LevelComplete => {
+                if action == Some(Action::Restart) {
+                    self.next_level();
+                }
+            },
+            GameState::Paused => {
+                if let Some(Action::Pause | Action::Restart) = action {
+                    self.play();
+                }
+            },
+            GameState::Running => {
+                if action == Some(Action::Pause) || *btn == Button::Keyboard(Key::Escape) {
+                    self.state = GameState::Paused;
+                    return;
+                }
+                if let Button::Keyboard(Key::F) = *btn {
+                    self.arm_phase();
+                    return;
+                }
+                if let Button::Keyboard(key) = *btn
+                    && let Some(dir) = self.key_turn(key)
+                {
+                    self.turn(dir);
+                }
+            }
+        }
+    }
+
+    // The mouse pointer moved onto `cell`, or off the board. Only the level editor follows it.
+    pub fn mouse_moved(&mut self, cell: Option<(i32, i32)>) {
+        if let (Some(editor), Some(cell)) = (self.editor.as_mut(), cell) {
+            editor.point_at(cell);
+            self.dirty = true;
+        }
+    }
+
+    // In the level editor, the left button puts walls down and the right one takes them away for as long as
+    // it is held
+    pub fn mouse_pressed(&mut self, button: MouseButton) {
+        let Some(editor) = self.editor.as_mut().filter(|_| !self.help_shown) else {
+            return;
+        };
+        match button {
+            MouseButton::Left => editor.start_painting(true),
+            MouseButton::Right => editor.start_painting(false),
+            _ => return,
+        }
+        self.dirty = true;
+    }
+
+    pub fn mouse_released(&mut self) {
+        if let Some(editor) = self.editor.as_mut() {
+            editor.stop_painting();
+        }
+    }
+
+    // Handle a key press from the window or the terminal: during a run steering keys only steer (through the
+    // turn buffer), even when the control scheme ignores some of them, and every other key, or any key outside
+    // a run, goes to `pressed`. When several steering keys arrive between two moves they are handled strictly
+    // in arrival order: with the turn buffer the first one that is a valid turn wins, with instant turns the
+    // last one does. In a two-player run, W, A, S and D steer player two instead. In a network run, all of
+    // them steer this machine's snake, and wait in the outbox to be sent before they do.
+    pub fn press_key(&mut self, key: Key) {
+        if self.settings.net_side.is_some()
+            && self.state == GameState::Running
+            && !self.help_shown
+            && let Some(dir) = self.key_turn(key).or_else(|| self.rival_key_turn(key))
+        {
+            self.outbox.push(dir);
+            return;
+        }
+        if let Some(dir) = self.rival_key_turn(key)
+            && self.state == GameState::Running
+        {
+            self.queue_rival_turn(dir);
+            return;
+        }
+        let steers = matches!(self.key_action(key), Some(Action::Up | Action::Down | Action::Left | Action::Right));
+        if !steers || self.state != GameState::Running {
+            self.pressed(&Button::Keyboard(key));
+        } else if let Some(dir) = self.key_turn(key) {
+            self.queue_turn(dir);
+        }
+    }
+
+    // Heading one of player two's keys asks for, or None if the key isn't one or nobody plays player two
+    fn rival_key_turn(&self, key: Key) -> Option<Direction> {
+        if self.settings.players != Players::Two {
+            return None;
+        }
+        match key {
+            Key::W => Some(Direction::Up),
+            Key::A => Some(Direction::Left),
+            Key::S => Some(Direction::Down),
+            Key::D => Some(Direction::Right),
+            _ => None,
+        }
+    }
+
+    // Close the leaderboard, the achievements and the stats, whichever is open over the menu
+    pub fn close_lists(&mut self) {
+        self.leaderboard_shown = false;
+        self.global_shown = false;
+        self.achievements_shown = false;
+        self.stats_shown = false;
+    }
+
+    // The action a key is bound to, if any
+    pub fn key_action(&self, key: Key) -> Option<Action> {
+        let bindings = &self.settings.key_bindings;
+        Action::ALL.into_iter().find(|&action| bindings.keys(action).iter().any(|name| key_from_name(name) == Some(key)))
+    }
+
+    // The first key bound to an action, which is what gamepad buttons stand in for
+    fn bound_key(&self, action: Action) -> Option<Key> {
+        self.settings.key_bindings.keys(action).iter().find_map(|name| key_from_name(name))
+    }
+
+    // Whether Escape should close the game. Otherwise it closes the help or a list (leaderboard, achievements,
+    // stats), stops binding a key, goes back a page on the menu, stops the demo, skips the initials entry, or
+    // pauses the run, and pressing it again on the pause screen quits.
+    pub fn quits_on_escape(&self) -> bool {
+        !self.help_shown && !self.leaderboard_shown && !self.achievements_shown && !self.stats_shown && self.rebinding.is_none() && self.menu_page == MenuPage::Main && self.initials.is_none() && self.demo.is_none() && self.editor.is_none() && self.state != GameState::Running
+    }
+
+    // The key a gamepad button stands in for, if any. The D-pad steers like the steering keys, A (south)
+    // acts as restart (pick on the menu, resume, restart), B (east) as Escape (close, skip, pause), and Start pauses
+    // a run and acts as restart everywhere else. Initials can only be typed on a keyboard.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn pad_key(&self, button: gilrs::Button) -> Option<Key> {
+        match button {
+            gilrs::Button::DPadUp => self.bound_key(Action::Up),
+            gilrs::Button::DPadDown => self.bound_key(Action::Down),
+            gilrs::Button::DPadLeft => self.bound_key(Action::Left),
+            gilrs::Button::DPadRight => self.bound_key(Action::Right),
+            gilrs::Button::South => self.bound_key(Action::Restart),
+            gilrs::Button::East => Some(Key::Escape),
+            gilrs::Button::Start if self.state == GameState::Running => self.bound_key(Action::Pause),
+            gilrs::Button::Start => self.bound_key(Action::Restart),
+            _ => None,
+        }
+    }
+
+    // The key the left stick stands in for when it's pushed towards `dir`
+    pub fn stick_key(&self, dir: &Direction) -> Option<Key> {
+        self.bound_key(match dir {
+            Direction::Up => Action::Up,
+            Direction::Down => Action::Down,
+            Direction::Left => Action::Left,
+            Direction::Right => Action::Right,
+        })
+    }
+
+    // Heading a steering key asks for under the control scheme, or None if the key doesn't steer. Relative
+    // turns build on the last queued turn, so two quick left turns make a U-turn over two moves.
+    pub fn key_turn(&self, key: Key) -> Option<Direction> {
+        let action = self.key_action(key)?;
+        match self.settings.control_scheme {
+            ControlScheme::Absolute => match action {
+                Action::Up => Some(Direction::Up),
+                Action::Down => Some(Direction::Down),
+                Action::Left => Some(Direction::Left),
+                Action::Right => Some(Direction::Right),
+                Action::Pause | Action::Restart => None,
+            },
+            ControlScheme::Relative => {
+                // Turns are relative to this machine's snake, which in a network run can be player two's
+                let (queued, snake) = match (self.settings.net_side, &self.rival) {
+                    (Some(Side::Guest), Some(rival)) => (&rival.queued_turns, &rival.snake),
+                    _ => (&self.queued_turns, &self.snake),
+                };
+                let heading = self.outbox.last().or(queued.back()).unwrap_or(&snake.dir);
+                match action {
+                    Action::Left => Some(heading.turn_left()),
+                    Action::Right => Some(heading.turn_right()),
+                    _ => None,
+                }
+            },
+        }
+    }
+}
+
+// The key a binding name stands for: "up", "down", "left" and "right" for the arrows, "space", "enter",
+// "tab", or a single letter or digit. Escape and the other fixed controls can't be bound.
+pub fn key_from_name(name: &str) -> Option<Key> {
+    match name {
+        "up" => Some(Key::Up),
+        "down" => Some(Key::Down),
+        "left" => Some(Key::Left),
+        "right" => Some(Key::Right),
+        "space" => Some(Key::Space),
+        "enter" => Some(Key::Return),
+        "tab" => Some(Key::Tab),
+        _ => {
+            let mut chars = name.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) if c.is_ascii_lowercase() || c.is_ascii_digit() => Some(Key::from(c as u32)),
+                _ => None,
+            }
+        },
+    }
+}
+
+// The name a key goes by in the bindings, the other way round from `key_from_name`; None for a key that
+// can't be bound
+pub fn key_name(key: Key) -> Option<String> {
+    match key {
+        Key::Up => Some("up".to_string()),
+        Key::Down => Some("down".to_string()),
+        Key::Left => Some("left".to_string()),
+        Key::Right => Some("right".to_string()),
+        Key::Space => Some("space".to_string()),
+        Key::Return => Some("enter".to_string()),
+        Key::Tab => Some("tab".to_string()),
+        _ => {
+            let code = key.code();
+            let typed = (0..128).contains(&code).then_some(code as u8 as char);
+            typed.filter(|c| c.is_ascii_lowercase() || c.is_ascii_digit()).map(String::from)
+        },
+    }
+}
+
+// A key binding name the way the help shows it: arrows as arrows, letters in capitals
+pub fn key_label(name: &str) -> String {
+    match name {
+        "up" => "^".to_string(),
+        "down" => "v".to_string(),
+        "left" => "<-".to_string(),
+        "right" => "->".to_string(),
+        "space" => "Space".to_string(),
+        "enter" => "Enter".to_string(),
+        "tab" => "Tab".to_string(),
+        _ => name.to_uppercase(),
+    }
+}
+
+// The letter a key types, for entering initials
+fn key_letter(key: Key) -> Option<char> {
+    let code = key.code();
+    (b'a' as i32..=b'z' as i32).contains(&code).then_some(code as u8 as char)
+}
+
+// Keys currently held down. The window reports OS key repeat as more presses of the same key, which
+// would flood the turn buffer, so only the first press until the key is released counts.
+#[derive(Default)]
+pub struct HeldKeys {
+    held: HashSet<Key>,
+}
+
+impl HeldKeys {
+    // Register a press; returns false for a repeat of a key that is still down
+    pub fn press(&mut self, key: Key) -> bool {
+        self.held.insert(key)
+    }
+
+    pub fn release(&mut self, key: Key) {
+        self.held.remove(&key);
+    }
+
+    pub fn clear(&mut self) {
+        self.held.clear();
+    }
+}
+
+// The left stick as another set of steering keys. Pushing it past the dead zone steers the way it points
+// (the axis pushed furthest wins), once, until it's let go or pushed another way.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Default)]
+pub struct Stick {
+    x: f32, // Right is positive
+    y: f32, // Up is positive
+    pointing: Option<Direction>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Stick {
+    // Register a new axis position; returns the direction it was just pushed towards, if any
+    pub fn move_axis(&mut self, axis: gilrs::Axis, value: f32) -> Option<Direction> {
+        match axis {
+            gilrs::Axis::LeftStickX => self.x = value,
+            gilrs::Axis::LeftStickY => self.y = value,
+            _ => return None,
+        }
+        let pointing = if self.x.abs().max(self.y.abs()) < STICK_DEAD_ZONE {
+            None
+        } else if self.x.abs() > self.y.abs() {
+            Some(if self.x > 0.0 { Direction::Right } else { Direction::Left })
+        } else {
+            Some(if self.y > 0.0 { Direction::Up } else { Direction::Down })
+        };
+        if pointing == self.pointing {
+            return None;
+        }
+        self.pointing = pointing.clone();
+        pointing
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{KeyBindings, Players, Settings, WallMode, COUNTDOWN_SECONDS};
+    use crate::menu::MenuItem;
+
+    // A run in progress with the snake fresh from the spawn, heading right from (10, 10), and no food in its way
+    fn running() -> Game {
+        let mut game = Game::new(&Settings::default());
+        game.foods.clear();
+        game.state = GameState::Running;
+        game
+    }
+
+    #[test]
+    fn the_number_keys_pick_the_game_speed() {
+        let mut game = running();
+        for (key, scale) in [(Key::D1, 0.25), (Key::D2, 0.5), (Key::D4, 2.0), (Key::D3, 1.0)] {
+            game.press_key(key);
+            assert_eq!(game.time_scale, scale);
+            assert_eq!(game.game_dt(0.1), 0.1 * scale);
+        }
+        assert!(game.state == GameState::Running, "changing the speed doesn't pause");
+    }
+
+    // What the window does with key events: presses go to the game unless they repeat a held key
+    enum Event {
+        Press(Key),
+        Release(Key),
+    }
+
+    fn feed(game: &mut Game, held: &mut HeldKeys, events: &[Event]) {
+        for event in events {
+            match *event {
+                Event::Press(key) => {
+                    if held.press(key) {
+                        game.press_key(key);
+                    }
+                },
+                Event::Release(key) => held.release(key),
+            }
+        }
+    }
+
+    #[test]
+    fn a_held_key_counts_once_until_let_go() {
+        let mut held = HeldKeys::default();
+        assert!(held.press(Key::Up));
+        assert!(!held.press(Key::Up), "a repeat");
+        assert!(held.press(Key::Left), "another key is a press of its own");
+        held.release(Key::Up);
+        assert!(held.press(Key::Up));
+        held.clear();
+        assert!(held.press(Key::Up) && held.press(Key::Left));
+    }
+
+    #[test]
+    fn key_repeat_does_not_fill_the_turn_buffer() {
+        let mut game = running();
+        game.settings.input_buffer_len = 3;
+        let mut held = HeldKeys::default();
+        // Up held down long enough to repeat, then Left tapped
+        let events = [Event::Press(Key::Up), Event::Press(Key::Up), Event::Press(Key::Up), Event::Press(Key::Left), Event::Release(Key::Left)];
+        feed(&mut game, &mut held, &events);
+        assert!(game.queued_turns == [Direction::Up, Direction::Left]);
+    }
+
+    #[test]
+    fn with_the_turn_buffer_the_first_valid_turn_wins() {
+        let mut game = running();
+        let mut held = HeldKeys::default();
+        // Left would reverse the snake, and Down after Up would reverse the turn before it
+        feed(&mut game, &mut held, &[Event::Press(Key::Left), Event::Press(Key::Up), Event::Press(Key::Down)]);
+        assert!(game.queued_turns == [Direction::Up]);
+        game.update();
+        assert_eq!(game.snake.head(), (10, 9));
+    }
+
+    #[test]
+    fn with_instant_turns_the_last_key_wins() {
+        let mut game = running();
+        game.settings.apply_turns_on_frame = true;
+        let mut held = HeldKeys::default();
+        feed(&mut game, &mut held, &[Event::Press(Key::Up), Event::Press(Key::Down)]);
+        game.update();
+        assert_eq!(game.snake.head(), (10, 11));
+    }
+
+    #[test]
+    fn help_pauses_the_game_and_swallows_keys() {
+        let mut game = running();
+        game.press_key(Key::H);
+        assert!(game.help_shown);
+        assert_eq!(game.game_dt(0.5), 0.0, "game time stops");
+        game.press_key(Key::Up);
+        game.press_key(Key::D4);
+        assert!(game.queued_turns.is_empty() && game.time_scale == 1.0);
+        game.press_key(Key::Escape);
+        assert!(!game.help_shown);
+        assert!(game.state == GameState::Running, "closing the help doesn't end the run");
+        game.press_key(Key::F1);
+        game.press_key(Key::F1);
+        assert!(!game.help_shown);
+    }
+
+    // A running game steered with the two relative keys, heading right from (10, 10)
+    fn relative() -> Game {
+        let mut game = running();
+        game.settings.control_scheme = ControlScheme::Relative;
+        game
+    }
+
+    #[test]
+    fn relative_keys_turn_from_the_heading() {
+        let game = relative();
+        assert!(game.key_turn(Key::Left) == Some(Direction::Up));
+        assert!(game.key_turn(Key::Right) == Some(Direction::Down));
+        assert!(game.key_turn(Key::Up).is_none());
+        assert!(game.key_turn(Key::Down).is_none());
+    }
+
+    #[test]
+    fn relative_turns_pressed_ahead_build_on_each_other() {
+        let mut game = relative();
+        game.settings.input_buffer_len = 2;
+        // Two lefts make a U-turn over two moves
+        game.press_key(Key::Left);
+        game.press_key(Key::Left);
+        assert!(game.queued_turns == [Direction::Up, Direction::Left]);
+        game.update();
+        game.update();
+        assert_eq!(game.snake.head(), (9, 9));
+        assert!(game.state == GameState::Running);
+    }
+
+    #[test]
+    fn relative_turns_keep_turning_round() {
+        let mut game = relative();
+        for _ in 0..4 {
+            game.press_key(Key::Right);
+            game.update();
+        }
+        assert_eq!(game.snake.head(), (10, 10), "four rights make a square");
+        assert!(game.snake.dir == Direction::Right);
+    }
+
+    // Go down the menu to `item`
+    fn go_to(game: &mut Game, item: MenuItem) {
+        while !game.menu.is_selected(item) {
+            game.press_key(Key::Down);
+        }
+    }
+
+    // Go down the menu to `item` and pick it with Enter
+    fn pick(game: &mut Game, item: MenuItem) {
+        go_to(game, item);
+        game.press_key(Key::Return);
+    }
+
+    #[test]
+    fn the_edges_change_on_the_options_page() {
+        let mut game = Game::new(&Settings { countdown: 0, ..Settings::default() });
+        pick(&mut game, MenuItem::Options);
+        assert!(game.menu.is_selected(MenuItem::Speed));
+        go_to(&mut game, MenuItem::Edges);
+        let mut seen = Vec::new();
+        for _ in 0..3 {
+            game.press_key(Key::Right);
+            seen.push(game.menu_label(MenuItem::Edges));
+        }
+        assert_eq!(seen, ["Edges: wrap around", "Edges: wrap for -1 points, -1 length", "Edges: deadly"]);
+        // Back to wrapping, which the run then plays by
+        game.press_key(Key::Left);
+        game.press_key(Key::Escape);
+        assert!(game.menu_page == MenuPage::Main && game.menu.is_selected(MenuItem::Options));
+        pick(&mut game, MenuItem::Play);
+        game.foods.clear();
+        for _ in 0..10 {
+            game.update();
+        }
+        assert_eq!(game.snake.head(), (0, 10));
+    }
+
+    #[test]
+    fn penalty_wrapping_keeps_the_costs_from_the_settings() {
+        let settings = Settings::builder().wall_mode(WallMode::WrapPenalty { point_cost: 4, shrink: 2 }).build().expect("valid settings");
+        let mut game = Game::new(&settings);
+        pick(&mut game, MenuItem::Options);
+        go_to(&mut game, MenuItem::Edges);
+        for _ in 0..3 {
+            game.press_key(Key::Right);
+        }
+        assert!(game.settings.wall_mode == WallMode::WrapPenalty { point_cost: 4, shrink: 2 });
+    }
+
+    #[test]
+    fn the_next_key_pressed_is_bound_to_the_action() {
+        let mut game = Game::new(&Settings { countdown: 0, ..Settings::default() });
+        pick(&mut game, MenuItem::Options);
+        pick(&mut game, MenuItem::Keys);
+        pick(&mut game, MenuItem::Bind(Action::Up));
+        game.press_key(Key::F5);
+        game.press_key(Key::Escape);
+        assert!(game.rebinding.is_none() && game.menu_page == MenuPage::Keys, "Escape gives up and stays on the page");
+        game.press_key(Key::Return);
+        game.press_key(Key::I);
+        assert_eq!(game.settings.key_bindings.up, ["i", "w"]);
+        // Taken over by down, which leaves up its other key
+        pick(&mut game, MenuItem::Bind(Action::Down));
+        game.press_key(Key::I);
+        assert_eq!(game.settings.key_bindings.up, ["w"]);
+        assert_eq!(game.settings.key_bindings.down, ["i", "s"]);
+        game.press_key(Key::Escape);
+        game.press_key(Key::Escape);
+        game.menu.select(MenuItem::Play);
+        game.press_key(Key::Return);
+        game.press_key(Key::I);
+        game.update();
+        assert_eq!(game.snake.head(), (10, 11));
+    }
+
+    #[test]
+    fn a_key_name_reads_back_as_the_same_key() {
+        for key in [Key::Up, Key::Return, Key::Space, Key::I, Key::D7] {
+            let name = key_name(key).unwrap();
+            assert_eq!(key_from_name(&name), Some(key), "{}", name);
+        }
+        assert_eq!(key_name(Key::F5), None);
+        assert_eq!(key_name(Key::Escape), None);
+    }
+
+    #[test]
+    fn the_edges_cant_change_during_a_run() {
+        let mut game = running();
+        game.pressed(&Button::Keyboard(Key::W));
+        assert!(game.settings.wall_mode == WallMode::Lethal);
+    }
+
+    #[test]
+    fn a_paused_run_stands_still() {
+        let mut game = running();
+        game.time_left = Some(10.0);
+        game.press_key(Key::P);
+        assert!(game.state == GameState::Paused);
+        assert_eq!(game.game_dt(1.0), 0.0);
+        game.update();
+        assert_eq!(game.snake.head(), (10, 10));
+        game.press_key(Key::Space);
+        assert!(game.state == GameState::Running && game.countdown_number() == Some(COUNTDOWN_SECONDS));
+        game.tick_clock(COUNTDOWN_SECONDS as f64);
+        game.update();
+        assert_eq!(game.snake.head(), (11, 10));
+    }
+
+    #[test]
+    fn escape_pauses_a_run_and_quits_from_the_pause_screen() {
+        let mut game = running();
+        assert!(!game.quits_on_escape());
+        game.press_key(Key::Escape);
+        assert!(game.state == GameState::Paused);
+        assert!(game.quits_on_escape());
+        game.press_key(Key::H);
+        assert!(!game.quits_on_escape(), "escape closes the help first");
+    }
+
+    #[test]
+    fn a_top_ten_run_takes_initials_from_the_keyboard() {
+        let path = std::env::temp_dir().join(format!("copperhead-initials-{}", std::process::id()));
+        let mut game = Game::new(&Settings { leaderboard_file: Some(path.clone()), ..Settings::default() });
+        game.state = GameState::GameOver;
+        game.score = 5;
+        game.pressed(&Button::Keyboard(Key::Space));
+        assert_eq!(game.initials.as_deref(), Some(""));
+        assert!(!game.quits_on_escape());
+        for key in [Key::A, Key::B, Key::C, Key::D, Key::Backspace, Key::Z] {
+            game.pressed(&Button::Keyboard(key));
+        }
+        assert_eq!(game.initials.as_deref(), Some("ABZ"));
+        game.pressed(&Button::Keyboard(Key::Return));
+        assert_eq!(game.initials, None);
+        assert!(game.state == GameState::GameOver);
+        let saved = crate::leaderboard::Leaderboard::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(saved.entries.len(), 1);
+        assert_eq!((saved.entries[0].name.as_str(), saved.entries[0].score), ("ABZ", 5));
+        // The same run isn't offered a second place
+        game.pressed(&Button::Keyboard(Key::Space));
+        assert!(game.state == GameState::Menu);
+    }
+
+    #[test]
+    fn the_stick_points_once_per_push() {
+        let mut stick = Stick::default();
+        assert!(stick.move_axis(gilrs::Axis::LeftStickX, 0.3).is_none(), "inside the dead zone");
+        assert!(stick.move_axis(gilrs::Axis::LeftStickX, 0.9) == Some(Direction::Right));
+        assert!(stick.move_axis(gilrs::Axis::LeftStickX, 0.6).is_none(), "still pushed the same way");
+        assert!(stick.move_axis(gilrs::Axis::LeftStickY, 0.95) == Some(Direction::Up), "the axis pushed furthest wins");
+        assert!(stick.move_axis(gilrs::Axis::RightStickX, -1.0).is_none());
+        stick.move_axis(gilrs::Axis::LeftStickX, 0.0);
+        assert!(stick.move_axis(gilrs::Axis::LeftStickY, 0.0).is_none(), "let go");
+        assert!(stick.move_axis(gilrs::Axis::LeftStickY, -0.8) == Some(Direction::Down));
+    }
+
+    #[test]
+    fn gamepad_buttons_stand_in_for_keys() {
+        let mut game = Game::new(&Settings { countdown: 0, ..Settings::default() });
+        game.foods.clear();
+        assert_eq!(game.pad_key(gilrs::Button::Start), Some(Key::Space));
+        game.press_key(game.pad_key(gilrs::Button::South).unwrap());
+        assert!(game.state == GameState::Running);
+        assert_eq!(game.pad_key(gilrs::Button::Start), Some(Key::P));
+        game.press_key(game.pad_key(gilrs::Button::DPadUp).unwrap());
+        game.update();
+        assert_eq!(game.snake.head(), (10, 9));
+        assert_eq!(game.pad_key(gilrs::Button::North), None);
+    }
+
+    #[test]
+    fn key_names_cover_letters_digits_and_the_named_keys() {
+        assert_eq!(key_from_name("w"), Some(Key::W));
+        assert_eq!(key_from_name("7"), Some(Key::D7));
+        assert_eq!(key_from_name("enter"), Some(Key::Return));
+        assert_eq!(key_from_name("W"), None);
+        assert_eq!(key_from_name("escape"), None);
+    }
+
+    #[test]
+    fn wasd_steers_and_keys_can_be_rebound() {
+        let mut game = running();
+        game.press_key(Key::W);
+        game.update();
+        assert_eq!(game.snake.head(), (10, 9));
+
+        let bindings = KeyBindings { left: vec!["j".to_string()], ..KeyBindings::default() };
+        let mut game = Game::new(&Settings { key_bindings: bindings, ..Settings::default() });
+        game.foods.clear();
+        game.state = GameState::Running;
+        game.press_key(Key::Down);
+        game.update();
+        game.press_key(Key::A);
+        game.update();
+        assert_eq!(game.snake.head(), (10, 12), "A no longer steers");
+        game.press_key(Key::J);
+        game.update();
+        assert_eq!(game.snake.head(), (9, 12));
+    }
+
+    #[test]
+    fn the_steering_keys_get_around_the_menu() {
+        let mut game = Game::new(&Settings::default());
+        game.press_key(Key::S);
+        assert!(game.menu.is_selected(MenuItem::Mode));
+        game.press_key(Key::W);
+        assert!(game.menu.is_selected(MenuItem::Play));
+        game.press_key(Key::W);
+        assert!(game.menu.is_selected(MenuItem::Quit), "going up from the top wraps around to the bottom");
+        game.press_key(Key::S);
+        // During a run they only steer
+        game.press_key(Key::Space);
+        assert!(game.state == GameState::Running && game.queued_turns.is_empty());
+        game.press_key(Key::W);
+        assert!(game.queued_turns == [Direction::Up]);
+    }
+
+    #[test]
+    fn wasd_steers_player_two_in_a_two_player_run() {
+        let mut game = Game::new(&Settings { players: Players::Two, ..Settings::default() });
+        game.press_key(Key::Space);
+        game.press_key(Key::W);
+        game.press_key(Key::Up);
+        assert!(game.rival.as_ref().is_some_and(|rival| rival.queued_turns == [Direction::Up]));
+        assert!(game.queued_turns == [Direction::Up]);
+    }
+
+    #[test]
+    fn any_key_stops_the_demo_and_only_that() {
+        let mut game = Game::new(&Settings::default());
+        game.demo = Some(Box::new(Game::new(&Settings::default())));
+        assert!(!game.quits_on_escape());
+        game.press_key(Key::Space);
+        assert!(game.demo.is_none() && game.state == GameState::Menu);
+        game.press_key(Key::Space);
+        assert!(game.state == GameState::Running);
+    }
+
+    #[test]
+    fn f3_shows_the_debug_overlay_and_does_nothing_else() {
+        let mut game = Game::new(&Settings::default());
+        game.demo = Some(Box::new(Game::new(&Settings::default())));
+        game.press_key(Key::F3);
+        assert!(game.debug_shown && game.demo.is_some() && game.state == GameState::Menu);
+        let mut game = running();
+        game.press_key(Key::F3);
+        game.press_key(Key::F3);
+        assert!(!game.debug_shown && game.state == GameState::Running);
+    }
+
+    #[test]
+    fn quick_taps_between_two_moves_all_count() {
+        let mut game = running();
+        game.press_key(Key::Up);
+        game.press_key(Key::Left);
+        game.update();
+        assert_eq!(game.snake.head(), (10, 9));
+        game.update();
+        assert_eq!(game.snake.head(), (9, 9));
+    }
+}
@@ -0,0 +1,165 @@
+// The local top 10: initials, score and date of the best runs, stored as one `score date name` line each
+
+use std::path::{Path, PathBuf};
+
+use crate::files::write_atomically;
+
+pub const LEADERBOARD_SIZE: usize = 10;
+pub const INITIALS_LEN: usize = 3;
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Entry {
+    pub name: String, // Up to INITIALS_LEN capital letters, or a player name on the global leaderboard
+    pub score: u32,
+    pub date: String, // Day the run was played, as YYYY-MM-DD (UTC)
+}
+
+// Best first; among equal scores the older entry stays ahead
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct Leaderboard {
+    pub entries: Vec<Entry>,
+}
+
+impl Leaderboard {
+    // Where interactive play keeps its leaderboard, or None when the platform has no data directory
+    pub fn path() -> Option<PathBuf> {
+        Some(dirs::data_dir()?.join("copperhead").join("leaderboard"))
+    }
+
+    // Read the leaderboard from `path`. A missing file is an empty leaderboard; a file that can't be read or
+    // has a malformed line is an error, which the caller can report before carrying on with an empty one.
+    pub fn load(path: &Path) -> Result<Leaderboard, String> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Leaderboard::default()),
+            Err(err) => return Err(err.to_string()),
+        };
+        Leaderboard::parse(&text)
+    }
+
+    // Read a leaderboard from text in the file format, one `score date name` line per entry
+    pub fn parse(text: &str) -> Result<Leaderboard, String> {
+        let mut board = Leaderboard::default();
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            let mut fields = line.split_whitespace();
+            let (Some(score), Some(date), Some(name)) = (fields.next(), fields.next(), fields.next()) else {
+                return Err(format!("malformed line {:?}", line));
+            };
+            let score = score.parse().map_err(|_| format!("malformed score in line {:?}", line))?;
+            board.insert(Entry { name: name.to_string(), score, date: date.to_string() });
+        }
+        Ok(board)
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        let text: String = self.entries.iter().map(|entry| format!("{} {} {}\n", entry.score, entry.date, entry.name)).collect();
+        write_atomically(path, &text)
+    }
+
+    // Whether a run with this score would make the list. Scoring nothing never does.
+    pub fn qualifies(&self, score: u32) -> bool {
+        score > 0 && (self.entries.len() < LEADERBOARD_SIZE || self.entries.iter().any(|entry| score > entry.score))
+    }
+
+    // Add an entry in its place and drop whatever falls off the end; returns its place counted from 0,
+    // or None if it didn't make the list
+    pub fn insert(&mut self, entry: Entry) -> Option<usize> {
+        let place = self.entries.iter().position(|other| entry.score > other.score).unwrap_or(self.entries.len());
+        if place >= LEADERBOARD_SIZE {
+            return None;
+        }
+        self.entries.insert(place, entry);
+        self.entries.truncate(LEADERBOARD_SIZE);
+        Some(place)
+    }
+}
+
+// Today's date in UTC as YYYY-MM-DD, from the system clock (days to civil date, after Howard Hinnant)
+pub fn today() -> String {
+    let secs = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |since| since.as_secs());
+    let days = (secs / 86_400) as i64 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+// The time now in UTC as YYYY-MM-DD-HHMMSS-mmm, for naming files that are saved in quick succession
+pub fn timestamp() -> String {
+    let millis = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |since| since.as_millis());
+    let secs = millis / 1000 % 86_400;
+    format!("{}-{:02}{:02}{:02}-{:03}", today(), secs / 3600, secs / 60 % 60, secs % 60, millis % 1000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, score: u32) -> Entry {
+        Entry { name: name.to_string(), score, date: "2026-01-01".to_string() }
+    }
+
+    fn full() -> Leaderboard {
+        let mut board = Leaderboard::default();
+        for score in 1..=LEADERBOARD_SIZE as u32 {
+            board.insert(entry("AAA", score * 10));
+        }
+        board
+    }
+
+    #[test]
+    fn entries_are_placed_best_first_with_ties_behind_older_ones() {
+        let mut board = Leaderboard::default();
+        assert_eq!(board.insert(entry("AAA", 20)), Some(0));
+        assert_eq!(board.insert(entry("BBB", 30)), Some(0));
+        assert_eq!(board.insert(entry("CCC", 20)), Some(2));
+        let names: Vec<&str> = board.entries.iter().map(|entry| entry.name.as_str()).collect();
+        assert_eq!(names, ["BBB", "AAA", "CCC"]);
+    }
+
+    #[test]
+    fn a_run_qualifies_while_there_is_room_or_it_beats_someone() {
+        assert!(Leaderboard::default().qualifies(1));
+        assert!(!Leaderboard::default().qualifies(0));
+        let board = full();
+        assert!(!board.qualifies(10));
+        assert!(board.qualifies(11));
+    }
+
+    #[test]
+    fn the_list_is_cut_at_its_size() {
+        let mut board = full();
+        assert_eq!(board.insert(entry("NEW", 55)), Some(5));
+        assert_eq!(board.entries.len(), LEADERBOARD_SIZE);
+        assert_eq!(board.entries.last().unwrap().score, 20);
+        assert_eq!(board.insert(entry("LOW", 5)), None);
+        assert_eq!(board.entries.len(), LEADERBOARD_SIZE);
+    }
+
+    #[test]
+    fn a_saved_leaderboard_loads_back() {
+        let path = std::env::temp_dir().join(format!("copperhead-leaderboard-{}", std::process::id()));
+        let mut board = Leaderboard::default();
+        board.insert(entry("ABC", 42));
+        board.insert(entry("XY", 7));
+        board.save(&path).unwrap();
+        assert_eq!(Leaderboard::load(&path), Ok(board));
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(Leaderboard::load(&path), Ok(Leaderboard::default()));
+    }
+
+    #[test]
+    fn timestamps_go_down_to_the_millisecond() {
+        let stamp = timestamp();
+        let (date, time) = stamp.split_at(10);
+        assert!(date <= today().as_str(), "{stamp}");
+        assert_eq!(time.len(), 11, "{stamp}");
+        assert!(time.starts_with('-') && time[7..8] == *"-", "{stamp}");
+        assert!(time[1..7].chars().chain(time[8..].chars()).all(|ch| ch.is_ascii_digit()), "{stamp}");
+    }
+}
@@ -0,0 +1,257 @@
+// Campaign levels, read from `.lvl` text files. A level file starts with `key: value` lines giving its name
+// and the score that clears it, then after a blank line the board row by row, one character per cell:
+//
+//     name: Pillars
+//     target: 15
+//
+//     ....................
+//     ..##..........##....
+//     ..>.................
+//
+// `#` is a wall, `.` is open floor, and one of `^`, `v`, `<` or `>` is the snake's head, facing that way,
+// with the body trailing behind it. A digit is one end of a portal, and the same digit once more is its
+// twin. The rows give the size of the board.
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+use crate::files::write_atomically;
+use crate::snake::{Direction, START_LENGTH};
+
+pub const LEVELS_DIR: &str = "assets/levels";
+const EXTENSION: &str = "lvl";
+
+#[derive(Clone)]
+pub struct Level {
+    pub name: String,
+    pub grid: (i32, i32), // Columns and rows of the map
+    pub walls: HashSet<(i32, i32)>, // Lethal cells, on top of whatever the settings add
+    pub portals: Vec<[(i32, i32); 2]>, // Pairs of portal cells, at most 10
+    pub start: (i32, i32), // Where the snake's head spawns
+    pub start_dir: Direction,
+    pub target: u32, // Score that clears the level
+}
+
+impl Level {
+    pub fn parse(text: &str) -> Result<Level, String> {
+        let mut lines = text.lines();
+        let (mut name, mut target) = (None, None);
+        for line in lines.by_ref() {
+            if line.trim().is_empty() {
+                break;
+            }
+            let Some((key, value)) = line.split_once(':') else {
+                return Err(format!("malformed header line {:?}", line));
+            };
+            match key.trim() {
+                "name" => name = Some(value.trim().to_string()),
+                "target" => target = Some(value.trim().parse().map_err(|_| format!("malformed target {:?}", value.trim()))?),
+                other => return Err(format!("unknown header {:?}", other)),
+            }
+        }
+        let name = name.ok_or("missing name")?;
+        let target = target.filter(|&target| target > 0).ok_or("the target must be a score of 1 or more")?;
+
+        let rows: Vec<&str> = lines.map(str::trim_end).filter(|row| !row.is_empty()).collect();
+        let columns = rows.first().map_or(0, |row| row.chars().count());
+        if rows.iter().any(|row| row.chars().count() != columns) {
+            return Err("every row of the map must be as long as the first".to_string());
+        }
+        let mut walls = HashSet::new();
+        let mut portals: BTreeMap<char, Vec<(i32, i32)>> = BTreeMap::new();
+        let mut start = None;
+        for (y, row) in rows.iter().enumerate() {
+            for (x, symbol) in row.chars().enumerate() {
+                let cell = (x as i32, y as i32);
+                let dir = match symbol {
+                    '#' => {
+                        walls.insert(cell);
+                        continue;
+                    },
+                    '.' => continue,
+                    '0'..='9' => {
+                        portals.entry(symbol).or_default().push(cell);
+                        continue;
+                    },
+                    '^' => Direction::Up,
+                    'v' => Direction::Down,
+                    '<' => Direction::Left,
+                    '>' => Direction::Right,
+                    other => return Err(format!("unknown map symbol {:?} at {}:{}", other, x, y)),
+                };
+                if start.replace((cell, dir)).is_some() {
+                    return Err("the map has more than one snake".to_string());
+                }
+            }
+        }
+        let Some((start, start_dir)) = start else {
+            return Err("the map has no snake; mark its head with ^, v, < or >".to_string());
+        };
+        let portals = portals
+            .into_iter()
+            .map(|(digit, ends)| <[(i32, i32); 2]>::try_from(ends).map_err(|_| format!("portal {} needs exactly two ends", digit)))
+            .collect::<Result<_, _>>()?;
+        let level = Level { name, grid: (columns as i32, rows.len() as i32), walls, portals, start, start_dir, target };
+        if !level.snake_fits() {
+            return Err(format!("the snake's body must have {} open cells behind its head", START_LENGTH - 1));
+        }
+        if !level.first_move_is_open() {
+            return Err(format!("the snake's first move, {}, runs into a wall or off the map", level.start_dir));
+        }
+        Ok(level)
+    }
+
+    // Cells the snake spawns on, head first, with the body trailing behind the head
+    pub fn snake_cells(&self) -> Vec<(i32, i32)> {
+        let (dx, dy) = self.start_dir.delta();
+        (0..START_LENGTH as i32).map(|i| (self.start.0 - dx * i, self.start.1 - dy * i)).collect()
+    }
+
+    // Whether all of the snake spawns on open floor inside the map
+    pub fn snake_fits(&self) -> bool {
+        self.snake_cells().iter().all(|&(x, y)| x >= 0 && x < self.grid.0 && y >= 0 && y < self.grid.1 && self.is_floor((x, y)))
+    }
+
+    // Whether `cell` is neither a wall nor a portal
+    pub fn is_floor(&self, cell: (i32, i32)) -> bool {
+        !self.walls.contains(&cell) && !self.portals.iter().flatten().any(|&end| end == cell)
+    }
+
+    // The cell the snake's first move goes into
+    pub fn ahead(&self) -> (i32, i32) {
+        let (dx, dy) = self.start_dir.delta();
+        (self.start.0 + dx, self.start.1 + dy)
+    }
+
+    // Whether the cell ahead of the head is inside the map and not a wall, so the first move doesn't kill the snake
+    pub fn first_move_is_open(&self) -> bool {
+        let (x, y) = self.ahead();
+        x >= 0 && x < self.grid.0 && y >= 0 && y < self.grid.1 && !self.walls.contains(&(x, y))
+    }
+
+    pub fn load(path: &Path) -> Result<Level, String> {
+        let text = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+        Level::parse(&text)
+    }
+
+    // The level in the file format `parse` reads
+    pub fn to_text(&self) -> String {
+        let mut text = format!("name: {}\ntarget: {}\n\n", self.name, self.target);
+        for y in 0..self.grid.1 {
+            for x in 0..self.grid.0 {
+                let portal = self.portals.iter().position(|ends| ends.contains(&(x, y)));
+                text.push(match &self.start_dir {
+                    _ if self.walls.contains(&(x, y)) => '#',
+                    _ if portal.is_some() => portal.and_then(|i| char::from_digit(i as u32, 10)).unwrap_or('.'),
+                    Direction::Up if (x, y) == self.start => '^',
+                    Direction::Down if (x, y) == self.start => 'v',
+                    Direction::Left if (x, y) == self.start => '<',
+                    Direction::Right if (x, y) == self.start => '>',
+                    _ => '.',
+                });
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    // Write to a temporary file first and move it into place, so a crash halfway never leaves a damaged file
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        write_atomically(path, &self.to_text())
+    }
+}
+
+// Every level in `dir`, in file name order, so numbering the files sets the order they are played in
+pub fn load_campaign(dir: &Path) -> Result<Vec<Level>, String> {
+    let entries = std::fs::read_dir(dir).map_err(|err| format!("{}: {}", dir.display(), err))?;
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == EXTENSION))
+        .collect();
+    paths.sort();
+    if paths.is_empty() {
+        return Err(format!("{}: no .{} files", dir.display(), EXTENSION));
+    }
+    paths.iter().map(|path| Level::load(path).map_err(|err| format!("{}: {}", path.display(), err))).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A level with `map` as its board
+    fn parse(map: &str) -> Result<Level, String> {
+        Level::parse(&format!("name: Test\ntarget: 5\n\n{}", map))
+    }
+
+    #[test]
+    fn the_arrow_sets_the_start_and_heading() {
+        let maps = [
+            (".....\n.....\n..^..\n.....\n.....\n", Direction::Up),
+            (".....\n.....\n..v..\n.....\n.....\n", Direction::Down),
+            (".....\n.....\n..<..\n.....\n.....\n", Direction::Left),
+            (".....\n.....\n..>..\n.....\n.....\n", Direction::Right),
+        ];
+        for (map, dir) in maps {
+            let level = parse(map).expect("a valid level");
+            assert!(level.start_dir == dir, "{} level starts {}", dir, level.start_dir);
+            assert_eq!((level.start, level.grid), ((2, 2), (5, 5)));
+        }
+    }
+
+    #[test]
+    fn a_start_against_a_wall_is_rejected() {
+        let err = parse(".....\n.....\n..>#.\n.....\n.....\n").err().expect("rejected");
+        assert!(err.contains("first move"), "{}", err);
+    }
+
+    #[test]
+    fn a_start_against_the_edge_is_rejected() {
+        assert!(parse("..^..\n.....\n.....\n.....\n.....\n").is_err());
+        assert!(parse(".....\n.....\n.....\n.....\n....>\n").is_err());
+    }
+
+    #[test]
+    fn a_body_without_room_is_rejected() {
+        assert!(parse(".....\n.....\n.#>..\n.....\n.....\n").is_err());
+        assert!(parse(".....\n.....\n.>...\n.....\n.....\n").is_err());
+    }
+
+    #[test]
+    fn malformed_headers_and_maps_are_rejected() {
+        assert!(Level::parse("name: Test\n\n..>..\n").is_err());
+        assert!(Level::parse("name: Test\ntarget: 0\n\n..>..\n").is_err());
+        assert!(parse("....\n..>..\n").is_err());
+        assert!(parse("..>..\n..<..\n").is_err());
+        assert!(parse("..x..\n").is_err());
+    }
+
+    #[test]
+    fn the_shipped_campaign_loads() {
+        let levels = load_campaign(Path::new(LEVELS_DIR)).expect("the campaign loads");
+        assert!(!levels.is_empty());
+        assert!(levels.iter().all(|level| level.grid == levels[0].grid));
+    }
+
+    #[test]
+    fn a_level_reads_back_as_it_was_written() {
+        let level = parse("#....\n.....\n..v..\n.....\n....#\n").expect("a valid level");
+        let again = Level::parse(&level.to_text()).expect("a valid level");
+        assert_eq!(again.to_text(), level.to_text());
+        assert!(again.walls == level.walls && again.start_dir == Direction::Down);
+    }
+
+    #[test]
+    fn matching_digits_make_a_portal_pair() {
+        let level = parse(".....\n.....\n..>1.\n.....\n1....\n").expect("a valid level");
+        assert!(level.portals == vec![[(3, 2), (0, 4)]]);
+        assert!(!level.is_floor((3, 2)));
+        assert!(parse(".....\n.....\n..>1.\n.....\n.....\n").is_err());
+        assert!(parse("2....\n.....\n..>1.\n.....\n1.2.2\n").is_err());
+    }
+
+    #[test]
+    fn portals_read_back_as_they_were_written() {
+        let level = parse("#....\n.1...\n..v..\n...1.\n0...0\n").expect("a valid level");
+        assert_eq!(Level::parse(&level.to_text()).expect("a valid level").to_text(), level.to_text());
+    }
+}
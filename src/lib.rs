@@ -0,0 +1,48 @@
+// Copperhead's game logic and its frontend pieces, usable without opening a window. `game` and `snake` hold
+// the rules and don't depend on Piston, `level` reads campaign levels for them and `editor` makes new ones,
+// and `mods` runs scripted rules on top of them. `env` lets learning agents play them; `render` and `input`
+// extend `Game` for the window and terminal, in the colors of a `theme`, `menu` is the title screen's menu,
+// `audio` plays music to go with it and `particles` throws sparks when food is eaten. The window is drawn
+// through the `renderer` trait, which `piston_renderer` implements with Piston; with the `sdl` feature, `sdl`
+// implements it with SDL2 instead, and `image_renderer` draws it into a picture for screenshots and for the
+// GIFs `recorder` makes of runs. `leaderboard`, `stats` and `achievements` keep what is remembered between
+// sessions, and `online` shares scores on a global leaderboard. `net` plays a two-player run between two
+// machines, and `spectate` streams a run to viewers. With the `wasm` feature, `web` plays the game in a
+// browser; the desktop-only modules are left out of that build.
+
+pub mod achievements;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod audio;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod config;
+pub mod editor;
+pub mod env;
+pub mod files;
+pub mod game;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod image_renderer;
+pub mod input;
+pub mod leaderboard;
+pub mod level;
+pub mod menu;
+pub mod mods;
+pub mod move_scheduler;
+pub mod net;
+pub mod online;
+pub mod particles;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod piston_renderer;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod recorder;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod render;
+pub mod renderer;
+#[cfg(all(feature = "sdl", not(target_arch = "wasm32")))]
+pub mod sdl;
+pub mod snake;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod spectate;
+pub mod stats;
+pub mod theme;
+#[cfg(feature = "wasm")]
+pub mod web;
@@ -2,423 +2,1041 @@ extern crate piston_window;
 extern crate rand;
 
 use piston_window::*;
-use rand::Rng;
-use std::collections::LinkedList;
-use ::image::io::Reader as ImageReader;
+use ::image::ImageReader;
 use ::image::ImageFormat;
+use copperhead::game::{Autopilot, CellShape, ControlScheme, FoodDecay, FoodKind, FoodSpawn, FrameTimes, Game, GameState, Players, RunStats, SavedRun, Scoring, Settings, TickResult, WallMode, COUNTDOWN_SECONDS, DEFAULT_FONT, INPUT_BUFFER_LEN, SPRITE_ATLAS};
+use copperhead::audio::Music;
+use copperhead::config::{Config, parse_hex_color};
+use copperhead::piston_renderer::PistonRenderer;
+use copperhead::renderer::Renderer;
+use copperhead::render::{ascii_board_size, ascii_origin, draw_ascii};
+use copperhead::input::{HeldKeys, Stick};
+use copperhead::achievements::Achievements;
+use copperhead::image_renderer::snapshot;
+use copperhead::leaderboard::{timestamp, Leaderboard};
+use copperhead::level::{load_campaign, LEVELS_DIR};
+use copperhead::move_scheduler::MoveScheduler;
+use copperhead::mods::{load_mods, MODS_DIR};
+use copperhead::net::Lockstep;
+use copperhead::recorder::Recorder;
+use copperhead::spectate::Spectators;
+use copperhead::stats::Stats;
+use copperhead::theme::Theme;
 
-const GRID_SIZE: (i32, i32) = (20, 20); // 20x20 grid
-const CELL_SIZE: i32 = 32; // Each cell is 32x32 pixels
-
-// Make the window big enough to show the border outside the playfield
-const BORDER_THICKNESS: f64 = 16.0;
-const BORDER_FULL: f64 = BORDER_THICKNESS * 2.0;
-const WINDOW_SIZE: [u32; 2] = [
-    (GRID_SIZE.0 * CELL_SIZE) as u32 + (BORDER_FULL as u32) * 2,
-    (GRID_SIZE.1 * CELL_SIZE) as u32 + (BORDER_FULL as u32) * 2,
-];
-
-#[derive(Clone, PartialEq)]
-enum Direction {
-    Left, Right, Up, Down
-}
-
-#[derive(PartialEq)]
-enum GameState {
-    Start,
-    Running,
-    GameOver,
-}
-
-struct Game {
-    snake: Snake,
-    food: (i32, i32),
-    score: u32,
-    high_score: u32,
-    state: GameState,
-}
-
-struct Snake {
-    body: LinkedList<(i32, i32)>,
-    dir: Direction,
-    grow_on_next: bool,
-}
-
-impl Game {
-    fn render<G: Graphics<Texture = piston_window::G2dTexture>>(&self, c: Context, g: &mut G, glyphs: &mut Glyphs) {
-        // Use a brighter, more saturated copper for the background to increase vibrancy
-        let copper_bg: [f32; 4] = [0.65, 0.40, 0.18, 1.0];
-        let food_color: [f32; 4] = [0.95, 0.64, 0.37, 1.0];
-        let border_color: [f32; 4] = [0.25, 0.13, 0.05, 1.0]; // darker border
-        clear(copper_bg, g);
-
-        // Draw dark border AROUND the playfield (outside the grid, not overlapping any cell)
-        let w = (GRID_SIZE.0 * CELL_SIZE) as f64;
-        let h = (GRID_SIZE.1 * CELL_SIZE) as f64;
-        let thickness = BORDER_THICKNESS;
-
-        // Top (thick for score text)
-        let border_height = thickness * 2.0;
-
-        // All borders now use border_height
-        rectangle(border_color, [0.0, 0.0, w + border_height * 2.0, border_height], c.transform.trans(0.0, 0.0), g); // Top
-        rectangle(border_color, [0.0, 0.0, w + border_height * 2.0, border_height], c.transform.trans(0.0, h + border_height), g); // Bottom
-        rectangle(border_color, [0.0, 0.0, border_height, h + border_height * 2.0], c.transform.trans(0.0, 0.0), g); // Left
-        rectangle(border_color, [0.0, 0.0, border_height, h + border_height * 2.0], c.transform.trans(w + border_height, 0.0), g); // Right
-
-        // Shift playfield drawing to border_height so grid is inside border
-        let playfield_transform = c.transform.trans(border_height, border_height);
-        if self.state == GameState::Running {
-            let food_square = [
-                (self.food.0 * CELL_SIZE) as f64,
-                (self.food.1 * CELL_SIZE) as f64,
-                CELL_SIZE as f64,
-                CELL_SIZE as f64,
-            ];
-            rectangle(food_color, food_square, playfield_transform, g);
-            self.snake.render(Context { transform: playfield_transform, ..c }, g);
-        }
-
-        // Draw overlays
-        let text_color: [f32; 4] = [0.95, 0.85, 0.65, 1.0];
-        let win_w = WINDOW_SIZE[0] as f64;
-        let win_h = WINDOW_SIZE[1] as f64;
-
-        // Helper for true centering: measure text width
-        use piston_window::CharacterCache;
-        match self.state {
-            GameState::Start => {
-                let title = "COPPERHEAD";
-                let prompt = "Press space to start";
-                let title_width = glyphs.width(48, title).unwrap_or(0.0);
-                let prompt_width = glyphs.width(24, prompt).unwrap_or(0.0);
-                let win_center_x = win_w / 2.0;
-                let win_center_y = win_h / 2.0;
-                text(text_color, 48, title, glyphs, c.transform.trans(win_center_x - title_width / 2.0, win_center_y - 60.0), g).ok();
-
-                // Draw a preview of the snake under the title
-                draw_snake_preview(c, g);
-
-                // Move the prompt further down, under the snake preview
-                let prompt_y = win_center_y + (CELL_SIZE as f64) + 50.0;
-                text(text_color, 24, prompt, glyphs, c.transform.trans(win_center_x - prompt_width / 2.0, prompt_y), g).ok();
-            },
-            GameState::Running => {
-                let score_str = format!("{}", self.score);
-                let score_width = glyphs.width(24, &score_str).unwrap_or(0.0);
-                text(text_color, 24, &score_str, glyphs, c.transform.trans(win_w / 2.0 - score_width / 2.0, border_height * 0.75), g).ok();
-            },
-            GameState::GameOver => {
-                // Red-tinted background for game over
-                let red_overlay: [f32; 4] = [0.6, 0.1, 0.1, 1.0];
-                clear(red_overlay, g);
-
-                // Draw playfield and snake in final position (no food)
-                let w = (GRID_SIZE.0 * CELL_SIZE) as f64;
-                let h = (GRID_SIZE.1 * CELL_SIZE) as f64;
-                let border_height = BORDER_THICKNESS * 2.0;
-                let border_color: [f32; 4] = [0.25, 0.13, 0.05, 1.0];
-
-                // Borders
-                rectangle(border_color, [0.0, 0.0, w + border_height * 2.0, border_height], c.transform.trans(0.0, 0.0), g); // Top
-                rectangle(border_color, [0.0, 0.0, w + border_height * 2.0, border_height], c.transform.trans(0.0, h + border_height), g); // Bottom
-                rectangle(border_color, [0.0, 0.0, border_height, h + border_height * 2.0], c.transform.trans(0.0, 0.0), g); // Left
-                rectangle(border_color, [0.0, 0.0, border_height, h + border_height * 2.0], c.transform.trans(w + border_height, 0.0), g); // Right
-                let playfield_transform = c.transform.trans(border_height, border_height);
-                self.snake.render(Context { transform: playfield_transform, ..c }, g);
-
-                // Overlay text
-                let text_color: [f32; 4] = [0.95, 0.85, 0.65, 1.0];
-                let win_w = WINDOW_SIZE[0] as f64;
-                let win_h = WINDOW_SIZE[1] as f64;
-                let over = "COILED!";
-                let score_str = format!("Score: {}", self.score);
-                let high_str = format!("Highest: {}", self.high_score);
-                let prompt = "Press space to restart";
-                let over_width = glyphs.width(48, over).unwrap_or(0.0);
-                let score_width = glyphs.width(24, &score_str).unwrap_or(0.0);
-                let high_width = glyphs.width(24, &high_str).unwrap_or(0.0);
-                let prompt_width = glyphs.width(20, prompt).unwrap_or(0.0);
-                text(text_color, 48, over, glyphs, c.transform.trans(win_w / 2.0 - over_width / 2.0, win_h / 2.0 - 40.0), g).ok();
-                text(text_color, 24, &score_str, glyphs, c.transform.trans(win_w / 2.0 - score_width / 2.0, win_h / 2.0 + 20.0), g).ok();
-                text(text_color, 24, &high_str, glyphs, c.transform.trans(win_w / 2.0 - high_width / 2.0, win_h / 2.0 + 60.0), g).ok();
-                text(text_color, 20, prompt, glyphs, c.transform.trans(win_w / 2.0 - prompt_width / 2.0, win_h / 2.0 + 110.0), g).ok();
-            }
-        }
-    }
-
-    fn update(&mut self) {
-        // Don't update if game is not running
-        if self.state != GameState::Running {
-            return;
+const IDLE_FPS: u64 = 10; // Frame cap on static screens (start, game over) to save power
+const SCREENSHOTS_DIR: &str = "screenshots"; // Where F12 saves the window's picture
+const BENCHMARK_MAX_MOVES: u32 = 100_000; // A benchmark game that gets this long is called off, in case the autopilot circles forever
+
+// Record the runs of `game`, or play on without recording if the font for the pictures can't be loaded
+fn start_recording(game: &Game) -> Option<Recorder> {
+    match Recorder::new(game) {
+        Ok(recorder) => Some(recorder),
+        Err(err) => {
+            eprintln!("copperhead: not recording: {}", err);
+            None
         }
+    }
+}
+
+// Draw the game again into a picture `view` pixels big and save it as a PNG named after the time in
+// SCREENSHOTS_DIR, telling where it went or what went wrong
+fn save_screenshot(game: &Game, view: [u32; 2]) {
+    let path = std::path::Path::new(SCREENSHOTS_DIR).join(format!("copperhead-{}.png", timestamp()));
+    let result = snapshot(game, view).and_then(|picture| {
+        std::fs::create_dir_all(SCREENSHOTS_DIR).map_err(|err| err.to_string())?;
+        picture.save(&path).map_err(|err| err.to_string())
+    });
+    match result {
+        Ok(()) => eprintln!("copperhead: screenshot saved to {}", path.display()),
+        Err(err) => eprintln!("copperhead: could not save screenshot: {}", err),
+    }
+}
 
-        // Food
-        let ate = self.snake.update(self.food);
-        if ate {
-            self.score += 1;
-            self.snake.grow();
-            self.spawn_food();
+// Counts the frames drawn and the updates made, and how long the updates took, over each second of real
+// time, for the debug overlay
+struct FrameMeter {
+    since: std::time::Instant,
+    frames: u32,
+    updates: u32,
+    busy: std::time::Duration, // Time spent in updates
+}
+
+impl FrameMeter {
+    fn new() -> Self {
+        FrameMeter { since: std::time::Instant::now(), frames: 0, updates: 0, busy: std::time::Duration::ZERO }
+    }
+
+    fn frame(&mut self) {
+        self.frames += 1;
+    }
+
+    fn update(&mut self, took: std::time::Duration) {
+        self.updates += 1;
+        self.busy += took;
+    }
+
+    // Once a second has gone by, hand the rates to the game and start counting again. An open overlay is
+    // drawn again to show them, even on a screen that otherwise stands still.
+    fn report(&mut self, game: &mut Game) {
+        let seconds = self.since.elapsed().as_secs_f64();
+        if seconds < 1.0 {
+            return;
+        }
+        game.frame_times = FrameTimes {
+            fps: self.frames as f64 / seconds,
+            ups: self.updates as f64 / seconds,
+            tick_ms: if self.updates > 0 { self.busy.as_secs_f64() * 1000.0 / self.updates as f64 } else { 0.0 },
+        };
+        if game.debug_shown {
+            game.mark_dirty();
         }
+        *self = FrameMeter::new();
+    }
+}
+
+// Connected gamepads: buttons and the left stick in, force feedback out. Quietly does nothing without a controller.
+struct Gamepads {
+    gilrs: Option<gilrs::Gilrs>,
+    rumble: bool, // Force feedback on eating and dying
+    effect: Option<gilrs::ff::Effect>, // Kept alive while playing, since dropping it stops the effect
+    stick: Stick,
+}
 
-        // Check wall collision (now with border thickness)
-        let (x, y) = self.snake.head();
-        if x < 0 || x >= GRID_SIZE.0 || y < 0 || y >= GRID_SIZE.1 || self.snake.self_collision() {
-            self.state = GameState::GameOver;
-            if self.score > self.high_score {
-                self.high_score = self.score;
+impl Gamepads {
+    fn new(rumble: bool) -> Self {
+        Gamepads { gilrs: gilrs::Gilrs::new().ok(), rumble, effect: None, stick: Stick::default() }
+    }
+
+    // Drain pending gamepad events, which also picks up (dis)connected controllers, and return the keys
+    // the presses among them stand in for, in order
+    fn poll(&mut self, game: &Game) -> Vec<Key> {
+        let mut keys = Vec::new();
+        let Some(gilrs) = self.gilrs.as_mut() else {
+            return keys;
+        };
+        while let Some(event) = gilrs.next_event() {
+            match event.event {
+                gilrs::EventType::ButtonPressed(button, _) => keys.extend(game.pad_key(button)),
+                gilrs::EventType::AxisChanged(axis, value, _) => {
+                    keys.extend(self.stick.move_axis(axis, value).and_then(|dir| game.stick_key(&dir)));
+                },
+                _ => {},
             }
         }
+        keys
     }
 
-    // Handle key presses
-    fn pressed(&mut self, btn: &Button) {
-        match self.state {
-            GameState::Start => {
-                if let &Button::Keyboard(Key::Space) = btn {
-                    self.state = GameState::Running;
-                }
-            },
-            GameState::GameOver => {
-                if let &Button::Keyboard(Key::Space) = btn {
-                    self.reset();
-                }
-            },
-            GameState::Running => {
-                let last_direction = self.snake.dir.clone();
-                self.snake.dir = match btn {
-                    &Button::Keyboard(Key::Up)
-                        if last_direction != Direction::Down => Direction::Up,
-                    &Button::Keyboard(Key::Down)
-                        if last_direction != Direction::Up => Direction::Down,
-                    &Button::Keyboard(Key::Left)
-                        if last_direction != Direction::Right => Direction::Left,
-                    &Button::Keyboard(Key::Right)
-                        if last_direction != Direction::Left => Direction::Right,
-                    _ => last_direction,
-                };
+    // Short, light blip
+    fn eat(&mut self) {
+        self.pulse(gilrs::ff::BaseEffectType::Weak { magnitude: 24_000 }, 80);
+    }
+
+    // Longer, heavy thud that can't be mistaken for eating
+    fn death(&mut self) {
+        self.pulse(gilrs::ff::BaseEffectType::Strong { magnitude: 60_000 }, 350);
+    }
+
+    fn pulse(&mut self, kind: gilrs::ff::BaseEffectType, ms: u32) {
+        use gilrs::ff::{BaseEffect, EffectBuilder, Repeat, Replay, Ticks};
+        let Some(gilrs) = self.gilrs.as_mut().filter(|_| self.rumble) else {
+            return;
+        };
+        let pads: Vec<_> = gilrs.gamepads().filter(|(_, pad)| pad.is_ff_supported()).map(|(id, _)| id).collect();
+        if pads.is_empty() {
+            return;
+        }
+        let duration = Ticks::from_ms(ms);
+        let effect = EffectBuilder::new()
+            .add_effect(BaseEffect {
+                kind,
+                scheduling: Replay { play_for: duration, ..Default::default() },
+                ..Default::default()
+            })
+            .gamepads(&pads)
+            .repeat(Repeat::For(duration))
+            .finish(gilrs);
+        if let Ok(effect) = effect
+            && effect.play().is_ok()
+        {
+            self.effect = Some(effect);
+        }
+    }
+}
+
+// Center the window on the primary monitor
+fn center_window(window: &mut PistonWindow) {
+    use winit::dpi::PhysicalPosition;
+
+    let win_size = window.size();
+    let winit_window = window.window.ctx.window();
+    let Some(monitor) = winit_window.primary_monitor().or_else(|| winit_window.current_monitor()) else {
+        return;
+    };
+    let (origin, monitor_size) = (monitor.position(), monitor.size());
+    let x = origin.x + (monitor_size.width.saturating_sub(win_size.width as u32) / 2) as i32;
+    let y = origin.y + (monitor_size.height.saturating_sub(win_size.height as u32) / 2) as i32;
+    winit_window.set_outer_position(PhysicalPosition::new(x, y));
+}
+
+// Switch between a window and borderless fullscreen on the window's current monitor. Leaving fullscreen
+// gives the window back its old size and place.
+fn toggle_fullscreen(window: &mut PistonWindow, game: &mut Game) {
+    use winit::window::Fullscreen;
+
+    game.settings.fullscreen = !game.settings.fullscreen;
+    window.window.ctx.window().set_fullscreen(game.settings.fullscreen.then_some(Fullscreen::Borderless(None)));
+    game.mark_dirty();
+}
+
+// Fit the window to a board that changed size on the options page. A fullscreen window stays as it is, with
+// the board scaled to it.
+fn fit_window(window: &mut PistonWindow, game: &mut Game) {
+    if !game.settings.fullscreen {
+        window.set_size(game.settings.layout().window_size());
+    }
+    game.mark_dirty();
+}
+
+// Put the window back where it was last session. Returns false (leaving the window alone) on first run,
+// or when that spot is no longer on any connected monitor.
+fn restore_window_position(window: &mut PistonWindow, position: Option<[i32; 2]>) -> bool {
+    use winit::dpi::PhysicalPosition;
+
+    let Some([x, y]) = position else {
+        return false;
+    };
+    let winit_window = window.window.ctx.window();
+    let on_screen = winit_window.available_monitors().any(|monitor| {
+        let (origin, size) = (monitor.position(), monitor.size());
+        x >= origin.x && y >= origin.y && x < origin.x + size.width as i32 && y < origin.y + size.height as i32
+    });
+    if on_screen {
+        winit_window.set_outer_position(PhysicalPosition::new(x, y));
+    }
+    on_screen
+}
+
+// Store the window position and size under [window] in the config, for the next session. The file is read
+// again first, so edits made to it while the game was running are kept.
+fn remember_window(window: &PistonWindow, path: &std::path::Path) -> Result<(), String> {
+    let position = window.window.ctx.window().outer_position().map_err(|err| err.to_string())?;
+    let size = window.size();
+    let mut config = Config::load(path)?;
+    config.window.position = Some([position.x, position.y]);
+    config.window.size = Some([size.width as u32, size.height as u32]);
+    config.save(path).map_err(|err| err.to_string())
+}
+
+// Puts the terminal into raw/alternate-screen mode and restores it when dropped,
+// so the shell is left usable however the game ends (quit, error or panic)
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter() -> std::io::Result<Self> {
+        use crossterm::{cursor, execute, terminal};
+        terminal::enable_raw_mode()?;
+        execute!(std::io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(TerminalGuard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        use crossterm::{cursor, execute, terminal};
+        let _ = execute!(std::io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+// The window key a terminal key stands for, so both frontends share the key handling and bindings
+fn terminal_key(code: crossterm::event::KeyCode) -> Option<Key> {
+    use crossterm::event::KeyCode;
+
+    match code {
+        KeyCode::Up => Some(Key::Up),
+        KeyCode::Down => Some(Key::Down),
+        KeyCode::Left => Some(Key::Left),
+        KeyCode::Right => Some(Key::Right),
+        KeyCode::Enter => Some(Key::Return),
+        KeyCode::Backspace => Some(Key::Backspace),
+        KeyCode::Tab => Some(Key::Tab),
+        KeyCode::F(1) => Some(Key::F1),
+        KeyCode::Char(c) if " -=+[]".contains(c) || c.is_ascii_alphanumeric() => Some(Key::from(c.to_ascii_lowercase() as u32)),
+        _ => None,
+    }
+}
+
+// Music keys work on every screen except while initials are being typed or a key is being bound, and give
+// way to keys bound to an action
+fn music_key(game: &mut Game, key: Key) -> bool {
+    if game.initials.is_some() || game.rebinding.is_some() || game.key_action(key).is_some() {
+        return false;
+    }
+    match key {
+        Key::M => game.toggle_mute(),
+        Key::Minus | Key::NumPadMinus => game.change_volume(-1),
+        Key::Equals | Key::Plus | Key::NumPadPlus => game.change_volume(1),
+        _ => return false,
+    }
+    true
+}
+
+// Options changed in game since it was `launched` are written back to the config file, which is left alone
+// otherwise. The file is read again first, so edits made to it while the game was running are kept, and so
+// are the window position and size stored in it.
+fn remember_options(path: Option<&std::path::Path>, launched: &Settings, settings: &Settings) {
+    if let Some(path) = path
+        && let Err(err) = Config::load(path).and_then(|saved| {
+            let changed = saved.remember(launched, settings);
+            if changed == saved { Ok(()) } else { changed.save(path).map_err(|err| err.to_string()) }
+        })
+    {
+        eprintln!("copperhead: could not remember the options in {}: {}", path.display(), err);
+    }
+}
+
+// Terminal frontend: same rules as the window, drawn with characters
+fn run_ascii(game: &mut Game, music: &mut Music, mut lockstep: Option<&mut Lockstep>, mut spectators: Option<&mut Spectators>, mut recorder: Option<&mut Recorder>) -> std::io::Result<()> {
+    use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+    use crossterm::terminal;
+    use std::time::{Duration, Instant};
+
+    let size = terminal::size()?;
+    let mut layout = game.settings.layout();
+    let board_size = ascii_board_size(layout);
+    let mut origin = ascii_origin(layout, size).ok_or_else(|| std::io::Error::other(format!(
+        "terminal is {}x{} but the board needs at least {}x{}, please enlarge it and try again",
+        size.0, size.1, board_size.0, board_size.1,
+    )))?;
+
+    let _guard = TerminalGuard::enter()?;
+    let mut last_update = Instant::now();
+    let mut scheduler = MoveScheduler::new(game.settings.move_interval);
+    draw_ascii(game, origin, true)?;
+
+    loop {
+        // Wait for input until the next frame is due (~100Hz, like the window's update rate)
+        let mut redraw_all = false;
+        while event::poll(Duration::from_millis(10))? {
+            match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => match key.code {
+                    KeyCode::Char(letter) if game.initials.is_some() => game.type_initial(letter),
+                    KeyCode::Esc if !game.quits_on_escape() => game.pressed(&Button::Keyboard(Key::Escape)),
+                    KeyCode::Esc | KeyCode::Char('q') => return Ok(()),
+                    code => {
+                        if let Some(key) = terminal_key(code)
+                            && !music_key(game, key)
+                        {
+                            game.press_key(key);
+                        }
+                    },
+                },
+                Event::Resize(cols, rows) => {
+                    // Recenter; a terminal that got too small just shows a hint until it's enlarged again
+                    origin = ascii_origin(layout, (cols, rows)).unwrap_or((0, 0));
+                    redraw_all = true;
+                },
+                _ => {},
+            }
+        }
+        if game.quit_requested {
+            return Ok(());
+        }
+        // The board can change size on the options page, and the speed too
+        if std::mem::take(&mut game.resized) {
+            layout = game.settings.layout();
+            origin = ascii_origin(layout, terminal::size()?).unwrap_or((0, 0));
+            redraw_all = true;
+        }
+        scheduler.set_interval(game.settings.move_interval);
+
+        let fits = ascii_origin(layout, terminal::size()?).is_some();
+        let dt = game.game_dt(last_update.elapsed().as_secs_f64());
+        last_update = Instant::now();
+        if fits {
+            if lockstep.is_none() {
+                game.tick_clock(dt);
+            }
+            let due = scheduler.tick(dt * game.move_speed());
+            for _ in 0..ready_moves(game, lockstep.as_deref_mut(), due) {
+                make_move(game, lockstep.as_deref_mut());
             }
+            if let Some(spectators) = spectators.as_deref_mut() {
+                spectators.publish(game);
+            }
+            if let Some(recorder) = recorder.as_deref_mut() {
+                recorder.capture(game);
+            }
+            music.follow(game);
+            draw_ascii(game, origin, redraw_all)?;
+        } else if redraw_all {
+            use crossterm::{cursor, execute, style};
+            execute!(std::io::stdout(), terminal::Clear(terminal::ClearType::All), cursor::MoveTo(0, 0), style::Print("Terminal too small, please enlarge it"))?;
         }
     }
+}
+
+// SDL2 frontend, for systems where Piston's window won't open: the same game and controls as that window
+#[cfg(feature = "sdl")]
+fn run_sdl(game: &mut Game, music: &mut Music, mut lockstep: Option<&mut Lockstep>, mut spectators: Option<&mut Spectators>, mut recorder: Option<&mut Recorder>) -> Result<(), String> {
+    use copperhead::sdl::{self, Fonts, SdlRenderer};
+    use sdl2::event::{Event, WindowEvent};
+    use std::path::Path;
+    use std::time::Instant;
+
+    let context = sdl2::init()?;
+    let mut canvas = sdl::open_window(&context, game.settings.layout().window_size(), game.settings.fullscreen)?;
+    let mut events = context.event_pump()?;
+    let ttf = sdl2::ttf::init().map_err(|err| err.to_string())?;
+    let textures = canvas.texture_creator();
+
+    // A custom font that can't be loaded falls back to the bundled one
+    let custom_font = game.settings.font_path.as_ref().and_then(|path| match Fonts::open(&ttf, path) {
+        Ok(fonts) => Some(fonts),
+        Err(err) => {
+            eprintln!("copperhead: could not load font {}: {}, using the default font", path.display(), err);
+            None
+        }
+    });
+    let mut fonts = match custom_font {
+        Some(fonts) => fonts,
+        None => Fonts::open(&ttf, Path::new(DEFAULT_FONT)).map_err(|err| format!("could not load font {}: {}", DEFAULT_FONT, err))?,
+    };
 
-    fn spawn_food(&mut self) {
-        let mut rng = rand::thread_rng();
-        loop {
-            let pos = (
-                rng.gen_range(0..GRID_SIZE.0),
-                rng.gen_range(0..GRID_SIZE.1),
-            );
-            if !self.snake.body.contains(&pos) {
-                self.food = pos;
-                break;
+    // Sprites that can't be loaded fall back to the plain shapes
+    let sprites = if game.settings.sprites {
+        match sdl::load_texture(&textures, SPRITE_ATLAS) {
+            Ok(atlas) => Some(atlas),
+            Err(err) => {
+                eprintln!("copperhead: could not load sprites {}: {}, drawing plain shapes instead", SPRITE_ATLAS, err);
+                None
             }
         }
+    } else {
+        None
+    };
+    game.settings.sprites = sprites.is_some();
+
+    let mut gamepads = Gamepads::new(game.settings.rumble);
+    let mut last_update = Instant::now();
+    let mut scheduler = MoveScheduler::new(game.settings.move_interval);
+    let mut held_keys = HeldKeys::default();
+    let mut meter = FrameMeter::new();
+    loop {
+        // Wait for input until the next update is due (~100Hz, like the Piston window's update rate)
+        let first = events.wait_event_timeout(10);
+        for event in first.into_iter().chain(events.poll_iter()) {
+            match event {
+                Event::Quit { .. } => return Ok(()),
+                Event::KeyDown { keycode: Some(keycode), .. } => {
+                    let key = sdl::key(keycode);
+                    if !held_keys.press(key) {
+                        continue;
+                    }
+                    if key == Key::Escape && game.quits_on_escape() {
+                        return Ok(());
+                    } else if key == Key::F11 {
+                        game.settings.fullscreen = !game.settings.fullscreen;
+                        sdl::set_fullscreen(&mut canvas, game.settings.fullscreen);
+                        game.mark_dirty();
+                    } else if key == Key::F12 {
+                        let (width, height) = canvas.window().size();
+                        save_screenshot(game, [width, height]);
+                    } else if !music_key(game, key) {
+                        game.press_key(key);
+                    }
+                },
+                Event::KeyUp { keycode: Some(keycode), .. } => held_keys.release(sdl::key(keycode)),
+                // The mouse only does something in the level editor
+                Event::MouseMotion { x, y, .. } => {
+                    let (width, height) = canvas.window().size();
+                    game.mouse_moved(game.cell_at([width as f64, height as f64], [x as f64, y as f64]));
+                },
+                Event::MouseButtonDown { mouse_btn, .. } => game.mouse_pressed(sdl::mouse_button(mouse_btn)),
+                Event::MouseButtonUp { .. } => game.mouse_released(),
+                Event::Window { win_event, .. } => {
+                    // Releases that happen while another window has focus never arrive
+                    if let WindowEvent::FocusLost = win_event {
+                        held_keys.clear();
+                        game.mouse_released();
+                    }
+                    // The window contents may be lost or rescaled, so draw them again
+                    game.mark_dirty();
+                },
+                _ => {},
+            }
+        }
+
+        game.print_json_lines();
+        let started = Instant::now();
+        // Gamepad presses act like the keys they stand in for, except that no button quits but the menu's Quit
+        for key in gamepads.poll(game) {
+            game.press_key(key);
+        }
+        if game.quit_requested {
+            return Ok(());
+        }
+        // The board can change size on the options page, and the speed too
+        if std::mem::take(&mut game.resized) && !game.settings.fullscreen {
+            sdl::set_size(&mut canvas, game.settings.layout().window_size());
+        }
+        scheduler.set_interval(game.settings.move_interval);
+        let dt = game.game_dt(last_update.elapsed().as_secs_f64());
+        last_update = Instant::now();
+        if lockstep.is_none() {
+            game.tick_clock(dt);
+        }
+        let due = scheduler.tick(dt * game.move_speed());
+        for _ in 0..ready_moves(game, lockstep.as_deref_mut(), due) {
+            let (foods_before, was_running) = (game.foods_eaten, game.state == GameState::Running);
+            make_move(game, lockstep.as_deref_mut());
+            if game.foods_eaten > foods_before {
+                gamepads.eat();
+            } else if was_running && game.state == GameState::GameOver && game.death_cause.is_some() {
+                gamepads.death();
+            }
+        }
+        if let Some(spectators) = spectators.as_deref_mut() {
+            spectators.publish(game);
+        }
+        if let Some(recorder) = recorder.as_deref_mut() {
+            recorder.capture(game);
+        }
+        music.follow(game);
+        game.move_progress = scheduler.progress();
+        if game.slides_snake() && game.is_animating() {
+            game.mark_dirty();
+        }
+        meter.update(started.elapsed());
+        meter.report(game);
+
+        if game.is_dirty() {
+            let mut renderer = SdlRenderer::new(&mut canvas, &textures, &mut fonts);
+            game.render(&mut renderer, sprites.as_ref());
+            renderer.present();
+            game.clear_dirty();
+            meter.frame();
+        }
     }
+}
 
-    fn reset(&mut self) {
-        self.snake = Snake::new();
-        self.score = 0;
-        self.state = GameState::Start;
-        self.spawn_food();
+// How many of the `due` moves can be made now: all of them, or in a network run only those the other
+// machine has sent its turns for. A network run keeps its clock by the move instead of by the frame, so
+// both machines' clocks agree.
+fn ready_moves(game: &mut Game, lockstep: Option<&mut Lockstep>, due: u32) -> u32 {
+    match lockstep {
+        Some(lockstep) => lockstep.ready(game, due),
+        None => due,
     }
 }
 
-impl Snake {
-    fn new() -> Self {
-        let mut body = LinkedList::new();
-        let y = GRID_SIZE.1 / 2;
-        let x = GRID_SIZE.0 / 2;
-        body.push_back((x, y));
-        body.push_back((x - 1, y));
-        body.push_back((x - 2, y));
-        Snake {
-            body,
-            dir: Direction::Right,
-            grow_on_next: false,
-        }
-    }
-    fn render<G: Graphics>(&self, c: Context, g: &mut G) {
-        let head_color: [f32; 4] = [0.90, 0.60, 0.25, 1.0]; // More coppery head
-        let eye_color: [f32; 4] = [0.1, 0.1, 0.1, 1.0];
-        let mut iter = self.body.iter();
-        if let Some(&(x, y)) = iter.next() {
-            let head_square = [
-                (x * CELL_SIZE) as f64,
-                (y * CELL_SIZE) as f64,
-                CELL_SIZE as f64,
-                CELL_SIZE as f64,
-            ];
-
-            // Always draw the head, even if it overlaps the body (game over)
-            rectangle(head_color, head_square, c.transform, g);
-
-            // Fake reflection: draw a lighter, semi-transparent rectangle on the upper left of the head
-            let reflection_color: [f32; 4] = [1.0, 0.95, 0.80, 0.35];
-            let refl_w = CELL_SIZE as f64 * 0.45;
-            let refl_h = CELL_SIZE as f64 * 0.18;
-            let refl_x = (x * CELL_SIZE) as f64 + CELL_SIZE as f64 * 0.10;
-            let refl_y = (y * CELL_SIZE) as f64 + CELL_SIZE as f64 * 0.10;
-            rectangle(reflection_color, [refl_x, refl_y, refl_w, refl_h], c.transform, g);
-
-            // Eyes (move slightly to the front of the head)
-            let cx = (x * CELL_SIZE) as f64 + CELL_SIZE as f64 / 2.0;
-            let cy = (y * CELL_SIZE) as f64 + CELL_SIZE as f64 / 2.0;
-            let eye_r = CELL_SIZE as f64 * 0.1;
-            let eye_offset_x = CELL_SIZE as f64 * 0.20;
-            let eye_offset_y = CELL_SIZE as f64 * 0.20;
-            let front_offset = CELL_SIZE as f64 * 0.18;
-
-            let (eye1, eye2) = match self.dir {
-                Direction::Up => (
-                    [cx - eye_offset_x, cy - front_offset],
-                    [cx + eye_offset_x, cy - front_offset],
-                ),
-                Direction::Down => (
-                    [cx - eye_offset_x, cy + front_offset],
-                    [cx + eye_offset_x, cy + front_offset],
-                ),
-                Direction::Left => (
-                    [cx - front_offset, cy - eye_offset_y],
-                    [cx - front_offset, cy + eye_offset_y],
-                ),
-                Direction::Right => (
-                    [cx + front_offset, cy - eye_offset_y],
-                    [cx + front_offset, cy + eye_offset_y],
-                ),
-            };
-
-            // Draw eyes as little squares instead of ellipses
-            let eye_size = eye_r * 2.0;
-            rectangle(eye_color, [eye1[0] - eye_r, eye1[1] - eye_r, eye_size, eye_size], c.transform, g);
-            rectangle(eye_color, [eye2[0] - eye_r, eye2[1] - eye_r, eye_size, eye_size], c.transform, g);
-
-            // Draw the rest of the body, skipping any segment at the head's position
-            for (i, &(bx, by)) in iter.enumerate() {
-                if bx == x && by == y {
-                    continue; // skip body segment that overlaps the head
+fn make_move(game: &mut Game, lockstep: Option<&mut Lockstep>) {
+    match lockstep {
+        Some(lockstep) => lockstep.update(game),
+        None => game.update(),
+    }
+}
+
+// Value following `flag` on the command line, if the flag is present; exits with a hint if the value is missing or malformed
+fn flag_value<T: std::str::FromStr>(args: &[String], flag: &str, expected: &str) -> Option<T> {
+    let i = args.iter().position(|arg| arg == flag)?;
+    match args.get(i + 1).and_then(|value| value.parse().ok()) {
+        Some(value) => Some(value),
+        None => {
+            eprintln!("copperhead: {} needs {}", flag, expected);
+            std::process::exit(1);
+        }
+    }
+}
+
+// Play one game with the autopilot steering, as fast as the CPU allows
+fn simulate(settings: &Settings) -> RunStats {
+    let mut game = Game::new(settings);
+    game.set_controller(Box::new(Autopilot));
+    while game.moves < BENCHMARK_MAX_MOVES {
+        if let TickResult::Over(_) | TickResult::Stopped = game.tick(None) {
+            break;
+        }
+    }
+    game.run_stats()
+}
+
+// Mean, extremes and (population) standard deviation of a benchmark metric
+struct Summary {
+    mean: f64,
+    min: f64,
+    max: f64,
+    stddev: f64,
+}
+
+fn summarize(values: &[f64]) -> Summary {
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    Summary {
+        mean,
+        min: values.iter().copied().fold(f64::INFINITY, f64::min),
+        max: values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        stddev: (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n).sqrt(),
+    }
+}
+
+// Play `games` autopilot games on seeds `first_seed`, `first_seed + 1`, ... and print the summary.
+// As JSON, each game's result line comes first, the same as a finished run prints with --json.
+fn run_benchmark(settings: &Settings, games: u32, first_seed: u64, json: bool) {
+    let runs: Vec<RunStats> = (0..games as u64)
+        .map(|i| simulate(&Settings { seed: Some(first_seed + i), ..settings.clone() }))
+        .collect();
+    let metrics = [
+        ("score", summarize(&runs.iter().map(|run| run.score as f64).collect::<Vec<_>>())),
+        ("length", summarize(&runs.iter().map(|run| run.length as f64).collect::<Vec<_>>())),
+        ("moves", summarize(&runs.iter().map(|run| run.moves as f64).collect::<Vec<_>>())),
+    ];
+
+    if json {
+        for run in &runs {
+            println!("{}", run.json());
+        }
+        let fields: Vec<String> = metrics
+            .iter()
+            .map(|(name, s)| format!("\"{}\":{{\"mean\":{},\"min\":{},\"max\":{},\"stddev\":{}}}", name, s.mean, s.min, s.max, s.stddev))
+            .collect();
+        println!("{{\"games\":{},\"first_seed\":{},{}}}", games, first_seed, fields.join(","));
+    } else {
+        println!("{} games from seed {}", games, first_seed);
+        for (name, s) in &metrics {
+            println!("{:<8} mean {:>9.2}  min {:>7}  max {:>7}  stddev {:>9.2}", name, s.mean, s.min, s.max, s.stddev);
+        }
+    }
+}
+
+// Parse a food spawn table like "normal=8,bonus=1,poison=1"
+fn parse_food_table(spec: &str) -> Option<Vec<(FoodKind, f64)>> {
+    spec.split(',')
+        .map(|entry| {
+            let (kind, weight) = entry.split_once('=')?;
+            Some((kind.trim().parse().ok()?, weight.trim().parse().ok()?))
+        })
+        .collect()
+}
+
+// Parse pre-placed items like "bonus@5:5,large@15:12" (kind at column:row)
+fn parse_placed_food(spec: &str) -> Option<Vec<(FoodKind, (i32, i32))>> {
+    spec.split(',')
+        .map(|entry| {
+            let (kind, cell) = entry.split_once('@')?;
+            let (x, y) = cell.split_once(':')?;
+            Some((kind.trim().parse().ok()?, (x.trim().parse().ok()?, y.trim().parse().ok()?)))
+        })
+        .collect()
+}
+
+// Parse food decay like "10,1,20" (max points, floor, half-life in moves)
+fn parse_food_decay(spec: &str) -> Option<FoodDecay> {
+    let mut numbers = spec.split(',').map(|n| n.trim().parse::<u32>().ok());
+    let decay = FoodDecay { max: numbers.next()??, floor: numbers.next()??, half_life: numbers.next()?? };
+    numbers.next().is_none().then_some(decay)
+}
+
+// Parse a wrap penalty like "1,2" (points lost, segments lost per crossing)
+fn parse_wrap_penalty(spec: &str) -> Option<WallMode> {
+    let (points, segments) = spec.split_once(',')?;
+    Some(WallMode::WrapPenalty { point_cost: points.trim().parse().ok()?, shrink: segments.trim().parse().ok()? })
+}
+
+// Parse a board size like "30x20" (columns x rows)
+fn parse_grid(spec: &str) -> Option<(i32, i32)> {
+    let (columns, rows) = spec.split_once('x')?;
+    Some((columns.trim().parse().ok()?, rows.trim().parse().ok()?))
+}
+
+// Open the game window, retrying with an older OpenGL version for drivers (often in VMs) that lack the default one
+fn build_window(size: [u32; 2], samples: u8, fullscreen: bool) -> Result<PistonWindow, Box<dyn std::error::Error>> {
+    let settings = WindowSettings::new("Copperhead", size)
+        .exit_on_esc(false) // Escape closes the help first, so quitting is handled by the game loop
+        .samples(samples)
+        .fullscreen(fullscreen);
+    settings.build().or_else(|err| {
+        eprintln!("copperhead: could not open a window with OpenGL 3.2 ({}), trying OpenGL 2.1", err);
+        settings.clone().graphics_api(OpenGL::V2_1).build()
+    })
+}
+
+// Ask whether to play in the terminal instead; only when someone is at the terminal to answer
+fn offer_ascii_fallback() -> bool {
+    use std::io::{IsTerminal, Write};
+
+    if !std::io::stdin().is_terminal() || !std::io::stdout().is_terminal() {
+        return false;
+    }
+    print!("Play in the terminal instead? [y/N] ");
+    std::io::stdout().flush().ok();
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer).is_ok() && matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+fn main() -> std::process::ExitCode {
+    use std::process::ExitCode;
+
+    let args: Vec<String> = std::env::args().collect();
+
+    // A missing config is written out with the defaults; one that is there but broken is not overwritten
+    let config_path = Config::path();
+    let config = match config_path.as_deref() {
+        Some(path) => match Config::load(path) {
+            Ok(config) => config,
+            Err(err) => {
+                eprintln!("copperhead: invalid config {}: {}", path.display(), err);
+                return ExitCode::FAILURE;
+            }
+        },
+        None => Config::default(),
+    };
+    if let Some(path) = config_path.as_deref().filter(|path| !path.exists())
+        && let Err(err) = config.save(path)
+    {
+        eprintln!("copperhead: could not write default config {}: {}", path.display(), err);
+    }
+
+    let time_limit = flag_value::<f64>(&args, "--time-attack", "a number of seconds, e.g. --time-attack 60");
+    let wall_density = flag_value::<f64>(&args, "--random-walls", "a density between 0 and 0.5, e.g. --random-walls 0.1");
+    let food_spawn = match flag_value::<u32>(&args, "--food-magnet", "a radius in cells, e.g. --food-magnet 5") {
+        Some(radius) => FoodSpawn::NearHead { radius },
+        None => FoodSpawn::Uniform,
+    };
+    let round_food = args.iter().any(|arg| arg == "--round-food");
+    let cell_shape = if round_food || args.iter().any(|arg| arg == "--rounded") {
+        CellShape::Rounded { round_food }
+    } else {
+        CellShape::Sharp
+    };
+    let defaults = Scoring::default();
+    let scoring = Scoring {
+        per_food: flag_value(&args, "--points-per-food", "a number of points, e.g. --points-per-food 10").unwrap_or(defaults.per_food),
+        survival_every: flag_value(&args, "--survival-points", "a number of moves per point, e.g. --survival-points 20").unwrap_or(defaults.survival_every),
+        length_bonus: flag_value(&args, "--length-bonus", "a number of points per segment, e.g. --length-bonus 2").unwrap_or(defaults.length_bonus),
+        food_decay: match flag_value::<String>(&args, "--food-decay", "max points, floor and half-life in moves, e.g. --food-decay 10,1,20") {
+            Some(spec) => match parse_food_decay(&spec) {
+                Some(decay) => Some(decay),
+                None => {
+                    eprintln!("copperhead: --food-decay needs max points, floor and half-life in moves like 10,1,20, got {}", spec);
+                    return ExitCode::FAILURE;
                 }
-                // Alternate color: even index = dark, odd index = light
-                let body_color = if i % 2 == 0 {
-                    [0.60, 0.30, 0.10, 1.0] // darker copper
-                } else {
-                    [0.85, 0.55, 0.22, 1.0] // lighter copper
-                };
-                let square = [
-                    (bx * CELL_SIZE) as f64,
-                    (by * CELL_SIZE) as f64,
-                    CELL_SIZE as f64,
-                    CELL_SIZE as f64,
-                ];
-                rectangle(body_color, square, c.transform, g);
+            },
+            None => None,
+        },
+        combo_window: flag_value(&args, "--combo", "a number of moves, e.g. --combo 15").unwrap_or(defaults.combo_window),
+    };
+    let wall_mode = match flag_value::<String>(&args, "--wrap-penalty", "a point cost and a number of segments, e.g. --wrap-penalty 1,1") {
+        Some(spec) => match parse_wrap_penalty(&spec) {
+            Some(mode) => mode,
+            None => {
+                eprintln!("copperhead: --wrap-penalty needs a point cost and a number of segments like 1,1, got {}", spec);
+                return ExitCode::FAILURE;
+            }
+        },
+        None if args.iter().any(|arg| arg == "--wrap") => WallMode::Wrap,
+        None => WallMode::Lethal,
+    };
+    let food_table = match flag_value::<String>(&args, "--food-table", "a list of weights, e.g. --food-table normal=8,bonus=1,poison=1") {
+        Some(spec) => match parse_food_table(&spec) {
+            Some(table) => table,
+            None => {
+                eprintln!("copperhead: --food-table entries look like kind=weight, with kinds normal, bonus, poison and large");
+                return ExitCode::FAILURE;
+            }
+        },
+        None => Settings::default().food_table,
+    };
+    let placed_food = match flag_value::<String>(&args, "--place-food", "a list of items, e.g. --place-food bonus@5:5,large@15:12") {
+        Some(spec) => match parse_placed_food(&spec) {
+            Some(items) => items,
+            None => {
+                eprintln!("copperhead: --place-food entries look like kind@x:y, with kinds normal, bonus, poison and large");
+                return ExitCode::FAILURE;
+            }
+        },
+        None => Vec::new(),
+    };
+    // A campaign plays through the levels in assets/levels, on a board the size of their maps
+    let campaign = if args.iter().any(|arg| arg == "--campaign") {
+        match load_campaign(std::path::Path::new(LEVELS_DIR)) {
+            Ok(levels) => levels,
+            Err(err) => {
+                eprintln!("copperhead: could not load the campaign: {}", err);
+                return ExitCode::FAILURE;
+            }
+        }
+    } else {
+        Vec::new()
+    };
+    // Mods are scripts in assets/mods, named without the .rhai extension
+    let mods = match flag_value::<String>(&args, "--mod", "mod names, e.g. --mod hunger,edge_food") {
+        Some(spec) => {
+            let names: Vec<&str> = spec.split(',').map(str::trim).collect();
+            match load_mods(std::path::Path::new(MODS_DIR), &names) {
+                Ok(mods) => mods,
+                Err(err) => {
+                    eprintln!("copperhead: could not load mod {}", err);
+                    return ExitCode::FAILURE;
+                }
+            }
+        },
+        None => Vec::new(),
+    };
+    let (columns, rows) = match flag_value::<String>(&args, "--grid", "a board size, e.g. --grid 30x30") {
+        Some(spec) => match parse_grid(&spec) {
+            Some(grid) => grid,
+            None => {
+                eprintln!("copperhead: --grid needs columns and rows like 30x30, got {}", spec);
+                return ExitCode::FAILURE;
+            }
+        },
+        None => campaign.first().map_or((config.board.columns, config.board.rows), |level| level.grid),
+    };
+    // Speed is given in moves per second, the inverse of the move interval
+    let move_interval = match flag_value::<f64>(&args, "--speed", "a number of moves per second, e.g. --speed 8") {
+        Some(speed) if speed.is_finite() && speed > 0.0 => 1.0 / speed,
+        Some(speed) => {
+            eprintln!("copperhead: --speed needs a positive number of moves per second, got {}", speed);
+            return ExitCode::FAILURE;
+        },
+        None => config.timing.move_interval,
+    };
+    let updates_per_second = match flag_value::<u64>(&args, "--ups", "a number of updates per second, e.g. --ups 200") {
+        Some(0) => {
+            eprintln!("copperhead: --ups needs at least 1 update per second");
+            return ExitCode::FAILURE;
+        },
+        Some(ups) => ups,
+        None => config.timing.updates_per_second,
+    };
+    // A theme named on the command line is used as it comes, without the colors the config changes
+    let mut theme = match flag_value::<Theme>(&args, "--theme", "a theme like copper, high-contrast or deuteranopia, e.g. --theme deuteranopia") {
+        Some(theme) => theme,
+        None => config.colors.theme().unwrap_or_default(),
+    };
+    if let Some(hex) = flag_value::<String>(&args, "--letterbox-color", "a hex color, e.g. --letterbox-color 1a1a1a") {
+        match parse_hex_color(&hex) {
+            Some(color) => theme.letterbox = color,
+            None => {
+                eprintln!("copperhead: --letterbox-color needs a hex color like 1a1a1a, got {}", hex);
+                return ExitCode::FAILURE;
             }
         }
     }
-    fn update(&mut self, food: (i32, i32)) -> bool {
-        let mut new_head = *self.body.front().expect("Snake has no body");
-        match self.dir {
-            Direction::Left => new_head.0 -= 1,
-            Direction::Right => new_head.0 += 1,
-            Direction::Up => new_head.1 -= 1,
-            Direction::Down => new_head.1 += 1,
+    let settings = config
+        .builder()
+        .grid(columns, rows)
+        .cell_size(flag_value(&args, "--cell-size", "a number of pixels, e.g. --cell-size 24").unwrap_or(config.board.cell_size))
+        .move_interval(move_interval)
+        .start_dir(flag_value(&args, "--start-dir", "up, down, left or right, e.g. --start-dir up").unwrap_or(config.board.start_dir.clone()))
+        .export_heatmap(args.iter().any(|arg| arg == "--export-heatmap"))
+        .time_scale(flag_value(&args, "--time-scale", "a speed multiplier, e.g. --time-scale 0.5").unwrap_or(1.0))
+        .countdown(flag_value(&args, "--countdown", "a number of seconds, e.g. --countdown 5").unwrap_or(COUNTDOWN_SECONDS))
+        .json_results(args.iter().any(|arg| arg == "--json"))
+        .time_limit(time_limit)
+        .arena_shrink(flag_value(&args, "--shrinking-arena", "a number of seconds per ring, e.g. --shrinking-arena 20"))
+        .food_wander(flag_value(&args, "--wandering-food", "a number of moves per step, e.g. --wandering-food 3"))
+        .wall_density(wall_density.unwrap_or(0.0))
+        .portal_pairs(flag_value(&args, "--portals", "a number of portal pairs, e.g. --portals 2").unwrap_or(0))
+        .assist_arrows(args.iter().any(|arg| arg == "--assist"))
+        .food_spawn(food_spawn)
+        .wall_mode(wall_mode)
+        .wall_ring(args.iter().any(|arg| arg == "--wall-ring"))
+        .shadows(args.iter().any(|arg| arg == "--shadows"))
+        .sprites(args.iter().any(|arg| arg == "--sprites"))
+        .control_scheme(if args.iter().any(|arg| arg == "--relative") { ControlScheme::Relative } else { ControlScheme::Absolute })
+        .cell_shape(cell_shape)
+        .scoring(scoring)
+        .food_table(food_table)
+        .food_count(flag_value(&args, "--food-count", "a number of foods, e.g. --food-count 3").unwrap_or(1))
+        .placed_food(placed_food)
+        .grow_every(flag_value(&args, "--grow-every", "a number of foods, e.g. --grow-every 3").unwrap_or(1))
+        .max_length(flag_value(&args, "--max-length", "a number of segments, e.g. --max-length 40"))
+        .seed(flag_value(&args, "--seed", "a whole number, e.g. --seed 42"))
+        .font_path(flag_value(&args, "--font", "a path to a TTF file, e.g. --font fonts/MyFont.ttf"))
+        .ui_scale(flag_value(&args, "--ui-scale", "a text size multiplier, e.g. --ui-scale 1.5").unwrap_or(1.0))
+        .theme(theme)
+        .board_align(flag_value(&args, "--board-align", "a placement like center, top-left or bottom, e.g. --board-align center").unwrap_or(config.window.board_align))
+        .fullscreen(config.window.fullscreen || args.iter().any(|arg| arg == "--fullscreen"))
+        .input_buffer_len(flag_value(&args, "--input-buffer", "a number of turns, e.g. --input-buffer 2").unwrap_or(INPUT_BUFFER_LEN))
+        .apply_turns_on_frame(args.iter().any(|arg| arg == "--instant-turns"))
+        .end_when_trapped(args.iter().any(|arg| arg == "--end-when-trapped"))
+        .power_ups(args.iter().any(|arg| arg == "--power-ups"))
+        .players(if args.iter().any(|arg| arg == "--vs-computer") {
+            Players::Computer
+        } else if args.iter().any(|arg| arg == "--two-players") {
+            Players::Two
+        } else {
+            Players::One
+        })
+        .rewinds(flag_value::<u32>(&args, "--rewind", "a number of rewinds per run, e.g. --rewind 3").unwrap_or(0))
+        .campaign(campaign)
+        .mods(mods)
+        .build();
+    let settings = match settings {
+        Ok(settings) => settings,
+        Err(err) => {
+            eprintln!("copperhead: invalid settings: {}", err);
+            return ExitCode::FAILURE;
         }
-        self.body.push_front(new_head);
-        let ate = new_head == food;
-        if !ate && !self.grow_on_next {
-            self.body.pop_back();
-        } else if self.grow_on_next {
-            self.grow_on_next = false;
+    };
+
+    if let Some(games) = flag_value::<u32>(&args, "--benchmark", "a number of games, e.g. --benchmark 100") {
+        if games == 0 {
+            eprintln!("copperhead: --benchmark needs at least 1 game");
+            return ExitCode::FAILURE;
         }
-        ate
+        run_benchmark(&settings, games, settings.seed.unwrap_or(0), args.iter().any(|arg| arg == "--json"));
+        return ExitCode::SUCCESS;
+    }
+
+    // Only games played by hand count towards the high score, the leaderboard and the achievements kept between
+    // sessions, only they are saved when left mid-way, and only they are sent to the global leaderboard. Mods
+    // can award any number of points, so runs with mods stay off the global leaderboard.
+    let settings = Settings {
+        stats_file: Stats::path(),
+        leaderboard_file: Leaderboard::path(),
+        achievements_file: Achievements::path(),
+        save_file: SavedRun::path(),
+        online_server: if settings.mods.is_empty() { config.online.server() } else { None },
+        ..settings
+    };
+
+    // A network run: the host waits here for the other player to join, and the guest connects to the host.
+    // Both then play player one and player two of the same seeded two-player run.
+    if args.iter().any(|arg| arg == "--host" || arg == "--join") && !settings.campaign.is_empty() {
+        eprintln!("copperhead: a network run is played on the open board, not a campaign");
+        return ExitCode::FAILURE;
     }
-    fn grow(&mut self) {
-        self.grow_on_next = true;
+    if args.iter().any(|arg| arg == "--host" || arg == "--join") && !settings.mods.is_empty() {
+        eprintln!("copperhead: a network run is played without mods");
+        return ExitCode::FAILURE;
     }
-    fn head(&self) -> (i32, i32) {
-        *self.body.front().unwrap()
+    let lockstep = if let Some(port) = flag_value::<u16>(&args, "--host", "a port number, e.g. --host 7777") {
+        eprintln!("copperhead: waiting on port {} for the other player to --join", port);
+        Some(Lockstep::host(port, settings.seed.unwrap_or_else(rand::random), settings.grid))
+    } else {
+        flag_value::<String>(&args, "--join", "a host and port, e.g. --join 192.168.1.20:7777").map(|addr| Lockstep::join(&addr, settings.grid))
+    };
+    let mut lockstep = match lockstep.transpose() {
+        Ok(lockstep) => lockstep,
+        Err(err) => {
+            eprintln!("copperhead: {}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+    let settings = match &lockstep {
+        Some(lockstep) => Settings { players: Players::Two, net_side: Some(lockstep.side), seed: Some(lockstep.seed()), ..settings },
+        None => settings,
+    };
+
+    // Viewers connect to the spectator port with a WebSocket and get the game as JSON every time it changes
+    let mut spectators = match flag_value::<u16>(&args, "--spectate", "a port number, e.g. --spectate 9001").map(Spectators::listen).transpose() {
+        Ok(spectators) => spectators,
+        Err(err) => {
+            eprintln!("copperhead: {}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // Runs are recorded as GIFs whatever plays them: the window, the SDL2 window or the terminal
+    let record = args.iter().any(|arg| arg == "--record");
+
+    // Result lines are printed once the terminal is back to normal, where they can't mess up the board
+    let play_ascii = |settings: &Settings, mut lockstep: Option<Lockstep>, mut spectators: Option<Spectators>| {
+        let mut game = Game::new(settings);
+        if lockstep.is_some() {
+            game.state = GameState::Running;
+        }
+        let mut music = Music::new(game.settings.volume, game.settings.muted);
+        let mut recorder = record.then(|| start_recording(&game)).flatten();
+        let result = run_ascii(&mut game, &mut music, lockstep.as_mut(), spectators.as_mut(), recorder.as_mut());
+        if let Some(lockstep) = lockstep.as_mut() {
+            lockstep.leave();
+        }
+        game.save_run();
+        game.finish_run();
+        game.finish_uploads();
+        game.print_json_lines();
+        remember_options(config_path.as_deref(), settings, &game.settings);
+        match result {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(err) => {
+                eprintln!("copperhead: {}", err);
+                ExitCode::FAILURE
+            }
+        }
+    };
+    if args.iter().any(|arg| arg == "--ascii") {
+        return play_ascii(&settings, lockstep, spectators);
     }
-    fn self_collision(&self) -> bool {
-        let head = self.head();
-        self.body.iter().skip(1).any(|&pos| pos == head)
+
+    // The SDL2 window stands in for Piston's, in builds that have it
+    #[cfg(feature = "sdl")]
+    let play_sdl = |settings: &Settings, mut lockstep: Option<Lockstep>, mut spectators: Option<Spectators>| {
+        let mut game = Game::new(settings);
+        if lockstep.is_some() {
+            game.state = GameState::Running;
+        }
+        let mut music = Music::new(game.settings.volume, game.settings.muted);
+        let mut recorder = record.then(|| start_recording(&game)).flatten();
+        let result = run_sdl(&mut game, &mut music, lockstep.as_mut(), spectators.as_mut(), recorder.as_mut());
+        if let Some(lockstep) = lockstep.as_mut() {
+            lockstep.leave();
+        }
+        game.save_run();
+        game.finish_run();
+        game.finish_uploads();
+        game.print_json_lines();
+        remember_options(config_path.as_deref(), settings, &game.settings);
+        match result {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(err) => {
+                eprintln!("copperhead: {}", err);
+                ExitCode::FAILURE
+            }
+        }
+    };
+    if args.iter().any(|arg| arg == "--sdl") {
+        #[cfg(feature = "sdl")]
+        return play_sdl(&settings, lockstep, spectators);
+        #[cfg(not(feature = "sdl"))]
+        {
+            eprintln!("copperhead: this build has no SDL2 window, build it with --features sdl to use --sdl");
+            return ExitCode::FAILURE;
+        }
     }
-}
 
-// Center the window
-fn center_window(window: &mut PistonWindow) {
-    use winit::dpi::{PhysicalPosition, PhysicalSize};
-    use winit::monitor::MonitorHandle;
+    // Rounded shapes need multisampling to get smooth edges; sharp cells are pixel-aligned and don't
+    let samples = if settings.cell_shape == CellShape::Sharp { 0 } else { 4 };
+    // The window opens at its size from last session, as long as the board still fits in it
+    let reset_window = args.iter().any(|arg| arg == "--reset-window");
+    let board_size = settings.layout().window_size();
+    let size = match config.window.size.filter(|_| !reset_window) {
+        Some([width, height]) => [width.max(board_size[0]), height.max(board_size[1])],
+        None => board_size,
+    };
+    let mut window = match build_window(size, samples, settings.fullscreen) {
+        Ok(window) => window,
+        Err(err) => {
+            eprintln!("copperhead: could not open a window: {}", err);
+            eprintln!("copperhead: this usually means there is no working OpenGL driver (common in VMs and CI)");
+            if offer_ascii_fallback() {
+                return play_ascii(&settings, lockstep, spectators);
+            }
+            eprintln!("copperhead: run with --ascii to play in the terminal instead");
+            return ExitCode::FAILURE;
+        }
+    };
 
-    let primary_monitor: MonitorHandle = window.window.ctx.window().current_monitor().unwrap();
-    let monitor_size: PhysicalSize<u32> = primary_monitor.size();
-    let win_size = window.size();
-    let x = (monitor_size.width.saturating_sub(win_size.width as u32)) / 2;
-    let y = (monitor_size.height.saturating_sub(win_size.height as u32)) / 2;
-    window.window.ctx.window().set_outer_position(PhysicalPosition::new(x as u32, y as u32));
-}
-
-fn draw_snake_preview<G: Graphics>(c: Context, g: &mut G) {
-    // Compute the center of the playfield in pixels (relative to window)
-    let border_height = BORDER_THICKNESS * 2.0;
-    let playfield_x = border_height;
-    let playfield_y = border_height;
-    let center_cell_x = GRID_SIZE.0 / 2;
-    let center_cell_y = GRID_SIZE.1 / 2;
-    let preview_cell = CELL_SIZE as f64;
-
-    // The snake is 3 long, horizontal, head to the right
-    // The leftmost segment is at (center_cell_x - 2, center_cell_y)
-    let preview_x = playfield_x + ((center_cell_x - 2) as f64) * preview_cell;
-    let preview_y = playfield_y + (center_cell_y as f64) * preview_cell;
-
-    for i in 0..3 {
-        // Draw from right to left so the head is at the right, body extends to the left
-        let bx = preview_x + (2 - i) as f64 * preview_cell;
-        let by = preview_y;
-        let is_head = i == 0;
-        let color = if is_head {
-            [0.90, 0.60, 0.25, 1.0]
-        } else if i == 1 {
-            // Neck (directly behind head) is always darker copper
-            [0.60, 0.30, 0.10, 1.0]
-        } else {
-            // Tail (alternates, but for 3-length snake, this is lighter copper)
-            [0.85, 0.55, 0.22, 1.0]
-        };
-        rectangle(color, [bx, by, preview_cell, preview_cell], c.transform, g);
-        if is_head {
-            // Reflection
-            let reflection_color: [f32; 4] = [1.0, 0.95, 0.80, 0.35];
-            let refl_w = preview_cell * 0.45;
-            let refl_h = preview_cell * 0.18;
-            let refl_x = bx + preview_cell * 0.10;
-            let refl_y = by + preview_cell * 0.10;
-            rectangle(reflection_color, [refl_x, refl_y, refl_w, refl_h], c.transform, g);
-
-            // Eyes (facing right)
-            let cx = bx + preview_cell / 2.0;
-            let cy = by + preview_cell / 2.0;
-            let eye_r = preview_cell * 0.1;
-            let eye_offset_y = preview_cell * 0.20;
-            let front_offset = preview_cell * 0.18;
-            let eye1 = [cx + front_offset, cy - eye_offset_y];
-            let eye2 = [cx + front_offset, cy + eye_offset_y];
-            let eye_color = [0.1, 0.1, 0.1, 1.0];
-            let eye_size = eye_r * 2.0;
-            rectangle(eye_color, [eye1[0] - eye_r, eye1[1] - eye_r, eye_size, eye_size], c.transform, g);
-            rectangle(eye_color, [eye2[0] - eye_r, eye2[1] - eye_r, eye_size, eye_size], c.transform, g);
-        }
-    }
-}
-
-fn main() {
-    let mut window: PistonWindow = WindowSettings::new("Copperhead", WINDOW_SIZE)
-        .exit_on_esc(true)
-        .build()
-        .unwrap();
-
-    window.set_ups(100); // Set updates per second to 100Hz
-    window.set_max_fps(120); // Set max frames per second to 120Hz
+    window.set_ups(updates_per_second);
+    window.set_max_fps(config.window.max_fps);
     window.set_title("Copperhead".to_string());
-    center_window(&mut window);
+    // A fullscreen window covers the screen, so it has no position of its own to restore
+    if !settings.fullscreen && (reset_window || !restore_window_position(&mut window, config.window.position)) {
+        center_window(&mut window);
+    }
 
     // Set window icon using winit (requires image crate)
     {
@@ -438,71 +1056,232 @@ fn main() {
         }
     }
 
-    let assets = std::path::Path::new("assets/JetBrainsMono-Regular.ttf");
-    let mut glyphs = window.load_font(assets).expect("Could not load font");
+    // A custom font that can't be loaded falls back to the bundled one
+    let custom_font = settings.font_path.as_ref().and_then(|path| match window.load_font(path) {
+        Ok(glyphs) => Some(glyphs),
+        Err(err) => {
+            eprintln!("copperhead: could not load font {}: {}, using the default font", path.display(), err);
+            None
+        }
+    });
+    let mut glyphs = match custom_font {
+        Some(glyphs) => glyphs,
+        None => match window.load_font(DEFAULT_FONT) {
+            Ok(glyphs) => glyphs,
+            Err(err) => {
+                eprintln!("copperhead: could not load font {}: {}", DEFAULT_FONT, err);
+                return ExitCode::FAILURE;
+            }
+        },
+    };
 
-    let mut game = Game {
-        snake: Snake::new(),
-        food: (5, 5),
-        score: 0,
-        high_score: 0,
-        state: GameState::Start,
+    // Sprites that can't be loaded fall back to the plain shapes
+    let sprites = if settings.sprites {
+        match G2dTexture::from_path(&mut window.create_texture_context(), SPRITE_ATLAS, Flip::None, &TextureSettings::new()) {
+            Ok(atlas) => Some(atlas),
+            Err(err) => {
+                eprintln!("copperhead: could not load sprites {}: {}, drawing plain shapes instead", SPRITE_ATLAS, err);
+                None
+            }
+        }
+    } else {
+        None
     };
-    game.spawn_food();
+    let settings = Settings { sprites: sprites.is_some(), ..settings };
+
+    let mut game = Game::new(&settings);
+    if lockstep.is_some() {
+        game.state = GameState::Running;
+    }
+    let mut gamepads = Gamepads::new(settings.rumble);
+    let mut music = Music::new(game.settings.volume, game.settings.muted);
+    let mut recorder = record.then(|| start_recording(&game)).flatten();
 
+    // Buffers are swapped by hand, only after something was actually drawn; swapping on every render
+    // event would flip back to stale frames
     let mut events = window.events;
-    let mut pending_direction: Option<Direction> = None;
+    events.set_swap_buffers(false);
     let mut last_update = std::time::Instant::now();
-    let mut snake_move_timer = 0.0f64;
-    let snake_move_interval = 0.10; // Snake moves every 100ms (10Hz)
+    let mut scheduler = MoveScheduler::new(settings.move_interval);
+    let mut max_fps = config.window.max_fps;
+    let mut held_keys = HeldKeys::default();
+    let mut meter = FrameMeter::new();
     while let Some(e) = events.next(&mut window) {
-        if let Some(Button::Keyboard(key)) = e.press_args() {
-            // Only queue direction change if not already queued
-            if pending_direction.is_none() {
-                let dir = match key {
-                    Key::Up => Some(Direction::Up),
-                    Key::Down => Some(Direction::Down),
-                    Key::Left => Some(Direction::Left),
-                    Key::Right => Some(Direction::Right),
-                    _ => None,
-                };
-                if let Some(d) = dir {
-                    pending_direction = Some(d);
-                } else {
-                    // For non-direction keys, still call pressed (e.g. Space)
-                    game.pressed(&Button::Keyboard(key));
-                }
+        game.print_json_lines();
+        if let Some(Button::Keyboard(key)) = e.press_args()
+            && held_keys.press(key)
+        {
+            if key == Key::Escape && game.quits_on_escape() {
+                window.set_should_close(true);
+            } else if key == Key::F11 {
+                toggle_fullscreen(&mut window, &mut game);
+            } else if key == Key::F12 {
+                let Size { width, height } = window.size();
+                save_screenshot(&game, [width as u32, height as u32]);
+            } else if !music_key(&mut game, key) {
+                game.press_key(key);
             }
         }
-        // Game logic update at fixed interval (100Hz)
+        if let Some(Button::Keyboard(key)) = e.release_args() {
+            held_keys.release(key);
+        }
+        // The mouse only does something in the level editor
+        if let Some(pos) = e.mouse_cursor_args() {
+            let Size { width, height } = window.size();
+            game.mouse_moved(game.cell_at([width, height], pos));
+        }
+        if let Some(Button::Mouse(button)) = e.press_args() {
+            game.mouse_pressed(button);
+        }
+        if let Some(Button::Mouse(_)) = e.release_args() {
+            game.mouse_released();
+        }
+        if e.focus_args() == Some(false) {
+            // Releases that happen while another window has focus never arrive
+            held_keys.clear();
+            game.mouse_released();
+        }
+        // Game logic update at fixed interval (100Hz unless configured otherwise)
         if let Some(_u) = e.update_args() {
+            let started = std::time::Instant::now();
+            // Gamepad presses act like the keys they stand in for, except that no button quits but the menu's Quit
+            for key in gamepads.poll(&game) {
+                game.press_key(key);
+            }
+            if game.quit_requested {
+                window.set_should_close(true);
+            }
+            // The board can change size on the options page, and the speed too
+            if std::mem::take(&mut game.resized) {
+                fit_window(&mut window, &mut game);
+            }
+            scheduler.set_interval(game.settings.move_interval);
             let now = std::time::Instant::now();
-            let dt = last_update.elapsed().as_secs_f64();
+            let dt = game.game_dt(last_update.elapsed().as_secs_f64());
             last_update = now;
-            snake_move_timer += dt;
-            // Only move the snake at the slower interval
-            if snake_move_timer >= snake_move_interval {
-                // Apply pending direction if any
-                if let Some(dir) = pending_direction.take() {
-                    let last_direction = game.snake.dir.clone();
-                    game.snake.dir = match dir {
-                        Direction::Up if last_direction != Direction::Down => Direction::Up,
-                        Direction::Down if last_direction != Direction::Up => Direction::Down,
-                        Direction::Left if last_direction != Direction::Right => Direction::Left,
-                        Direction::Right if last_direction != Direction::Left => Direction::Right,
-                        _ => last_direction,
-                    };
+            if lockstep.is_none() {
+                game.tick_clock(dt);
+            }
+            // Only move the snake at the slower interval, which power-ups can speed up or slow down
+            let due = scheduler.tick(dt * game.move_speed());
+            for _ in 0..ready_moves(&mut game, lockstep.as_mut(), due) {
+                let (foods_before, was_running) = (game.foods_eaten, game.state == GameState::Running);
+                make_move(&mut game, lockstep.as_mut());
+
+                if game.foods_eaten > foods_before {
+                    gamepads.eat();
+                } else if was_running && game.state == GameState::GameOver && game.death_cause.is_some() {
+                    gamepads.death();
                 }
-                game.update();
-                snake_move_timer -= snake_move_interval;
             }
+            if let Some(spectators) = spectators.as_mut() {
+                spectators.publish(&game);
+            }
+            if let Some(recorder) = recorder.as_mut() {
+                recorder.capture(&game);
+            }
+            music.follow(&game);
+            // A sliding snake changes the picture on every update, not just on every move
+            game.move_progress = scheduler.progress();
+            if game.slides_snake() && game.is_animating() {
+                game.mark_dirty();
+            }
+            meter.update(started.elapsed());
+            meter.report(&mut game);
+        }
+        // Throttle rendering while the screen is static, back to full speed once it animates again
+        let target_fps = if game.is_animating() { config.window.max_fps } else { IDLE_FPS.min(config.window.max_fps) };
+        if target_fps != max_fps {
+            events.set_max_fps(target_fps);
+            max_fps = target_fps;
+        }
+
+        // The window contents may be lost or rescaled, so draw them again
+        if e.resize_args().is_some() || e.focus_args().is_some() {
+            game.mark_dirty();
         }
-        // Render as fast as possible
-        if let Some(_r) = e.render_args() {
+
+        // Render only when something changed; a snake moving at 10Hz needs about 10 frames a second
+        if e.render_args().is_some() && game.is_dirty() {
             window.draw_2d(&e, |c, g, device| {
-                game.render(c, g, &mut glyphs);
-                glyphs.factory.encoder.flush(device);
+                let mut renderer = PistonRenderer::new(c, g, &mut glyphs, |glyphs: &mut Glyphs| glyphs.factory.encoder.flush(device));
+                game.render(&mut renderer, sprites.as_ref());
+                renderer.present();
             });
+            Window::swap_buffers(&mut window);
+            game.clear_dirty();
+            meter.frame();
         }
     }
+    if let Some(lockstep) = lockstep.as_mut() {
+        lockstep.leave();
+    }
+    game.save_run();
+    game.finish_run();
+    game.finish_uploads();
+    game.print_json_lines();
+    remember_options(config_path.as_deref(), &settings, &game.settings);
+    // Nor does it have a position or size worth keeping, so a run that ends fullscreen, even one switched
+    // there with F11, leaves the ones from the last windowed session in place
+    if !game.settings.fullscreen
+        && let Some(path) = config_path.as_deref()
+        && let Err(err) = remember_window(&window, path)
+    {
+        eprintln!("copperhead: could not remember the window position: {}", err);
+    }
+    ExitCode::SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remembering_the_options_keeps_the_rest_of_the_file() {
+        let path = std::env::temp_dir().join(format!("copperhead-main-options-{}.toml", std::process::id()));
+        let mut config = Config::default();
+        config.save(&path).unwrap();
+        let launched = config.builder().build().unwrap();
+        // The window was remembered there while the game ran
+        config.window.position = Some([-1200, 40]);
+        config.window.size = Some([900, 700]);
+        config.save(&path).unwrap();
+        remember_options(Some(&path), &launched, &Settings { grid: (25, 25), ..launched.clone() });
+        let saved = Config::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!((saved.board.columns, saved.board.rows), (25, 25));
+        assert_eq!((saved.window.position, saved.window.size), (Some([-1200, 40]), Some([900, 700])));
+    }
+
+    #[test]
+    fn benchmark_runs_repeat_for_the_same_seed() {
+        let settings = Settings::builder().seed(Some(3)).build().unwrap();
+        let (a, b) = (simulate(&settings), simulate(&settings));
+        assert_eq!((a.score, a.length, a.moves), (b.score, b.length, b.moves));
+        assert!(a.moves > 0);
+    }
+
+    #[test]
+    fn benchmark_runs_report_their_seed_and_cause() {
+        let settings = Settings::builder().seed(Some(3)).build().unwrap();
+        let run = simulate(&settings);
+        assert_eq!(run.seed, 3);
+        assert!(run.cause != "Unfinished" || run.moves == BENCHMARK_MAX_MOVES);
+        assert!(run.json().starts_with(&format!("{{\"score\":{},", run.score)));
+    }
+
+    #[test]
+    fn food_decay_parses_from_three_numbers() {
+        assert!(parse_food_decay("10,1,20") == Some(FoodDecay { max: 10, floor: 1, half_life: 20 }));
+        assert!(parse_food_decay("10,1").is_none());
+        assert!(parse_food_decay("10,1,20,5").is_none());
+        assert!(parse_food_decay("10,x,20").is_none());
+    }
+
+    #[test]
+    fn grid_parses_as_columns_by_rows() {
+        assert_eq!(parse_grid("30x20"), Some((30, 20)));
+        assert_eq!(parse_grid("30"), None);
+        assert_eq!(parse_grid("30xtwenty"), None);
+    }
 }
@@ -0,0 +1,404 @@
+// The title screen's menu. `Menu` is the widget: a list of items with one of them selected, where moving
+// past either end wraps around to the other. The menu's items, what picking one does and what changing its
+// value with left and right does are the game's business, and are here too as `MenuItem` and the `Game`
+// methods below; `input` feeds them keys, and `render` and `web` draw them. The menu has three pages: the
+// main one, the options, and the keys, which are among the options.
+
+use crate::game::{Action, Game};
+use crate::input::key_label;
+
+pub struct Menu<T> {
+    items: Vec<T>,
+    selected: usize, // Index into `items`
+}
+
+impl<T: Copy + PartialEq> Menu<T> {
+    // A menu with its first item selected
+    pub fn new(items: Vec<T>) -> Self {
+        Menu { items, selected: 0 }
+    }
+
+    pub fn items(&self) -> &[T] {
+        &self.items
+    }
+
+    // The selected item, or None if the menu is empty
+    pub fn selected(&self) -> Option<T> {
+        self.items.get(self.selected).copied()
+    }
+
+    pub fn is_selected(&self, item: T) -> bool {
+        self.selected() == Some(item)
+    }
+
+    // Move the selection `step` items down, or up for a negative step, wrapping around at the ends
+    pub fn move_by(&mut self, step: i32) {
+        if !self.items.is_empty() {
+            self.selected = (self.selected as i64 + step as i64).rem_euclid(self.items.len() as i64) as usize;
+        }
+    }
+
+    // Select `item`, if the menu has it
+    pub fn select(&mut self, item: T) {
+        if let Some(index) = self.items.iter().position(|&other| other == item) {
+            self.selected = index;
+        }
+    }
+
+    // Swap in new items, keeping the same item selected if it's still there, or the first one if not
+    pub fn set_items(&mut self, items: Vec<T>) {
+        let selected = self.selected();
+        self.items = items;
+        self.selected = 0;
+        if let Some(item) = selected {
+            self.select(item);
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MenuPage {
+    Main,
+    Options,
+    Keys,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MenuItem {
+    Continue, // Pick up the run left mid-way last time
+    Play,
+    Mode, // Classic play or time attack
+    Options, // Opens the options page
+    Leaderboard, // The top 10, then the global one if there is one
+    Achievements,
+    Stats,
+    Quit,
+    // On the options page
+    Speed, // Moves a second
+    Board, // Columns and rows
+    Theme,
+    Volume, // Of the music; picking it mutes or unmutes
+    Keys, // Opens the keys page
+    Edges, // What the edges of the board do
+    Players,
+    Editor, // The level editor
+    Back, // Back to the page before
+    // On the keys page, one per action; picking it waits for the action's new key
+    Bind(Action),
+}
+
+impl MenuItem {
+    // Whether left and right change the item's value, which picking it does as well
+    pub fn has_value(self) -> bool {
+        matches!(self, MenuItem::Mode | MenuItem::Speed | MenuItem::Board | MenuItem::Theme | MenuItem::Volume | MenuItem::Edges | MenuItem::Players)
+    }
+}
+
+impl Game {
+    // The items on the menu's open page. Lists only show up when the settings keep them, and a campaign is
+    // for one player on the board its levels are made for. A browser tab can't be quit from inside, plays no
+    // music and keeps its canvas the size it is.
+    pub fn menu_items(&self) -> Vec<MenuItem> {
+        let mut items = Vec::new();
+        let campaign = !self.settings.campaign.is_empty();
+        match self.menu_page {
+            MenuPage::Main => {
+                if self.saved_score().is_some() {
+                    items.push(MenuItem::Continue);
+                }
+                items.extend([MenuItem::Play, MenuItem::Mode, MenuItem::Options]);
+                if self.settings.leaderboard_file.is_some() {
+                    items.push(MenuItem::Leaderboard);
+                }
+                if self.settings.achievements_file.is_some() {
+                    items.push(MenuItem::Achievements);
+                }
+                if self.settings.stats_file.is_some() {
+                    items.push(MenuItem::Stats);
+                }
+                #[cfg(not(target_arch = "wasm32"))]
+                items.push(MenuItem::Quit);
+            },
+            MenuPage::Options => {
+                items.push(MenuItem::Speed);
+                #[cfg(not(target_arch = "wasm32"))]
+                if !campaign {
+                    items.push(MenuItem::Board);
+                }
+                items.push(MenuItem::Theme);
+                #[cfg(not(target_arch = "wasm32"))]
+                items.push(MenuItem::Volume);
+                items.extend([MenuItem::Keys, MenuItem::Edges]);
+                if !campaign {
+                    items.push(MenuItem::Players);
+                }
+                items.extend([MenuItem::Editor, MenuItem::Back]);
+            },
+            MenuPage::Keys => {
+                items.extend(Action::ALL.map(MenuItem::Bind));
+                items.push(MenuItem::Back);
+            },
+        }
+        items
+    }
+
+    // Bring the menu up to date with the page that's open and what it has to offer
+    pub fn refresh_menu(&mut self) {
+        let items = self.menu_items();
+        self.menu.set_items(items);
+    }
+
+    // How `item` reads on the menu, with its value if it has one
+    pub fn menu_label(&self, item: MenuItem) -> String {
+        match item {
+            MenuItem::Continue => format!("Continue ({})", self.saved_score().unwrap_or_default()),
+            MenuItem::Play => "Play".to_string(),
+            MenuItem::Mode => match self.settings.time_limit {
+                Some(limit) => format!("Mode: time attack {}s, best {}", limit, self.high_score),
+                None => format!("Mode: classic, best {}", self.high_score),
+            },
+            MenuItem::Options => "Options".to_string(),
+            MenuItem::Leaderboard => "Leaderboard".to_string(),
+            MenuItem::Achievements => "Achievements".to_string(),
+            MenuItem::Stats => "Stats".to_string(),
+            MenuItem::Quit => "Quit".to_string(),
+            MenuItem::Speed => format!("Speed: {} moves a second", (1.0 / self.settings.move_interval).round()),
+            MenuItem::Board => format!("Board: {}x{}", self.settings.grid.0, self.settings.grid.1),
+            MenuItem::Theme => format!("Theme: {}", self.settings.theme.name),
+            MenuItem::Volume if self.settings.muted => "Music: muted".to_string(),
+            MenuItem::Volume => format!("Music: {}%", self.settings.volume),
+            MenuItem::Keys => "Keys".to_string(),
+            MenuItem::Edges => self.settings.wall_mode.describe(),
+            MenuItem::Players => self.settings.players.describe().to_string(),
+            MenuItem::Editor => "Level editor".to_string(),
+            MenuItem::Back => "Back".to_string(),
+            MenuItem::Bind(action) if self.rebinding == Some(action) => format!("{:?}: press a key, Esc to cancel", action),
+            MenuItem::Bind(action) => {
+                let keys: Vec<String> = self.settings.key_bindings.keys(action).iter().map(|name| key_label(name)).collect();
+                format!("{:?}: {}", action, keys.join(" "))
+            },
+        }
+    }
+
+    // Do what the selected item is for. Items with a value go on to the next one.
+    pub fn pick_menu_item(&mut self) {
+        let Some(item) = self.menu.selected() else {
+            return;
+        };
+        match item {
+            MenuItem::Continue => {
+                self.close_lists();
+                self.continue_run();
+            },
+            MenuItem::Play => {
+                self.close_lists();
+                self.play();
+            },
+            MenuItem::Volume => self.toggle_mute(),
+            MenuItem::Mode | MenuItem::Speed | MenuItem::Board | MenuItem::Theme | MenuItem::Edges | MenuItem::Players => self.change_menu_item(1),
+            MenuItem::Options => {
+                self.close_lists();
+                self.open_menu_page(MenuPage::Options);
+            },
+            MenuItem::Keys => self.open_menu_page(MenuPage::Keys),
+            // With a global leaderboard, picking it again goes from the local top 10 to the global one before
+            // closing
+            MenuItem::Leaderboard => {
+                let (shown, global) = match (self.leaderboard_shown, self.global_shown) {
+                    (false, _) => (true, false),
+                    (true, false) if self.online.is_some() => (true, true),
+                    _ => (false, false),
+                };
+                self.close_lists();
+                self.leaderboard_shown = shown;
+                self.global_shown = global;
+                if let Some(online) = self.online.as_mut().filter(|_| global) {
+                    online.refresh();
+                }
+            },
+            MenuItem::Achievements => {
+                let shown = !self.achievements_shown;
+                self.close_lists();
+                self.achievements_shown = shown;
+            },
+            MenuItem::Stats => {
+                let shown = !self.stats_shown;
+                self.close_lists();
+                self.stats_shown = shown;
+            },
+            MenuItem::Quit => self.quit_requested = true,
+            MenuItem::Editor => self.open_editor(),
+            MenuItem::Back => self.menu_back(),
+            MenuItem::Bind(action) => self.rebinding = Some(action),
+        }
+    }
+
+    // Change the selected item's value `step` ways: up for a positive step, down for a negative one. Values
+    // that are a choice between names go round in either direction.
+    pub fn change_menu_item(&mut self, step: i32) {
+        match self.menu.selected() {
+            Some(MenuItem::Mode) => self.toggle_time_attack(),
+            Some(MenuItem::Speed) => self.change_speed(step),
+            Some(MenuItem::Board) => self.change_grid(step),
+            Some(MenuItem::Theme) => self.cycle_theme(step),
+            Some(MenuItem::Volume) => self.change_volume(step),
+            Some(MenuItem::Edges) => self.cycle_wall_mode(),
+            Some(MenuItem::Players) => self.cycle_players(),
+            _ => {},
+        }
+    }
+
+    // Open `page` with its first item selected
+    fn open_menu_page(&mut self, page: MenuPage) {
+        self.menu_page = page;
+        self.menu = Menu::new(self.menu_items());
+    }
+
+    // Go back to the page before, with the item that opened this one selected
+    pub fn menu_back(&mut self) {
+        let (page, item) = match self.menu_page {
+            MenuPage::Main => return,
+            MenuPage::Options => (MenuPage::Main, MenuItem::Options),
+            MenuPage::Keys => (MenuPage::Options, MenuItem::Keys),
+        };
+        self.open_menu_page(page);
+        self.menu.select(item);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{GameState, Players, Settings};
+    use crate::level::Level;
+    use crate::snake::Direction;
+
+    #[test]
+    fn moving_past_either_end_wraps_around() {
+        let mut menu = Menu::new(vec![1, 2, 3]);
+        menu.move_by(-1);
+        assert_eq!(menu.selected(), Some(3));
+        menu.move_by(2);
+        assert_eq!(menu.selected(), Some(2));
+        Menu::<i32>::new(Vec::new()).move_by(1);
+    }
+
+    #[test]
+    fn new_items_keep_the_selection_if_they_can() {
+        let mut menu = Menu::new(vec![1, 2, 3]);
+        menu.select(2);
+        menu.set_items(vec![0, 2]);
+        assert!(menu.is_selected(2));
+        menu.set_items(vec![4, 5]);
+        assert!(menu.is_selected(4));
+    }
+
+    #[test]
+    fn the_main_page_offers_what_the_settings_keep() {
+        let game = Game::new(&Settings::default());
+        assert_eq!(game.menu.items(), [MenuItem::Play, MenuItem::Mode, MenuItem::Options, MenuItem::Quit]);
+        let settings = Settings { stats_file: Some("stats".into()), achievements_file: Some("achievements".into()), ..Settings::default() };
+        let game = Game::new(&settings);
+        assert_eq!(game.menu.items(), [MenuItem::Play, MenuItem::Mode, MenuItem::Options, MenuItem::Achievements, MenuItem::Stats, MenuItem::Quit]);
+    }
+
+    #[test]
+    fn options_open_as_a_page_of_their_own() {
+        let mut game = Game::new(&Settings::default());
+        game.menu.select(MenuItem::Options);
+        game.pick_menu_item();
+        assert_eq!(game.menu_page, MenuPage::Options);
+        assert_eq!(game.menu.items(), [
+            MenuItem::Speed, MenuItem::Board, MenuItem::Theme, MenuItem::Volume, MenuItem::Keys, MenuItem::Edges, MenuItem::Players, MenuItem::Editor, MenuItem::Back,
+        ]);
+        game.menu.select(MenuItem::Players);
+        game.pick_menu_item();
+        assert!(game.settings.players == Players::Two && game.menu.is_selected(MenuItem::Players));
+        game.menu.select(MenuItem::Back);
+        game.pick_menu_item();
+        assert!(game.menu_page == MenuPage::Main && game.menu.is_selected(MenuItem::Options));
+    }
+
+    #[test]
+    fn a_campaign_keeps_its_board_and_has_no_players_option() {
+        let level = Level { name: "one".to_string(), grid: (20, 20), walls: Default::default(), portals: Vec::new(), start: (10, 10), start_dir: Direction::Right, target: 10 };
+        let mut game = Game::new(&Settings { campaign: vec![level], ..Settings::default() });
+        game.menu_page = MenuPage::Options;
+        assert!(!game.menu_items().contains(&MenuItem::Players));
+        assert!(!game.menu_items().contains(&MenuItem::Board));
+    }
+
+    #[test]
+    fn the_options_change_both_ways_and_read_back() {
+        let mut game = Game::new(&Settings::default());
+        game.open_menu_page(MenuPage::Options);
+        assert_eq!(game.menu_label(MenuItem::Speed), "Speed: 10 moves a second");
+        game.change_menu_item(1);
+        assert_eq!(game.menu_label(MenuItem::Speed), "Speed: 11 moves a second");
+        game.menu.select(MenuItem::Theme);
+        game.change_menu_item(-1);
+        assert_eq!(game.menu_label(MenuItem::Theme), "Theme: deuteranopia");
+        game.menu.select(MenuItem::Volume);
+        game.change_menu_item(-2);
+        assert_eq!(game.menu_label(MenuItem::Volume), "Music: 30%");
+        game.pick_menu_item();
+        assert_eq!(game.menu_label(MenuItem::Volume), "Music: muted");
+    }
+
+    #[test]
+    fn a_new_board_size_starts_over_on_the_options_page() {
+        let mut game = Game::new(&Settings::default());
+        game.open_menu_page(MenuPage::Options);
+        game.change_menu_item(1);
+        game.menu.select(MenuItem::Board);
+        game.change_menu_item(1);
+        assert!(game.resized && game.settings.grid == (25, 25));
+        assert!(game.menu_page == MenuPage::Options && game.menu.is_selected(MenuItem::Board));
+        assert_eq!(game.menu_label(MenuItem::Speed), "Speed: 11 moves a second", "the other options stay");
+        game.resized = false;
+        for _ in 0..10 {
+            game.change_menu_item(-1);
+        }
+        assert_eq!(game.settings.grid, (10, 10));
+        game.resized = false;
+        game.change_menu_item(-1);
+        assert!(!game.resized, "no smaller than the smallest board");
+    }
+
+    #[test]
+    fn the_keys_page_lists_every_action_and_goes_back_to_the_options() {
+        let mut game = Game::new(&Settings::default());
+        game.open_menu_page(MenuPage::Options);
+        game.menu.select(MenuItem::Keys);
+        game.pick_menu_item();
+        assert_eq!(game.menu.items().len(), Action::ALL.len() + 1);
+        assert_eq!(game.menu_label(MenuItem::Bind(Action::Up)), "Up: ^ W");
+        game.pick_menu_item();
+        assert_eq!(game.rebinding, Some(Action::Up));
+        assert_eq!(game.menu_label(MenuItem::Bind(Action::Up)), "Up: press a key, Esc to cancel");
+        game.rebinding = None;
+        game.menu_back();
+        assert!(game.menu_page == MenuPage::Options && game.menu.is_selected(MenuItem::Keys));
+    }
+
+    #[test]
+    fn picking_a_list_again_closes_it() {
+        let mut game = Game::new(&Settings { stats_file: Some("stats".into()), ..Settings::default() });
+        game.menu.select(MenuItem::Stats);
+        game.pick_menu_item();
+        assert!(game.stats_shown);
+        game.pick_menu_item();
+        assert!(!game.stats_shown);
+    }
+
+    #[test]
+    fn play_and_quit_do_what_they_say() {
+        let mut game = Game::new(&Settings::default());
+        game.menu.select(MenuItem::Quit);
+        game.pick_menu_item();
+        assert!(game.quit_requested && game.state == GameState::Menu);
+        game.menu.select(MenuItem::Play);
+        game.pick_menu_item();
+        assert!(game.state == GameState::Running);
+    }
+}
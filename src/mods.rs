@@ -0,0 +1,375 @@
+// Mods: game variants written as Rhai scripts in `assets/mods`, played with `--mod name`. A mod is a few
+// hook functions, each optional, that run at the same points of a run as a `GameMode`'s hooks:
+//
+//     fn on_tick(game) { }                  // every move, right after the snake moved
+//     fn on_eat(game, food) { }             // after food was eaten and scored; food is #{ kind, x, y }
+//     fn on_death(game, cause) { }          // the run is about to end: "wall", "self", "rock", "time",
+//                                           // "trapped" or "rival"
+//     fn spawn_food_override(game, cells) { }  // return the cells new food may land on, out of `cells`
+//                                              // (each one [x, y]), or nothing to leave them all
+//
+// Hooks can read `game.score`, `moves`, `length`, `head_x`, `head_y`, `direction` ("up", "down", "left" or
+// "right"), `columns`, `rows` and `foods_eaten`, and change the run only through `game.award(points)`,
+// `game.grow(segments)`, `game.shrink(segments)` and `game.notice(text)`, the line under the game over
+// screen. `spawn_food_override` only reads. Whatever a mod keeps between hooks goes in `this`, an object
+// map that starts out empty every run. Only the functions are used; code outside them never runs.
+//
+// A hook that errors or runs too long (as in an endless loop) stops its mod for the rest of the run, with
+// the error printed once.
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::path::Path;
+use std::rc::Rc;
+use rhai::{Array, CallFnOptions, Dynamic, Engine, FuncArgs, Map, Scope, AST};
+use crate::game::{DeathCause, Food, FoodKind, Game, GameMode, ScoreSource};
+use crate::snake::Direction;
+
+pub const MODS_DIR: &str = "assets/mods";
+const EXTENSION: &str = "rhai";
+const HOOKS: [(&str, usize); 4] = [("on_tick", 1), ("on_eat", 2), ("on_death", 2), ("spawn_food_override", 2)]; // With their parameter counts
+// Limits on what one hook call may do, so a broken mod can't hang the game or eat all memory
+const MAX_OPERATIONS: u64 = 1_000_000;
+const MAX_CALL_LEVELS: usize = 32;
+const MAX_EXPR_DEPTH: usize = 64;
+const MAX_STRING_SIZE: usize = 1_000;
+const MAX_COLLECTION_SIZE: usize = 10_000; // Items in an array or an object map
+
+// A mod's compiled script
+#[derive(Clone)]
+pub struct Mod {
+    pub name: String, // The file name, without the extension
+    ast: AST,
+}
+
+impl Mod {
+    pub fn load(path: &Path) -> Result<Mod, String> {
+        let name = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+        let script = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+        let ast = engine().compile(script).map_err(|err| err.to_string())?;
+        for function in ast.iter_functions() {
+            if let Some((hook, params)) = HOOKS.iter().find(|(hook, _)| *hook == function.name)
+                && function.params.len() != *params
+            {
+                return Err(format!("{} takes {} parameter(s), not {}", hook, params, function.params.len()));
+            }
+        }
+        Ok(Mod { name, ast })
+    }
+
+    // The mod's hooks as a game mode, fresh for a new run
+    pub fn mode(&self) -> Box<dyn GameMode> {
+        let hooks = self.ast.iter_functions().map(|function| function.name.to_string()).collect();
+        Box::new(ScriptMode { name: self.name.clone(), engine: engine(), ast: self.ast.clone(), hooks, state: Dynamic::from_map(Map::new()), stopped: false })
+    }
+}
+
+// The mods called `names`, from the `.rhai` files in `dir`
+pub fn load_mods(dir: &Path, names: &[&str]) -> Result<Vec<Mod>, String> {
+    names
+        .iter()
+        .map(|name| {
+            let path = dir.join(format!("{}.{}", name, EXTENSION));
+            Mod::load(&path).map_err(|err| format!("{}: {}", path.display(), err))
+        })
+        .collect()
+}
+
+// An engine with the limits set and `Game` open to scripts
+fn engine() -> Engine {
+    let mut engine = Engine::new();
+    engine
+        .set_max_operations(MAX_OPERATIONS)
+        .set_max_call_levels(MAX_CALL_LEVELS)
+        .set_max_expr_depths(MAX_EXPR_DEPTH, MAX_EXPR_DEPTH)
+        .set_max_string_size(MAX_STRING_SIZE)
+        .set_max_array_size(MAX_COLLECTION_SIZE)
+        .set_max_map_size(MAX_COLLECTION_SIZE);
+    engine
+        .register_type_with_name::<ModGame>("Game")
+        .register_get("score", |game: &mut ModGame| game.0.borrow().score)
+        .register_get("moves", |game: &mut ModGame| game.0.borrow().moves)
+        .register_get("length", |game: &mut ModGame| game.0.borrow().length)
+        .register_get("head_x", |game: &mut ModGame| game.0.borrow().head.0)
+        .register_get("head_y", |game: &mut ModGame| game.0.borrow().head.1)
+        .register_get("direction", |game: &mut ModGame| game.0.borrow().direction.to_string())
+        .register_get("columns", |game: &mut ModGame| game.0.borrow().grid.0)
+        .register_get("rows", |game: &mut ModGame| game.0.borrow().grid.1)
+        .register_get("foods_eaten", |game: &mut ModGame| game.0.borrow().foods_eaten)
+        .register_fn("award", |game: &mut ModGame, points: i64| game.change(|view| view.award = view.award.saturating_add(count(points))))
+        .register_fn("grow", |game: &mut ModGame, segments: i64| game.change(|view| view.grow = view.grow.saturating_add(count(segments))))
+        .register_fn("shrink", |game: &mut ModGame, segments: i64| game.change(|view| view.shrink = view.shrink.saturating_add(count(segments))))
+        .register_fn("notice", |game: &mut ModGame, text: String| game.change(|view| view.notice = Some(text)));
+    engine
+}
+
+// A script's whole number as an amount; nothing for a negative one
+fn count(n: i64) -> u32 {
+    n.clamp(0, u32::MAX as i64) as u32
+}
+
+// What a hook sees of the game: a copy of what it may read, and the changes it asked for. Scripts get
+// their own copy of every value passed to them, so the view is shared to get the changes back out.
+#[derive(Clone)]
+struct ModGame(Rc<RefCell<View>>);
+
+struct View {
+    score: i64,
+    moves: i64,
+    length: i64,
+    head: (i64, i64),
+    direction: &'static str,
+    grid: (i64, i64),
+    foods_eaten: i64,
+    award: u32,
+    grow: u32,
+    shrink: u32,
+    notice: Option<String>,
+}
+
+impl ModGame {
+    fn new(game: &Game) -> Self {
+        let (x, y) = game.snake.head();
+        ModGame(Rc::new(RefCell::new(View {
+            score: game.score.into(),
+            moves: game.moves.into(),
+            length: game.snake.body().len() as i64,
+            head: (x.into(), y.into()),
+            direction: direction_name(&game.snake.dir),
+            grid: (game.settings.grid.0.into(), game.settings.grid.1.into()),
+            foods_eaten: game.foods_eaten.into(),
+            award: 0,
+            grow: 0,
+            shrink: 0,
+            notice: None,
+        })))
+    }
+
+    fn change(&mut self, f: impl FnOnce(&mut View)) {
+        f(&mut self.0.borrow_mut());
+    }
+
+    // Make the changes the hook asked for
+    fn apply(&self, game: &mut Game) {
+        let view = self.0.borrow();
+        if view.award > 0 {
+            game.award(ScoreSource::Mod, view.award);
+        }
+        if view.grow > 0 {
+            game.snake.grow(view.grow, game.settings.max_length);
+        }
+        game.snake.shrink(view.shrink);
+        if let Some(notice) = &view.notice {
+            game.notice = Some(notice.clone());
+        }
+        game.mark_dirty();
+    }
+}
+
+// A mod playing along in one run
+struct ScriptMode {
+    name: String,
+    engine: Engine,
+    ast: AST,
+    hooks: HashSet<String>, // The functions the script has
+    state: Dynamic, // `this` in every hook
+    stopped: bool, // Set once a hook failed
+}
+
+impl ScriptMode {
+    // Call the hook `name` with `args`, if the script has it. Returns what the hook returned, or None when
+    // there is no such hook or the mod is stopped.
+    fn call(&mut self, name: &str, args: impl FuncArgs) -> Option<Dynamic> {
+        if self.stopped || !self.hooks.contains(name) {
+            return None;
+        }
+        let options = CallFnOptions::new().eval_ast(false).bind_this_ptr(&mut self.state);
+        match self.engine.call_fn_with_options::<Dynamic>(options, &mut Scope::new(), &self.ast, name, args) {
+            Ok(result) => Some(result),
+            Err(err) => {
+                self.stop(&format!("{}: {}", name, err));
+                None
+            },
+        }
+    }
+
+    fn stop(&mut self, err: &str) {
+        eprintln!("copperhead: mod {} stopped in {}", self.name, err);
+        self.stopped = true;
+    }
+}
+
+impl GameMode for ScriptMode {
+    fn on_tick(&mut self, game: &mut Game) {
+        let view = ModGame::new(game);
+        if self.call("on_tick", (view.clone(),)).is_some() {
+            view.apply(game);
+        }
+    }
+
+    fn on_spawn_food(&mut self, game: &Game, candidates: &mut Vec<(i32, i32)>) {
+        let cells: Array = candidates.iter().map(|&(x, y)| Dynamic::from_array(vec![Dynamic::from(x as i64), Dynamic::from(y as i64)])).collect();
+        let Some(result) = self.call("spawn_food_override", (ModGame::new(game), cells)) else {
+            return;
+        };
+        if result.is_unit() {
+            return;
+        }
+        match cells_from(result) {
+            Some(allowed) => candidates.retain(|cell| allowed.contains(cell)),
+            None => self.stop("spawn_food_override: it must return an array of [x, y] cells"),
+        }
+    }
+
+    fn on_food_eaten(&mut self, game: &mut Game, food: Food) {
+        let (x, y) = food.pos;
+        let food: Map = [("kind".into(), Dynamic::from(food_name(food.kind).to_string())), ("x".into(), Dynamic::from(x as i64)), ("y".into(), Dynamic::from(y as i64))].into_iter().collect();
+        let view = ModGame::new(game);
+        if self.call("on_eat", (view.clone(), Dynamic::from_map(food))).is_some() {
+            view.apply(game);
+        }
+    }
+
+    fn on_death(&mut self, game: &mut Game, cause: DeathCause) -> bool {
+        let view = ModGame::new(game);
+        if self.call("on_death", (view.clone(), cause_name(cause).to_string())).is_some() {
+            view.apply(game);
+        }
+        true
+    }
+}
+
+// The cells in a script's array of [x, y] pairs, or None if it isn't one
+fn cells_from(value: Dynamic) -> Option<HashSet<(i32, i32)>> {
+    value
+        .into_array()
+        .ok()?
+        .into_iter()
+        .map(|cell| match cell.into_array().ok()?.as_slice() {
+            [x, y] => Some((x.as_int().ok()? as i32, y.as_int().ok()? as i32)),
+            _ => None,
+        })
+        .collect()
+}
+
+fn direction_name(dir: &Direction) -> &'static str {
+    match dir {
+        Direction::Up => "up",
+        Direction::Down => "down",
+        Direction::Left => "left",
+        Direction::Right => "right",
+    }
+}
+
+fn food_name(kind: FoodKind) -> &'static str {
+    match kind {
+        FoodKind::Normal => "normal",
+        FoodKind::Bonus => "bonus",
+        FoodKind::Poison => "poison",
+        FoodKind::Large => "large",
+        FoodKind::Golden => "golden",
+    }
+}
+
+fn cause_name(cause: DeathCause) -> &'static str {
+    match cause {
+        DeathCause::Wall => "wall",
+        DeathCause::SelfBite => "self",
+        DeathCause::Obstacle => "rock",
+        DeathCause::TimeUp => "time",
+        DeathCause::Trapped => "trapped",
+        DeathCause::Rival => "rival",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{GameState, Settings, WallMode};
+
+    // The mod in `script`, loaded from a temp file named after `name`
+    fn script(name: &str, script: &str) -> Result<Mod, String> {
+        let path = std::env::temp_dir().join(format!("copperhead-mod-{}-{}.{}", std::process::id(), name, EXTENSION));
+        std::fs::write(&path, script).unwrap();
+        let loaded = Mod::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        loaded
+    }
+
+    // A running game on a wrapping board with `mods` playing along, its food out of the snake's way
+    fn game(mods: Vec<Mod>) -> Game {
+        let mut game = Game::new(&Settings::builder().wall_mode(WallMode::Wrap).mods(mods).build().unwrap());
+        let row = game.snake.head().1;
+        game.foods.truncate(1);
+        game.foods[0].pos = (0, row + 3);
+        game.state = GameState::Running;
+        game
+    }
+
+    #[test]
+    fn the_shipped_mods_load() {
+        let mods = load_mods(Path::new(MODS_DIR), &["hunger", "edge_food"]).unwrap();
+        assert_eq!(mods.iter().map(|m| m.name.as_str()).collect::<Vec<_>>(), ["hunger", "edge_food"]);
+        let err = load_mods(Path::new(MODS_DIR), &["no_such_mod"]).err().unwrap();
+        assert!(err.contains("no_such_mod.rhai"));
+    }
+
+    #[test]
+    fn broken_scripts_are_rejected() {
+        assert!(script("params", "fn on_tick(game, extra) { }").is_err_and(|err| err.contains("on_tick takes 1")));
+        assert!(script("syntax", "fn on_tick(game) { game.award( }").is_err());
+        assert!(script("helper", "fn helper(a, b, c) { a } fn on_tick(game) { }").is_ok(), "other functions take any parameters");
+    }
+
+    #[test]
+    fn hooks_change_the_run_through_the_game_and_keep_state_in_this() {
+        let counter = script("counter", "fn on_tick(game) { this.n = (this.n ?? 0) + 1; game.award(this.n); if this.n == 3 { game.notice(\"three\"); } }").unwrap();
+        let mut game = game(vec![counter]);
+        for _ in 0..4 {
+            game.tick(None);
+        }
+        assert_eq!(game.score, 1 + 2 + 3 + 4);
+        assert_eq!(game.notice.as_deref(), Some("three"));
+        assert!(game.results().iter().any(|(label, value)| *label == "From mods" && value == "10"));
+    }
+
+    #[test]
+    fn a_hungry_snake_starves() {
+        let mods = load_mods(Path::new(MODS_DIR), &["hunger"]).unwrap();
+        let mut game = game(mods);
+        game.snake.grow(4, None);
+        for _ in 0..69 {
+            game.tick(None);
+        }
+        let full = game.snake.body().len();
+        game.tick(None);
+        assert_eq!(game.snake.body().len(), full - 1, "a segment goes 10 moves into starving");
+    }
+
+    #[test]
+    fn edge_food_only_leaves_the_edge_cells() {
+        let mods = load_mods(Path::new(MODS_DIR), &["edge_food"]).unwrap();
+        let game = game(Vec::new());
+        let mut mode = mods[0].mode();
+        let mut cells = vec![(0, 5), (5, 5), (19, 3), (7, 19), (18, 18)];
+        mode.on_spawn_food(&game, &mut cells);
+        assert_eq!(cells, [(0, 5), (19, 3), (7, 19)]);
+    }
+
+    #[test]
+    fn a_hook_that_runs_away_stops_its_mod() {
+        let runaway = script("runaway", "fn on_tick(game) { this.n = (this.n ?? 0) + 1; game.award(1); if this.n == 3 { loop { } } }").unwrap();
+        let mut game = game(vec![runaway]);
+        for _ in 0..8 {
+            game.tick(None);
+        }
+        assert!(game.state == GameState::Running);
+        assert_eq!(game.score, 2, "the award before the loop is dropped along with the mod");
+
+        let wrong = script("wrong", "fn spawn_food_override(game, cells) { 42 }").unwrap();
+        let mut mode = wrong.mode();
+        let mut cells = vec![(1, 1), (2, 2)];
+        mode.on_spawn_food(&game, &mut cells);
+        assert_eq!(cells.len(), 2, "a bad answer leaves every cell");
+    }
+}
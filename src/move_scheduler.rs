@@ -0,0 +1,42 @@
+pub const MAX_CATCH_UP_MOVES: u32 = 3; // Most moves made in one update after a stall; the rest of the backlog is dropped
+
+// Turns elapsed game time into snake moves, one per `interval` seconds. Time left over from one update
+// carries into the next, so moves stay evenly spaced however the updates are spread. After a stall (a
+// dragged window, a laptop waking from sleep), at most MAX_CATCH_UP_MOVES moves are made at once and
+// the rest of the backlog is forgotten, instead of the snake racing ahead for seconds to catch up.
+pub struct MoveScheduler {
+    accumulator: f64, // Game seconds since the last move
+    interval: f64, // Game seconds between moves
+}
+
+impl MoveScheduler {
+    pub fn new(interval: f64) -> Self {
+        MoveScheduler { accumulator: 0.0, interval }
+    }
+
+    // Advance by `dt` game seconds; returns how many moves to make now
+    pub fn tick(&mut self, dt: f64) -> u32 {
+        self.accumulator += dt;
+        let due = (self.accumulator / self.interval).floor();
+        if due < 1.0 {
+            return 0;
+        }
+        if due > MAX_CATCH_UP_MOVES as f64 {
+            // Keep only the time towards the next move
+            self.accumulator %= self.interval;
+            return MAX_CATCH_UP_MOVES;
+        }
+        self.accumulator -= due * self.interval;
+        due as u32
+    }
+
+    // Moves come `interval` game seconds apart from now on, for the speed changed on the options page
+    pub fn set_interval(&mut self, interval: f64) {
+        self.interval = interval;
+    }
+
+    // How far along the wait for the next move is, from 0 right after a move to almost 1 just before the next
+    pub fn progress(&self) -> f64 {
+        (self.accumulator / self.interval).min(1.0)
+    }
+}
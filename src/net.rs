@@ -0,0 +1,467 @@
+// Head-to-head over the network: two machines each run the same two-player `Game` and only tell each other
+// which way their player steered. The host plays player one and picks the seed; the guest plays player two.
+// Both sides make a move only once they have the turns both players pressed before it (lockstep), so the two
+// games stay the same move for move without ever sending the board. After every move each side sends a
+// checksum of its game, and the match stops as soon as the two disagree.
+//
+// The protocol is text lines over one TCP connection:
+//
+//     hello VERSION SEED COLUMNS ROWS   host, once connected
+//     hello VERSION COLUMNS ROWS        guest, in answer
+//     move ROUND TURNS                  turns (U, D, L, R, or - for none) pressed before the next move
+//     check ROUND N CHECKSUM            the game after move N
+//     restart ROUND                     starting round ROUND (the first is 0), each with a seed of its own
+//     bye                               leaving
+//
+// Everything the rules look at has to be the same on both sides, so both players need the same options.
+
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+use crate::game::{Game, GameState};
+use crate::snake::Direction;
+
+const VERSION: u32 = 1; // Protocol version; both sides must speak the same one
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_AHEAD: usize = 2; // Moves whose turns are sent before the other side's have arrived
+
+// Which player this machine plays
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Side {
+    Host, // Player one, who waits for the other to join
+    Guest, // Player two, who joins the host
+}
+
+// A line from the other side, or the end of the connection
+enum Message {
+    Hello(Vec<u64>), // The greeting's numbers
+    Move { round: u32, turns: Vec<Direction> },
+    Check { round: u32, step: u32, checksum: u64 },
+    Restart { round: u32 },
+    Gone(String), // Why the connection ended
+}
+
+pub struct Lockstep {
+    pub side: Side,
+    stream: TcpStream, // Written to here; a thread of its own reads from a clone
+    inbox: Receiver<Message>,
+    base_seed: u64, // Round N plays with this plus N as its seed
+    round: u32,
+    step: u32, // Moves made this round
+    owed: usize, // Moves the clock has asked for that haven't been made yet
+    mine: VecDeque<Vec<Direction>>, // Turns sent for the next moves, first one first
+    theirs: VecDeque<Vec<Direction>>, // Turns received for the next moves
+    checks: VecDeque<(u32, u64)>, // Checksums after this side's last moves, until the other side's arrive
+    their_checks: VecDeque<(u32, u64)>, // Checksums the other side sent, until this side has made those moves
+    connected: bool,
+}
+
+impl Lockstep {
+    // Wait for a guest on `port` and agree on `seed` and the board size. Blocks until someone joins.
+    pub fn host(port: u16, seed: u64, grid: (i32, i32)) -> Result<Lockstep, String> {
+        let listener = TcpListener::bind(("0.0.0.0", port)).map_err(|err| format!("could not listen on port {}: {}", port, err))?;
+        let (stream, _) = listener.accept().map_err(|err| err.to_string())?;
+        let mut lockstep = Lockstep::new(Side::Host, stream, seed)?;
+        lockstep.send(&format!("hello {} {} {} {}", VERSION, seed, grid.0, grid.1));
+        let hello = lockstep.read_hello()?;
+        match hello.as_slice() {
+            [version, columns, rows] if *version == VERSION as u64 && (*columns as i32, *rows as i32) == grid => Ok(lockstep),
+            [version, ..] if *version != VERSION as u64 => Err("the other player runs another version of the game".to_string()),
+            _ => Err("the other player has another board size".to_string()),
+        }
+    }
+
+    // Join the host at `addr` (host:port) and take its seed; fails if the board sizes differ
+    pub fn join(addr: &str, grid: (i32, i32)) -> Result<Lockstep, String> {
+        let target = addr
+            .to_socket_addrs()
+            .map_err(|err| format!("{}: {}", addr, err))?
+            .next()
+            .ok_or_else(|| format!("{} has no address", addr))?;
+        let stream = TcpStream::connect_timeout(&target, CONNECT_TIMEOUT).map_err(|err| format!("could not connect to {}: {}", addr, err))?;
+        let mut lockstep = Lockstep::new(Side::Guest, stream, 0)?;
+        let hello = lockstep.read_hello()?;
+        let [version, seed, columns, rows] = hello.as_slice() else {
+            return Err("the host sent a malformed greeting".to_string());
+        };
+        if *version != VERSION as u64 {
+            return Err("the host runs another version of the game".to_string());
+        }
+        lockstep.send(&format!("hello {} {} {}", VERSION, grid.0, grid.1));
+        if (*columns as i32, *rows as i32) != grid {
+            return Err(format!("the host plays on a {}x{} board, this one is {}x{}", columns, rows, grid.0, grid.1));
+        }
+        lockstep.base_seed = *seed;
+        Ok(lockstep)
+    }
+
+    fn new(side: Side, stream: TcpStream, base_seed: u64) -> Result<Lockstep, String> {
+        stream.set_nodelay(true).map_err(|err| err.to_string())?;
+        let reader = stream.try_clone().map_err(|err| err.to_string())?;
+        let (sender, inbox) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(reader).lines() {
+                let message = match line {
+                    Ok(line) => parse(&line),
+                    Err(err) => Message::Gone(err.to_string()),
+                };
+                let gone = matches!(message, Message::Gone(_));
+                if sender.send(message).is_err() || gone {
+                    return;
+                }
+            }
+            sender.send(Message::Gone("the other player left".to_string())).ok();
+        });
+        Ok(Lockstep {
+            side,
+            stream,
+            inbox,
+            base_seed,
+            round: 0,
+            step: 0,
+            owed: 0,
+            mine: VecDeque::new(),
+            theirs: VecDeque::new(),
+            checks: VecDeque::new(),
+            their_checks: VecDeque::new(),
+            connected: true,
+        })
+    }
+
+    // The seed of the first round, which the game must be started with
+    pub fn seed(&self) -> u64 {
+        self.base_seed
+    }
+
+    // The greeting's numbers, waited for here before the game starts
+    fn read_hello(&mut self) -> Result<Vec<u64>, String> {
+        match self.inbox.recv_timeout(CONNECT_TIMEOUT) {
+            Ok(Message::Hello(numbers)) => Ok(numbers),
+            Ok(Message::Gone(reason)) => Err(reason),
+            Ok(_) => Err("the other side didn't greet first".to_string()),
+            Err(_) => Err("the other side didn't answer".to_string()),
+        }
+    }
+
+    // Take in what the other side sent and start a new round if either side asked for one. Then send this
+    // side's turns for the moves `due` adds to those owed, and return how many moves can be made now: as
+    // many as are owed and have the turns of both sides.
+    pub fn ready(&mut self, game: &mut Game, due: u32) -> u32 {
+        while let Ok(message) = self.inbox.try_recv() {
+            match message {
+                Message::Move { round, turns } if round == self.round => self.theirs.push_back(turns),
+                Message::Check { round, step, checksum } if round == self.round => {
+                    self.their_checks.push_back((step, checksum));
+                    self.compare(game);
+                },
+                Message::Restart { round } if round > self.round => self.start_round(game, round),
+                Message::Gone(reason) => self.disconnect(game, &reason),
+                // A greeting mid-match, or what's left of a round this side has already moved on from
+                _ => {},
+            }
+        }
+        if game.restart_requested {
+            game.restart_requested = false;
+            if self.connected {
+                let round = self.round + 1;
+                self.send(&format!("restart {}", round));
+                self.start_round(game, round);
+            }
+        }
+        if !self.connected || game.state != GameState::Running {
+            self.owed = 0;
+            return 0;
+        }
+        self.owed = (self.owed + due as usize).min(MAX_AHEAD);
+        while self.mine.len() < self.owed {
+            let turns = std::mem::take(&mut game.outbox);
+            let letters: String = turns.iter().map(letter).collect();
+            self.send(&format!("move {} {}", self.round, if letters.is_empty() { "-" } else { &letters }));
+            self.mine.push_back(turns);
+        }
+        self.owed.min(self.theirs.len()) as u32
+    }
+
+    // Make one of the moves `ready` counted: both players' turns, then a move's worth of game clock and the
+    // move itself
+    pub fn update(&mut self, game: &mut Game) {
+        let (Some(mine), Some(theirs)) = (self.mine.pop_front(), self.theirs.pop_front()) else {
+            return;
+        };
+        let (one, two) = match self.side {
+            Side::Host => (mine, theirs),
+            Side::Guest => (theirs, mine),
+        };
+        game.apply_turns(&one, &two);
+        game.tick_clock(game.settings.move_interval / game.move_speed());
+        if game.state == GameState::Running {
+            game.update();
+        }
+        self.owed = self.owed.saturating_sub(1);
+        self.step += 1;
+        let checksum = checksum(game);
+        self.checks.push_back((self.step, checksum));
+        self.send(&format!("check {} {} {}", self.round, self.step, checksum));
+        self.compare(game);
+    }
+
+    // Tell the other side this one is leaving
+    pub fn leave(&mut self) {
+        if self.connected {
+            self.send("bye");
+            self.connected = false;
+        }
+    }
+
+    // Compare the checksums both sides have for the same moves, dropping each pair once it's compared
+    fn compare(&mut self, game: &mut Game) {
+        while let (Some(&(step, ours)), Some(&(their_step, theirs))) = (self.checks.front(), self.their_checks.front()) {
+            if step < their_step {
+                self.checks.pop_front();
+            } else if their_step < step {
+                self.their_checks.pop_front();
+            } else if ours != theirs {
+                self.disconnect(game, "the games went out of sync; both players need the same options");
+                return;
+            } else {
+                self.checks.pop_front();
+                self.their_checks.pop_front();
+            }
+        }
+    }
+
+    // Begin round `round` on a fresh board from the round's seed, right away
+    fn start_round(&mut self, game: &mut Game, round: u32) {
+        self.round = round;
+        self.step = 0;
+        self.owed = 0;
+        self.mine.clear();
+        self.theirs.clear();
+        self.checks.clear();
+        self.their_checks.clear();
+        game.settings.seed = Some(self.base_seed.wrapping_add(round as u64));
+        game.reset();
+        game.state = GameState::Running;
+    }
+
+    // The match is over: show why on the game over screen, and stop sending
+    fn disconnect(&mut self, game: &mut Game, reason: &str) {
+        if !self.connected {
+            return;
+        }
+        self.connected = false;
+        game.notice = Some(format!("Disconnected: {}", reason));
+        if matches!(game.state, GameState::Running | GameState::Paused) {
+            game.state = GameState::GameOver;
+        }
+        game.dirty = true;
+    }
+
+    // A lost connection shows up as a Gone message from the reading thread, so write errors are dropped here
+    fn send(&mut self, line: &str) {
+        if self.connected {
+            writeln!(self.stream, "{}", line).ok();
+        }
+    }
+}
+
+fn parse(line: &str) -> Message {
+    let malformed = || Message::Gone(format!("malformed line {:?}", line));
+    let mut fields = line.split_whitespace();
+    let kind = fields.next().unwrap_or_default();
+    let rest: Vec<&str> = fields.collect();
+    let numbers: Option<Vec<u64>> = rest.iter().map(|field| field.parse().ok()).collect();
+    match (kind, rest.as_slice(), numbers.as_deref()) {
+        ("hello", _, Some(numbers)) => Message::Hello(numbers.to_vec()),
+        ("move", [round, turns], _) => {
+            let turns: Option<Vec<Direction>> = turns.trim_matches('-').chars().map(direction).collect();
+            match (round.parse(), turns) {
+                (Ok(round), Some(turns)) => Message::Move { round, turns },
+                _ => malformed(),
+            }
+        },
+        ("check", _, Some(&[round, step, checksum])) => Message::Check { round: round as u32, step: step as u32, checksum },
+        ("restart", _, Some(&[round])) => Message::Restart { round: round as u32 },
+        ("bye", [], _) => Message::Gone("the other player left".to_string()),
+        _ => malformed(),
+    }
+}
+
+fn letter(dir: &Direction) -> char {
+    match dir {
+        Direction::Up => 'U',
+        Direction::Down => 'D',
+        Direction::Left => 'L',
+        Direction::Right => 'R',
+    }
+}
+
+fn direction(letter: char) -> Option<Direction> {
+    match letter {
+        'U' => Some(Direction::Up),
+        'D' => Some(Direction::Down),
+        'L' => Some(Direction::Left),
+        'R' => Some(Direction::Right),
+        _ => None,
+    }
+}
+
+// FNV-1a over the scores, both snakes and the food, which is where two games drifting apart shows first
+fn checksum(game: &Game) -> u64 {
+    let rival = game.rival.as_ref();
+    let numbers = [game.score, rival.map_or(0, |rival| rival.score)]
+        .into_iter()
+        .chain(game.snake.body().iter().chain(rival.into_iter().flat_map(|rival| rival.snake.body())).chain(game.foods.iter().map(|food| &food.pos)).flat_map(|&(x, y)| [x as u32, y as u32]));
+    numbers.fold(0xcbf2_9ce4_8422_2325, |hash: u64, n| {
+        n.to_le_bytes().iter().fold(hash, |hash, &byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Players, Settings};
+
+    // A host on a free local port and a guest joined to it, with boards of `host_grid` and `guest_grid`
+    fn connect(host_grid: (i32, i32), guest_grid: (i32, i32)) -> (Result<Lockstep, String>, Result<Lockstep, String>) {
+        let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let host = std::thread::spawn(move || Lockstep::host(port, 42, host_grid));
+        // The host may not be listening yet
+        let mut guest = Lockstep::join(&format!("127.0.0.1:{}", port), guest_grid);
+        for _ in 0..200 {
+            if guest.as_ref().is_err_and(|err| err.starts_with("could not connect")) {
+                std::thread::sleep(Duration::from_millis(10));
+                guest = Lockstep::join(&format!("127.0.0.1:{}", port), guest_grid);
+            }
+        }
+        (host.join().unwrap(), guest)
+    }
+
+    // This side's game of a match, running from the agreed seed
+    fn game(lockstep: &Lockstep, settings: Settings) -> Game {
+        let mut game = Game::new(&Settings { players: Players::Two, net_side: Some(lockstep.side), seed: Some(lockstep.seed()), ..settings });
+        game.state = GameState::Running;
+        game
+    }
+
+    // Ask both sides for one more move, then wait until each has the other's turns for it; false if they
+    // never come or a game stopped
+    fn next_move(host: &mut Lockstep, one: &mut Game, guest: &mut Lockstep, two: &mut Game) -> bool {
+        let mut due = 1;
+        for _ in 0..500 {
+            let ready = (host.ready(one, due), guest.ready(two, due));
+            if ready.0 > 0 && ready.1 > 0 {
+                return true;
+            }
+            if !host.connected || !guest.connected || one.state != GameState::Running || two.state != GameState::Running {
+                return false;
+            }
+            due = 0;
+            std::thread::sleep(Duration::from_millis(2));
+        }
+        false
+    }
+
+    // A turn now and then, different for each side
+    fn press(game: &mut Game, step: u32, salt: u32) {
+        let dirs = [Direction::Up, Direction::Left, Direction::Down, Direction::Right];
+        if (step + salt).is_multiple_of(4) {
+            game.outbox.push(dirs[((step / 4 + salt) % 4) as usize].clone());
+        }
+    }
+
+    fn same(one: &Game, two: &Game) -> bool {
+        let rivals = |game: &Game| game.rival.as_ref().map(|rival| (rival.snake.body().clone(), rival.score));
+        one.state == two.state
+            && one.score == two.score
+            && one.snake.body() == two.snake.body()
+            && rivals(one) == rivals(two)
+            && one.foods == two.foods
+    }
+
+    #[test]
+    fn two_games_over_localhost_stay_the_same_round_after_round() {
+        let (host, guest) = connect((20, 20), (20, 20));
+        let (mut host, mut guest) = (host.unwrap(), guest.unwrap());
+        let (mut one, mut two) = (game(&host, Settings::default()), game(&guest, Settings::default()));
+        assert!(same(&one, &two));
+        for round in 0..4 {
+            let mut moves = 0;
+            while one.state == GameState::Running && moves < 150 {
+                press(&mut one, moves, 0);
+                press(&mut two, moves, 1);
+                assert!(next_move(&mut host, &mut one, &mut guest, &mut two), "round {} stalled at move {}", round, moves);
+                host.update(&mut one);
+                guest.update(&mut two);
+                assert!(same(&one, &two), "round {} drifted apart at move {}", round, moves);
+                moves += 1;
+            }
+            // Restart on the host; the guest follows once it hears
+            one.restart_requested = true;
+            host.ready(&mut one, 0);
+            for _ in 0..500 {
+                guest.ready(&mut two, 0);
+                if guest.round == round + 1 {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(2));
+            }
+            assert_eq!((host.round, guest.round), (round + 1, round + 1));
+            assert!(same(&one, &two) && one.run_seed == 42 + round as u64 + 1);
+        }
+        assert!(host.connected && guest.connected && one.notice.is_none() && two.notice.is_none());
+        host.leave();
+        for _ in 0..500 {
+            guest.ready(&mut two, 0);
+            if !guest.connected {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(2));
+        }
+        assert!(two.notice.as_deref().is_some_and(|notice| notice.contains("left")));
+    }
+
+    #[test]
+    fn games_with_different_options_are_reported_out_of_sync() {
+        let (host, guest) = connect((20, 20), (20, 20));
+        let (mut host, mut guest) = (host.unwrap(), guest.unwrap());
+        let mut one = game(&host, Settings::default());
+        let mut two = game(&guest, Settings { food_count: 3, ..Settings::default() });
+        for _ in 0..20 {
+            if !next_move(&mut host, &mut one, &mut guest, &mut two) {
+                break;
+            }
+            host.update(&mut one);
+            guest.update(&mut two);
+        }
+        // Whichever side compared first stopped; the other one hears nothing more and stops too
+        for game in [&one, &two] {
+            if let Some(notice) = &game.notice {
+                assert!(notice.contains("out of sync"));
+                assert!(game.state == GameState::GameOver);
+            }
+        }
+        assert!(one.notice.is_some() || two.notice.is_some());
+    }
+
+    #[test]
+    fn a_guest_on_another_board_size_cant_join() {
+        let (host, guest) = connect((20, 20), (30, 20));
+        assert!(host.is_err_and(|err| err.contains("board size")));
+        assert!(guest.is_err_and(|err| err.contains("20x20")));
+    }
+
+    #[test]
+    fn lines_parse_into_messages() {
+        assert!(matches!(parse("move 3 UL"), Message::Move { round: 3, ref turns } if *turns == [Direction::Up, Direction::Left]));
+        assert!(matches!(parse("move 0 -"), Message::Move { round: 0, ref turns } if turns.is_empty()));
+        assert!(matches!(parse("check 1 7 99"), Message::Check { round: 1, step: 7, checksum: 99 }));
+        assert!(matches!(parse("restart 2"), Message::Restart { round: 2 }));
+        assert!(matches!(parse("hello 1 20 20"), Message::Hello(ref numbers) if *numbers == [1, 20, 20]));
+        assert!(matches!(parse("bye"), Message::Gone(_)));
+        assert!(matches!(parse("move 1 UX"), Message::Gone(_)));
+        assert!(matches!(parse("check 1 x 3"), Message::Gone(_)));
+    }
+}
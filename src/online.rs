@@ -0,0 +1,273 @@
+// The global leaderboard: an optional web server that takes scores and hands out the top 10 of everyone
+// who sends it theirs. It speaks plain HTTP/1.0 in the local leaderboard's own `score date name` lines:
+//
+//     GET  <path>  answers with the top 10, best first
+//     POST <path>  with one line in the body adds a score
+//
+// Every request runs on a thread of its own and reports back through a channel, so a slow or unreachable
+// server never holds up a frame. Only `http://` addresses work; put a proxy in front for HTTPS.
+
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use crate::leaderboard::Leaderboard;
+
+const TIMEOUT: Duration = Duration::from_secs(5); // For connecting, and for each read and write after that
+const MAX_RESPONSE: u64 = 64 * 1024; // Bytes; more than any top 10 needs
+pub const MAX_NAME_LEN: usize = 12; // Characters in a player name
+
+// Where the global leaderboard lives, from an address like `http://scores.example.com:8080/copperhead`
+#[derive(Clone, PartialEq, Debug)]
+pub struct Server {
+    host: String,
+    port: u16,
+    path: String,
+}
+
+impl std::str::FromStr for Server {
+    type Err = String;
+
+    fn from_str(url: &str) -> Result<Self, Self::Err> {
+        let Some(rest) = url.strip_prefix("http://") else {
+            return Err(format!("expected an address starting with http://, got {:?}", url));
+        };
+        let (authority, path) = match rest.find('/') {
+            Some(slash) => rest.split_at(slash),
+            None => (rest, "/"),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().map_err(|_| format!("malformed port {:?}", port))?),
+            None => (authority, 80),
+        };
+        if host.is_empty() {
+            return Err(format!("no host in {:?}", url));
+        }
+        Ok(Server { host: host.to_string(), port, path: path.to_string() })
+    }
+}
+
+// Whether `name` can go on the global leaderboard: one word of letters, digits, `-` or `_`
+pub fn valid_name(name: &str) -> bool {
+    (1..=MAX_NAME_LEN).contains(&name.chars().count()) && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+// What a request thread sends back
+enum Reply {
+    Submitted(Result<u32, String>), // The score sent, or why it didn't get there
+    Top(Result<Leaderboard, String>),
+}
+
+pub struct OnlineBoard {
+    server: Server,
+    name: String, // Player name the scores go in under
+    sender: Sender<Reply>,
+    replies: Receiver<Reply>,
+    submissions: Vec<JoinHandle<()>>, // Scores on their way, waited for before the game closes
+    pub top: Option<Leaderboard>, // The top 10 as last fetched
+    pub status: Option<String>, // What the last request is doing or how it went
+}
+
+impl OnlineBoard {
+    pub fn new(server: Server, name: String) -> Self {
+        let (sender, replies) = mpsc::channel();
+        OnlineBoard { server, name, sender, replies, submissions: Vec::new(), top: None, status: None }
+    }
+
+    // Fetch the top 10 in the background
+    pub fn refresh(&mut self) {
+        self.status = Some("Fetching the top 10...".to_string());
+        let (server, sender) = (self.server.clone(), self.sender.clone());
+        std::thread::spawn(move || {
+            sender.send(Reply::Top(fetch(&server))).ok();
+        });
+    }
+
+    // Send a finished run's score in the background, then fetch the top 10 it may have joined
+    pub fn submit(&mut self, score: u32, date: &str) {
+        self.status = Some(format!("Sending {} as {}...", score, self.name));
+        let line = format!("{} {} {}\n", score, date, self.name);
+        let (server, sender) = (self.server.clone(), self.sender.clone());
+        self.submissions.retain(|submission| !submission.is_finished());
+        self.submissions.push(std::thread::spawn(move || {
+            // The top 10 goes first, so what the list says last is that the score got there
+            let sent = request(&server, "POST", &line).map(|_| score);
+            if sent.is_ok() {
+                sender.send(Reply::Top(fetch(&server))).ok();
+            }
+            sender.send(Reply::Submitted(sent)).ok();
+        }));
+    }
+
+    // Take in whatever the request threads have sent back, without waiting; true if anything changed
+    pub fn poll(&mut self) -> bool {
+        let mut changed = false;
+        while let Ok(reply) = self.replies.try_recv() {
+            self.status = match reply {
+                Reply::Submitted(Ok(score)) => Some(format!("Sent {} as {}", score, self.name)),
+                Reply::Submitted(Err(err)) => Some(format!("Could not send the score: {}", err)),
+                Reply::Top(Ok(top)) => {
+                    self.top = Some(top);
+                    None
+                },
+                Reply::Top(Err(err)) => Some(format!("Could not fetch the top 10: {}", err)),
+            };
+            changed = true;
+        }
+        changed
+    }
+
+    // Wait for scores still being sent, each for at most a few timeouts, so quitting right after a run
+    // doesn't lose its score
+    pub fn finish(&mut self) {
+        for submission in self.submissions.drain(..) {
+            submission.join().ok();
+        }
+    }
+}
+
+// The top 10 from the server
+fn fetch(server: &Server) -> Result<Leaderboard, String> {
+    Leaderboard::parse(&request(server, "GET", "")?)
+}
+
+// One HTTP/1.0 request, which keeps the answer simple: no chunks, and the server closes the connection
+// once it's done. Returns the body of a 2xx answer.
+fn request(server: &Server, method: &str, body: &str) -> Result<String, String> {
+    let addr = (server.host.as_str(), server.port)
+        .to_socket_addrs()
+        .map_err(|err| err.to_string())?
+        .next()
+        .ok_or_else(|| format!("{} has no address", server.host))?;
+    let mut stream = TcpStream::connect_timeout(&addr, TIMEOUT).map_err(|err| err.to_string())?;
+    stream.set_read_timeout(Some(TIMEOUT)).map_err(|err| err.to_string())?;
+    stream.set_write_timeout(Some(TIMEOUT)).map_err(|err| err.to_string())?;
+    write!(
+        stream,
+        "{} {} HTTP/1.0\r\nHost: {}\r\nUser-Agent: copperhead/{}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\n\r\n{}",
+        method,
+        server.path,
+        server.host,
+        env!("CARGO_PKG_VERSION"),
+        body.len(),
+        body,
+    )
+    .map_err(|err| err.to_string())?;
+
+    let mut response = Vec::new();
+    stream.take(MAX_RESPONSE).read_to_end(&mut response).map_err(|err| err.to_string())?;
+    let response = String::from_utf8_lossy(&response);
+    let (head, body) = response.split_once("\r\n\r\n").ok_or("malformed answer")?;
+    let status = head.lines().next().unwrap_or_default();
+    match status.split_whitespace().nth(1).and_then(|code| code.parse::<u16>().ok()) {
+        Some(200..=299) => Ok(body.to_string()),
+        Some(_) => Err(format!("the server answered {:?}", status)),
+        None => Err("malformed answer".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    // A server on a free local port that gives each of `answers` to one request in turn, and hands back the
+    // requests it got once they're all answered
+    fn serve(answers: Vec<&'static str>) -> (Server, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let server = format!("http://127.0.0.1:{}/scores", listener.local_addr().unwrap().port()).parse().unwrap();
+        let handle = std::thread::spawn(move || {
+            answers
+                .into_iter()
+                .map(|answer| {
+                    let (mut stream, _) = listener.accept().unwrap();
+                    let mut request = Vec::new();
+                    let mut buf = [0; 1024];
+                    // Read the head, then as much body as it says there is
+                    loop {
+                        let n = stream.read(&mut buf).unwrap();
+                        request.extend_from_slice(&buf[..n]);
+                        let text = String::from_utf8_lossy(&request);
+                        if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                            let len = head.lines().find_map(|line| line.strip_prefix("Content-Length: ")).unwrap().parse().unwrap();
+                            if body.len() >= len || n == 0 {
+                                break;
+                            }
+                        }
+                    }
+                    stream.write_all(answer.as_bytes()).unwrap();
+                    String::from_utf8(request).unwrap()
+                })
+                .collect()
+        });
+        (server, handle)
+    }
+
+    // Poll until a reply comes in, giving up after a few seconds
+    fn wait_for_reply(board: &mut OnlineBoard) {
+        for _ in 0..500 {
+            if board.poll() {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        panic!("no reply");
+    }
+
+    #[test]
+    fn addresses_parse_into_host_port_and_path() {
+        let server: Server = "http://scores.example.com:8080/copperhead".parse().unwrap();
+        assert_eq!(server, Server { host: "scores.example.com".to_string(), port: 8080, path: "/copperhead".to_string() });
+        let server: Server = "http://localhost".parse().unwrap();
+        assert_eq!((server.port, server.path.as_str()), (80, "/"));
+        assert!("https://localhost/".parse::<Server>().is_err());
+        assert!("http://:80/".parse::<Server>().is_err());
+        assert!("http://localhost:http/".parse::<Server>().is_err());
+    }
+
+    #[test]
+    fn names_are_one_short_word() {
+        assert!(valid_name("ada") && valid_name("x-_9") && valid_name(&"a".repeat(MAX_NAME_LEN)));
+        assert!(!valid_name("") && !valid_name("two words") && !valid_name(&"a".repeat(MAX_NAME_LEN + 1)));
+        assert!(!valid_name("émile"));
+    }
+
+    #[test]
+    fn a_sent_score_is_posted_and_the_top_10_fetched() {
+        let (server, handle) = serve(vec![
+            "HTTP/1.0 204 No Content\r\n\r\n",
+            "HTTP/1.0 200 OK\r\nContent-Type: text/plain\r\n\r\n120 2026-10-01 ada\n80 2026-09-30 bob\n",
+        ]);
+        let mut board = OnlineBoard::new(server, "ada".to_string());
+        board.submit(120, "2026-10-01");
+        board.finish();
+        let requests = handle.join().unwrap();
+        assert!(requests[0].starts_with("POST /scores HTTP/1.0\r\n"));
+        assert!(requests[0].ends_with("\r\n\r\n120 2026-10-01 ada\n"));
+        assert!(requests[1].starts_with("GET /scores HTTP/1.0\r\n"));
+        assert!(board.poll());
+        assert_eq!(board.status.as_deref(), Some("Sent 120 as ada"));
+        let top = board.top.as_ref().unwrap();
+        assert_eq!(top.entries.iter().map(|entry| entry.score).collect::<Vec<_>>(), [120, 80]);
+    }
+
+    #[test]
+    fn an_error_answer_or_no_server_is_reported() {
+        let (server, handle) = serve(vec!["HTTP/1.0 500 Internal Server Error\r\n\r\n"]);
+        let mut board = OnlineBoard::new(server, "ada".to_string());
+        board.refresh();
+        wait_for_reply(&mut board);
+        handle.join().unwrap();
+        assert!(board.status.as_deref().is_some_and(|status| status.starts_with("Could not fetch the top 10: the server answered")));
+        assert!(board.top.is_none());
+
+        // Nothing listens on a port that was just given back
+        let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let mut board = OnlineBoard::new(format!("http://127.0.0.1:{}/", port).parse().unwrap(), "ada".to_string());
+        board.submit(5, "2026-10-01");
+        board.finish();
+        assert!(board.poll());
+        assert!(board.status.as_deref().is_some_and(|status| status.starts_with("Could not send the score")));
+    }
+}
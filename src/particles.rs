@@ -0,0 +1,123 @@
+// Sparks thrown off where the snake eats. They are only decoration: the rules never look at them, and
+// they draw from their own random numbers so the food and walls of a seeded run stay the same.
+
+use rand::{Rng, SeedableRng};
+use std::f64::consts::TAU;
+
+const BURST_SIZE: usize = 12; // Sparks per food eaten
+const MAX_PARTICLES: usize = 200; // Oldest sparks are dropped past this, in case nobody advances them
+const SPEED: std::ops::Range<f64> = 2.0..5.0; // Cells per second as they leave the food
+const LIFETIME: std::ops::Range<f64> = 0.3..0.6; // Seconds
+const DRAG: f64 = 4.0; // How quickly they slow down; the speed drops by e every 1/DRAG seconds
+
+pub struct Particle {
+    pub pos: [f64; 2], // In cells from the playfield's top left corner
+    velocity: [f64; 2], // Cells per second
+    age: f64, // Seconds
+    life: f64, // Seconds it lasts in total
+}
+
+impl Particle {
+    // How much of its life is left, from 1 when it's spawned to 0 when it's gone
+    pub fn remaining(&self) -> f64 {
+        (1.0 - self.age / self.life).max(0.0)
+    }
+}
+
+pub struct Particles {
+    particles: Vec<Particle>,
+    rng: rand::rngs::StdRng,
+}
+
+impl Particles {
+    pub fn new(seed: u64) -> Self {
+        Particles { particles: Vec::new(), rng: rand::rngs::StdRng::seed_from_u64(seed) }
+    }
+
+    // Scatter a burst of sparks in every direction from the middle of `cell`
+    pub fn burst(&mut self, (x, y): (i32, i32)) {
+        for i in 0..BURST_SIZE {
+            // Evenly spread around the circle, with some wobble so no two bursts look alike
+            let angle = (i as f64 + self.rng.gen_range(-0.4..0.4)) * TAU / BURST_SIZE as f64;
+            let speed = self.rng.gen_range(SPEED);
+            self.particles.push(Particle {
+                pos: [x as f64 + 0.5, y as f64 + 0.5],
+                velocity: [angle.cos() * speed, angle.sin() * speed],
+                age: 0.0,
+                life: self.rng.gen_range(LIFETIME),
+            });
+        }
+        let excess = self.particles.len().saturating_sub(MAX_PARTICLES);
+        self.particles.drain(..excess);
+    }
+
+    // Move every spark along by `dt` game seconds and drop the ones that have burned out
+    pub fn update(&mut self, dt: f64) {
+        let slowdown = (-DRAG * dt).exp();
+        for particle in &mut self.particles {
+            particle.pos[0] += particle.velocity[0] * dt;
+            particle.pos[1] += particle.velocity[1] * dt;
+            particle.velocity = particle.velocity.map(|v| v * slowdown);
+            particle.age += dt;
+        }
+        self.particles.retain(|particle| particle.age < particle.life);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.particles.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Particle> {
+        self.particles.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_burst_starts_in_the_middle_of_the_cell() {
+        let mut particles = Particles::new(1);
+        particles.burst((3, 4));
+        assert_eq!(particles.iter().count(), BURST_SIZE);
+        assert!(particles.iter().all(|particle| particle.pos == [3.5, 4.5] && particle.remaining() == 1.0));
+    }
+
+    #[test]
+    fn sparks_fly_outwards_and_burn_out() {
+        let mut particles = Particles::new(1);
+        particles.burst((0, 0));
+        particles.update(0.1);
+        for particle in particles.iter() {
+            let distance = (particle.pos[0] - 0.5).hypot(particle.pos[1] - 0.5);
+            assert!(distance > 0.0 && distance < SPEED.end * 0.1, "{distance}");
+            assert!(particle.remaining() > 0.0 && particle.remaining() < 1.0);
+        }
+        particles.update(LIFETIME.end);
+        assert!(particles.is_empty());
+    }
+
+    #[test]
+    fn old_sparks_make_room_for_new_ones() {
+        let mut particles = Particles::new(1);
+        for _ in 0..MAX_PARTICLES {
+            particles.burst((0, 0));
+        }
+        particles.burst((5, 5));
+        assert_eq!(particles.iter().count(), MAX_PARTICLES);
+        assert_eq!(particles.iter().filter(|particle| particle.pos == [5.5, 5.5]).count(), BURST_SIZE);
+    }
+
+    #[test]
+    fn the_same_seed_throws_the_same_sparks() {
+        let positions = |seed| {
+            let mut particles = Particles::new(seed);
+            particles.burst((2, 2));
+            particles.update(0.2);
+            particles.iter().map(|particle| particle.pos).collect::<Vec<_>>()
+        };
+        assert_eq!(positions(7), positions(7));
+        assert_ne!(positions(7), positions(8));
+    }
+}
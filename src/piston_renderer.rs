@@ -0,0 +1,85 @@
+// The window's `Renderer`: Piston's 2D graphics for the shapes and images, and its glyph cache for the text
+
+use piston_window::{CharacterCache, Context, G2dTexture, Glyphs, Graphics, Image};
+use crate::renderer::{Color, Renderer, Transform};
+
+// Piston's backends take at most 1024 vertices per call; this is the largest whole number of triangles below that
+const CHUNK_VERTICES: usize = 1023;
+
+pub struct PistonRenderer<'a, G, F> {
+    context: Context,
+    graphics: &'a mut G,
+    glyphs: &'a mut Glyphs,
+    flush: F,
+}
+
+impl<'a, G, F> PistonRenderer<'a, G, F>
+where
+    G: Graphics<Texture = G2dTexture>,
+    F: FnMut(&mut Glyphs),
+{
+    // Draw one frame into what `draw_2d` hands over. Text is queued in `glyphs` and only drawn once
+    // `present` calls `flush` with it, which sends it to the device.
+    pub fn new(context: Context, graphics: &'a mut G, glyphs: &'a mut Glyphs, flush: F) -> Self {
+        PistonRenderer { context, graphics, glyphs, flush }
+    }
+}
+
+impl<G, F> Renderer for PistonRenderer<'_, G, F>
+where
+    G: Graphics<Texture = G2dTexture>,
+    F: FnMut(&mut Glyphs),
+{
+    type Texture = G2dTexture;
+
+    fn transform(&self) -> Transform {
+        self.context.transform
+    }
+
+    fn view_size(&self) -> [f64; 2] {
+        self.context.get_view_size()
+    }
+
+    fn clear(&mut self, color: Color) {
+        piston_window::clear(color, self.graphics);
+    }
+
+    fn rectangle(&mut self, color: Color, rect: [f64; 4], transform: Transform) {
+        piston_window::rectangle(color, rect, transform, self.graphics);
+    }
+
+    fn ellipse(&mut self, color: Color, rect: [f64; 4], transform: Transform) {
+        piston_window::ellipse(color, rect, transform, self.graphics);
+    }
+
+    fn polygon(&mut self, color: Color, points: &[[f64; 2]], transform: Transform) {
+        piston_window::polygon(color, points, transform, self.graphics);
+    }
+
+    fn triangles(&mut self, color: Color, vertices: &[[f32; 2]]) {
+        if vertices.is_empty() {
+            return;
+        }
+        self.graphics.tri_list(&Default::default(), &color, |f| {
+            for chunk in vertices.chunks(CHUNK_VERTICES) {
+                f(chunk);
+            }
+        });
+    }
+
+    fn draw_text(&mut self, color: Color, size: u32, text: &str, transform: Transform) {
+        piston_window::text(color, size, text, self.glyphs, transform, self.graphics).ok();
+    }
+
+    fn text_width(&mut self, size: u32, text: &str) -> f64 {
+        self.glyphs.width(size, text).unwrap_or(0.0)
+    }
+
+    fn image(&mut self, texture: &G2dTexture, source: [f64; 4], rect: [f64; 4], transform: Transform) {
+        Image::new().src_rect(source).rect(rect).draw(texture, &self.context.draw_state, transform, self.graphics);
+    }
+
+    fn present(&mut self) {
+        (self.flush)(self.glyphs);
+    }
+}
@@ -0,0 +1,224 @@
+// Recording runs as animated GIFs, with `--record`. Every move of a run is drawn into a picture through
+// `ImageRenderer`, the same way the window shows it, and handed to a thread of its own that encodes it as the
+// next frame of the GIF right away, so the game never waits on the encoder. When the run ends, the GIF is
+// finished and saved in RECORDINGS_DIR, named after the time and the score. A run left before it ends is
+// thrown away.
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::JoinHandle;
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, RgbaImage};
+use rusttype::Font;
+use crate::game::{Game, GameState};
+use crate::image_renderer::{load_font, load_sprites, ImageRenderer};
+use crate::leaderboard::timestamp;
+
+pub const RECORDINGS_DIR: &str = "recordings";
+const PARTIAL_FILE: &str = "recording.gif.part"; // The GIF being written, until the run ends
+const ENCODER_SPEED: i32 = 20; // From 1 (smallest file) to 30 (fastest); recordings are made while playing
+
+// What the encoding thread gets
+enum Job {
+    Frame(RgbaImage, Delay), // The next frame of the run, and how long it stays up
+    Finish(String), // The run ended; save it under this name
+    Discard, // The run was left before it ended
+}
+
+pub struct Recorder {
+    jobs: Option<Sender<Job>>, // Dropped to let the thread finish
+    encoder: Option<JoinHandle<()>>,
+    font: Font<'static>,
+    sprites: Option<RgbaImage>,
+    recording: bool, // A run is being recorded
+    moves: u32, // Moves made when the last frame was taken
+}
+
+impl Recorder {
+    // Record the runs of `game` from now on
+    pub fn new(game: &Game) -> Result<Recorder, String> {
+        Recorder::saving_in(game, Path::new(RECORDINGS_DIR))
+    }
+
+    // Record the runs of `game` from now on, saving them in `dir`
+    fn saving_in(game: &Game, dir: &Path) -> Result<Recorder, String> {
+        let font = load_font(game)?;
+        let (jobs, inbox) = mpsc::channel();
+        let dir = dir.to_path_buf();
+        let encoder = std::thread::spawn(move || encode(inbox, &dir));
+        Ok(Recorder { jobs: Some(jobs), encoder: Some(encoder), font, sprites: load_sprites(game), recording: false, moves: 0 })
+    }
+
+    // Take a frame when the snake moved, and finish or throw away the recording when the run is over;
+    // meant to be called every frame
+    pub fn capture(&mut self, game: &Game) {
+        match game.state {
+            GameState::Running if !self.recording || game.moves != self.moves => {
+                self.recording = true;
+                self.moves = game.moves;
+                let delay = game.settings.move_interval / game.time_scale / game.move_speed();
+                let frame = self.draw(game);
+                self.send(Job::Frame(frame, Delay::from_numer_denom_ms((delay * 1000.0).round() as u32, 1)));
+            },
+            GameState::GameOver | GameState::LevelComplete if self.recording => {
+                self.recording = false;
+                self.send(Job::Finish(format!("copperhead-{}-{}.gif", timestamp(), game.score)));
+            },
+            GameState::Menu if self.recording => {
+                self.recording = false;
+                self.send(Job::Discard);
+            },
+            _ => {},
+        }
+    }
+
+    // The game as the window shows it, at the board's own size
+    fn draw(&self, game: &Game) -> RgbaImage {
+        let mut renderer = ImageRenderer::new(game.settings.layout().window_size(), &self.font);
+        game.render(&mut renderer, self.sprites.as_ref());
+        renderer.into_image()
+    }
+
+    fn send(&self, job: Job) {
+        if let Some(jobs) = &self.jobs {
+            jobs.send(job).ok();
+        }
+    }
+}
+
+// A run still going when the game closes isn't kept, but one that ended is finished before the game exits
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        if self.recording {
+            self.send(Job::Discard);
+        }
+        self.jobs = None;
+        if let Some(encoder) = self.encoder.take() {
+            encoder.join().ok();
+        }
+    }
+}
+
+// Encode the frames of each run as they come in, into PARTIAL_FILE in `dir` until the run ends. A run that
+// can't be written is reported once and skipped.
+fn encode(inbox: Receiver<Job>, dir: &Path) {
+    let partial = dir.join(PARTIAL_FILE);
+    let mut gif: Option<GifEncoder<BufWriter<File>>> = None;
+    let mut failed = false;
+    for job in inbox {
+        match job {
+            Job::Frame(picture, delay) => {
+                if failed {
+                    continue;
+                }
+                if gif.is_none() {
+                    match start(dir, &partial) {
+                        Ok(encoder) => gif = Some(encoder),
+                        Err(err) => {
+                            eprintln!("copperhead: could not record the run to {}: {}", partial.display(), err);
+                            failed = true;
+                            continue;
+                        },
+                    }
+                }
+                if let Some(encoder) = gif.as_mut()
+                    && let Err(err) = encoder.encode_frame(Frame::from_parts(picture, 0, 0, delay))
+                {
+                    eprintln!("copperhead: could not record the run to {}: {}", partial.display(), err);
+                    gif = None;
+                    failed = true;
+                }
+            },
+            Job::Finish(name) => {
+                // Dropping the encoder writes the end of the GIF
+                let path = dir.join(name);
+                if gif.take().is_some() {
+                    match std::fs::rename(&partial, &path) {
+                        Ok(()) => eprintln!("copperhead: run recorded to {}", path.display()),
+                        Err(err) => eprintln!("copperhead: could not save the recording as {}: {}", path.display(), err),
+                    }
+                }
+                failed = false;
+            },
+            Job::Discard => {
+                if gif.take().is_some() {
+                    std::fs::remove_file(&partial).ok();
+                }
+                failed = false;
+            },
+        }
+    }
+}
+
+// A looping GIF written to `path` in `dir`
+fn start(dir: &Path, path: &Path) -> Result<GifEncoder<BufWriter<File>>, String> {
+    std::fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+    let file = File::create(path).map_err(|err| err.to_string())?;
+    let mut encoder = GifEncoder::new_with_speed(BufWriter::new(file), ENCODER_SPEED);
+    encoder.set_repeat(Repeat::Infinite).map_err(|err| err.to_string())?;
+    Ok(encoder)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::AnimationDecoder;
+    use image::codecs::gif::GifDecoder;
+    use crate::game::Settings;
+
+    // An empty folder of its own for the recordings of test `name`
+    fn recordings(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("copperhead-recordings-{}-{}", name, std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        dir
+    }
+
+    // The files in `dir`, by name
+    fn files(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = std::fs::read_dir(dir).map_or_else(|_| Vec::new(), |entries| entries.map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned()).collect());
+        names.sort();
+        names
+    }
+
+    // Record a run of `moves` moves, each frame captured twice, and end it in `end`
+    fn record(dir: &Path, moves: usize, end: GameState) -> Game {
+        let mut game = Game::new(&Settings::default());
+        game.foods.clear();
+        game.state = GameState::Running;
+        let mut recorder = Recorder::saving_in(&game, dir).expect("the bundled font loads");
+        for _ in 0..moves {
+            recorder.capture(&game);
+            recorder.capture(&game);
+            game.update();
+        }
+        recorder.capture(&game);
+        game.state = end;
+        game.score = 7;
+        recorder.capture(&game);
+        game
+    }
+
+    #[test]
+    fn a_finished_run_is_saved_with_a_frame_per_move() {
+        let dir = recordings("finished");
+        let game = record(&dir, 3, GameState::GameOver);
+        let saved = files(&dir);
+        assert!(saved.len() == 1 && saved[0].starts_with("copperhead-") && saved[0].ends_with("-7.gif"), "{saved:?}");
+        let gif = GifDecoder::new(std::io::BufReader::new(File::open(dir.join(&saved[0])).unwrap())).unwrap();
+        let frames = gif.into_frames().collect_frames().unwrap();
+        assert_eq!(frames.len(), 4);
+        let interval = (game.settings.move_interval * 1000.0).round() as u32;
+        assert!(frames.iter().all(|frame| frame.delay().numer_denom_ms() == (interval, 1)));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn a_run_left_before_it_ends_is_thrown_away() {
+        let dir = recordings("left");
+        record(&dir, 2, GameState::Menu);
+        assert!(files(&dir).is_empty());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,1692 @@
+// Drawing the game: the window renderer, through any `Renderer` backend, and the terminal one
+
+use crate::renderer::{rounded_rect_outline, transform_pos, Renderer, Transform, Transformed, CELL_ROUNDING};
+use crate::game::{Action, BoardAlign, BoardLayout, CellShape, ControlScheme, FoodKind, Game, GameState, Players, PowerUp, MAX_COMBO, PHASE_FLASH_MOVES, POWER_UP_MOVES, REWIND_SECONDS, TOAST_SECONDS};
+use crate::editor::EDITOR_FILE;
+use crate::leaderboard::INITIALS_LEN;
+use crate::level::LEVELS_DIR;
+use crate::snake::{Direction, Snake};
+use crate::input::key_label;
+use crate::net::Side;
+use crate::theme::{with_alpha, Skin, Theme};
+
+const SHADOW_OFFSET: f64 = 0.06; // How far drop shadows fall down and to the right, as a fraction of the cell size
+const HUD_SCORE_DIGITS: usize = 4; // Room reserved for the score, so it stays put as digits are added
+const HUD_CLOCK_CHARS: usize = 5; // Room reserved for the time attack clock ("999.9")
+const SPRITE_SIZE: f64 = 64.0; // Pixels per tile in the sprite atlas, which has its tiles side by side
+const PORTAL_PULSE: f64 = 1.2; // Times per second a portal's ring swells and shrinks back
+const PORTAL_SPIN: f64 = 0.75; // Turns per second of the spark circling inside a portal
+const ASCII_PORTALS: [&str; 10] = ["@0", "@1", "@2", "@3", "@4", "@5", "@6", "@7", "@8", "@9"];
+
+// How a snake is drawn: from the sprite atlas, or as shapes in a skin's colors
+pub enum SnakeLook<'a, T> {
+    Sprites(&'a T),
+    Shapes(Skin),
+}
+
+impl<'a, T> SnakeLook<'a, T> {
+    // Player one's look: the atlas when there is one, the theme's skin otherwise. The atlas only has copper
+    // pieces, so player two is always drawn from shapes.
+    fn player_one(sprites: Option<&'a T>, theme: &Theme) -> Self {
+        sprites.map_or(SnakeLook::Shapes(theme.player_one), SnakeLook::Sprites)
+    }
+}
+
+impl Game {
+    // Draw the current screen; `sprites` is the sprite atlas, when the snake and the food are drawn from it
+    pub fn render<R: Renderer>(&self, g: &mut R, sprites: Option<&R::Texture>) {
+        let theme = self.settings.theme;
+
+        // Everything else is drawn relative to the board's top left corner; the rest of the window is letterbox
+        let layout = self.settings.layout();
+        let [win_w, win_h] = layout.window_size().map(f64::from);
+        g.clear(theme.letterbox);
+        let (origin, scale) = self.board_placement(g.view_size());
+        let transform = g.transform().trans(origin[0], origin[1]).scale(scale, scale);
+        let board = [0.0, 0.0, win_w, win_h];
+        g.rectangle(theme.board, board, transform);
+
+        // On the start screen the demo, once it plays, is drawn on the board behind the title
+        let shown = match (&self.demo, &self.state) {
+            (Some(demo), GameState::Menu) => demo.as_ref(),
+            _ => self,
+        };
+
+        // Shadows go first, so the border cuts off whatever reaches past the edge of the grid
+        let in_run = matches!(shown.state, GameState::Running | GameState::Paused);
+        if shown.settings.shadows && in_run {
+            let [px, py] = layout.playfield_origin();
+            shown.render_shadows(transform.trans(px, py), g);
+        }
+
+        // Draw dark border AROUND the playfield (outside the grid, not overlapping any cell)
+        for rect in layout.border_rects() {
+            g.rectangle(theme.border, rect, transform);
+        }
+
+        // Thick enough for the score text
+        let border_height = layout.playfield_origin()[1];
+
+        // Shift playfield drawing so the grid is inside the border
+        let [px, py] = layout.playfield_origin();
+        let playfield_transform = transform.trans(px, py);
+        if in_run {
+            for food in &shown.foods {
+                // Timed food blinks through its last second
+                if food.moves_left.is_some_and(|moves| moves % 2 == 1 && moves as f64 * shown.settings.move_interval < 1.0) {
+                    continue;
+                }
+                let food_square = layout.square(food.pos);
+                match (sprites, shown.settings.cell_shape) {
+                    (Some(atlas), _) => draw_sprite(atlas, Sprite::Food(food.kind), food_square, 0.0, false, playfield_transform, g),
+                    (None, CellShape::Rounded { round_food: true }) => g.ellipse(theme.food_color(food.kind), food_square, playfield_transform),
+                    (None, shape) => g.draw_cell(theme.food_color(food.kind), food_square, shape, playfield_transform),
+                }
+                // A bar along the bottom of timed food shrinks as its time runs out
+                if let Some(moves) = food.moves_left {
+                    let [left, top, size, _] = food_square;
+                    let width = size * moves as f64 / shown.golden_food_moves() as f64;
+                    g.rectangle(theme.border, [left, top + size * 0.85, width, size * 0.15], playfield_transform);
+                }
+            }
+            shown.render_obstacles(playfield_transform, g);
+            draw_portals(&shown.portals, layout, shown.elapsed, &theme, playfield_transform, g);
+            shown.render_dead_zone(playfield_transform, g);
+            shown.render_phase(playfield_transform, g);
+            if let Some((power_up, pos)) = shown.power_up {
+                let [x, y, size, _] = layout.square(pos);
+                let inset = size * 0.1;
+                draw_power_up(power_up, [x + inset, y + inset, size - 2.0 * inset, size - 2.0 * inset], &theme, playfield_transform, g);
+            }
+            shown.render_wrap_flash(playfield_transform, g);
+            shown.snake.render(playfield_transform, g, shown.settings.cell_shape, layout.cell as f64, shown.snake_progress(), SnakeLook::player_one(sprites, &theme));
+            if let Some(rival) = &shown.rival {
+                rival.snake.render(playfield_transform, g, shown.settings.cell_shape, layout.cell as f64, shown.snake_progress(), SnakeLook::Shapes(theme.player_two));
+            }
+            shown.render_particles(playfield_transform, g);
+            if shown.settings.assist_arrows {
+                shown.render_assist_arrows(playfield_transform, g);
+            }
+        }
+
+        // Draw overlays
+        let text_color = theme.text;
+
+        match self.state {
+            GameState::Menu if self.editor.is_some() => self.render_editor(transform, g, sprites),
+            GameState::Menu => {
+                let title = "COPPERHEAD";
+                let title_width = g.text_width(self.font_size(48), title);
+                let win_center_x = win_w / 2.0;
+                let win_center_y = win_h / 2.0;
+                g.draw_text(text_color, self.font_size(48), title, transform.trans(win_center_x - title_width / 2.0, win_center_y - 60.0));
+
+                // Dim the demo so the title stands out, or draw a preview of the snake under the title
+                if let Some(demo) = &self.demo {
+                    g.rectangle([0.0, 0.0, 0.0, 0.35], board, transform);
+                    let label = format!("DEMO  {}", demo.score);
+                    let label_width = g.text_width(self.font_size(24), &label);
+                    g.draw_text(text_color, self.font_size(24), &label, transform.trans(win_center_x - label_width / 2.0, border_height * 0.75));
+                } else {
+                    draw_snake_preview(&self.settings.start_dir, self.settings.cell_shape, layout, sprites, &theme, transform, g);
+                }
+
+                // The menu goes under the snake preview, one item per line. The selected item is highlighted,
+                // with arrows around it when left and right change its value.
+                let mut y = win_center_y + layout.cell as f64 + 50.0;
+                for &item in self.menu.items() {
+                    let selected = self.menu.is_selected(item);
+                    let label = match self.menu_label(item) {
+                        label if selected && item.has_value() => format!("< {} >", label),
+                        label if selected => format!("> {} <", label),
+                        label => label,
+                    };
+                    let color = if selected { theme.record } else { text_color };
+                    let width = g.text_width(self.font_size(20), &label);
+                    g.draw_text(color, self.font_size(20), &label, transform.trans(win_center_x - width / 2.0, y));
+                    y += 28.0;
+                }
+                // A campaign is played level by level, starting from the one shown here
+                if let Some(level) = self.current_level() {
+                    let line = format!("Level {} of {}: {}, score {} to clear", self.level + 1, self.settings.campaign.len(), level.name, level.target);
+                    let width = g.text_width(self.font_size(16), &line);
+                    g.draw_text(text_color, self.font_size(16), &line, transform.trans(win_center_x - width / 2.0, y + 8.0));
+                }
+            },
+            GameState::Running => {
+                // The score turns gold once it's a new record
+                let record_color = theme.record;
+                let score_color = if self.beat_high { record_color } else { text_color };
+
+                // The font is monospaced, so readouts get a fixed number of character cells and never
+                // shift around as digits come and go. The score is left-aligned in a centered slot.
+                let advance = g.text_width(self.font_size(24), "0");
+                if let Some(rival) = &self.rival {
+                    // Two players: each score in its snake's color, player one's right-aligned to the left
+                    // of the middle and player two's left-aligned to the right of it
+                    let one = format!("{:>width$}", self.score, width = HUD_SCORE_DIGITS);
+                    let one_left = win_w / 2.0 - advance * (HUD_SCORE_DIGITS as f64 + 1.0);
+                    g.draw_text(theme.player_one.head, self.font_size(24), &one, transform.trans(one_left, border_height * 0.75));
+                    let two = rival.score.to_string();
+                    g.draw_text(theme.player_two.head, self.font_size(24), &two, transform.trans(win_w / 2.0 + advance, border_height * 0.75));
+                } else {
+                    let score_left = win_w / 2.0 - advance * HUD_SCORE_DIGITS as f64 / 2.0;
+                    let score_str = self.score.to_string();
+                    g.draw_text(score_color, self.font_size(24), &score_str, transform.trans(score_left, border_height * 0.75));
+
+                    // While a combo window is open, the multiplier follows the score slot, with a bar under it
+                    // for the moves left to keep it going
+                    if self.combo_moves > 0 {
+                        let combo_color = theme.combo;
+                        let combo_left = score_left + advance * (HUD_SCORE_DIGITS as f64 + 0.5);
+                        let combo_str = format!("x{}", self.combo);
+                        g.draw_text(combo_color, self.font_size(16), &combo_str, transform.trans(combo_left, border_height * 0.7));
+                        let combo_width = g.text_width(self.font_size(16), &combo_str);
+                        let left = self.combo_moves as f64 / self.settings.scoring.combo_window as f64;
+                        g.rectangle(combo_color, [combo_left, border_height * 0.7 + 3.0, combo_width * left, 2.0], transform);
+                    }
+                }
+
+                // Time attack countdown in the top right, right-aligned in its slot, turning red for the last ten seconds
+                if let Some(left) = self.time_left {
+                    let clock_color = if left < 10.0 { theme.danger } else { text_color };
+                    let clock_str = format!("{:>width$.1}", left, width = HUD_CLOCK_CHARS);
+                    let clock_left = win_w - border_height - advance * HUD_CLOCK_CHARS as f64;
+                    g.draw_text(clock_color, self.font_size(24), &clock_str, transform.trans(clock_left, border_height * 0.75));
+                }
+
+                // With decaying food, each food's current value sits on it in small print
+                if self.settings.scoring.food_decay.is_some() {
+                    let value_size = self.font_size(12);
+                    let [px, py] = layout.playfield_origin();
+                    for food in &self.foods {
+                        let value = self.food_value(food, self.moves).to_string();
+                        let value_width = g.text_width(value_size, &value);
+                        let [left, top, size, _] = layout.square(food.pos);
+                        let x = px + left + size / 2.0 - value_width / 2.0;
+                        let y = py + top - 2.0;
+                        g.draw_text(text_color, value_size, &value, transform.trans(x, y));
+                    }
+                }
+
+                // Phase charges in the top left, lit up while one is armed
+                if self.phase_charges > 0 {
+                    let phase_color = if self.phase_armed { theme.phase } else { text_color };
+                    let phase_str = format!("PHASE x{}", self.phase_charges);
+                    g.draw_text(phase_color, self.font_size(16), &phase_str, transform.trans(border_height, border_height * 0.7));
+                }
+
+                // Game speed in the bottom left, when it isn't normal
+                if self.time_scale != 1.0 {
+                    let speed_str = format!("SPEED {}x", self.time_scale);
+                    g.draw_text(text_color, self.font_size(16), &speed_str, transform.trans(border_height, win_h - border_height * 0.3));
+                }
+
+                // Power-ups in effect in the bottom right, each with a bar for the time it has left
+                let icon = border_height * 0.5;
+                for (i, &(power_up, moves)) in self.effects.iter().enumerate() {
+                    let x = win_w - border_height - icon - i as f64 * icon * 1.5;
+                    let y = win_h - border_height * 0.85;
+                    draw_power_up(power_up, [x, y, icon, icon], &theme, transform, g);
+                    let left = moves as f64 / POWER_UP_MOVES as f64;
+                    g.rectangle(text_color, [x, y + icon + 2.0, icon * left, 3.0], transform);
+                }
+
+                // Warn that the next move is the last one, when the game doesn't end that by itself
+                if self.is_trapped() {
+                    let trapped = "TRAPPED";
+                    let trapped_width = g.text_width(self.font_size(16), trapped);
+                    g.draw_text(theme.danger, self.font_size(16), trapped, transform.trans(win_w / 2.0 - trapped_width / 2.0, win_h - border_height * 0.3));
+                }
+
+                // Flash a banner right after the old record falls
+                if self.celebration_moves % 2 == 1 {
+                    let banner = "NEW HIGH SCORE!";
+                    let banner_width = g.text_width(self.font_size(32), banner);
+                    g.draw_text(record_color, self.font_size(32), banner, transform.trans(win_w / 2.0 - banner_width / 2.0, border_height + 48.0));
+                }
+
+                // Before the snake goes, the seconds left count down big on the lightly dimmed board, above
+                // the middle where the snake starts
+                if let Some(number) = self.countdown_number() {
+                    g.rectangle([0.0, 0.0, 0.0, 0.25], board, transform);
+                    let number = number.to_string();
+                    let number_width = g.text_width(self.font_size(96), &number);
+                    g.draw_text(text_color, self.font_size(96), &number, transform.trans(win_w / 2.0 - number_width / 2.0, win_h / 3.0 + 32.0));
+                }
+            },
+            GameState::Paused => {
+                // Dim the frozen board so the text stands out, but leave it visible
+                g.rectangle([0.0, 0.0, 0.0, 0.45], board, transform);
+                let paused = "PAUSED";
+                let paused_width = g.text_width(self.font_size(48), paused);
+                g.draw_text(text_color, self.font_size(48), paused, transform.trans(win_w / 2.0 - paused_width / 2.0, win_h / 2.0));
+                let hint = "P or space to resume, Esc to quit";
+                let hint_width = g.text_width(self.font_size(20), hint);
+                g.draw_text(text_color, self.font_size(20), hint, transform.trans(win_w / 2.0 - hint_width / 2.0, win_h / 2.0 + 40.0));
+            },
+            GameState::LevelComplete => {
+                // The cleared board stays in view, dimmed, with the walls, portals and the snake as they ended
+                self.render_obstacles(playfield_transform, g);
+                draw_portals(&self.portals, layout, self.elapsed, &theme, playfield_transform, g);
+                self.snake.render(playfield_transform, g, self.settings.cell_shape, layout.cell as f64, 1.0, SnakeLook::player_one(sprites, &theme));
+                g.rectangle([0.0, 0.0, 0.0, 0.45], board, transform);
+
+                let (headline, prompt) = if self.campaign_won() {
+                    ("CAMPAIGN COMPLETE!", "Space: play again from level 1".to_string())
+                } else {
+                    let next = &self.settings.campaign[self.level + 1];
+                    ("LEVEL COMPLETE!", format!("Space: level {}, {}", self.level + 2, next.name))
+                };
+                let headline_width = g.text_width(self.font_size(48), headline);
+                g.draw_text(text_color, self.font_size(48), headline, transform.trans(win_w / 2.0 - headline_width / 2.0, win_h / 2.0 - 20.0));
+                let cleared = match self.current_level() {
+                    Some(level) => format!("{}: {} points", level.name, self.score),
+                    None => String::new(),
+                };
+                for (line, y) in [(cleared, win_h / 2.0 + 20.0), (prompt, win_h / 2.0 + 50.0)] {
+                    let line_width = g.text_width(self.font_size(20), &line);
+                    g.draw_text(text_color, self.font_size(20), &line, transform.trans(win_w / 2.0 - line_width / 2.0, y));
+                }
+            },
+            GameState::GameOver => {
+                // Red-tinted background for game over
+                g.rectangle(theme.game_over, board, transform);
+
+                // Draw playfield and snake in final position (no food)
+                for rect in layout.border_rects() {
+                    g.rectangle(theme.border, rect, transform);
+                }
+                self.render_obstacles(playfield_transform, g);
+                draw_portals(&self.portals, layout, self.elapsed, &theme, playfield_transform, g);
+                self.snake.render(playfield_transform, g, self.settings.cell_shape, layout.cell as f64, 1.0, SnakeLook::player_one(sprites, &theme));
+                if let Some(rival) = &self.rival {
+                    rival.snake.render(playfield_transform, g, self.settings.cell_shape, layout.cell as f64, 1.0, SnakeLook::Shapes(theme.player_two));
+                }
+
+                // Overlay text
+                let (over, cause) = self.outcome();
+                let line_size = self.font_size(20);
+                let centered_line = |color: [f32; 4], line: &str, y: f64, g: &mut R| {
+                    let x = win_w / 2.0 - g.text_width(line_size, line) / 2.0;
+                    g.draw_text(color, line_size, line, transform.trans(x, y));
+                };
+
+                if self.can_rewind() {
+                    let rewind = format!("REWIND? Press R to go back {} seconds ({} left)", REWIND_SECONDS, self.rewinds_left);
+                    centered_line(theme.record, &rewind, win_h / 2.0 - 175.0, g);
+                }
+                let over_width = g.text_width(self.font_size(48), over);
+                g.draw_text(text_color, self.font_size(48), over, transform.trans(win_w / 2.0 - over_width / 2.0, win_h / 2.0 - 120.0));
+                centered_line(text_color, cause, win_h / 2.0 - 85.0, g);
+
+                // Results as two columns; the font is monospaced, so padding every line to the same
+                // length lines the columns up
+                let mut y = win_h / 2.0 - 40.0;
+                for (label, value) in self.results() {
+                    let line = format!("{:<15}{:>22}", label, value);
+                    centered_line(text_color, &line, y, g);
+                    y += 26.0;
+                }
+
+                // A run that makes the top 10 asks for initials before anything else
+                if let Some(initials) = &self.initials {
+                    let entry = format!("TOP 10! Your initials: {:_<width$}", initials, width = INITIALS_LEN);
+                    centered_line(theme.record, &entry, win_h / 2.0 + 170.0, g);
+                } else {
+                    let prompt = "Space: restart   C: copy results";
+                    centered_line(text_color, prompt, win_h / 2.0 + 170.0, g);
+                }
+
+                // Heatmap hint, replaced by the outcome once an export or copy was attempted
+                let notice = match &self.initials {
+                    Some(_) => "Type up to 3 letters, Enter to save, Esc to skip",
+                    None => self.notice.as_deref().unwrap_or("Press E to save a heatmap of this run"),
+                };
+                let notice_width = g.text_width(self.font_size(16), notice);
+                g.draw_text(text_color, self.font_size(16), notice, transform.trans(win_w / 2.0 - notice_width / 2.0, win_h / 2.0 + 200.0));
+            }
+        }
+
+        if self.leaderboard_shown && self.global_shown {
+            self.render_overlay("GLOBAL TOP 10", &self.global_lines(), transform, g);
+        } else if self.leaderboard_shown {
+            self.render_overlay("TOP 10", &self.leaderboard_lines(), transform, g);
+        }
+        if self.achievements_shown {
+            self.render_overlay("ACHIEVEMENTS", &self.achievement_lines(), transform, g);
+        }
+        if self.stats_shown {
+            self.render_overlay("STATS", &self.stats_lines(), transform, g);
+        }
+        if self.debug_shown {
+            self.render_debug(shown, transform, g);
+        }
+        self.render_toast(transform, g);
+        if self.help_shown {
+            self.render_overlay("HELP", &self.help_lines(), transform, g);
+        }
+    }
+
+    // Where the board goes in a window whose drawable area is `view` big: its top left corner, and how much
+    // it is scaled up. Fullscreen scales the board up to fill as much of the screen as it can.
+    fn board_placement(&self, view: [f64; 2]) -> ([f64; 2], f64) {
+        let [win_w, win_h] = self.settings.layout().window_size().map(f64::from);
+        let scale = if self.settings.fullscreen { (view[0] / win_w).min(view[1] / win_h).max(1.0) } else { 1.0 };
+        (board_origin(view, [win_w * scale, win_h * scale], self.settings.board_align), scale)
+    }
+
+    // The board cell under `pos` in a window whose drawable area is `view` big, or None off the grid
+    pub fn cell_at(&self, view: [f64; 2], pos: [f64; 2]) -> Option<(i32, i32)> {
+        let (origin, scale) = self.board_placement(view);
+        let layout = self.settings.layout();
+        let [px, py] = layout.playfield_origin();
+        let size = layout.cell as f64;
+        let cell = (((pos[0] - origin[0]) / scale - px) / size, ((pos[1] - origin[1]) / scale - py) / size);
+        let cell = (cell.0.floor() as i32, cell.1.floor() as i32);
+        layout.index(cell).map(|_| cell)
+    }
+
+    // The level editor in place of the title: the walls painted so far, the snake where it will start, and
+    // a frame around the cell at the cursor
+    fn render_editor<R: Renderer>(&self, transform: Transform, g: &mut R, sprites: Option<&R::Texture>) {
+        let Some(editor) = &self.editor else {
+            return;
+        };
+        let layout = self.settings.layout();
+        let [win_w, win_h] = layout.window_size().map(f64::from);
+        let [px, py] = layout.playfield_origin();
+        let playfield_transform = transform.trans(px, py);
+        let mut walls = Batch::new(self.settings.theme.border);
+        for &pos in &editor.level.walls {
+            walls.cell(layout.square(pos), self.settings.cell_shape, transform);
+        }
+        walls.draw(g);
+        draw_portals(&editor.level.portals, layout, 0.0, &self.settings.theme, transform, g);
+        editor.snake().render(playfield_transform, g, self.settings.cell_shape, layout.cell as f64, 1.0, SnakeLook::player_one(sprites, &self.settings.theme));
+
+        let [left, top, cell, _] = layout.square(editor.cursor);
+        let edge = (cell * 0.1).max(2.0);
+        let mut frame = Batch::new(with_alpha(self.settings.theme.glint, 0.9));
+        for rect in [[left, top, cell, edge], [left, top + cell - edge, cell, edge], [left, top, edge, cell], [left + cell - edge, top, edge, cell]] {
+            frame.rectangle(rect, transform);
+        }
+        frame.draw(g);
+
+        // What is being edited along the top, the last save or load or a hint along the bottom
+        let text_color = self.settings.theme.text;
+        let heading = format!("EDITOR  {}, score {} to clear", editor.level.name, editor.level.target);
+        let footer = editor.notice.clone().unwrap_or_else(|| "H: editor keys   Esc: back".to_string());
+        for (line, size, y) in [(heading, self.font_size(20), py * 0.75), (footer, self.font_size(16), win_h - py * 0.3)] {
+            let width = g.text_width(size, &line);
+            g.draw_text(text_color, size, &line, transform.trans(win_w / 2.0 - width / 2.0, y));
+        }
+    }
+
+    // Contents of the help overlay: the goal, every key, and the options that change how this game plays.
+    // Lines are kept short enough for the terminal board as well.
+    fn help_lines(&self) -> Vec<String> {
+        if self.editor.is_some() {
+            return vec![
+                "Paint a level for the campaign. It is".to_string(),
+                format!("saved as {}/{}.", LEVELS_DIR, EDITOR_FILE),
+                String::new(),
+                "Arrows    Move the cursor".to_string(),
+                "Mouse     Point the cursor".to_string(),
+                "Space     Wall on / off".to_string(),
+                "Left btn  Hold to put walls down".to_string(),
+                "Right btn Hold to take walls away".to_string(),
+                "P         Snake here / turn it".to_string(),
+                "[ / ]     Target score down / up".to_string(),
+                "C         Clear all walls".to_string(),
+                "S / L     Save / load".to_string(),
+                "H / F1    Show / hide this help".to_string(),
+                "Esc       Back to the title".to_string(),
+            ];
+        }
+        let mut lines = vec![
+            "Eat food to grow and score. Don't hit".to_string(),
+            "the walls, the rocks or yourself.".to_string(),
+            String::new(),
+            match self.settings.control_scheme {
+                ControlScheme::Absolute => format!("{:<10}Steer", self.steering_keys()),
+                ControlScheme::Relative => format!("{:<10}Turn left / right", format!("{} / {}", self.first_key(Action::Left), self.first_key(Action::Right))),
+            },
+            format!("{:<10}Pick on the menu / restart", self.first_key(Action::Restart)),
+            format!("{:<10}Pause / resume", self.first_key(Action::Pause)),
+            "Enter     Pick on the menu".to_string(),
+            format!("{:<10}Choose on the menu", format!("{} / {}", self.first_key(Action::Up), self.first_key(Action::Down))),
+            format!("{:<10}Change a choice", format!("{} / {}", self.first_key(Action::Left), self.first_key(Action::Right))),
+            "F         Arm a phase charge".to_string(),
+            "1 2 3 4   Speed 0.25x 0.5x 1x 2x".to_string(),
+            "M / - / + Mute / music volume".to_string(),
+        ];
+        if self.settings.rewinds > 0 {
+            lines.push(format!("R         Rewind {}s after dying", REWIND_SECONDS));
+        }
+        lines.push("C         Copy results after a run".to_string());
+        lines.push("E         Save a heatmap after a run".to_string());
+        lines.push("H / F1    Show / hide this help".to_string());
+        lines.push("F3        Debug overlay".to_string());
+        lines.push("F11       Fullscreen (window only)".to_string());
+        lines.push("F12       Screenshot (window only)".to_string());
+        lines.push("Esc       Close help / pause / quit".to_string());
+        lines.push(String::new());
+
+        lines.push(self.settings.wall_mode.describe());
+        if let Some(limit) = self.settings.time_limit {
+            lines.push(format!("Time attack: {}s", limit));
+        }
+        if let Some(level) = self.current_level() {
+            lines.push(format!("Campaign: level {} of {}, {}", self.level + 1, self.settings.campaign.len(), level.name));
+        }
+        if let Some(secs) = self.settings.arena_shrink {
+            lines.push(format!("Shrinking arena: walls close in every {}s", secs));
+        }
+        if let Some(every) = self.settings.food_wander {
+            lines.push(format!("Wandering food: a step every {} moves", every));
+        }
+        if self.settings.wall_density > 0.0 {
+            lines.push(format!("Random walls: {:.0}% of the board", self.settings.wall_density * 100.0));
+        }
+        if self.settings.portal_pairs > 0 {
+            lines.push(format!("Portals: {} random pairs", self.settings.portal_pairs));
+        }
+        if self.settings.food_count > 1 || !self.settings.placed_food.is_empty() {
+            lines.push(format!("Food: {} at a time, {} placed", self.settings.food_count, self.settings.placed_food.len()));
+        }
+        if self.settings.end_when_trapped {
+            lines.push("Runs end when the snake is trapped".to_string());
+        }
+        if self.settings.scoring.combo_window > 0 {
+            lines.push(format!("Combo: food within {} moves, up to x{}", self.settings.scoring.combo_window, MAX_COMBO));
+        }
+        if let Some(decay) = self.settings.scoring.food_decay {
+            lines.push(format!("Food: {} decaying to {}, half-life {}", decay.max, decay.floor, decay.half_life));
+        }
+        match self.settings.net_side {
+            Some(Side::Host) => lines.push("Network match: you are player one".to_string()),
+            Some(Side::Guest) => lines.push("Network match: you are player two".to_string()),
+            None if self.rival.is_some() => lines.push(self.settings.players.describe().to_string()),
+            None => {},
+        }
+        if self.settings.power_ups {
+            lines.push("Power-ups: speed, slow-mo, ghost, shrink".to_string());
+        }
+        if !self.settings.mods.is_empty() {
+            let names: Vec<&str> = self.settings.mods.iter().map(|m| m.name.as_str()).collect();
+            lines.push(format!("Mods: {}", names.join(", ")));
+        }
+        if self.online.is_some() {
+            lines.push(format!("Global leaderboard as {} (L twice)", self.settings.player_name));
+        }
+        lines.push(format!("Speed: {}x", self.time_scale));
+        lines
+    }
+
+    // How the help names the first key bound to `action`
+    fn first_key(&self, action: Action) -> String {
+        self.settings.key_bindings.keys(action).first().map_or_else(String::new, |name| key_label(name))
+    }
+
+    // The steering keys in a few characters: "Arrows" when the arrows steer, then one more key per direction
+    // in up, left, down, right order (so the defaults read "Arrows/WASD")
+    fn steering_keys(&self) -> String {
+        let bindings = &self.settings.key_bindings;
+        let steering = [(Action::Up, "up"), (Action::Left, "left"), (Action::Down, "down"), (Action::Right, "right")];
+        let arrows = steering.iter().all(|&(action, arrow)| bindings.keys(action).iter().any(|name| name == arrow));
+        let others: Vec<String> = steering
+            .iter()
+            .filter_map(|&(action, arrow)| bindings.keys(action).iter().find(|name| *name != arrow).map(|name| key_label(name)))
+            .collect();
+        match (arrows, others.len() == steering.len()) {
+            (true, true) => format!("Arrows/{}", others.concat()),
+            (true, false) => "Arrows".to_string(),
+            (false, _) => steering.iter().map(|&(action, _)| self.first_key(action)).collect::<Vec<_>>().join(" "),
+        }
+    }
+
+    // Translucent panel with a title and left-aligned lines (the help, the leaderboard), drawn over whatever screen is showing
+    fn render_overlay<R: Renderer>(&self, title: &str, lines: &[String], transform: Transform, g: &mut R) {
+        let text_color = self.settings.theme.text;
+        let [win_w, win_h] = self.settings.layout().window_size().map(f64::from);
+        g.rectangle([0.0, 0.0, 0.0, 0.8], [0.0, 0.0, win_w, win_h], transform);
+
+        let title_width = g.text_width(self.font_size(32), title);
+        g.draw_text(text_color, self.font_size(32), title, transform.trans(win_w / 2.0 - title_width / 2.0, 90.0));
+
+        // The font is monospaced, so the widest line decides where the left-aligned block starts
+        let size = self.font_size(16);
+        let block_width = lines.iter().map(|line| g.text_width(size, line)).fold(0.0, f64::max);
+        let mut y = 140.0;
+        for line in lines {
+            g.draw_text(text_color, size, line, transform.trans(win_w / 2.0 - block_width / 2.0, y));
+            y += 22.0 * self.settings.ui_scale;
+        }
+    }
+
+    // The oldest achievement unlocked lately, announced in a box along the bottom of the board. It fades out
+    // over its last half second.
+    fn render_toast<R: Renderer>(&self, transform: Transform, g: &mut R) {
+        let Some(achievement) = self.toasts.front() else {
+            return;
+        };
+        let layout = self.settings.layout();
+        let [win_w, win_h] = layout.window_size().map(f64::from);
+        let [_, py] = layout.playfield_origin();
+        let fade = ((TOAST_SECONDS - self.toast_time) * 2.0).clamp(0.0, 1.0) as f32;
+        let headline = format!("Achievement unlocked: {}", achievement.title());
+        let size = self.font_size(16);
+        let line_height = 22.0 * self.settings.ui_scale;
+        let width = [headline.as_str(), achievement.description()].iter().map(|line| g.text_width(size, line)).fold(0.0, f64::max);
+        let (box_w, box_h) = (width + 24.0, line_height * 2.0 + 16.0);
+        let (left, top) = (win_w / 2.0 - box_w / 2.0, win_h - py - box_h - 12.0);
+        draw_rounded_rect(with_alpha(self.settings.theme.panel, 0.9 * fade), [left, top, box_w, box_h], 8.0, transform, g);
+        let gold = with_alpha(self.settings.theme.record, fade);
+        let plain = with_alpha(self.settings.theme.text, fade);
+        for (i, (line, color)) in [(headline.as_str(), gold), (achievement.description(), plain)].into_iter().enumerate() {
+            let x = win_w / 2.0 - g.text_width(size, line) / 2.0;
+            g.draw_text(color, size, line, transform.trans(x, top + 8.0 + line_height * (i as f64 + 0.8)));
+        }
+    }
+
+    // Frame rates and the state of `shown`, the game on the board, in the top left corner of the playfield
+    fn render_debug<R: Renderer>(&self, shown: &Game, transform: Transform, g: &mut R) {
+        let times = self.frame_times;
+        let head = shown.snake.head();
+        let foods: Vec<String> = shown.foods.iter().map(|food| format!("{},{}", food.pos.0, food.pos.1)).collect();
+        let lines = [
+            format!("FPS  {:.1}", times.fps),
+            format!("UPS  {:.1}", times.ups),
+            format!("Tick {:.3} ms", times.tick_ms),
+            format!("Len  {}", shown.snake.body().len()),
+            format!("Head {},{}", head.0, head.1),
+            format!("Food {}", if foods.is_empty() { "-".to_string() } else { foods.join(" ") }),
+        ];
+        let size = self.font_size(12);
+        let line_height = 16.0 * self.settings.ui_scale;
+        let width = lines.iter().map(|line| g.text_width(size, line)).fold(0.0, f64::max);
+        let [px, py] = self.settings.layout().playfield_origin();
+        g.rectangle([0.0, 0.0, 0.0, 0.6], [px, py, width + 12.0, line_height * lines.len() as f64 + 8.0], transform);
+        for (i, line) in lines.iter().enumerate() {
+            g.draw_text([0.55, 1.0, 0.55, 1.0], size, line, transform.trans(px + 6.0, py + 4.0 + line_height * (i as f64 + 0.8)));
+        }
+    }
+
+    // Text size after applying the UI scale; measuring and drawing must both go through this to stay centered
+    fn font_size(&self, base: u32) -> u32 {
+        (base as f64 * self.settings.ui_scale).round() as u32
+    }
+
+    // Rocks share the border's dark color, so it's clear they are just as deadly. The wall ring is drawn here too.
+    fn render_obstacles<R: Renderer>(&self, transform: Transform, g: &mut R) {
+        let layout = self.settings.layout();
+        let mut rocks = Batch::new(self.settings.theme.border);
+        for &pos in &self.obstacles {
+            rocks.cell(layout.square(pos), self.settings.cell_shape, transform);
+        }
+        rocks.draw(g);
+
+        // The wall ring is a row of dark bricks, so it reads as part of the border rather than as loose rocks
+        let (mut bricks, mut mortar) = (Batch::new(self.settings.theme.bricks), Batch::new(self.settings.theme.border));
+        for y in 0..layout.grid.1 {
+            for x in 0..layout.grid.0 {
+                if layout.in_ring((x, y)) {
+                    let [left, top, cell, _] = layout.square((x, y));
+                    mortar.rectangle([left, top, cell, cell], transform);
+                    bricks.rectangle([left + 2.0, top + 2.0, cell - 4.0, cell - 4.0], transform);
+                }
+            }
+        }
+        mortar.draw(g);
+        bricks.draw(g);
+    }
+
+    // The rings a shrinking arena has closed are a dull red, darker than the board, and the next ring to
+    // go blinks red for the last seconds before it closes
+    fn render_dead_zone<R: Renderer>(&self, transform: Transform, g: &mut R) {
+        if self.settings.arena_shrink.is_none() {
+            return;
+        }
+        let (configured, arena) = (self.settings.layout(), self.layout());
+        let warned = self.ring_closing() && self.shrink_in.is_some_and(|moves| moves % 2 == 0);
+        let next = arena.shrunk(1);
+        let (mut dead, mut warning) = (Batch::new(self.settings.theme.dead_zone), Batch::new(with_alpha(self.settings.theme.danger, 0.35)));
+        for y in 0..arena.grid.1 {
+            for x in 0..arena.grid.0 {
+                if arena.in_ring((x, y)) && !configured.in_ring((x, y)) {
+                    dead.rectangle(arena.square((x, y)), transform);
+                } else if warned && next.in_ring((x, y)) && !arena.in_ring((x, y)) {
+                    warning.rectangle(arena.square((x, y)), transform);
+                }
+            }
+        }
+        dead.draw(g);
+        warning.draw(g);
+    }
+
+    // The phase pickup is a cyan diamond; a successful phase briefly washes the playfield in the same cyan
+    fn render_phase<R: Renderer>(&self, transform: Transform, g: &mut R) {
+        if let Some(pos) = self.phase_pickup {
+            let [left, top, cell, _] = self.settings.layout().square(pos);
+            let (cx, cy) = (left + cell / 2.0, top + cell / 2.0);
+            let r = cell * 0.4;
+            g.polygon(self.settings.theme.phase, &[[cx, cy - r], [cx + r, cy], [cx, cy + r], [cx - r, cy]], transform);
+        }
+        if self.phase_flash > 0 {
+            let alpha = 0.25 * self.phase_flash as f32 / PHASE_FLASH_MOVES as f32;
+            g.rectangle(with_alpha(self.settings.theme.phase, alpha), self.settings.layout().open_rect(), transform);
+        }
+    }
+
+    // Drop shadows under the food and the snake, cast down and to the right. They are drawn in one opaque
+    // color that matches black at 25% over the board, so overlapping shapes don't darken each other.
+    fn render_shadows<R: Renderer>(&self, transform: Transform, g: &mut R) {
+        let layout = self.settings.layout();
+        let cell = layout.cell as f64;
+        let offset = cell * SHADOW_OFFSET;
+        let transform = transform.trans(offset, offset);
+        let mut shadow = Batch::new(self.settings.theme.shadow());
+        for food in &self.foods {
+            let square = layout.square(food.pos);
+            match self.settings.cell_shape {
+                CellShape::Rounded { round_food: true } => {
+                    shadow.disc([square[0] + square[2] / 2.0, square[1] + square[3] / 2.0], cell, transform);
+                },
+                shape => shadow.cell(square, shape, transform),
+            }
+        }
+        self.snake.add_silhouette(transform, cell, self.settings.cell_shape, self.snake_progress(), &mut shadow);
+        if let Some(rival) = &self.rival {
+            rival.snake.add_silhouette(transform, cell, self.settings.cell_shape, self.snake_progress(), &mut shadow);
+        }
+        shadow.draw(g);
+    }
+
+    // How far through its last move the snake is drawn: all the way, unless it slides between cells and
+    // isn't waiting for the countdown
+    fn snake_progress(&self) -> f64 {
+        if self.slides_snake() && self.countdown == 0.0 { self.move_progress } else { 1.0 }
+    }
+
+    // Whether the snake slides from cell to cell, so every update changes the picture. Sprites have
+    // corner pieces that only fit whole cells, so they always step.
+    pub fn slides_snake(&self) -> bool {
+        self.settings.smooth_movement && !self.settings.sprites
+    }
+
+    // Copper sparks flying off eaten food, shrinking and fading as they burn out
+    fn render_particles<R: Renderer>(&self, transform: Transform, g: &mut R) {
+        let cell = self.settings.layout().cell as f64;
+        for particle in self.particles.iter() {
+            let remaining = particle.remaining();
+            let size = cell * 0.2 * (0.5 + 0.5 * remaining);
+            let [x, y] = particle.pos.map(|v| v * cell);
+            g.rectangle(with_alpha(self.settings.theme.sparks, remaining as f32), [x - size / 2.0, y - size / 2.0, size, size], transform);
+        }
+    }
+
+    // A penalty wrap briefly lights up the border in red
+    fn render_wrap_flash<R: Renderer>(&self, transform: Transform, g: &mut R) {
+        if self.wrap_flash == 0 {
+            return;
+        }
+        let alpha = 0.6 * self.wrap_flash as f32 / PHASE_FLASH_MOVES as f32;
+        let layout = self.layout();
+        let [x, y, w, h] = layout.open_rect();
+        let edge = layout.cell as f64 * 0.25;
+        for rect in [[x, y, w, edge], [x, y + h - edge, w, edge], [x, y, edge, h], [x + w - edge, y, edge, h]] {
+            g.rectangle(with_alpha(self.settings.theme.danger, alpha), rect, transform);
+        }
+    }
+
+    // Faint arrows in the cell ahead of the head: the current heading, plus a brighter one for a queued turn.
+    // They sit outside the head cell so the eyes stay visible.
+    fn render_assist_arrows<R: Renderer>(&self, transform: Transform, g: &mut R) {
+        let heading_color = with_alpha(self.settings.theme.glint, 0.20);
+        let queued_color = with_alpha(self.settings.theme.glint, 0.50);
+        let [left, top, cell, _] = self.settings.layout().square(self.snake.head());
+        let center = [left + cell / 2.0, top + cell / 2.0];
+        let ahead = |dir: &Direction| {
+            let (dx, dy) = dir.delta();
+            [center[0] + dx as f64 * cell, center[1] + dy as f64 * cell]
+        };
+        draw_arrow(heading_color, ahead(&self.snake.dir), &self.snake.dir, cell * 0.35, transform, g);
+        if let Some(dir) = self.queued_turns.front().filter(|&dir| *dir != self.snake.dir && *dir != self.snake.dir.opposite()) {
+            draw_arrow(queued_color, ahead(dir), dir, cell * 0.5, transform, g);
+        }
+    }
+}
+
+impl Snake {
+    // Draw the snake on a grid of `cell`-sized squares, `progress` of the way from where the last move
+    // started to where it ended (1 for exactly on the cells), in `look`
+    pub fn render<R: Renderer>(&self, transform: Transform, g: &mut R, shape: CellShape, cell: f64, progress: f64, look: SnakeLook<R::Texture>) {
+        let skin = match look {
+            SnakeLook::Sprites(atlas) => {
+                self.render_sprites(atlas, transform, g, cell);
+                return;
+            },
+            SnakeLook::Shapes(skin) => skin,
+        };
+        if !self.body().is_empty() {
+            let pose = self.pose(progress, cell);
+            let head_center = pose.point(pose.along[0]);
+
+            // Draw the rest of the body first (tail to neck). Every segment keeps to its own stretch of
+            // the body, so the two alternating colors can each go out in one batch; only where a stripe
+            // ends right on a bend does the round joint of one cover a sliver of the other.
+            // Even index = dark, odd index = light (counted from the neck).
+            let mut body = skin.stripes.map(Batch::new);
+            Snake::add_body(&pose, transform, cell, &mut body);
+            for batch in &body {
+                batch.draw(g);
+            }
+
+            // The head is drawn facing right around the origin, then rotated into place, so the
+            // highlight and eyes keep the same spot relative to the direction of travel
+            let head_transform = transform.trans(head_center[0], head_center[1]).rot_rad(self.dir.angle());
+            let mut head = Batch::new(skin.head);
+            Snake::add_head(head_transform, cell, shape, &mut head);
+            head.draw(g);
+            let half = cell / 2.0;
+
+            // Fake reflection: draw a lighter, semi-transparent rectangle on the upper part of the head.
+            // It stays clear of the corners, so it fits inside the rounded head too (as a pill there).
+            let reflection_color = with_alpha(skin.shine, 0.35);
+            let refl_w = cell * 0.45;
+            let refl_h = cell * 0.18;
+            let reflection = [-refl_w / 2.0, -half + cell * 0.10, refl_w, refl_h];
+            match shape {
+                CellShape::Sharp => g.rectangle(reflection_color, reflection, head_transform),
+                CellShape::Rounded { .. } => draw_rounded_rect(reflection_color, reflection, refl_h / 2.0, head_transform, g),
+            }
+
+            // Eyes, slightly towards the front of the head
+            let eye_r = cell * 0.1;
+            let eye_offset = cell * 0.20;
+            let front_offset = cell * 0.18;
+
+            // Draw eyes as little squares instead of ellipses, or as dots on a rounded head
+            let eye_size = eye_r * 2.0;
+            for side in [-1.0, 1.0] {
+                let eye_square = [front_offset - eye_r, side * eye_offset - eye_r, eye_size, eye_size];
+                match shape {
+                    CellShape::Sharp => g.rectangle(skin.eyes, eye_square, head_transform),
+                    CellShape::Rounded { .. } => g.ellipse(skin.eyes, eye_square, head_transform),
+                }
+            }
+        }
+    }
+
+    // Where each segment is drawn, `progress` of the way through the last move. A segment whose last move
+    // was one step along the body slides from its old cell to its new one; anything else (growing, a wrap,
+    // a phase hop) stays on its own cell, since sliding there would cut across the board.
+    fn pose(&self, progress: f64, cell: f64) -> Pose {
+        let mut cells: Vec<(i32, i32)> = self.body().iter().copied().collect();
+        let slid = |i: usize| match self.previous.get(i) {
+            Some(&from) => adjacent(from, cells[i]) && cells.get(i + 1).is_none_or(|&next| next == from),
+            None => false,
+        };
+        let along: Vec<f64> = (0..cells.len()).map(|i| if slid(i) { i as f64 + 1.0 - progress } else { i as f64 }).collect();
+        let tail = cells.len() - 1;
+        if slid(tail) {
+            cells.push(self.previous[tail]);
+        }
+        let points = cells.iter().map(|&(x, y)| [(x as f64 + 0.5) * cell, (y as f64 + 0.5) * cell]).collect();
+        Pose { cells, points, along }
+    }
+
+    // Add the body's segments (tail to neck) to `batches`, cycling through them from the neck onwards,
+    // skipping any segment at the head's position. Each segment reaches halfway to its neighbors along the
+    // path; the neck reaches on under the head.
+    fn add_body(pose: &Pose, transform: Transform, cell: f64, batches: &mut [Batch]) {
+        let body_width = cell * 0.76; // Body is slightly narrower than the head
+        let tail_width = cell * 0.36; // Tail tapers down to this width at its tip
+        let (cells, along) = (&pose.cells, &pose.along);
+        let joined = |i: usize| adjacent(cells[i], cells[i + 1]); // Whether segments `i` and `i + 1` connect
+        let tail = along.len() - 1;
+        for i in (1..along.len()).rev() {
+            if cells[i] == cells[0] {
+                continue; // skip body segment that overlaps the head
+            }
+            let count = batches.len();
+            let batch = &mut batches[(i - 1) % count];
+            let start = match i {
+                _ if !joined(i - 1) => i as f64,
+                1 => along[0],
+                _ => (along[i - 1] + along[i]) / 2.0,
+            };
+            if i == tail {
+                // Taper from full body width down to a narrow rounded tip
+                batch.stroke(&pose.points, start, along[i], body_width, tail_width, transform);
+                batch.disc(pose.point(along[i]), tail_width, transform);
+                continue;
+            }
+            let end = if joined(i) { (along[i] + along[i + 1]) / 2.0 } else { along[i] };
+            batch.stroke(&pose.points, start, end, body_width, body_width, transform);
+        }
+    }
+
+    // Add the head's outline, facing right around the origin of `transform`: square towards the neck,
+    // rounded towards the direction of travel. Rounded cells round the neck side off as well.
+    fn add_head(transform: Transform, cell: f64, shape: CellShape, batch: &mut Batch) {
+        let half = cell / 2.0;
+        if let CellShape::Rounded { .. } = shape {
+            batch.cell([-half, -half, cell, cell], shape, transform);
+        } else {
+            batch.link([0.0, 0.0], (-1, 0), half, cell, cell, transform);
+            batch.disc([0.0, 0.0], cell, transform);
+        }
+    }
+
+    // Draw every segment as the sprite that fits its neighbors, tail to neck and then the head on top.
+    // Sprites face right with the tail side on the left; corners turn right and are mirrored for left turns.
+    fn render_sprites<R: Renderer>(&self, atlas: &R::Texture, transform: Transform, g: &mut R, cell: f64) {
+        let cells: Vec<(i32, i32)> = self.body().iter().copied().collect();
+        let joints = self.joints();
+        let square = |(x, y): (i32, i32)| [x as f64 * cell, y as f64 * cell, cell, cell];
+        for i in (1..cells.len()).rev() {
+            if cells[i] == cells[0] {
+                continue; // skip body segment that overlaps the head
+            }
+            let (sprite, heading, mirrored) = segment_sprite(&joints[i]);
+            draw_sprite(atlas, sprite, square(cells[i]), heading.angle(), mirrored, transform, g);
+        }
+        draw_sprite(atlas, Sprite::Head, square(cells[0]), self.dir.angle(), false, transform, g);
+    }
+
+    // Add the outline of the whole snake, head included, to one batch (for its shadow)
+    fn add_silhouette(&self, transform: Transform, cell: f64, shape: CellShape, progress: f64, batch: &mut Batch) {
+        let pose = self.pose(progress, cell);
+        Snake::add_body(&pose, transform, cell, std::slice::from_mut(batch));
+        let [x, y] = pose.point(pose.along[0]);
+        Snake::add_head(transform.trans(x, y).rot_rad(self.dir.angle()), cell, shape, batch);
+    }
+}
+
+// Tiles of the sprite atlas, in order
+#[derive(Clone, Copy, PartialEq)]
+enum Sprite {
+    Head,
+    Body,
+    Corner,
+    Tail,
+    Food(FoodKind),
+}
+
+impl Sprite {
+    fn tile(self) -> usize {
+        match self {
+            Sprite::Head => 0,
+            Sprite::Body => 1,
+            Sprite::Corner => 2,
+            Sprite::Tail => 3,
+            Sprite::Food(FoodKind::Normal) => 4,
+            Sprite::Food(FoodKind::Bonus) => 5,
+            Sprite::Food(FoodKind::Poison) => 6,
+            Sprite::Food(FoodKind::Large) => 7,
+            Sprite::Food(FoodKind::Golden) => 8,
+        }
+    }
+}
+
+// The tile for a body segment (not the head) that runs through its cell the way `joint` says, the heading
+// it is turned to and whether it is mirrored
+fn segment_sprite((into, out): &(Option<Direction>, Direction)) -> (Sprite, Direction, bool) {
+    match into {
+        None => (Sprite::Tail, out.clone(), false),
+        Some(into) if into == out => (Sprite::Body, into.clone(), false),
+        Some(into) => (Sprite::Corner, into.clone(), *out != into.turn_right()),
+    }
+}
+
+// Draw `sprite` filling `square`, turned by `angle` around its center and optionally mirrored top to bottom
+fn draw_sprite<R: Renderer>(atlas: &R::Texture, sprite: Sprite, square: [f64; 4], angle: f64, mirrored: bool, transform: Transform, g: &mut R) {
+    let [x, y, w, h] = square;
+    let transform = transform.trans(x + w / 2.0, y + h / 2.0).rot_rad(angle).scale(1.0, if mirrored { -1.0 } else { 1.0 });
+    g.image(atlas, [sprite.tile() as f64 * SPRITE_SIZE, 0.0, SPRITE_SIZE, SPRITE_SIZE], [-w / 2.0, -h / 2.0, w, h], transform);
+}
+
+// Whether two grid cells share an edge; cells on opposite sides of a wrap don't
+fn adjacent(a: (i32, i32), b: (i32, i32)) -> bool {
+    (a.0 - b.0).abs() + (a.1 - b.1).abs() == 1
+}
+
+// The snake as drawn in between moves: the cells its body runs through (head first, then the cell the
+// tail just left if it slid out of one), their centers in pixels, and how far down that path each segment
+// sits, in cells
+struct Pose {
+    cells: Vec<(i32, i32)>,
+    points: Vec<[f64; 2]>,
+    along: Vec<f64>,
+}
+
+impl Pose {
+    // Pixel position `along` cells down the path
+    fn point(&self, along: f64) -> [f64; 2] {
+        point_along(&self.points, along)
+    }
+}
+
+// Position `along` steps down a polyline, between its points either side
+fn point_along(points: &[[f64; 2]], along: f64) -> [f64; 2] {
+    let i = (along.floor() as usize).min(points.len() - 1);
+    let (a, b) = (points[i], *points.get(i + 1).unwrap_or(&points[i]));
+    let f = along - i as f64;
+    [a[0] + (b[0] - a[0]) * f, a[1] + (b[1] - a[1]) * f]
+}
+
+// Corners of a straight band from `from` to `to`, `from_width` wide at one end and `to_width` at the
+// other, or None when the ends coincide
+fn band_quad(from: [f64; 2], to: [f64; 2], from_width: f64, to_width: f64) -> Option<[[f64; 2]; 4]> {
+    let (dx, dy) = (to[0] - from[0], to[1] - from[1]);
+    let length = dx.hypot(dy);
+    if length < 1e-9 {
+        return None;
+    }
+    let (px, py) = (-dy / length / 2.0, dx / length / 2.0); // half a unit across the band
+    Some([
+        [from[0] + px * from_width, from[1] + py * from_width],
+        [to[0] + px * to_width, to[1] + py * to_width],
+        [to[0] - px * to_width, to[1] - py * to_width],
+        [from[0] - px * from_width, from[1] - py * from_width],
+    ])
+}
+
+// Corners of the quad `Batch::link` adds, or None when there is no neighbor to link to
+fn link_quad(center: [f64; 2], towards: (i32, i32), half: f64, center_width: f64, edge_width: f64) -> Option<[[f64; 2]; 4]> {
+    if towards == (0, 0) {
+        return None;
+    }
+    let (dx, dy) = (towards.0 as f64, towards.1 as f64);
+    let edge = [center[0] + dx * half, center[1] + dy * half];
+    let (px, py) = (-dy, dx); // perpendicular to the link
+    Some([
+        [edge[0] + px * edge_width / 2.0, edge[1] + py * edge_width / 2.0],
+        [edge[0] - px * edge_width / 2.0, edge[1] - py * edge_width / 2.0],
+        [center[0] - px * center_width / 2.0, center[1] - py * center_width / 2.0],
+        [center[0] + px * center_width / 2.0, center[1] + py * center_width / 2.0],
+    ])
+}
+
+// Triangles of one color, collected so that many shapes go to the GPU in a few draw calls instead of one
+// call each. Shapes in one batch are drawn in the order they were added, but all of them at the moment the
+// batch is drawn, so only batch shapes that don't overlap shapes of other colors drawn in between.
+pub struct Batch {
+    color: [f32; 4],
+    vertices: Vec<[f32; 2]>, // Already transformed, three per triangle
+}
+
+impl Batch {
+    // Segments of a full circle, the same as Piston's `ellipse` uses
+    const DISC_RESOLUTION: usize = 128;
+
+    pub fn new(color: [f32; 4]) -> Self {
+        Batch { color, vertices: Vec::new() }
+    }
+
+    // Add a convex polygon as a fan of triangles, like Piston's `polygon` does
+    fn polygon(&mut self, points: &[[f64; 2]], transform: Transform) {
+        let corners: Vec<[f32; 2]> = points
+            .iter()
+            .map(|&point| {
+                let [x, y] = transform_pos(transform, point);
+                [x as f32, y as f32]
+            })
+            .collect();
+        for i in 1..corners.len().saturating_sub(1) {
+            self.vertices.extend([corners[0], corners[i], corners[i + 1]]);
+        }
+    }
+
+    fn rectangle(&mut self, [x, y, w, h]: [f64; 4], transform: Transform) {
+        self.polygon(&[[x, y], [x + w, y], [x + w, y + h], [x, y + h]], transform);
+    }
+
+    // Add a filled circle of the given diameter around a cell center
+    fn disc(&mut self, center: [f64; 2], diameter: f64, transform: Transform) {
+        let r = diameter / 2.0;
+        let outline: Vec<[f64; 2]> = (0..Self::DISC_RESOLUTION)
+            .map(|i| {
+                let angle = std::f64::consts::TAU * i as f64 / Self::DISC_RESOLUTION as f64;
+                [center[0] + r * angle.cos(), center[1] + r * angle.sin()]
+            })
+            .collect();
+        self.polygon(&outline, transform);
+    }
+
+    // Add the half of a segment between its cell center and the edge shared with a neighbor, `half` a cell
+    // away, narrowing from `edge_width` at the shared edge to `center_width` at the center
+    fn link(&mut self, center: [f64; 2], towards: (i32, i32), half: f64, center_width: f64, edge_width: f64, transform: Transform) {
+        if let Some(quad) = link_quad(center, towards, half, center_width, edge_width) {
+            self.polygon(&quad, transform);
+        }
+    }
+
+    // Add the stretch of the polyline `points` between `from` and `to` steps along it, narrowing from
+    // `from_width` to `to_width`, with a round joint on every point it passes so bends get a smooth outer corner
+    fn stroke(&mut self, points: &[[f64; 2]], from: f64, to: f64, from_width: f64, to_width: f64, transform: Transform) {
+        let width_at = |along: f64| if to > from { from_width + (to_width - from_width) * (along - from) / (to - from) } else { to_width };
+        let centers: Vec<f64> = (from.ceil() as usize..=to.floor() as usize).map(|i| i as f64).collect();
+        let mut stops = vec![from];
+        stops.extend(centers.iter().copied().filter(|&along| along > from && along < to));
+        stops.push(to);
+        for pair in stops.windows(2) {
+            let (a, b) = (point_along(points, pair[0]), point_along(points, pair[1]));
+            if let Some(quad) = band_quad(a, b, width_at(pair[0]), width_at(pair[1])) {
+                self.polygon(&quad, transform);
+            }
+        }
+        for along in centers {
+            self.disc(point_along(points, along), width_at(along), transform);
+        }
+    }
+
+    // Same as `Renderer::draw_cell`
+    fn cell(&mut self, square: [f64; 4], shape: CellShape, transform: Transform) {
+        match shape {
+            CellShape::Sharp => self.rectangle(square, transform),
+            CellShape::Rounded { .. } => self.polygon(&rounded_rect_outline(square, square[2] * CELL_ROUNDING), transform),
+        }
+    }
+
+    fn draw<R: Renderer>(&self, g: &mut R) {
+        g.triangles(self.color, &self.vertices);
+    }
+}
+
+// Draw a triangle of the given size around `center`, pointing towards `dir`
+fn draw_arrow<R: Renderer>(color: [f32; 4], center: [f64; 2], dir: &Direction, size: f64, transform: Transform, g: &mut R) {
+    let (dx, dy) = dir.delta();
+    let (dx, dy) = (dx as f64 * size / 2.0, dy as f64 * size / 2.0);
+    let (px, py) = (-dy, dx); // perpendicular, same length
+    let triangle = [
+        [center[0] + dx, center[1] + dy],
+        [center[0] - dx + px, center[1] - dy + py],
+        [center[0] - dx - px, center[1] - dy - py],
+    ];
+    g.polygon(color, &triangle, transform);
+}
+
+// Icon for a power-up filling `square`: a disc in the power-up's color with a symbol for what it does
+fn draw_power_up<R: Renderer>(power_up: PowerUp, square: [f64; 4], theme: &Theme, transform: Transform, g: &mut R) {
+    let [x, y, size, _] = square;
+    let (cx, cy) = (x + size / 2.0, y + size / 2.0);
+    let color = theme.power_up_color(power_up);
+    g.ellipse(color, square, transform);
+    let ink = theme.border;
+    let r = size * 0.25;
+    match power_up {
+        // Fast-forward
+        PowerUp::Speed => {
+            draw_arrow(ink, [cx - r * 0.5, cy], &Direction::Right, r, transform, g);
+            draw_arrow(ink, [cx + r * 0.5, cy], &Direction::Right, r, transform, g);
+        },
+        // Hourglass
+        PowerUp::SlowMotion => {
+            g.polygon(ink, &[[cx - r, cy - r], [cx + r, cy - r], [cx, cy]], transform);
+            g.polygon(ink, &[[cx - r, cy + r], [cx + r, cy + r], [cx, cy]], transform);
+        },
+        // Hollow ring
+        PowerUp::Ghost => {
+            g.ellipse(ink, [cx - r, cy - r, 2.0 * r, 2.0 * r], transform);
+            g.ellipse(color, [cx - r * 0.6, cy - r * 0.6, 1.2 * r, 1.2 * r], transform);
+        },
+        // Arrows pointing inwards
+        PowerUp::Shrink => {
+            draw_arrow(ink, [cx - r * 0.6, cy], &Direction::Right, r, transform, g);
+            draw_arrow(ink, [cx + r * 0.6, cy], &Direction::Left, r, transform, g);
+        },
+    }
+}
+
+// Each pair of portals has its own color, so it's clear which ends belong together. An end is a ring that
+// swells and shrinks around a dark middle, with a spark circling inside; the two ends of a pair spin
+// opposite ways.
+fn draw_portals<R: Renderer>(portals: &[[(i32, i32); 2]], layout: BoardLayout, time: f64, theme: &Theme, transform: Transform, g: &mut R) {
+    use std::f64::consts::TAU;
+
+    let r = layout.cell as f64 * (0.42 + 0.05 * (time * TAU * PORTAL_PULSE).sin());
+    let (core, spark) = (r * 0.65, layout.cell as f64 * 0.08);
+    for (i, ends) in portals.iter().enumerate() {
+        let color = theme.portals[i % theme.portals.len()];
+        for (&end, turn) in ends.iter().zip([1.0, -1.0]) {
+            let [x, y, size, _] = layout.square(end);
+            let (cx, cy) = (x + size / 2.0, y + size / 2.0);
+            g.ellipse(color, [cx - r, cy - r, 2.0 * r, 2.0 * r], transform);
+            g.ellipse(theme.portal_core, [cx - core, cy - core, 2.0 * core, 2.0 * core], transform);
+            let angle = turn * time * TAU * PORTAL_SPIN;
+            let (sx, sy) = (cx + angle.cos() * core * 0.55, cy + angle.sin() * core * 0.55);
+            g.ellipse(color, [sx - spark, sy - spark, 2.0 * spark, 2.0 * spark], transform);
+        }
+    }
+}
+
+// Top left corner of a board (border included) of `board` size in a window of `view` size, leaving the spare
+// room around it as `align` says. The board never moves off the top left edge of a window that is too small.
+fn board_origin(view: [f64; 2], board: [f64; 2], align: BoardAlign) -> [f64; 2] {
+    let (fx, fy) = align.fractions();
+    let spare_x = (view[0] - board[0]).max(0.0);
+    let spare_y = (view[1] - board[1]).max(0.0);
+    [(spare_x * fx).floor(), (spare_y * fy).floor()]
+}
+
+// Draw a rectangle with its corners rounded off by `radius`, as one convex polygon
+// (Piston's `rectangle` can't round corners)
+fn draw_rounded_rect<R: Renderer>(color: [f32; 4], rect: [f64; 4], radius: f64, transform: Transform, g: &mut R) {
+    g.polygon(color, &rounded_rect_outline(rect, radius), transform);
+}
+
+fn draw_snake_preview<R: Renderer>(dir: &Direction, shape: CellShape, layout: BoardLayout, sprites: Option<&R::Texture>, theme: &Theme, transform: Transform, g: &mut R) {
+    // The preview is the starting snake drawn where it will spawn, so it always matches the in-game look
+    let [px, py] = layout.playfield_origin();
+    let playfield_transform = transform.trans(px, py);
+    Snake::new(dir.clone(), layout.grid).render(playfield_transform, g, shape, layout.cell as f64, 1.0, SnakeLook::player_one(sprites, theme));
+}
+
+// Two characters for a snake segment in the terminal: the head points where it's going, straight
+// stretches run along the body, turns are rounded and the tail thins out
+fn ascii_segment(is_head: bool, (into, out): (Option<Direction>, Direction)) -> &'static str {
+    let vertical = matches!(out, Direction::Up | Direction::Down);
+    match (is_head, into) {
+        (true, _) => match out {
+            Direction::Up => "/\\",
+            Direction::Down => "\\/",
+            Direction::Left => "<=",
+            Direction::Right => "=>",
+        },
+        (false, None) if vertical => "::",
+        (false, None) => "--",
+        (false, Some(into)) if into != out => "()",
+        (false, Some(_)) if vertical => "||",
+        (false, Some(_)) => "==",
+    }
+}
+
+// Size of the board in terminal cells: each grid cell is two characters wide, plus the border and a HUD line
+pub fn ascii_board_size(layout: BoardLayout) -> (u16, u16) {
+    ((layout.grid.0 * 2 + 2) as u16, (layout.grid.1 + 3) as u16)
+}
+
+// Top-left corner that keeps the board centered in a terminal of the given size,
+// or None if the board doesn't fit
+pub fn ascii_origin(layout: BoardLayout, terminal_size: (u16, u16)) -> Option<(u16, u16)> {
+    let (cols, rows) = terminal_size;
+    let (w, h) = ascii_board_size(layout);
+    if cols < w || rows < h {
+        return None;
+    }
+    Some(((cols - w) / 2, (rows - h) / 2))
+}
+
+pub fn draw_ascii(game: &Game, origin: (u16, u16), clear: bool) -> std::io::Result<()> {
+    use crossterm::{cursor, queue, style, terminal};
+    use std::collections::HashMap;
+    use std::io::Write;
+
+    let mut out = std::io::stdout();
+    if clear {
+        queue!(out, terminal::Clear(terminal::ClearType::All))?;
+    }
+    let (ox, oy) = origin;
+    let (columns, rows) = game.settings.grid;
+    let width = ascii_board_size(game.settings.layout()).0 as usize;
+    // On the start screen the demo, once it plays, fills the board
+    let shown = match (&game.demo, &game.state) {
+        (Some(demo), GameState::Menu) => demo.as_ref(),
+        _ => game,
+    };
+    let in_run = matches!(shown.state, GameState::Running | GameState::Paused);
+
+    // HUD line above the board. The editor shows its last save or load there, or the level's target.
+    let editor_hud = game.editor.as_ref().map(|editor| editor.notice.clone().unwrap_or_else(|| format!("EDITOR target {} - h: keys, esc: back", editor.level.target)));
+    let hud = match game.state {
+        _ if game.help_shown => "HELP - h or esc to close".to_string(),
+        _ if game.leaderboard_shown && game.global_shown => "GLOBAL TOP 10 - space or esc to close".to_string(),
+        _ if game.leaderboard_shown && game.online.is_some() => "TOP 10 - space: global, esc: close".to_string(),
+        _ if game.leaderboard_shown => "TOP 10 - space or esc to close".to_string(),
+        _ if game.achievements_shown => "ACHIEVEMENTS - space or esc to close".to_string(),
+        _ if game.stats_shown => "STATS - space or esc to close".to_string(),
+        _ if editor_hud.is_some() => editor_hud.unwrap_or_default(),
+        GameState::Menu if game.demo.is_some() => format!("DEMO  Score: {} - any key for the title", shown.score),
+        // The terminal has room for one line of menu: the selected item, with the keys to get around
+        GameState::Menu => {
+            let item = game.menu.selected().map(|item| (game.menu_label(item), item.has_value()));
+            match item {
+                Some((label, true)) => format!("< {} > up/down, left/right, space", label),
+                Some((label, false)) => format!("> {} < up/down, space", label),
+                None => String::new(),
+            }
+        },
+        GameState::Running if game.countdown > 0.0 => format!("GET READY - {}", game.countdown.ceil()),
+        GameState::Running => {
+            let mut hud = match &game.rival {
+                Some(rival) if game.settings.players == Players::Computer => format!("You: {}  CPU: {}", game.score, rival.score),
+                Some(rival) => format!("P1: {}  P2: {}", game.score, rival.score),
+                None => format!("Score: {}", game.score),
+            };
+            if let Some(left) = game.time_left {
+                hud += &format!("  Time: {:.1}", left);
+            }
+            if game.combo_moves > 0 {
+                hud += &format!("  Combo: x{} ({:.1}s)", game.combo, game.combo_moves as f64 * game.settings.move_interval / game.move_speed());
+            }
+            if let Some(level) = game.current_level() {
+                hud += &format!("  Level {}: {} to go", game.level + 1, level.target.saturating_sub(game.score));
+            }
+            if let Some(moves) = game.shrink_in {
+                hud += &format!("  Walls: {:.1}s", moves as f64 * game.settings.move_interval);
+            }
+            if game.settings.scoring.food_decay.is_some()
+                && let Some(value) = game.foods.iter().map(|food| game.food_value(food, game.moves)).max()
+            {
+                hud += &format!("  Food: {}", value);
+            }
+            if game.phase_charges > 0 {
+                hud += &format!("  Phase: {}{}", game.phase_charges, if game.phase_armed { " (armed)" } else { "" });
+            }
+            for &(power_up, moves) in &game.effects {
+                hud += &format!("  {}: {:.1}s", power_up.name(), moves as f64 * game.settings.move_interval / game.move_speed());
+            }
+            if game.is_trapped() {
+                hud += "  TRAPPED";
+            }
+            if game.time_scale != 1.0 {
+                hud += &format!("  Speed: {}x", game.time_scale);
+            }
+            hud
+        },
+        GameState::Paused => "PAUSED - p to resume, esc to quit".to_string(),
+        GameState::LevelComplete if game.campaign_won() => format!("CAMPAIGN COMPLETE! Score: {} - space to play again", game.score),
+        GameState::LevelComplete => format!("LEVEL {} COMPLETE! Score: {} - space for the next level", game.level + 1, game.score),
+        GameState::GameOver if game.initials.is_some() => {
+            let initials = game.initials.as_deref().unwrap_or_default();
+            format!("TOP 10! initials: {:_<width$} - enter: save", initials, width = INITIALS_LEN)
+        },
+        GameState::GameOver if game.can_rewind() => format!("COILED! Score: {} - r to rewind ({} left), space to restart", game.score, game.rewinds_left),
+        GameState::GameOver => format!("COILED! Score: {}  Highest: {} - space to restart", game.score, game.high_score),
+    };
+    queue!(out, cursor::MoveTo(ox, oy), style::Print(format!("{:^width$.width$}", hud, width = width)))?;
+
+    let border = "#".repeat(width);
+    queue!(out, cursor::MoveTo(ox, oy + 1), style::Print(&border))?;
+    // Each body cell is drawn the way the body runs through it, so turns and the heading show
+    // Player two's body uses other characters, so the snakes can be told apart
+    let mut snake: HashMap<(i32, i32), &str> = HashMap::new();
+    if let Some(editor) = &game.editor {
+        let preview = editor.snake();
+        snake.extend(preview.body().iter().zip(preview.joints()).enumerate().rev().map(|(i, (&pos, joint))| (pos, ascii_segment(i == 0, joint))));
+    } else if shown.state != GameState::Menu {
+        if let Some(rival) = &shown.rival {
+            for (i, (&pos, joint)) in rival.snake.body().iter().zip(rival.snake.joints()).enumerate().rev() {
+                let segment = match ascii_segment(i == 0, joint) {
+                    "==" => "~~",
+                    "||" => "!!",
+                    "()" => "{}",
+                    segment => segment,
+                };
+                snake.insert(pos, segment);
+            }
+        }
+        snake.extend(shown.snake.body().iter().zip(shown.snake.joints()).enumerate().rev().map(|(i, (&pos, joint))| (pos, ascii_segment(i == 0, joint))));
+    }
+    let overlay = if game.help_shown {
+        Some(game.help_lines())
+    } else if game.leaderboard_shown && game.global_shown {
+        Some(game.global_lines())
+    } else if game.leaderboard_shown {
+        Some(game.leaderboard_lines())
+    } else if game.achievements_shown {
+        Some(game.achievement_lines())
+    } else if game.stats_shown {
+        Some(game.stats_lines())
+    } else {
+        None
+    };
+    if let Some(lines) = overlay {
+        for y in 0..rows as usize {
+            let line = lines.get(y).map_or("", String::as_str);
+            let row = format!("# {:<inner$.inner$} #", line, inner = width - 4);
+            queue!(out, cursor::MoveTo(ox, oy + 2 + y as u16), style::Print(row))?;
+        }
+        queue!(out, cursor::MoveTo(ox, oy + 2 + rows as u16), style::Print(&border))?;
+        return out.flush();
+    }
+    let editor = game.editor.as_ref();
+    let portals: &[[(i32, i32); 2]] = match editor {
+        Some(editor) => &editor.level.portals,
+        None if in_run => &shown.portals,
+        None => &[],
+    };
+    for y in 0..rows {
+        let mut row = String::with_capacity(width);
+        row.push('#');
+        for x in 0..columns {
+            let cell = if editor.is_some_and(|editor| editor.cursor == (x, y)) {
+                "[]"
+            } else if let Some(segment) = snake.get(&(x, y)) {
+                segment
+            } else if (shown.state != GameState::Menu && shown.obstacles.contains(&(x, y)))
+                || shown.settings.layout().in_ring((x, y))
+                || editor.is_some_and(|editor| editor.level.walls.contains(&(x, y)))
+            {
+                "##"
+            } else if shown.layout().in_ring((x, y)) {
+                "::"
+            } else if let Some(i) = portals.iter().position(|ends| ends.contains(&(x, y))) {
+                ASCII_PORTALS[i % ASCII_PORTALS.len()]
+            } else if let Some(food) = shown.foods.iter().find(|food| food.pos == (x, y)).filter(|_| in_run) {
+                match food.kind {
+                    FoodKind::Normal => "<>",
+                    FoodKind::Bonus => "$$",
+                    FoodKind::Poison => "xx",
+                    FoodKind::Large => "OO",
+                    FoodKind::Golden => "**",
+                }
+            } else if in_run && Some((x, y)) == shown.phase_pickup {
+                "()"
+            } else if let Some((power_up, _)) = shown.power_up.filter(|&(_, pos)| in_run && pos == (x, y)) {
+                match power_up {
+                    PowerUp::Speed => ">>",
+                    PowerUp::SlowMotion => "zz",
+                    PowerUp::Ghost => "%%",
+                    PowerUp::Shrink => "><",
+                }
+            } else {
+                "  "
+            };
+            row.push_str(cell);
+        }
+        row.push('#');
+        queue!(out, cursor::MoveTo(ox, oy + 2 + y as u16), style::Print(row))?;
+    }
+    // A newly unlocked achievement is announced in the bottom border
+    let bottom = match game.toasts.front() {
+        Some(achievement) => format!("{:#^width$.width$}", format!(" Unlocked: {} ", achievement.title()), width = width),
+        None => border,
+    };
+    queue!(out, cursor::MoveTo(ox, oy + 2 + rows as u16), style::Print(&bottom))?;
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{FrameTimes, Settings, WallMode, DEFAULT_CELL_SIZE, DEFAULT_GRID};
+    use crate::renderer::Color;
+
+    const IDENTITY: Transform = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+
+    #[test]
+    fn the_board_sits_where_it_is_aligned() {
+        // 600 by 500 pixels of spare room around the board
+        let board = [640.0, 640.0];
+        let view = [board[0] + 600.0, board[1] + 500.0];
+        let origins = [
+            (BoardAlign::TopLeft, [0.0, 0.0]),
+            (BoardAlign::Top, [300.0, 0.0]),
+            (BoardAlign::TopRight, [600.0, 0.0]),
+            (BoardAlign::Left, [0.0, 250.0]),
+            (BoardAlign::Center, [300.0, 250.0]),
+            (BoardAlign::Right, [600.0, 250.0]),
+            (BoardAlign::BottomLeft, [0.0, 500.0]),
+            (BoardAlign::Bottom, [300.0, 500.0]),
+            (BoardAlign::BottomRight, [600.0, 500.0]),
+        ];
+        for (align, origin) in origins {
+            assert_eq!(board_origin(view, board, align), origin);
+        }
+    }
+
+    #[test]
+    fn the_board_keeps_to_whole_pixels() {
+        assert_eq!(board_origin([641.0, 641.0], [640.0, 640.0], BoardAlign::Center), [0.0, 0.0]);
+    }
+
+    #[test]
+    fn a_small_window_keeps_the_board_in_the_top_left_corner() {
+        assert_eq!(board_origin([300.0, 200.0], [640.0, 640.0], BoardAlign::BottomRight), [0.0, 0.0]);
+    }
+
+    #[test]
+    fn text_sizes_follow_the_ui_scale() {
+        let mut game = Game::new(&Settings::default());
+        assert_eq!(game.font_size(24), 24);
+        game.settings.ui_scale = 1.5;
+        assert_eq!(game.font_size(24), 36);
+        assert_eq!(game.font_size(15), 23);
+    }
+
+    #[test]
+    fn help_lists_the_options_in_play() {
+        let mut game = Game::new(&Settings::default());
+        assert!(game.help_lines().iter().any(|line| line == "Edges: deadly"));
+        assert!(!game.help_lines().iter().any(|line| line.starts_with("Time attack")));
+        game.settings.time_limit = Some(60.0);
+        game.settings.wall_mode = WallMode::Wrap;
+        let lines = game.help_lines();
+        assert!(lines.iter().any(|line| line == "Time attack: 60s"));
+        assert!(lines.iter().any(|line| line == "Edges: wrap around"));
+    }
+
+    #[test]
+    fn batched_shapes_become_fans_of_transformed_triangles() {
+        let mut batch = Batch::new([1.0; 4]);
+        let shifted = IDENTITY.trans(100.0, 50.0);
+        batch.rectangle([0.0, 0.0, 10.0, 20.0], shifted);
+        assert_eq!(batch.vertices, [[100.0, 50.0], [110.0, 50.0], [110.0, 70.0], [100.0, 50.0], [110.0, 70.0], [100.0, 70.0]]);
+        batch.disc([0.0, 0.0], 8.0, shifted);
+        assert_eq!(batch.vertices.len(), 6 + (Batch::DISC_RESOLUTION - 2) * 3);
+        assert!(batch.vertices[6..].iter().all(|&[x, y]| ((x - 100.0).hypot(y - 50.0) - 4.0).abs() < 1e-4));
+    }
+
+    #[test]
+    fn a_link_needs_a_neighbor() {
+        let half = DEFAULT_CELL_SIZE as f64 / 2.0;
+        assert_eq!(link_quad([0.0, 0.0], (0, 0), half, 4.0, 4.0), None);
+        let quad = link_quad([0.0, 0.0], (1, 0), half, 4.0, 2.0).expect("a quad towards the right");
+        assert_eq!(quad, [[half, 1.0], [half, -1.0], [0.0, -2.0], [0.0, 2.0]]);
+    }
+
+    #[test]
+    fn the_shadow_covers_exactly_the_body_and_head() {
+        // A snake with two bends in it
+        let mut snake = Snake::new(Direction::Right, DEFAULT_GRID);
+        snake.grow(2, None);
+        for dir in [Direction::Down, Direction::Left] {
+            snake.dir = dir;
+            snake.update();
+        }
+        let shape = CellShape::Sharp;
+        let cell = DEFAULT_CELL_SIZE as f64;
+        let mut silhouette = Batch::new([0.0; 4]);
+        snake.add_silhouette(IDENTITY, cell, shape, 0.5, &mut silhouette);
+
+        let mut parts = [Batch::new([0.0; 4]), Batch::new([0.0; 4]), Batch::new([0.0; 4])];
+        let pose = snake.pose(0.5, cell);
+        Snake::add_body(&pose, IDENTITY, cell, &mut parts[..2]);
+        let [x, y] = pose.point(pose.along[0]);
+        Snake::add_head(IDENTITY.trans(x, y).rot_rad(snake.dir.angle()), cell, shape, &mut parts[2]);
+
+        let sorted = |mut vertices: Vec<[f32; 2]>| {
+            vertices.sort_by(|a, b| a.partial_cmp(b).expect("no NaN"));
+            vertices
+        };
+        let colored = parts.into_iter().flat_map(|batch| batch.vertices).collect();
+        assert_eq!(sorted(silhouette.vertices), sorted(colored));
+    }
+
+    #[test]
+    fn the_snake_slides_from_its_old_cells_to_its_new_ones() {
+        let mut snake = Snake::new(Direction::Right, DEFAULT_GRID);
+        snake.update();
+        let cell = DEFAULT_CELL_SIZE as f64;
+        let center = |(x, y): (i32, i32)| [(x as f64 + 0.5) * cell, (y as f64 + 0.5) * cell];
+        let (x, y) = snake.head();
+        assert_eq!(snake.pose(1.0, cell).point(0.0), center((x, y)));
+        let halfway = snake.pose(0.5, cell);
+        assert_eq!(halfway.point(halfway.along[0]), [center((x, y))[0] - cell / 2.0, center((x, y))[1]]);
+        // The tail slides out of the cell it left, so the path runs one cell past the body
+        assert_eq!(halfway.cells.len(), snake.body().len() + 1);
+        assert_eq!(snake.pose(0.0, cell).point(snake.pose(0.0, cell).along[0]), center(snake.previous[0]));
+    }
+
+    #[test]
+    fn a_growing_tail_and_a_wrap_stay_on_their_cells() {
+        let cell = DEFAULT_CELL_SIZE as f64;
+        let mut snake = Snake::new(Direction::Right, DEFAULT_GRID);
+        snake.grow(1, None);
+        snake.update();
+        let pose = snake.pose(0.5, cell);
+        assert_eq!(pose.cells.len(), snake.body().len());
+        assert_eq!(pose.along[snake.body().len() - 1], (snake.body().len() - 1) as f64);
+
+        // Across the edge the head jumps rather than sliding over the board
+        let mut snake = Snake::new(Direction::Right, DEFAULT_GRID);
+        snake.update();
+        snake.move_head((0, snake.head().1));
+        snake.previous[0] = (DEFAULT_GRID.0 - 1, snake.previous[0].1);
+        assert_eq!(snake.pose(0.5, cell).along[0], 0.0);
+    }
+
+    #[test]
+    fn the_snake_only_slides_with_smooth_movement_on() {
+        let mut game = Game::new(&Settings::default());
+        game.move_progress = 0.25;
+        game.settings.smooth_movement = false;
+        assert_eq!(game.snake_progress(), 1.0);
+        game.settings.smooth_movement = true;
+        assert_eq!(game.snake_progress(), 0.25);
+    }
+
+    // A snake laid out along `body`, head first, heading `dir`
+    fn laid_out(body: &[(i32, i32)], dir: Direction) -> Snake {
+        Snake::along(dir, body)
+    }
+
+    #[test]
+    fn segments_pick_their_tile_from_their_neighbors() {
+        // Head at the top right, running left, then turning down, then down to the tail
+        let joints = laid_out(&[(5, 5), (4, 5), (3, 5), (3, 6), (3, 7)], Direction::Right).joints();
+        let (sprite, heading, mirrored) = segment_sprite(&joints[1]);
+        assert!(sprite == Sprite::Body && heading == Direction::Right && !mirrored);
+        // Coming up from below and turning right onto the row
+        let (sprite, heading, mirrored) = segment_sprite(&joints[2]);
+        assert!(sprite == Sprite::Corner && heading == Direction::Up && !mirrored);
+        let (sprite, heading, mirrored) = segment_sprite(&joints[3]);
+        assert!(sprite == Sprite::Body && heading == Direction::Up && !mirrored);
+        let (sprite, heading, mirrored) = segment_sprite(&joints[4]);
+        assert!(sprite == Sprite::Tail && heading == Direction::Up && !mirrored);
+    }
+
+    #[test]
+    fn left_turns_use_the_mirrored_corner() {
+        // Running right along a row, then turning up
+        let joints = laid_out(&[(4, 4), (4, 5), (3, 5), (2, 5)], Direction::Up).joints();
+        let (sprite, heading, mirrored) = segment_sprite(&joints[1]);
+        assert!(sprite == Sprite::Corner && heading == Direction::Right && mirrored);
+        assert!(heading.angle() == 0.0);
+    }
+
+    #[test]
+    fn a_wrap_still_reads_as_a_straight_piece() {
+        // Heading right off the right edge of a 20 cell wide board and on from the left edge
+        let joints = laid_out(&[(1, 3), (0, 3), (19, 3), (18, 3)], Direction::Right).joints();
+        for (i, sprite) in [(1, Sprite::Body), (2, Sprite::Body), (3, Sprite::Tail)] {
+            let (tile, heading, _) = segment_sprite(&joints[i]);
+            assert!(tile == sprite && heading == Direction::Right);
+        }
+    }
+
+    #[test]
+    fn the_terminal_snake_points_and_bends() {
+        let snake = laid_out(&[(5, 4), (5, 5), (4, 5), (3, 5)], Direction::Up);
+        let segments: Vec<&str> = snake.joints().into_iter().enumerate().map(|(i, joint)| ascii_segment(i == 0, joint)).collect();
+        assert_eq!(segments, ["/\\", "()", "==", "--"]);
+    }
+
+    // A renderer that draws nothing and only notes where each piece of text goes, with a monospaced
+    // advance of 0.6 of the font size
+    #[derive(Default)]
+    struct TextLog(Vec<(String, u32, [f64; 2])>);
+
+    impl Renderer for TextLog {
+        type Texture = ();
+
+        fn transform(&self) -> Transform {
+            IDENTITY
+        }
+        fn view_size(&self) -> [f64; 2] {
+            [800.0, 800.0]
+        }
+        fn clear(&mut self, _color: Color) {}
+        fn rectangle(&mut self, _color: Color, _rect: [f64; 4], _transform: Transform) {}
+        fn ellipse(&mut self, _color: Color, _rect: [f64; 4], _transform: Transform) {}
+        fn polygon(&mut self, _color: Color, _points: &[[f64; 2]], _transform: Transform) {}
+        fn triangles(&mut self, _color: Color, _vertices: &[[f32; 2]]) {}
+        fn draw_text(&mut self, _color: Color, size: u32, text: &str, transform: Transform) {
+            self.0.push((text.to_string(), size, [transform[0][2], transform[1][2]]));
+        }
+        fn text_width(&mut self, size: u32, text: &str) -> f64 {
+            text.chars().count() as f64 * size as f64 * 0.6
+        }
+        fn image(&mut self, _texture: &(), _source: [f64; 4], _rect: [f64; 4], _transform: Transform) {}
+        fn present(&mut self) {}
+    }
+
+    // Where the HUD draws the score `score` during a run
+    fn score_position(game: &mut Game, score: u32) -> [f64; 2] {
+        game.score = score;
+        let mut log = TextLog::default();
+        game.render(&mut log, None);
+        let (_, _, pos) = log.0.into_iter().find(|(text, size, _)| *text == score.to_string() && *size == 24).expect("the score is drawn");
+        pos
+    }
+
+    #[test]
+    fn the_score_stays_put_as_digits_are_added() {
+        let mut game = Game::new(&Settings::default());
+        game.state = GameState::Running;
+        let one = score_position(&mut game, 1);
+        assert_eq!(score_position(&mut game, 10), one);
+        assert_eq!(score_position(&mut game, 100), one);
+        assert_eq!(score_position(&mut game, 1000), one);
+    }
+
+    #[test]
+    fn the_time_attack_clock_stays_put_as_it_counts_down() {
+        let mut game = Game::new(&Settings::builder().time_limit(Some(120.0)).build().expect("valid settings"));
+        game.state = GameState::Running;
+        let clock_at = |game: &mut Game, left: f64| {
+            game.time_left = Some(left);
+            let mut log = TextLog::default();
+            game.render(&mut log, None);
+            log.0.into_iter().find(|(text, _, _)| text.trim() == format!("{:.1}", left)).map(|(text, _, pos)| (text.len(), pos))
+        };
+        let long = clock_at(&mut game, 100.0);
+        assert!(long.is_some());
+        assert_eq!(clock_at(&mut game, 60.0), long);
+        assert_eq!(clock_at(&mut game, 5.5), long);
+    }
+
+    #[test]
+    fn the_debug_overlay_shows_the_frame_times_and_the_snake() {
+        let mut game = Game::new(&Settings::default());
+        game.state = GameState::Running;
+        game.foods[0].pos = (3, 4);
+        game.frame_times = FrameTimes { fps: 59.94, ups: 100.0, tick_ms: 0.25 };
+        let lines = |game: &mut Game| {
+            let mut log = TextLog::default();
+            game.render(&mut log, None);
+            log.0.into_iter().map(|(text, _, _)| text).collect::<Vec<_>>()
+        };
+        assert!(!lines(&mut game).iter().any(|text| text.starts_with("FPS")));
+        game.debug_shown = true;
+        let lines = lines(&mut game);
+        let (x, y) = game.snake.head();
+        for line in ["FPS  59.9", "UPS  100.0", "Tick 0.250 ms", &format!("Head {x},{y}"), "Food 3,4"] {
+            assert!(lines.iter().any(|text| text == line), "{line} in {lines:?}");
+        }
+    }
+}
@@ -0,0 +1,139 @@
+// What `Game::render` needs from a graphics backend. The game only draws through the `Renderer` trait, so
+// the window's Piston backend (`piston_renderer`) is one implementation among possible others. Shapes are
+// placed in board pixels and mapped to the backend's own coordinates by a transform: a 2x3 matrix, laid out
+// the same as Piston's, that starts from `Renderer::transform` and is moved, turned and scaled from there.
+
+use crate::game::CellShape;
+
+pub type Color = [f32; 4]; // Red, green, blue and alpha, from 0 to 1
+pub type Transform = [[f64; 3]; 2];
+
+pub const CELL_ROUNDING: f64 = 0.25; // Corner radius of rounded cells, as a fraction of the cell size
+
+pub trait Renderer {
+    // A loaded image, such as the sprite atlas
+    type Texture;
+
+    // Where board pixel (0, 0) goes, unscaled; every transform the game draws with starts from this one
+    fn transform(&self) -> Transform;
+
+    // Size of the area drawn on, in pixels
+    fn view_size(&self) -> [f64; 2];
+
+    // Fill the whole area with `color`
+    fn clear(&mut self, color: Color);
+
+    fn rectangle(&mut self, color: Color, rect: [f64; 4], transform: Transform);
+
+    // A filled ellipse just inside `rect`
+    fn ellipse(&mut self, color: Color, rect: [f64; 4], transform: Transform);
+
+    // A filled convex polygon through `points`, in order
+    fn polygon(&mut self, color: Color, points: &[[f64; 2]], transform: Transform);
+
+    // Triangles of one color, three corners each, already transformed; many shapes in one call
+    fn triangles(&mut self, color: Color, vertices: &[[f32; 2]]);
+
+    // `text` at `size` points, its baseline starting at the origin of `transform`
+    fn draw_text(&mut self, color: Color, size: u32, text: &str, transform: Transform);
+
+    // How wide `text` is drawn at `size` points
+    fn text_width(&mut self, size: u32, text: &str) -> f64;
+
+    // The part `source` of `texture`, in texture pixels, stretched over `rect`
+    fn image(&mut self, texture: &Self::Texture, source: [f64; 4], rect: [f64; 4], transform: Transform);
+
+    // Finish the frame, sending out whatever the backend still holds on to
+    fn present(&mut self);
+
+    // One board cell in the configured shape
+    fn draw_cell(&mut self, color: Color, square: [f64; 4], shape: CellShape, transform: Transform) {
+        match shape {
+            CellShape::Sharp => self.rectangle(color, square, transform),
+            CellShape::Rounded { .. } => self.polygon(color, &rounded_rect_outline(square, square[2] * CELL_ROUNDING), transform),
+        }
+    }
+}
+
+// Building transforms up step by step, each step applying before the ones already in the transform
+pub trait Transformed {
+    fn trans(self, x: f64, y: f64) -> Self;
+    fn rot_rad(self, angle: f64) -> Self;
+    fn scale(self, sx: f64, sy: f64) -> Self;
+}
+
+impl Transformed for Transform {
+    fn trans(self, x: f64, y: f64) -> Self {
+        multiply(self, [[1.0, 0.0, x], [0.0, 1.0, y]])
+    }
+
+    fn rot_rad(self, angle: f64) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        multiply(self, [[cos, -sin, 0.0], [sin, cos, 0.0]])
+    }
+
+    fn scale(self, sx: f64, sy: f64) -> Self {
+        multiply(self, [[sx, 0.0, 0.0], [0.0, sy, 0.0]])
+    }
+}
+
+// Where `transform` puts `pos`
+pub fn transform_pos(transform: Transform, [x, y]: [f64; 2]) -> [f64; 2] {
+    let [a, b] = transform;
+    [a[0] * x + a[1] * y + a[2], b[0] * x + b[1] * y + b[2]]
+}
+
+// `a` after `b`
+fn multiply(a: Transform, b: Transform) -> Transform {
+    let row = |r: [f64; 3]| [r[0] * b[0][0] + r[1] * b[1][0], r[0] * b[0][1] + r[1] * b[1][1], r[0] * b[0][2] + r[1] * b[1][2] + r[2]];
+    [row(a[0]), row(a[1])]
+}
+
+// Outline of a rectangle with its corners rounded off by `radius`, clockwise
+pub fn rounded_rect_outline(rect: [f64; 4], radius: f64) -> Vec<[f64; 2]> {
+    const CORNER_STEPS: usize = 4; // Segments per quarter circle; plenty at cell size
+    let [x, y, w, h] = rect;
+    let r = radius.min(w / 2.0).min(h / 2.0);
+
+    // Corner arc centers clockwise from the top right, with the angle each arc starts at (y points down)
+    let corners = [(x + w - r, y + r, -90.0f64), (x + w - r, y + h - r, 0.0), (x + r, y + h - r, 90.0), (x + r, y + r, 180.0)];
+    let mut outline = Vec::with_capacity(corners.len() * (CORNER_STEPS + 1));
+    for (cx, cy, start) in corners {
+        for step in 0..=CORNER_STEPS {
+            let angle = (start + 90.0 * step as f64 / CORNER_STEPS as f64).to_radians();
+            outline.push([cx + r * angle.cos(), cy + r * angle.sin()]);
+        }
+    }
+    outline
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IDENTITY: Transform = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+
+    fn close(a: [f64; 2], b: [f64; 2]) -> bool {
+        (a[0] - b[0]).abs() < 1e-9 && (a[1] - b[1]).abs() < 1e-9
+    }
+
+    #[test]
+    fn later_steps_apply_first() {
+        // Scaled by 2, then turned a quarter clockwise (y points down), then moved by (10, 20)
+        let transform = IDENTITY.trans(10.0, 20.0).rot_rad(std::f64::consts::FRAC_PI_2).scale(2.0, 2.0);
+        assert!(close(transform_pos(transform, [1.0, 0.0]), [10.0, 22.0]));
+        assert!(close(transform_pos(transform, [0.0, 1.0]), [8.0, 20.0]));
+        assert!(close(transform_pos(IDENTITY.scale(3.0, 0.5), [2.0, 4.0]), [6.0, 2.0]));
+    }
+
+    #[test]
+    fn a_rounded_outline_stays_inside_its_rectangle() {
+        let rect = [10.0, 10.0, 20.0, 10.0];
+        let outline = rounded_rect_outline(rect, 8.0);
+        assert!(outline.iter().all(|&[x, y]| (10.0 - 1e-9..=30.0 + 1e-9).contains(&x) && (10.0 - 1e-9..=20.0 + 1e-9).contains(&y)));
+        // The radius is cut to half the short side, so the left and right ends are half circles
+        assert!(outline.iter().any(|&point| close(point, [30.0, 15.0])));
+        assert!(outline.iter().any(|&point| close(point, [10.0, 15.0])));
+        assert!(!outline.iter().any(|&point| close(point, [30.0, 10.0])), "corners are cut off");
+    }
+}
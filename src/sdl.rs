@@ -0,0 +1,226 @@
+// The SDL2 frontend pieces, built with the `sdl` feature for systems where Piston's window or its OpenGL
+// backend won't work: opening the window, `SdlRenderer` to draw the game in it, and SDL's keys and mouse
+// buttons as the ones the game knows. Shapes are drawn with SDL2_gfx and text with SDL2_ttf, so both
+// libraries need to be installed next to SDL2 itself.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use piston_input::{Key, MouseButton};
+use sdl2::gfx::primitives::DrawRenderer;
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::{Color as SdlColor, PixelFormatEnum};
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, Texture, TextureCreator, WindowCanvas};
+use sdl2::ttf::{Font, Sdl2TtfContext};
+use sdl2::video::{FullscreenType, WindowContext};
+use sdl2::Sdl;
+use crate::renderer::{transform_pos, Color, Renderer, Transform};
+
+const ELLIPSE_RESOLUTION: usize = 64; // Corners of the polygon an ellipse is drawn as
+const CHECK_FONT_SIZE: u16 = 16; // Size a font is first opened at, to find out whether it loads at all
+
+// Open the game's window at `size`, or covering the screen, and a canvas to draw in it
+pub fn open_window(context: &Sdl, [width, height]: [u32; 2], fullscreen: bool) -> Result<WindowCanvas, String> {
+    let video = context.video()?;
+    let mut builder = video.window("Copperhead", width, height);
+    builder.position_centered().resizable();
+    if fullscreen {
+        builder.fullscreen_desktop();
+    }
+    let window = builder.build().map_err(|err| err.to_string())?;
+    window.into_canvas().build().map_err(|err| err.to_string())
+}
+
+// Switch between a window and borderless fullscreen
+pub fn set_fullscreen(canvas: &mut WindowCanvas, fullscreen: bool) {
+    let mode = if fullscreen { FullscreenType::Desktop } else { FullscreenType::Off };
+    canvas.window_mut().set_fullscreen(mode).ok();
+}
+
+// Resize the window to `size`, for a board that changed size
+pub fn set_size(canvas: &mut WindowCanvas, [width, height]: [u32; 2]) {
+    canvas.window_mut().set_size(width, height).ok();
+}
+
+// An image file as a texture, such as the sprite atlas
+pub fn load_texture<'r>(textures: &'r TextureCreator<WindowContext>, path: &str) -> Result<Texture<'r>, String> {
+    let image = image::open(path).map_err(|err| err.to_string())?.into_rgba8();
+    let (width, height) = image.dimensions();
+    // ABGR8888 is packed into a little-endian number, so its bytes are in the image's RGBA order
+    let mut texture = textures.create_texture_static(PixelFormatEnum::ABGR8888, width, height).map_err(|err| err.to_string())?;
+    texture.update(None, image.as_raw(), width as usize * 4).map_err(|err| err.to_string())?;
+    texture.set_blend_mode(BlendMode::Blend);
+    Ok(texture)
+}
+
+// The key an SDL key stands for; Piston numbers its keys the same way SDL does
+pub fn key(keycode: Keycode) -> Key {
+    Key::from(keycode.into_i32() as u32)
+}
+
+pub fn mouse_button(button: sdl2::mouse::MouseButton) -> MouseButton {
+    match button {
+        sdl2::mouse::MouseButton::Left => MouseButton::Left,
+        sdl2::mouse::MouseButton::Middle => MouseButton::Middle,
+        sdl2::mouse::MouseButton::Right => MouseButton::Right,
+        sdl2::mouse::MouseButton::X1 => MouseButton::X1,
+        sdl2::mouse::MouseButton::X2 => MouseButton::X2,
+        sdl2::mouse::MouseButton::Unknown => MouseButton::Unknown,
+    }
+}
+
+// The game's font, opened at each size the first time that size is drawn
+pub struct Fonts<'r> {
+    ttf: &'r Sdl2TtfContext,
+    path: PathBuf,
+    sizes: HashMap<u16, Font<'r, 'static>>,
+}
+
+impl<'r> Fonts<'r> {
+    // Fails when the font can't be loaded
+    pub fn open(ttf: &'r Sdl2TtfContext, path: &Path) -> Result<Self, String> {
+        let font = ttf.load_font(path, CHECK_FONT_SIZE)?;
+        Ok(Fonts { ttf, path: path.to_path_buf(), sizes: HashMap::from([(CHECK_FONT_SIZE, font)]) })
+    }
+
+    fn at(&mut self, size: u16) -> Option<&Font<'r, 'static>> {
+        if !self.sizes.contains_key(&size) {
+            let font = self.ttf.load_font(&self.path, size).ok()?;
+            self.sizes.insert(size, font);
+        }
+        self.sizes.get(&size)
+    }
+}
+
+// Draws a frame on an SDL canvas. SDL works in window pixels, so the transforms the game hands over map
+// straight to them.
+pub struct SdlRenderer<'a, 'r> {
+    canvas: &'a mut WindowCanvas,
+    textures: &'r TextureCreator<WindowContext>,
+    fonts: &'a mut Fonts<'r>,
+}
+
+impl<'a, 'r> SdlRenderer<'a, 'r> {
+    pub fn new(canvas: &'a mut WindowCanvas, textures: &'r TextureCreator<WindowContext>, fonts: &'a mut Fonts<'r>) -> Self {
+        SdlRenderer { canvas, textures, fonts }
+    }
+}
+
+impl<'r> Renderer for SdlRenderer<'_, 'r> {
+    type Texture = Texture<'r>;
+
+    fn transform(&self) -> Transform {
+        [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]
+    }
+
+    fn view_size(&self) -> [f64; 2] {
+        let (width, height) = self.canvas.output_size().unwrap_or((0, 0));
+        [width as f64, height as f64]
+    }
+
+    fn clear(&mut self, color: Color) {
+        self.canvas.set_draw_color(sdl_color(color));
+        self.canvas.clear();
+    }
+
+    fn rectangle(&mut self, color: Color, [x, y, w, h]: [f64; 4], transform: Transform) {
+        self.polygon(color, &[[x, y], [x + w, y], [x + w, y + h], [x, y + h]], transform);
+    }
+
+    fn ellipse(&mut self, color: Color, [x, y, w, h]: [f64; 4], transform: Transform) {
+        let (rx, ry) = (w / 2.0, h / 2.0);
+        let outline: Vec<[f64; 2]> = (0..ELLIPSE_RESOLUTION)
+            .map(|i| {
+                let angle = std::f64::consts::TAU * i as f64 / ELLIPSE_RESOLUTION as f64;
+                [x + rx + rx * angle.cos(), y + ry + ry * angle.sin()]
+            })
+            .collect();
+        self.polygon(color, &outline, transform);
+    }
+
+    fn polygon(&mut self, color: Color, points: &[[f64; 2]], transform: Transform) {
+        let (xs, ys): (Vec<i16>, Vec<i16>) = points
+            .iter()
+            .map(|&point| {
+                let [x, y] = transform_pos(transform, point);
+                (x.round() as i16, y.round() as i16)
+            })
+            .unzip();
+        self.canvas.filled_polygon(&xs, &ys, sdl_color(color)).ok();
+    }
+
+    fn triangles(&mut self, color: Color, vertices: &[[f32; 2]]) {
+        let corner = |[x, y]: [f32; 2]| (x.round() as i16, y.round() as i16);
+        for triangle in vertices.chunks_exact(3) {
+            let ((x1, y1), (x2, y2), (x3, y3)) = (corner(triangle[0]), corner(triangle[1]), corner(triangle[2]));
+            self.canvas.filled_trigon(x1, y1, x2, y2, x3, y3, sdl_color(color)).ok();
+        }
+    }
+
+    // Text is laid out at the size it ends up on screen, so it stays sharp when the board is scaled up
+    fn draw_text(&mut self, color: Color, size: u32, text: &str, transform: Transform) {
+        let scale = transform[0][1].hypot(transform[1][1]);
+        let [x, y] = transform_pos(transform, [0.0, 0.0]);
+        let Some(font) = self.fonts.at((size as f64 * scale).round().max(1.0) as u16) else {
+            return;
+        };
+        let Ok(surface) = font.render(text).blended(sdl_color(color)) else {
+            return;
+        };
+        let Ok(texture) = self.textures.create_texture_from_surface(&surface) else {
+            return;
+        };
+        let top = y.round() as i32 - font.ascent();
+        self.canvas.copy(&texture, None, Rect::new(x.round() as i32, top, surface.width(), surface.height())).ok();
+    }
+
+    fn text_width(&mut self, size: u32, text: &str) -> f64 {
+        let Some(font) = self.fonts.at(size as u16) else {
+            return 0.0;
+        };
+        font.size_of(text).map_or(0.0, |(width, _)| width as f64)
+    }
+
+    // SDL only turns and flips pictures around their middle, so the transform is taken apart into where the
+    // middle goes, how far it's scaled and turned, and whether it's mirrored
+    fn image(&mut self, texture: &Texture<'r>, source: [f64; 4], [x, y, w, h]: [f64; 4], transform: Transform) {
+        let [[a, b, _], [c, d, _]] = transform;
+        let [cx, cy] = transform_pos(transform, [x + w / 2.0, y + h / 2.0]);
+        let (w, h) = (w * a.hypot(c), h * b.hypot(d));
+        let target = Rect::new((cx - w / 2.0).round() as i32, (cy - h / 2.0).round() as i32, w.round() as u32, h.round() as u32);
+        let [sx, sy, sw, sh] = source;
+        let source = Rect::new(sx as i32, sy as i32, sw as u32, sh as u32);
+        let mirrored = a * d - b * c < 0.0;
+        self.canvas.copy_ex(texture, source, target, c.atan2(a).to_degrees(), None, false, mirrored).ok();
+    }
+
+    fn present(&mut self) {
+        self.canvas.present();
+    }
+}
+
+fn sdl_color([r, g, b, a]: Color) -> SdlColor {
+    let byte = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+    SdlColor::RGBA(byte(r), byte(g), byte(b), byte(a))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sdl_keys_are_the_game_keys_with_the_same_number() {
+        assert_eq!(key(Keycode::UP), Key::Up);
+        assert_eq!(key(Keycode::LEFT), Key::Left);
+        assert_eq!(key(Keycode::ESCAPE), Key::Escape);
+        assert_eq!(key(Keycode::A), Key::A);
+        assert_eq!(key(Keycode::SPACE), Key::Space);
+        assert_eq!(key(Keycode::F11), Key::F11);
+    }
+
+    #[test]
+    fn colors_round_to_bytes_and_stay_in_range() {
+        assert_eq!(sdl_color([1.0, 0.5, 0.0, 0.35]), SdlColor::RGBA(255, 128, 0, 89));
+        assert_eq!(sdl_color([1.5, -0.2, 0.0, 1.0]), SdlColor::RGBA(255, 0, 0, 255));
+    }
+}
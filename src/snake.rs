@@ -0,0 +1,511 @@
+// The snake: its body, heading and movement on the grid
+
+use std::collections::{HashMap, VecDeque};
+use crate::game::TailChase;
+
+pub const START_DIRECTION: Direction = Direction::Right; // Heading of a freshly spawned snake
+pub const START_LENGTH: usize = 3; // Segments of a freshly spawned snake
+
+#[derive(Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum Direction {
+    Left, Right, Up, Down
+}
+
+impl Direction {
+    pub fn opposite(&self) -> Direction {
+        match self {
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+        }
+    }
+
+    // A quarter turn counter-clockwise on screen, as seen from above
+    pub fn turn_left(&self) -> Direction {
+        match self {
+            Direction::Up => Direction::Left,
+            Direction::Left => Direction::Down,
+            Direction::Down => Direction::Right,
+            Direction::Right => Direction::Up,
+        }
+    }
+
+    // A quarter turn clockwise on screen
+    pub fn turn_right(&self) -> Direction {
+        self.turn_left().opposite()
+    }
+
+    // Unit step in grid coordinates
+    pub fn delta(&self) -> (i32, i32) {
+        match self {
+            Direction::Left => (-1, 0),
+            Direction::Right => (1, 0),
+            Direction::Up => (0, -1),
+            Direction::Down => (0, 1),
+        }
+    }
+
+    // Heading of a step from one cell to a neighboring one, or None if the cells aren't neighbors
+    pub fn of_step(from: (i32, i32), to: (i32, i32)) -> Option<Direction> {
+        match (to.0 - from.0, to.1 - from.1) {
+            (-1, 0) => Some(Direction::Left),
+            (1, 0) => Some(Direction::Right),
+            (0, -1) => Some(Direction::Up),
+            (0, 1) => Some(Direction::Down),
+            _ => None,
+        }
+    }
+
+    // Rotation from facing right, in radians; positive turns clockwise on screen since y points down
+    pub fn angle(&self) -> f64 {
+        match self {
+            Direction::Right => 0.0,
+            Direction::Down => std::f64::consts::FRAC_PI_2,
+            Direction::Left => std::f64::consts::PI,
+            Direction::Up => -std::f64::consts::FRAC_PI_2,
+        }
+    }
+}
+
+impl std::str::FromStr for Direction {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "up" => Ok(Direction::Up),
+            "down" => Ok(Direction::Down),
+            "left" => Ok(Direction::Left),
+            "right" => Ok(Direction::Right),
+            _ => Err(()),
+        }
+    }
+}
+
+impl std::fmt::Display for Direction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Direction::Up => "up",
+            Direction::Down => "down",
+            Direction::Left => "left",
+            Direction::Right => "right",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// The body is only changed through the methods below, which keep `occupied` in step with it, so asking
+// whether a cell is taken doesn't mean walking the whole snake. A saved snake is its body, steps and heading;
+// loading one fills `occupied` in again from the body.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[serde(from = "SavedSnake", into = "SavedSnake")]
+pub struct Snake {
+    body: VecDeque<(i32, i32)>, // Head first
+    steps: VecDeque<Direction>, // Heading of the move onto each cell of the body, head first
+    occupied: HashMap<(i32, i32), u32>, // Segments on each cell the body covers; more than one only while it overlaps itself
+    pub dir: Direction,
+    pub last_dir: Direction, // Direction of the last move
+    pub pending_growth: u32, // Segments still to be added, one per move
+    pub previous: Vec<(i32, i32)>, // Body before the last move, head first, for drawing the snake in between cells
+    vacated_tail: Option<(i32, i32)>, // Cell the tail left on the last move, if it moved at all
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SavedSnake {
+    body: Vec<(i32, i32)>,
+    #[serde(default)]
+    steps: Vec<Direction>, // Missing from a save that predates them, in which case they're worked out from the body
+    dir: Direction,
+    last_dir: Direction,
+    pending_growth: u32,
+    previous: Vec<(i32, i32)>,
+    vacated_tail: Option<(i32, i32)>,
+}
+
+impl From<Snake> for SavedSnake {
+    fn from(snake: Snake) -> Self {
+        SavedSnake {
+            body: snake.body.into_iter().collect(),
+            steps: snake.steps.into_iter().collect(),
+            dir: snake.dir,
+            last_dir: snake.last_dir,
+            pending_growth: snake.pending_growth,
+            previous: snake.previous,
+            vacated_tail: snake.vacated_tail,
+        }
+    }
+}
+
+impl From<SavedSnake> for Snake {
+    fn from(saved: SavedSnake) -> Self {
+        let mut snake = if saved.steps.len() == saved.body.len() {
+            let mut snake = Snake::along(saved.last_dir.clone(), &[]);
+            for (cell, step) in saved.body.into_iter().zip(saved.steps) {
+                snake.push_back(cell, step);
+            }
+            snake
+        } else {
+            Snake::along(saved.last_dir.clone(), &saved.body)
+        };
+        snake.dir = saved.dir;
+        snake.last_dir = saved.last_dir;
+        snake.pending_growth = saved.pending_growth;
+        snake.previous = saved.previous;
+        snake.vacated_tail = saved.vacated_tail;
+        snake
+    }
+}
+
+impl Snake {
+    // Spawn in the middle of a grid of `grid` columns and rows facing `dir`, with the body trailing behind the head
+    pub fn new(dir: Direction, grid: (i32, i32)) -> Self {
+        Snake::at(dir, (grid.0 / 2, grid.1 / 2))
+    }
+
+    // Spawn with the head on cell `(x, y)` facing `dir`, with the body trailing behind it
+    pub fn at(dir: Direction, (x, y): (i32, i32)) -> Self {
+        let (dx, dy) = dir.delta();
+        let cells: Vec<(i32, i32)> = (0..START_LENGTH as i32).map(|i| (x - dx * i, y - dy * i)).collect();
+        Snake::along(dir, &cells)
+    }
+
+    pub fn update(&mut self) {
+        self.previous = self.body.iter().copied().collect();
+        let mut new_head = *self.body.front().expect("Snake has no body");
+        match self.dir {
+            Direction::Left => new_head.0 -= 1,
+            Direction::Right => new_head.0 += 1,
+            Direction::Up => new_head.1 -= 1,
+            Direction::Down => new_head.1 += 1,
+        }
+        self.push_front(new_head, self.dir.clone());
+        self.last_dir = self.dir.clone();
+
+        // Growing means keeping the tail in place, so each pending segment skips exactly one pop
+        self.vacated_tail = if self.pending_growth > 0 {
+            self.pending_growth -= 1;
+            None
+        } else {
+            self.pop_back()
+        };
+    }
+
+    // Lay a snake facing `dir` along `cells`, head first. Where two cells aren't neighbors the step
+    // there counts as the same as the step ahead of it, as it would after a wrap.
+    pub fn along(dir: Direction, cells: &[(i32, i32)]) -> Self {
+        let mut snake = Snake {
+            body: VecDeque::new(),
+            steps: VecDeque::new(),
+            occupied: HashMap::new(),
+            last_dir: dir.clone(),
+            dir: dir.clone(),
+            pending_growth: 0,
+            previous: Vec::new(),
+            vacated_tail: None,
+        };
+        let mut ahead = dir;
+        for (i, &cell) in cells.iter().enumerate() {
+            if let Some(&behind) = cells.get(i + 1) {
+                ahead = Direction::of_step(behind, cell).unwrap_or(ahead);
+            }
+            snake.push_back(cell, ahead.clone());
+        }
+        snake.previous = cells.to_vec();
+        snake
+    }
+
+    // Put the head on `cell` instead of where it is, for wrapping around the edge or hopping over a wall.
+    // The head keeps the step that brought it there, so the body reads as straight across the jump.
+    pub fn move_head(&mut self, cell: (i32, i32)) {
+        let step = self.steps.front().cloned().unwrap_or_else(|| self.dir.clone());
+        self.pop_front();
+        self.push_front(cell, step);
+    }
+
+    // Head towards `dir` from the next move on, unless that would reverse straight into the neck. The neck
+    // is behind the last move, which can differ from the heading once turns are applied between moves.
+    pub fn steer(&mut self, dir: Direction) {
+        if dir != self.last_dir.opposite() {
+            self.dir = dir;
+        }
+    }
+
+    // Queue `amount` extra segments, but not beyond `max_length` counting what is already queued;
+    // repeated eats stack up rather than overwrite each other
+    pub fn grow(&mut self, amount: u32, max_length: Option<usize>) {
+        let room = match max_length {
+            Some(max) => max.saturating_sub(self.body.len() + self.pending_growth as usize) as u32,
+            None => u32::MAX,
+        };
+        self.pending_growth += amount.min(room);
+    }
+
+    // Lose `amount` segments, cancelling growth that hasn't happened yet first, but never drop below
+    // the starting length
+    pub fn shrink(&mut self, amount: u32) {
+        let cancelled = amount.min(self.pending_growth);
+        self.pending_growth -= cancelled;
+        for _ in cancelled..amount {
+            if self.body.len() <= START_LENGTH {
+                break;
+            }
+            self.pop_back();
+        }
+    }
+
+    // How the body runs through each segment, head first: the heading coming in from the segment behind
+    // (None for the tail) and going out towards the one ahead (the snake's heading, for the head).
+    // Where the two differ, the body turns. These come from the steps the snake actually took, so a
+    // wrap or a hop across the board reads as the straight move it was.
+    pub fn joints(&self) -> Vec<(Option<Direction>, Direction)> {
+        let last = self.steps.len().saturating_sub(1);
+        (0..self.steps.len())
+            .map(|i| {
+                let into = if i < last { Some(self.steps[i].clone()) } else { None };
+                let out = if i == 0 { self.dir.clone() } else { self.steps[i - 1].clone() };
+                (into, out)
+            })
+            .collect()
+    }
+
+    // The heading of the move onto each cell of the body, head first
+    pub fn steps(&self) -> &VecDeque<Direction> {
+        &self.steps
+    }
+
+    pub fn head(&self) -> (i32, i32) {
+        *self.body.front().unwrap()
+    }
+
+    pub fn tail(&self) -> (i32, i32) {
+        *self.body.back().unwrap()
+    }
+
+    // The cells of the body, head first
+    pub fn body(&self) -> &VecDeque<(i32, i32)> {
+        &self.body
+    }
+
+    // Whether any part of the body covers `cell`
+    pub fn occupies(&self, cell: (i32, i32)) -> bool {
+        self.occupied.contains_key(&cell)
+    }
+
+    // Whether any part of the body behind the head covers `cell`
+    pub fn occupies_behind_head(&self, cell: (i32, i32)) -> bool {
+        let segments = self.occupied.get(&cell).copied().unwrap_or(0);
+        segments > u32::from(cell == self.head())
+    }
+
+    pub fn self_collision(&self, tail_chase: TailChase) -> bool {
+        let head = self.head();
+        let bit_tail = tail_chase == TailChase::Strict && self.vacated_tail == Some(head);
+        bit_tail || self.occupies_behind_head(head)
+    }
+
+    fn push_front(&mut self, cell: (i32, i32), step: Direction) {
+        self.body.push_front(cell);
+        self.steps.push_front(step);
+        *self.occupied.entry(cell).or_insert(0) += 1;
+    }
+
+    fn push_back(&mut self, cell: (i32, i32), step: Direction) {
+        self.body.push_back(cell);
+        self.steps.push_back(step);
+        *self.occupied.entry(cell).or_insert(0) += 1;
+    }
+
+    fn pop_front(&mut self) -> Option<(i32, i32)> {
+        let cell = self.body.pop_front()?;
+        self.steps.pop_front();
+        self.vacate(cell);
+        Some(cell)
+    }
+
+    fn pop_back(&mut self) -> Option<(i32, i32)> {
+        let cell = self.body.pop_back()?;
+        self.steps.pop_back();
+        self.vacate(cell);
+        Some(cell)
+    }
+
+    // One segment fewer on `cell`, forgetting the cell once none are left
+    fn vacate(&mut self, cell: (i32, i32)) {
+        if let Some(segments) = self.occupied.get_mut(&cell) {
+            *segments -= 1;
+            if *segments == 0 {
+                self.occupied.remove(&cell);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::DEFAULT_GRID;
+
+    #[test]
+    fn the_snake_spawns_trailing_behind_its_heading() {
+        for dir in [Direction::Up, Direction::Down, Direction::Left, Direction::Right] {
+            let snake = Snake::new(dir.clone(), DEFAULT_GRID);
+            let (dx, dy) = dir.delta();
+            let body: Vec<(i32, i32)> = snake.body.iter().copied().collect();
+            for pair in body.windows(2) {
+                assert_eq!((pair[0].0 - pair[1].0, pair[0].1 - pair[1].1), (dx, dy));
+            }
+            // The first move lands on a free cell inside the grid
+            let (x, y) = (body[0].0 + dx, body[0].1 + dy);
+            assert!(x >= 0 && x < DEFAULT_GRID.0 && y >= 0 && y < DEFAULT_GRID.1);
+            assert!(!body.contains(&(x, y)));
+        }
+    }
+
+    #[test]
+    fn start_directions_parse_by_name() {
+        assert!("up".parse::<Direction>() == Ok(Direction::Up));
+        assert!("left".parse::<Direction>() == Ok(Direction::Left));
+        assert!("north".parse::<Direction>().is_err());
+        assert!("Up".parse::<Direction>().is_err());
+    }
+
+    // The body length after each of `moves` moves
+    fn lengths(snake: &mut Snake, moves: usize) -> Vec<usize> {
+        (0..moves)
+            .map(|_| {
+                snake.update();
+                snake.body.len()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn growing_by_three_takes_the_next_three_moves() {
+        let mut snake = Snake::new(Direction::Right, DEFAULT_GRID);
+        snake.grow(3, None);
+        assert_eq!(lengths(&mut snake, 5), [4, 5, 6, 6, 6]);
+        assert_eq!(snake.pending_growth, 0);
+    }
+
+    #[test]
+    fn quick_eats_stack_up() {
+        let mut snake = Snake::new(Direction::Right, DEFAULT_GRID);
+        snake.grow(2, None);
+        snake.update();
+        snake.grow(2, None);
+        assert_eq!(snake.pending_growth, 3);
+        assert_eq!(lengths(&mut snake, 4), [5, 6, 7, 7]);
+    }
+
+    #[test]
+    fn the_tail_stays_put_while_growing() {
+        let mut snake = Snake::new(Direction::Right, DEFAULT_GRID);
+        let tail = *snake.body.back().unwrap();
+        snake.grow(2, None);
+        lengths(&mut snake, 2);
+        assert_eq!(*snake.body.back().unwrap(), tail);
+        lengths(&mut snake, 1);
+        assert_ne!(*snake.body.back().unwrap(), tail);
+    }
+
+    #[test]
+    fn quarter_turns_go_round() {
+        let all = [Direction::Up, Direction::Left, Direction::Down, Direction::Right];
+        for (dir, left) in all.iter().zip(all.iter().cycle().skip(1)) {
+            assert!(dir.turn_left() == *left);
+            assert!(dir.turn_left().turn_right() == *dir);
+            assert!(dir.turn_right().turn_right() == dir.opposite());
+            assert!(dir.turn_left().turn_left().turn_left().turn_left() == *dir);
+            assert!(dir.turn_left() != dir.opposite(), "a single turn never reverses");
+        }
+    }
+
+    #[test]
+    fn only_neighbors_are_a_step_apart() {
+        assert!(Direction::of_step((3, 3), (3, 2)) == Some(Direction::Up));
+        assert!(Direction::of_step((3, 3), (4, 3)) == Some(Direction::Right));
+        assert!(Direction::of_step((0, 3), (19, 3)).is_none());
+        assert!(Direction::of_step((3, 3), (4, 4)).is_none());
+    }
+
+    #[test]
+    fn a_turn_bends_the_body() {
+        let mut snake = Snake::new(Direction::Right, DEFAULT_GRID);
+        snake.dir = Direction::Down;
+        snake.update();
+        let joints = snake.joints();
+        assert!(joints[0] == (Some(Direction::Down), Direction::Down));
+        assert!(joints[1] == (Some(Direction::Right), Direction::Down));
+        assert!(joints[2] == (None, Direction::Right));
+    }
+
+    #[test]
+    fn a_wrap_keeps_the_body_straight() {
+        let mut snake = Snake::at(Direction::Left, (0, 5));
+        snake.update();
+        snake.move_head((DEFAULT_GRID.0 - 1, 5));
+        assert_eq!(snake.body().iter().copied().collect::<Vec<_>>(), [(DEFAULT_GRID.0 - 1, 5), (0, 5), (1, 5)]);
+        let joints = snake.joints();
+        assert!(joints.iter().all(|joint| joint.1 == Direction::Left));
+        assert!(joints[0].0 == Some(Direction::Left) && joints[1].0 == Some(Direction::Left) && joints[2].0.is_none());
+    }
+
+    #[test]
+    fn a_snake_laid_across_a_gap_runs_straight_through_it() {
+        let snake = Snake::along(Direction::Up, &[(4, 0), (4, 14), (4, 15), (3, 15)]);
+        assert!(snake.steps().iter().take(2).all(|step| *step == Direction::Up));
+        assert!(snake.joints()[2] == (Some(Direction::Right), Direction::Up));
+    }
+
+    #[test]
+    fn occupancy_follows_the_body_as_it_moves() {
+        let mut snake = Snake::at(Direction::Right, (5, 5));
+        assert!(snake.occupies((3, 5)) && !snake.occupies((6, 5)));
+        snake.update();
+        assert!(snake.occupies((6, 5)) && !snake.occupies((3, 5)));
+        assert!(!snake.occupies_behind_head(snake.head()));
+        assert_eq!(snake.steps().len(), snake.body().len());
+    }
+
+    #[test]
+    fn a_cell_covered_twice_stays_taken_until_both_segments_leave() {
+        let mut snake = Snake::along(Direction::Left, &[(5, 5), (5, 6), (4, 6), (4, 5), (5, 5)]);
+        assert!(snake.occupies_behind_head((5, 5)) && snake.self_collision(TailChase::Classic));
+        snake.update();
+        assert!(snake.occupies((5, 5)));
+        for _ in 0..4 {
+            snake.update();
+        }
+        assert!(!snake.occupies((5, 5)));
+    }
+
+    // A snake that wrapped and then turned, so its steps can't all be read off the body
+    fn wrapped_and_turned() -> Snake {
+        let mut snake = Snake::at(Direction::Left, (0, 5));
+        snake.update();
+        snake.move_head((DEFAULT_GRID.0 - 1, 5));
+        snake.dir = Direction::Up;
+        snake.update();
+        snake
+    }
+
+    #[test]
+    fn a_saved_snake_keeps_its_steps() {
+        let snake = wrapped_and_turned();
+        let loaded: Snake = toml::from_str(&toml::to_string(&snake).unwrap()).unwrap();
+        assert!(loaded.body() == snake.body() && loaded.steps() == snake.steps());
+        assert!(loaded.steps()[1] == Direction::Left);
+        assert!(loaded.occupies((DEFAULT_GRID.0 - 1, 5)) && !loaded.occupies((1, 5)));
+    }
+
+    #[test]
+    fn a_snake_saved_without_steps_lays_them_along_its_body() {
+        let snake = wrapped_and_turned();
+        let text = toml::to_string(&snake).unwrap();
+        let text: String = text.lines().filter(|line| !line.starts_with("steps")).map(|line| format!("{line}\n")).collect();
+        let loaded: Snake = toml::from_str(&text).unwrap();
+        assert!(loaded.body() == snake.body());
+        assert_eq!(loaded.steps().len(), loaded.body().len());
+        assert!(loaded.steps()[0] == Direction::Up && loaded.steps()[1] == Direction::Up);
+    }
+}
@@ -0,0 +1,181 @@
+// Spectator mode: a WebSocket server that sends the live game to whoever connects, for stream overlays and
+// web viewers. Every change to the game goes out to every viewer as one JSON text message, such as
+//
+//     {"state":"running","moves":57,"grid":[20,20],"score":4,"snake":[[9,4],[9,5],[9,6]],
+//      "rival":null,"food":[{"kind":"normal","pos":[3,12]}],"walls":[[5,5]]}
+//
+// `snake` runs head first, `rival` holds player two's `score` and `snake` in a two-player run, and `walls`
+// are the rocks and walls inside the board. Viewers only listen; anything they send is ignored.
+//
+// Connections are taken and written to on threads of their own, so a slow viewer never holds up a frame.
+
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::Duration;
+use tungstenite::{Message, WebSocket};
+use crate::game::{FoodKind, Game, GameState};
+
+const WRITE_TIMEOUT: Duration = Duration::from_secs(2); // A viewer that takes longer to take a message is dropped
+
+// What the sending thread gets: a viewer that just connected, or the game as it is now
+enum Event {
+    Viewer(Box<WebSocket<TcpStream>>),
+    Frame(String),
+}
+
+pub struct Spectators {
+    events: Sender<Event>,
+    last: String, // The last frame sent, so an unchanged game isn't sent again
+}
+
+impl Spectators {
+    // Take viewers on `port` from now on, until the game closes
+    pub fn listen(port: u16) -> Result<Spectators, String> {
+        let listener = TcpListener::bind(("0.0.0.0", port)).map_err(|err| format!("could not listen on port {}: {}", port, err))?;
+        let (events, inbox) = mpsc::channel();
+        let viewers = events.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let viewers = viewers.clone();
+                // The handshake gets a thread of its own, so one that stalls doesn't keep others out
+                std::thread::spawn(move || {
+                    stream.set_write_timeout(Some(WRITE_TIMEOUT)).ok();
+                    if let Ok(viewer) = tungstenite::accept(stream) {
+                        viewers.send(Event::Viewer(Box::new(viewer))).ok();
+                    }
+                });
+            }
+        });
+        std::thread::spawn(move || broadcast(inbox));
+        Ok(Spectators { events, last: String::new() })
+    }
+
+    // Send the game to every viewer if it changed since the last call; meant to be called every frame
+    pub fn publish(&mut self, game: &Game) {
+        let frame = frame(game);
+        if frame != self.last {
+            self.events.send(Event::Frame(frame.clone())).ok();
+            self.last = frame;
+        }
+    }
+}
+
+// Hand every frame to every viewer, starting a new viewer off with the latest one. A viewer that can't be
+// written to has left, and is dropped.
+fn broadcast(inbox: Receiver<Event>) {
+    let mut viewers: Vec<Box<WebSocket<TcpStream>>> = Vec::new();
+    let mut last: Option<String> = None;
+    for event in inbox {
+        match event {
+            Event::Viewer(mut viewer) => {
+                if let Some(frame) = &last
+                    && viewer.send(Message::text(frame.clone())).is_err()
+                {
+                    continue;
+                }
+                viewers.push(viewer);
+            },
+            Event::Frame(frame) => {
+                viewers.retain_mut(|viewer| viewer.send(Message::text(frame.clone())).is_ok());
+                last = Some(frame);
+            },
+        }
+    }
+}
+
+// The game as one JSON object
+fn frame(game: &Game) -> String {
+    let state = match game.state {
+        GameState::Menu => "menu",
+        GameState::Running => "running",
+        GameState::Paused => "paused",
+        GameState::GameOver => "game_over",
+        GameState::LevelComplete => "level_complete",
+    };
+    let rival = match &game.rival {
+        Some(rival) => format!("{{\"score\":{},\"snake\":{}}}", rival.score, cells(rival.snake.body().iter())),
+        None => "null".to_string(),
+    };
+    let food: Vec<String> = game.foods.iter().map(|food| format!("{{\"kind\":\"{}\",\"pos\":[{},{}]}}", kind(food.kind), food.pos.0, food.pos.1)).collect();
+    let mut walls: Vec<&(i32, i32)> = game.obstacles.iter().collect();
+    walls.sort();
+    format!(
+        "{{\"state\":\"{}\",\"moves\":{},\"grid\":[{},{}],\"score\":{},\"snake\":{},\"rival\":{},\"food\":[{}],\"walls\":{}}}",
+        state,
+        game.moves,
+        game.settings.grid.0,
+        game.settings.grid.1,
+        game.score,
+        cells(game.snake.body().iter()),
+        rival,
+        food.join(","),
+        cells(walls.into_iter()),
+    )
+}
+
+fn cells<'a>(cells: impl Iterator<Item = &'a (i32, i32)>) -> String {
+    let cells: Vec<String> = cells.map(|(x, y)| format!("[{},{}]", x, y)).collect();
+    format!("[{}]", cells.join(","))
+}
+
+fn kind(kind: FoodKind) -> &'static str {
+    match kind {
+        FoodKind::Normal => "normal",
+        FoodKind::Bonus => "bonus",
+        FoodKind::Poison => "poison",
+        FoodKind::Large => "large",
+        FoodKind::Golden => "golden",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Settings;
+    use crate::snake::{Direction, Snake};
+
+    // A running game with a short snake, one food and one wall
+    fn game() -> Game {
+        let mut game = Game::new(&Settings::default());
+        game.snake = Snake::along(Direction::Right, &[(5, 5), (4, 5), (3, 5)]);
+        game.foods.truncate(1);
+        game.foods[0].pos = (9, 2);
+        game.foods[0].kind = FoodKind::Bonus;
+        game.obstacles = [(12, 12)].into_iter().collect();
+        game.state = GameState::Running;
+        game
+    }
+
+    #[test]
+    fn a_frame_is_the_game_as_json() {
+        let mut game = game();
+        game.score = 7;
+        assert_eq!(
+            frame(&game),
+            "{\"state\":\"running\",\"moves\":0,\"grid\":[20,20],\"score\":7,\"snake\":[[5,5],[4,5],[3,5]],\
+             \"rival\":null,\"food\":[{\"kind\":\"bonus\",\"pos\":[9,2]}],\"walls\":[[12,12]]}"
+        );
+    }
+
+    #[test]
+    fn a_viewer_gets_the_current_game_and_then_each_change() {
+        let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let mut spectators = Spectators::listen(port).unwrap();
+        let mut game = game();
+        spectators.publish(&game);
+
+        // Joining late still starts off with the game as it is
+        let stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let (mut viewer, _) = tungstenite::client(format!("ws://127.0.0.1:{}", port), stream).unwrap();
+        assert_eq!(viewer.read().unwrap(), Message::text(frame(&game)));
+
+        game.update();
+        spectators.publish(&game);
+        let moved = viewer.read().unwrap();
+        assert_eq!(moved, Message::text(frame(&game)));
+        assert!(moved.to_text().unwrap().contains("\"moves\":1,"));
+        spectators.publish(&game);
+        assert_eq!(spectators.last, frame(&game), "an unchanged game isn't sent again");
+    }
+}
@@ -0,0 +1,155 @@
+// Numbers kept between sessions, stored as `name value` lines so more can be added without breaking old files
+
+use std::path::{Path, PathBuf};
+
+use crate::files::write_atomically;
+
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct Stats {
+    pub high_score: u32, // Best score without a time limit
+    pub time_attack_high_score: u32, // Best score in time attack, whatever the time limit
+    pub games_played: u32, // Finished one-player runs, of any kind
+    pub total_score: u64, // Score of all of those runs added up
+    pub food_eaten: u64,
+    pub seconds_played: f64, // Game time, so slow motion counts for less than real time
+    pub longest_snake: u32, // Most segments a snake ever had at the end of a run
+}
+
+impl Stats {
+    // Where interactive play keeps its stats, or None when the platform has no data directory
+    pub fn path() -> Option<PathBuf> {
+        Some(dirs::data_dir()?.join("copperhead").join("stats"))
+    }
+
+    // Read stats from `path`. A missing file is a first run and gives the defaults. A file that can't be
+    // read or has a malformed line is an error, which the caller can report before carrying on with the defaults.
+    // Unknown names are skipped, so files written by newer versions still load.
+    pub fn load(path: &Path) -> Result<Stats, String> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Stats::default()),
+            Err(err) => return Err(err.to_string()),
+        };
+        let mut stats = Stats::default();
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            let (name, value) = line.split_once(' ').ok_or_else(|| format!("malformed line {:?}", line))?;
+            match name {
+                "high_score" => stats.high_score = parse(value, "high score")?,
+                "time_attack_high_score" => stats.time_attack_high_score = parse(value, "high score")?,
+                "games_played" => stats.games_played = parse(value, "game count")?,
+                "total_score" => stats.total_score = parse(value, "total score")?,
+                "food_eaten" => stats.food_eaten = parse(value, "food count")?,
+                "seconds_played" => stats.seconds_played = parse(value, "play time")?,
+                "longest_snake" => stats.longest_snake = parse(value, "snake length")?,
+                _ => {},
+            }
+        }
+        Ok(stats)
+    }
+
+    // Add a finished run to the lifetime totals
+    pub fn count_run(&mut self, score: u32, food_eaten: u32, seconds: f64, length: u32) {
+        self.games_played += 1;
+        self.total_score += score as u64;
+        self.food_eaten += food_eaten as u64;
+        self.seconds_played += seconds;
+        self.longest_snake = self.longest_snake.max(length);
+    }
+
+    // Mean score per finished run, 0 before the first one
+    pub fn average_score(&self) -> f64 {
+        if self.games_played == 0 { 0.0 } else { self.total_score as f64 / self.games_played as f64 }
+    }
+
+    // The high score of runs with a time limit, or of runs without one
+    pub fn best(&self, time_attack: bool) -> u32 {
+        if time_attack { self.time_attack_high_score } else { self.high_score }
+    }
+
+    pub fn set_best(&mut self, time_attack: bool, score: u32) {
+        if time_attack {
+            self.time_attack_high_score = score;
+        } else {
+            self.high_score = score;
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        let lines = [
+            ("high_score", self.high_score.to_string()),
+            ("time_attack_high_score", self.time_attack_high_score.to_string()),
+            ("games_played", self.games_played.to_string()),
+            ("total_score", self.total_score.to_string()),
+            ("food_eaten", self.food_eaten.to_string()),
+            ("seconds_played", format!("{:.1}", self.seconds_played)),
+            ("longest_snake", self.longest_snake.to_string()),
+        ];
+        let text: String = lines.iter().map(|(name, value)| format!("{} {}\n", name, value)).collect();
+        write_atomically(path, &text)
+    }
+}
+
+fn parse<T: std::str::FromStr>(value: &str, what: &str) -> Result<T, String> {
+    value.trim().parse().map_err(|_| format!("malformed {} {:?}", what, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A fresh path under the system temp directory, unique to this test process and `name`
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("copperhead-stats-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn a_missing_file_gives_the_defaults() {
+        assert_eq!(Stats::load(&temp_path("missing")), Ok(Stats::default()));
+    }
+
+    #[test]
+    fn saved_stats_load_back() {
+        let path = temp_path("round-trip");
+        Stats { high_score: 42, time_attack_high_score: 9, ..Stats::default() }.save(&path).unwrap();
+        assert_eq!(Stats::load(&path), Ok(Stats { high_score: 42, time_attack_high_score: 9, ..Stats::default() }));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn a_malformed_value_is_an_error() {
+        let path = temp_path("malformed");
+        std::fs::write(&path, "high_score lots\n").unwrap();
+        assert!(Stats::load(&path).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn unknown_lines_are_skipped() {
+        let path = temp_path("unknown");
+        std::fs::write(&path, "longest_run 9\nhigh_score 7\n").unwrap();
+        assert_eq!(Stats::load(&path), Ok(Stats { high_score: 7, ..Stats::default() }));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn each_kind_of_run_keeps_its_own_best() {
+        let mut stats = Stats::default();
+        stats.set_best(true, 30);
+        stats.set_best(false, 12);
+        assert_eq!((stats.best(true), stats.best(false)), (30, 12));
+    }
+
+    #[test]
+    fn lifetime_totals_add_up_and_survive_a_save() {
+        let mut stats = Stats::default();
+        assert_eq!(stats.average_score(), 0.0);
+        stats.count_run(10, 4, 30.0, 7);
+        stats.count_run(20, 6, 45.5, 5);
+        assert_eq!((stats.games_played, stats.total_score, stats.food_eaten, stats.longest_snake), (2, 30, 10, 7));
+        assert_eq!(stats.average_score(), 15.0);
+        let path = temp_path("totals");
+        stats.save(&path).unwrap();
+        assert_eq!(Stats::load(&path), Ok(stats));
+        std::fs::remove_file(path).unwrap();
+    }
+}
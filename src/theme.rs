@@ -0,0 +1,369 @@
+// Every color the game is drawn in, gathered in a `Theme`. A few themes come built in: the classic copper one,
+// a high-contrast one and one safe for red-green colorblindness (deuteranopia), which tells food, poison and
+// the two snakes apart by blue against yellow and by brightness instead of by red against green. A theme is
+// picked by name, and any of its colors can be changed in the config by the names in `Theme::names`.
+
+use crate::game::{FoodKind, PowerUp};
+use crate::renderer::Color;
+
+pub const DEFAULT_THEME: &str = "copper";
+pub const THEME_NAMES: [&str; 3] = ["copper", "high-contrast", "deuteranopia"];
+
+// Colors of a snake drawn from shapes
+#[derive(Clone, Copy, PartialEq)]
+pub struct Skin {
+    pub head: Color,
+    pub stripes: [Color; 2], // Alternating body colors, starting at the neck
+    pub eyes: Color,
+    pub shine: Color, // The reflection on the head, drawn see-through
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Theme {
+    pub name: &'static str, // The built-in theme it started out as, one of THEME_NAMES
+    pub board: Color,
+    pub border: Color, // Also walls, rocks and the ink on pickups
+    pub text: Color,
+    pub letterbox: Color, // Window background around the board
+    pub food: Color,
+    pub bonus_food: Color,
+    pub poison: Color,
+    pub large_food: Color,
+    pub golden_food: Color,
+    pub player_one: Skin,
+    pub player_two: Skin,
+    pub portals: [Color; 4], // One per pair, taken in turn
+    pub portal_core: Color, // Dark middle of every portal
+    pub speed: Color, // Power-ups
+    pub slow_motion: Color,
+    pub ghost: Color,
+    pub shrink: Color,
+    pub record: Color, // A new high score, and anything else worth celebrating
+    pub danger: Color, // Warnings: the clock running out, a trapped snake, a ring about to close
+    pub combo: Color,
+    pub phase: Color, // The phase pickup, and the armed phase charges
+    pub bricks: Color, // The wall ring
+    pub dead_zone: Color, // Rings a shrinking arena has closed
+    pub game_over: Color, // Background of the game over screen
+    pub glint: Color, // Assist arrows and the editor's cursor
+    pub sparks: Color, // Flying off eaten food
+    pub panel: Color, // Behind achievement toasts
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::COPPER
+    }
+}
+
+impl Theme {
+    pub const COPPER: Theme = Theme {
+        name: "copper",
+        board: [0.65, 0.40, 0.18, 1.0], // A bright, saturated copper
+        border: [0.25, 0.13, 0.05, 1.0],
+        text: [0.95, 0.85, 0.65, 1.0],
+        letterbox: [0.65, 0.40, 0.18, 1.0], // Blends in with the board
+        food: [0.95, 0.64, 0.37, 1.0],
+        bonus_food: [1.0, 0.84, 0.30, 1.0], // Gold, like the record score
+        poison: [0.45, 0.70, 0.25, 1.0], // Sickly green
+        large_food: [0.85, 0.30, 0.15, 1.0], // Deep red
+        golden_food: [1.0, 0.92, 0.55, 1.0], // Paler and brighter than bonus food
+        player_one: Skin {
+            head: [0.90, 0.60, 0.25, 1.0], // More coppery head
+            stripes: [[0.60, 0.30, 0.10, 1.0], [0.85, 0.55, 0.22, 1.0]], // Darker and lighter copper
+            eyes: [0.1, 0.1, 0.1, 1.0],
+            shine: [1.0, 0.95, 0.80, 1.0],
+        },
+        player_two: Skin {
+            head: [0.60, 0.70, 0.85, 1.0], // Blue-gray, so it stands apart from the copper one
+            stripes: [[0.28, 0.35, 0.48, 1.0], [0.50, 0.60, 0.75, 1.0]],
+            eyes: [0.1, 0.1, 0.1, 1.0],
+            shine: [1.0, 0.95, 0.80, 1.0],
+        },
+        portals: [
+            [0.65, 0.40, 0.95, 1.0], // Violet
+            [0.25, 0.80, 0.80, 1.0], // Teal
+            [0.95, 0.40, 0.70, 1.0], // Magenta
+            [0.60, 0.90, 0.30, 1.0], // Lime
+        ],
+        portal_core: [0.12, 0.06, 0.16, 1.0],
+        speed: [0.95, 0.45, 0.20, 1.0], // Hot orange
+        slow_motion: [0.45, 0.65, 0.95, 1.0], // Cool blue
+        ghost: [0.92, 0.92, 0.97, 1.0], // Pale white
+        shrink: [0.70, 0.45, 0.85, 1.0], // Violet
+        record: [1.0, 0.84, 0.30, 1.0],
+        danger: [0.95, 0.35, 0.25, 1.0],
+        combo: [0.95, 0.55, 0.20, 1.0],
+        phase: [0.55, 0.90, 1.0, 1.0], // Cyan
+        bricks: [0.32, 0.17, 0.07, 1.0],
+        dead_zone: [0.45, 0.16, 0.10, 1.0], // A dull red, darker than the board
+        game_over: [0.6, 0.1, 0.1, 1.0],
+        glint: [1.0, 0.95, 0.80, 1.0],
+        sparks: [1.0, 0.70, 0.40, 1.0],
+        panel: [0.12, 0.07, 0.03, 1.0],
+    };
+
+    // Pure black and white for the board and text, with every piece in a bright color of its own
+    pub const HIGH_CONTRAST: Theme = Theme {
+        name: "high-contrast",
+        board: [0.0, 0.0, 0.0, 1.0],
+        border: [0.40, 0.40, 0.45, 1.0],
+        text: [1.0, 1.0, 1.0, 1.0],
+        letterbox: [0.0, 0.0, 0.0, 1.0],
+        food: [1.0, 1.0, 1.0, 1.0],
+        bonus_food: [1.0, 0.85, 0.0, 1.0],
+        poison: [0.70, 0.30, 1.0, 1.0],
+        large_food: [1.0, 0.25, 0.25, 1.0],
+        golden_food: [1.0, 1.0, 0.55, 1.0],
+        player_one: Skin {
+            head: [1.0, 0.90, 0.0, 1.0],
+            stripes: [[0.85, 0.65, 0.0, 1.0], [1.0, 0.80, 0.0, 1.0]],
+            eyes: [0.0, 0.0, 0.0, 1.0],
+            shine: [1.0, 1.0, 1.0, 1.0],
+        },
+        player_two: Skin {
+            head: [0.0, 0.90, 1.0, 1.0],
+            stripes: [[0.0, 0.55, 0.75, 1.0], [0.0, 0.75, 0.95, 1.0]],
+            eyes: [0.0, 0.0, 0.0, 1.0],
+            shine: [1.0, 1.0, 1.0, 1.0],
+        },
+        portals: [
+            [1.0, 0.0, 1.0, 1.0],
+            [0.0, 1.0, 1.0, 1.0],
+            [1.0, 0.55, 0.0, 1.0],
+            [0.30, 1.0, 0.30, 1.0],
+        ],
+        portal_core: [0.0, 0.0, 0.0, 1.0],
+        speed: [1.0, 0.55, 0.0, 1.0],
+        slow_motion: [0.30, 0.60, 1.0, 1.0],
+        ghost: [1.0, 1.0, 1.0, 1.0],
+        shrink: [0.80, 0.40, 1.0, 1.0],
+        record: [1.0, 0.90, 0.0, 1.0],
+        danger: [1.0, 0.20, 0.20, 1.0],
+        combo: [1.0, 0.55, 0.0, 1.0],
+        phase: [0.0, 1.0, 1.0, 1.0],
+        bricks: [0.65, 0.65, 0.70, 1.0],
+        dead_zone: [0.35, 0.0, 0.0, 1.0],
+        game_over: [0.30, 0.0, 0.0, 1.0],
+        glint: [1.0, 1.0, 1.0, 1.0],
+        sparks: [1.0, 1.0, 0.60, 1.0],
+        panel: [0.0, 0.0, 0.0, 1.0],
+    };
+
+    // The Okabe-Ito colors, which stay apart for red-green colorblind players: yellows and oranges against
+    // blues and purples, on a dark slate board
+    pub const DEUTERANOPIA: Theme = Theme {
+        name: "deuteranopia",
+        board: [0.18, 0.20, 0.26, 1.0],
+        border: [0.07, 0.08, 0.11, 1.0],
+        text: [0.95, 0.95, 0.95, 1.0],
+        letterbox: [0.18, 0.20, 0.26, 1.0],
+        food: [0.94, 0.89, 0.26, 1.0], // Yellow
+        bonus_food: [0.34, 0.71, 0.91, 1.0], // Sky blue
+        poison: [0.80, 0.47, 0.65, 1.0], // Reddish purple
+        large_food: [0.84, 0.37, 0.0, 1.0], // Vermilion
+        golden_food: [1.0, 1.0, 1.0, 1.0],
+        player_one: Skin {
+            head: [0.90, 0.62, 0.0, 1.0], // Orange
+            stripes: [[0.65, 0.45, 0.0, 1.0], [0.85, 0.58, 0.0, 1.0]],
+            eyes: [0.0, 0.0, 0.0, 1.0],
+            shine: [1.0, 1.0, 1.0, 1.0],
+        },
+        player_two: Skin {
+            head: [0.34, 0.71, 0.91, 1.0], // Sky blue
+            stripes: [[0.0, 0.35, 0.60, 1.0], [0.0, 0.45, 0.70, 1.0]], // Blue
+            eyes: [0.0, 0.0, 0.0, 1.0],
+            shine: [1.0, 1.0, 1.0, 1.0],
+        },
+        portals: [
+            [0.34, 0.71, 0.91, 1.0],
+            [0.94, 0.89, 0.26, 1.0],
+            [0.80, 0.47, 0.65, 1.0],
+            [0.0, 0.62, 0.45, 1.0], // Bluish green
+        ],
+        portal_core: [0.05, 0.05, 0.08, 1.0],
+        speed: [0.90, 0.62, 0.0, 1.0],
+        slow_motion: [0.0, 0.45, 0.70, 1.0],
+        ghost: [0.95, 0.95, 0.95, 1.0],
+        shrink: [0.80, 0.47, 0.65, 1.0],
+        record: [0.94, 0.89, 0.26, 1.0],
+        danger: [0.84, 0.37, 0.0, 1.0],
+        combo: [0.90, 0.62, 0.0, 1.0],
+        phase: [0.34, 0.71, 0.91, 1.0],
+        bricks: [0.30, 0.33, 0.40, 1.0],
+        dead_zone: [0.08, 0.09, 0.12, 1.0],
+        game_over: [0.30, 0.15, 0.0, 1.0],
+        glint: [1.0, 1.0, 1.0, 1.0],
+        sparks: [0.94, 0.89, 0.26, 1.0],
+        panel: [0.07, 0.08, 0.11, 1.0],
+    };
+
+    pub fn food_color(&self, kind: FoodKind) -> Color {
+        match kind {
+            FoodKind::Normal => self.food,
+            FoodKind::Bonus => self.bonus_food,
+            FoodKind::Poison => self.poison,
+            FoodKind::Large => self.large_food,
+            FoodKind::Golden => self.golden_food,
+        }
+    }
+
+    pub fn power_up_color(&self, power_up: PowerUp) -> Color {
+        match power_up {
+            PowerUp::Speed => self.speed,
+            PowerUp::SlowMotion => self.slow_motion,
+            PowerUp::Ghost => self.ghost,
+            PowerUp::Shrink => self.shrink,
+        }
+    }
+
+    // Drop shadows are one opaque color that matches black at 25% over the board
+    pub fn shadow(&self) -> Color {
+        let [r, g, b, _] = self.board;
+        [r * 0.75, g * 0.75, b * 0.75, 1.0]
+    }
+
+    // Every color by the name the config knows it by
+    pub fn names(&mut self) -> [(&'static str, &mut Color); 38] {
+        let [portal_1, portal_2, portal_3, portal_4] = &mut self.portals;
+        let [snake_dark, snake_light] = &mut self.player_one.stripes;
+        let [rival_dark, rival_light] = &mut self.player_two.stripes;
+        [
+            ("board", &mut self.board),
+            ("border", &mut self.border),
+            ("text", &mut self.text),
+            ("letterbox", &mut self.letterbox),
+            ("food", &mut self.food),
+            ("bonus_food", &mut self.bonus_food),
+            ("poison", &mut self.poison),
+            ("large_food", &mut self.large_food),
+            ("golden_food", &mut self.golden_food),
+            ("snake_head", &mut self.player_one.head),
+            ("snake_dark", snake_dark),
+            ("snake_light", snake_light),
+            ("snake_eyes", &mut self.player_one.eyes),
+            ("snake_shine", &mut self.player_one.shine),
+            ("rival_head", &mut self.player_two.head),
+            ("rival_dark", rival_dark),
+            ("rival_light", rival_light),
+            ("rival_eyes", &mut self.player_two.eyes),
+            ("rival_shine", &mut self.player_two.shine),
+            ("portal_1", portal_1),
+            ("portal_2", portal_2),
+            ("portal_3", portal_3),
+            ("portal_4", portal_4),
+            ("portal_core", &mut self.portal_core),
+            ("speed", &mut self.speed),
+            ("slow_motion", &mut self.slow_motion),
+            ("ghost", &mut self.ghost),
+            ("shrink", &mut self.shrink),
+            ("record", &mut self.record),
+            ("danger", &mut self.danger),
+            ("combo", &mut self.combo),
+            ("phase", &mut self.phase),
+            ("bricks", &mut self.bricks),
+            ("dead_zone", &mut self.dead_zone),
+            ("game_over", &mut self.game_over),
+            ("glint", &mut self.glint),
+            ("sparks", &mut self.sparks),
+            ("panel", &mut self.panel),
+        ]
+    }
+
+    // The built-in theme called `name`, with every color this theme changed from its own built-in one
+    // changed the same way; None if there is no theme by that name
+    pub fn switch_to(&self, name: &str) -> Option<Theme> {
+        let mut switched: Theme = name.parse().ok()?;
+        let mut base: Theme = self.name.parse().ok()?;
+        let mut changed = *self;
+        for ((_, color), ((_, base_color), (_, switched_color))) in changed.names().into_iter().zip(base.names().into_iter().zip(switched.names())) {
+            if *color != *base_color {
+                *switched_color = *color;
+            }
+        }
+        Some(switched)
+    }
+
+    // Change the color called `name`; false if the theme has no color by that name
+    pub fn set(&mut self, name: &str, color: Color) -> bool {
+        match self.names().into_iter().find(|(known, _)| *known == name) {
+            Some((_, slot)) => {
+                *slot = color;
+                true
+            },
+            None => false,
+        }
+    }
+}
+
+// A built-in theme by name, as on the command line and in the config
+impl std::str::FromStr for Theme {
+    type Err = ();
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "copper" => Ok(Theme::COPPER),
+            "high-contrast" => Ok(Theme::HIGH_CONTRAST),
+            "deuteranopia" => Ok(Theme::DEUTERANOPIA),
+            _ => Err(()),
+        }
+    }
+}
+
+// `color` made see-through, `alpha` from 0 (invisible) to 1 (as opaque as it was)
+pub fn with_alpha([r, g, b, a]: Color, alpha: f32) -> Color {
+    [r, g, b, a * alpha]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_theme_is_known_by_its_name() {
+        let themes: Vec<Theme> = THEME_NAMES.iter().map(|name| name.parse().unwrap()).collect();
+        assert!(themes == [Theme::COPPER, Theme::HIGH_CONTRAST, Theme::DEUTERANOPIA]);
+        assert!(DEFAULT_THEME.parse::<Theme>() == Ok(Theme::default()));
+        assert!("neon".parse::<Theme>().is_err());
+    }
+
+    #[test]
+    fn each_color_has_a_name_of_its_own() {
+        let mut theme = Theme::default();
+        let mut names: Vec<&str> = theme.names().into_iter().map(|(name, _)| name).collect();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), 38);
+    }
+
+    #[test]
+    fn setting_a_color_changes_only_that_one() {
+        let mut theme = Theme::COPPER;
+        let blue = [0.0, 0.0, 1.0, 1.0];
+        assert!(theme.set("rival_light", blue));
+        assert!(theme.player_two.stripes == [Theme::COPPER.player_two.stripes[0], blue]);
+        assert!(Theme { player_two: Theme::COPPER.player_two, ..theme } == Theme::COPPER);
+        assert!(!theme.set("rival_lite", blue));
+    }
+
+    #[test]
+    fn switching_themes_keeps_the_colors_that_were_changed() {
+        let mut theme = Theme::COPPER;
+        let blue = [0.0, 0.0, 1.0, 1.0];
+        theme.set("food", blue);
+        let switched = theme.switch_to("deuteranopia").unwrap();
+        assert!(switched == Theme { food: blue, ..Theme::DEUTERANOPIA });
+        assert!(switched.switch_to("copper") == Some(theme));
+        assert!(theme.switch_to("neon").is_none());
+    }
+
+    #[test]
+    fn the_colorblind_theme_keeps_the_pieces_apart_without_red_against_green() {
+        // Red-green colorblind players see the blue against yellow of each pair; `b - (r + g) / 2` measures it
+        let blueness = |[r, g, b, _]: Color| b - (r + g) / 2.0;
+        let theme = Theme::DEUTERANOPIA;
+        for (one, two) in [(theme.food, theme.bonus_food), (theme.food, theme.poison), (theme.player_one.head, theme.player_two.head)] {
+            assert!((blueness(one) - blueness(two)).abs() > 0.3, "{one:?} and {two:?}");
+        }
+    }
+}
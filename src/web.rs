@@ -0,0 +1,248 @@
+// The browser frontend, built with the `wasm` feature: the game on a `<canvas>`, steered from the keyboard.
+// It draws with the canvas's own 2D context instead of Piston, in plain shapes and the browser's monospace
+// font. What needs the desktop is left out: files (the config, high scores, saved runs, campaign levels and
+// sprites), the clipboard, music, gamepads and anything over the network.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use piston_input::Key;
+use wasm_bindgen::JsCast;
+use wasm_bindgen::prelude::*;
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, KeyboardEvent};
+use crate::game::{Game, GameState, Settings};
+use crate::move_scheduler::MoveScheduler;
+use crate::input::{key_from_name, HeldKeys};
+use crate::theme::Skin;
+
+const DIM_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.35]; // Laid over the board under the start and game over text
+
+// The per-frame callback, which asks for the next frame with itself
+type FrameLoop = Rc<RefCell<Option<Closure<dyn FnMut(f64)>>>>;
+
+// Start the game on the canvas with the id `canvas_id`, which is resized to fit the board. It keeps running
+// until the page is closed.
+#[wasm_bindgen]
+pub fn start(canvas_id: &str) -> Result<(), JsValue> {
+    let window = web_sys::window().ok_or("no window")?;
+    let document = window.document().ok_or("no document")?;
+    let canvas: HtmlCanvasElement = document.get_element_by_id(canvas_id).ok_or_else(|| format!("no element with id {:?}", canvas_id))?.dyn_into()?;
+    let settings = Settings::builder().build().map_err(|err| err.to_string())?;
+    let [width, height] = settings.layout().window_size();
+    canvas.set_width(width);
+    canvas.set_height(height);
+    let context: CanvasRenderingContext2d = canvas.get_context("2d")?.ok_or("no 2D context")?.dyn_into()?;
+
+    let game = Rc::new(RefCell::new(Game::new(&settings)));
+    let held = Rc::new(RefCell::new(HeldKeys::default()));
+    listen_keys(&document, &game, &held)?;
+
+    // One update and one picture per animation frame, which the browser paces to the display
+    let mut scheduler = MoveScheduler::new(settings.move_interval);
+    let mut last_frame: Option<f64> = None;
+    let frame: FrameLoop = Rc::new(RefCell::new(None));
+    let next_frame = frame.clone();
+    *frame.borrow_mut() = Some(Closure::new(move |now: f64| {
+        {
+            let mut game = game.borrow_mut();
+            let dt = game.game_dt(last_frame.map_or(0.0, |last| (now - last) / 1000.0));
+            last_frame = Some(now);
+            game.tick_clock(dt);
+            // The speed can be changed on the options page
+            scheduler.set_interval(game.settings.move_interval);
+            for _ in 0..scheduler.tick(dt * game.move_speed()) {
+                game.update();
+            }
+            game.move_progress = scheduler.progress();
+            if game.is_dirty() {
+                draw(&game, &context);
+                game.clear_dirty();
+            }
+        }
+        if let Some(frame) = next_frame.borrow().as_ref() {
+            request_frame(frame);
+        }
+    }));
+    if let Some(frame) = frame.borrow().as_ref() {
+        request_frame(frame);
+    }
+    Ok(())
+}
+
+fn request_frame(frame: &Closure<dyn FnMut(f64)>) {
+    if let Some(window) = web_sys::window() {
+        window.request_animation_frame(frame.as_ref().unchecked_ref()).ok();
+    }
+}
+
+// Key presses go to the game the way the window's do, with held keys counted once. There's no quitting from
+// a page, so Escape only does what it does short of that.
+fn listen_keys(document: &web_sys::Document, game: &Rc<RefCell<Game>>, held: &Rc<RefCell<HeldKeys>>) -> Result<(), JsValue> {
+    let (game, presses) = (game.clone(), held.clone());
+    let on_press = Closure::<dyn FnMut(KeyboardEvent)>::new(move |event: KeyboardEvent| {
+        let Some(key) = browser_key(&event.key()) else {
+            return;
+        };
+        // The arrow keys and space would scroll the page as well
+        event.prevent_default();
+        if !presses.borrow_mut().press(key) {
+            return;
+        }
+        let mut game = game.borrow_mut();
+        if key != Key::Escape || !game.quits_on_escape() {
+            game.press_key(key);
+        }
+    });
+    let releases = held.clone();
+    let on_release = Closure::<dyn FnMut(KeyboardEvent)>::new(move |event: KeyboardEvent| {
+        if let Some(key) = browser_key(&event.key()) {
+            releases.borrow_mut().release(key);
+        }
+    });
+    document.add_event_listener_with_callback("keydown", on_press.as_ref().unchecked_ref())?;
+    document.add_event_listener_with_callback("keyup", on_release.as_ref().unchecked_ref())?;
+    // The listeners live as long as the page does
+    on_press.forget();
+    on_release.forget();
+    Ok(())
+}
+
+// The key a browser key name stands for
+fn browser_key(name: &str) -> Option<Key> {
+    match name {
+        "ArrowUp" => Some(Key::Up),
+        "ArrowDown" => Some(Key::Down),
+        "ArrowLeft" => Some(Key::Left),
+        "ArrowRight" => Some(Key::Right),
+        " " => Some(Key::Space),
+        "Enter" => Some(Key::Return),
+        "Tab" => Some(Key::Tab),
+        "Escape" => Some(Key::Escape),
+        "Backspace" => Some(Key::Backspace),
+        "F1" => Some(Key::F1),
+        _ => key_from_name(&name.to_lowercase()),
+    }
+}
+
+fn draw(game: &Game, context: &CanvasRenderingContext2d) {
+    let layout = game.settings.layout();
+    let theme = game.settings.theme;
+    let [width, height] = layout.window_size().map(f64::from);
+    fill(context, theme.board, [0.0, 0.0, width, height]);
+    for rect in layout.border_rects() {
+        fill(context, theme.border, rect);
+    }
+
+    // On the start screen the demo, once it plays, is drawn behind the title
+    let shown = match (&game.demo, &game.state) {
+        (Some(demo), GameState::Menu) => demo.as_ref(),
+        _ => game,
+    };
+    let [px, py] = layout.playfield_origin();
+    let cell = |pos| {
+        let [x, y, w, h] = layout.square(pos);
+        [px + x, py + y, w, h]
+    };
+    if shown.state != GameState::Menu || game.demo.is_some() {
+        for &pos in &shown.obstacles {
+            fill(context, theme.border, cell(pos));
+        }
+        for food in &shown.foods {
+            fill(context, theme.food_color(food.kind), inset(cell(food.pos), 0.1));
+        }
+        if let Some(rival) = &shown.rival {
+            draw_snake(context, rival.snake.body().iter().map(|&pos| cell(pos)), theme.player_two);
+        }
+        draw_snake(context, shown.snake.body().iter().map(|&pos| cell(pos)), theme.player_one);
+    }
+
+    // The score along the top border, both players' in a two-player run
+    context.set_font(&format!("{}px monospace", (px * 0.6).round()));
+    context.set_text_align("center");
+    context.set_text_baseline("middle");
+    let score = match &shown.rival {
+        Some(rival) => format!("{}  :  {}", shown.score, rival.score),
+        None => format!("{}   best {}", shown.score, game.high_score),
+    };
+    let score = match shown.time_left {
+        Some(left) => format!("{}   {:.1}s", score, left),
+        None => score,
+    };
+    text(context, theme.text, &score, [width / 2.0, py / 2.0]);
+
+    let lines: Vec<String> = match game.state {
+        GameState::Menu if game.demo.is_some() => vec!["COPPERHEAD".to_string(), "Press any key".to_string()],
+        GameState::Menu => {
+            let mut lines = vec!["COPPERHEAD".to_string()];
+            for &item in game.menu.items() {
+                let label = game.menu_label(item);
+                lines.push(if game.menu.is_selected(item) { format!("> {} <", label) } else { label });
+            }
+            lines
+        },
+        GameState::Paused => vec!["PAUSED".to_string(), "P or Space to resume".to_string()],
+        GameState::Running if game.countdown > 0.0 => vec![game.countdown.ceil().to_string()],
+        GameState::GameOver => {
+            let (over, cause) = game.outcome();
+            let mut lines = vec![over.to_string(), cause.to_string(), "Space to play again".to_string()];
+            lines.extend(game.notice.clone());
+            lines
+        },
+        GameState::Running | GameState::LevelComplete => Vec::new(),
+    };
+    if !lines.is_empty() {
+        fill(context, DIM_COLOR, [px, py, width - 2.0 * px, height - 2.0 * py]);
+        let line_height = px * 0.9;
+        let top = height / 2.0 - line_height * (lines.len() as f64 - 1.0) / 2.0;
+        for (i, line) in lines.iter().enumerate() {
+            text(context, theme.text, line, [width / 2.0, top + line_height * i as f64]);
+        }
+    }
+}
+
+// A snake's cells, head first, with the head in the skin's head color and the body in its darker stripe
+fn draw_snake(context: &CanvasRenderingContext2d, cells: impl Iterator<Item = [f64; 4]>, skin: Skin) {
+    for (i, rect) in cells.enumerate() {
+        fill(context, if i == 0 { skin.head } else { skin.stripes[0] }, inset(rect, 0.05));
+    }
+}
+
+// `rect` shrunk by `amount` of its size on every side
+fn inset([x, y, w, h]: [f64; 4], amount: f64) -> [f64; 4] {
+    [x + w * amount, y + h * amount, w * (1.0 - 2.0 * amount), h * (1.0 - 2.0 * amount)]
+}
+
+fn fill(context: &CanvasRenderingContext2d, color: [f32; 4], [x, y, w, h]: [f64; 4]) {
+    context.set_fill_style_str(&css(color));
+    context.fill_rect(x, y, w, h);
+}
+
+fn text(context: &CanvasRenderingContext2d, color: [f32; 4], line: &str, [x, y]: [f64; 2]) {
+    context.set_fill_style_str(&css(color));
+    context.fill_text(line, x, y).ok();
+}
+
+fn css([r, g, b, a]: [f32; 4]) -> String {
+    format!("rgba({}, {}, {}, {})", (r * 255.0).round(), (g * 255.0).round(), (b * 255.0).round(), a)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn browser_key_names_map_to_game_keys() {
+        assert_eq!(browser_key("ArrowLeft"), Some(Key::Left));
+        assert_eq!(browser_key(" "), Some(Key::Space));
+        assert_eq!(browser_key("Escape"), Some(Key::Escape));
+        // Letters come in either case, depending on Shift and Caps Lock
+        assert_eq!(browser_key("w"), Some(Key::W));
+        assert_eq!(browser_key("W"), Some(Key::W));
+        assert_eq!(browser_key("Meta"), None);
+    }
+
+    #[test]
+    fn colors_and_cells_convert_for_the_canvas() {
+        assert_eq!(css([1.0, 0.5, 0.0, 0.35]), "rgba(255, 128, 0, 0.35)");
+        assert_eq!(inset([10.0, 20.0, 40.0, 40.0], 0.1), [14.0, 24.0, 32.0, 32.0]);
+    }
+}
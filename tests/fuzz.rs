@@ -0,0 +1,161 @@
+// Random-walk fuzzing of the game rules: many seeded games steered by random (but never reversing) turns,
+// with the game's invariants checked after every move. The seeds are fixed, so a failure names a game that
+// plays out the same way every time.
+
+use copperhead::game::{Controller, FoodKind, Game, GameState, GameView, Players, PowerUp, Scoring, Settings, SettingsBuilder, WallMode};
+use copperhead::level::{LEVELS_DIR, load_campaign};
+use copperhead::snake::{Direction, START_LENGTH};
+use rand::SeedableRng;
+use rand::seq::SliceRandom;
+use std::collections::HashSet;
+
+const GAMES: u64 = 200; // Games per configuration, on seeds 0, 1, 2, ...
+const MAX_MOVES: u32 = 100_000; // A game that gets this long is called off
+
+// Play one game with random turns, checking the game's invariants after every move. Returns the number
+// of moves made, or a description of the first broken invariant.
+fn random_walk(settings: &Settings) -> Result<u32, String> {
+    let mut game = Game::new(settings);
+    game.set_controller(Box::new(RandomTurns(rand::rngs::StdRng::seed_from_u64(game.run_seed))));
+    // Length is only predictable from the food count when every food grows the snake by the same amount
+    let plain_growth = settings.food_table.iter().all(|&(kind, weight)| kind == FoodKind::Normal || weight == 0.0)
+        && settings.placed_food.iter().all(|&(kind, _)| kind == FoodKind::Normal)
+        && settings.grow_every == 1
+        && settings.max_length.is_none()
+        && !settings.power_ups
+        && !matches!(settings.wall_mode, WallMode::WrapPenalty { shrink: 1.., .. });
+    game.state = GameState::Running;
+    // With random walls the board may hold fewer foods than asked for
+    let expected_foods = settings.food_count + settings.placed_food.len();
+    if settings.wall_density == 0.0 && game.foods.len() != expected_foods {
+        return Err(format!("seed {}: {} foods at the start instead of {}", game.run_seed, game.foods.len(), expected_foods));
+    }
+    loop {
+        let fail = |what: &str| Err(format!("seed {}, move {}: {}", game.run_seed, game.moves, what));
+        let length = game.snake.body().len() + game.snake.pending_growth as usize;
+        if plain_growth && length != START_LENGTH + (game.foods_eaten * settings.growth_per_food) as usize {
+            return fail(&format!("length {} after {} foods", length, game.foods_eaten));
+        }
+        if game.state != GameState::Running || game.moves >= MAX_MOVES {
+            break;
+        }
+        if !game.layout().contains(game.snake.head()) {
+            return fail("head left the board without dying");
+        }
+        let cells: HashSet<(i32, i32)> = game.snake.body().iter().copied().collect();
+        if cells.len() != game.snake.body().len() && game.effect_moves(PowerUp::Ghost) == 0 {
+            return fail("snake overlaps itself without dying");
+        }
+        if game.foods.iter().any(|food| cells.contains(&food.pos) || game.obstacles.contains(&food.pos)) {
+            return fail("food lies on the snake or a rock");
+        }
+        let food_cells: HashSet<(i32, i32)> = game.foods.iter().map(|food| food.pos).collect();
+        if food_cells.len() != game.foods.len() {
+            return fail("two foods share a cell");
+        }
+        if game.foods.iter().any(|food| game.portal_exit(food.pos).is_some()) {
+            return fail("food lies on a portal");
+        }
+        if game.obstacles.iter().any(|rock| cells.contains(rock)) {
+            return fail("snake lies on a rock without dying");
+        }
+
+        game.tick(None);
+    }
+    Ok(game.moves)
+}
+
+// Steers the fuzzed snake: random turns from a seeded RNG, but never straight back
+struct RandomTurns(rand::rngs::StdRng);
+
+impl Controller for RandomTurns {
+    fn next_move(&mut self, view: &GameView) -> Option<Direction> {
+        let choices: Vec<Direction> = [Direction::Up, Direction::Down, Direction::Left, Direction::Right]
+            .into_iter()
+            .filter(|dir| *dir != view.snake().last_dir.opposite())
+            .collect();
+        choices.choose(&mut self.0).cloned()
+    }
+}
+
+// Random-walk GAMES seeded games with the settings `builder` makes, failing on the first broken invariant
+fn fuzz(builder: SettingsBuilder) {
+    let settings = builder.build().expect("valid settings");
+    for seed in 0..GAMES {
+        if let Err(err) = random_walk(&Settings { seed: Some(seed), ..settings.clone() }) {
+            panic!("invariant broken at {}", err);
+        }
+    }
+}
+
+#[test]
+fn default_rules() {
+    fuzz(Settings::builder());
+}
+
+#[test]
+fn random_walls() {
+    fuzz(Settings::builder().wall_density(0.15));
+}
+
+#[test]
+fn a_wall_ring() {
+    fuzz(Settings::builder().wall_ring(true));
+}
+
+#[test]
+fn a_wall_ring_with_wrapping_edges_and_random_walls() {
+    fuzz(Settings::builder().wall_ring(true).wall_mode(WallMode::Wrap).wall_density(0.1));
+}
+
+#[test]
+fn wrapping_edges() {
+    fuzz(Settings::builder().wall_mode(WallMode::Wrap).food_count(3));
+}
+
+#[test]
+fn wrapping_edges_with_a_penalty() {
+    fuzz(Settings::builder().wall_mode(WallMode::WrapPenalty { point_cost: 1, shrink: 1 }));
+}
+
+#[test]
+fn mixed_food() {
+    let table = vec![(FoodKind::Normal, 6.0), (FoodKind::Bonus, 2.0), (FoodKind::Large, 1.0), (FoodKind::Poison, 1.0)];
+    fuzz(Settings::builder().food_table(table));
+}
+
+#[test]
+fn combos_with_poison() {
+    let table = vec![(FoodKind::Normal, 3.0), (FoodKind::Poison, 1.0)];
+    fuzz(Settings::builder().food_table(table).scoring(Scoring { combo_window: 8, ..Scoring::default() }).food_count(2));
+}
+#[test]
+fn power_ups() {
+    fuzz(Settings::builder().power_ups(true).wall_mode(WallMode::Wrap));
+}
+
+#[test]
+fn against_the_computer() {
+    fuzz(Settings::builder().players(Players::Computer));
+}
+
+#[test]
+fn a_shrinking_arena() {
+    fuzz(Settings::builder().arena_shrink(Some(2.0)).wall_mode(WallMode::Wrap));
+}
+
+#[test]
+fn the_campaign() {
+    let levels = load_campaign(std::path::Path::new(LEVELS_DIR)).expect("the campaign loads");
+    fuzz(Settings::builder().grid(levels[0].grid.0, levels[0].grid.1).campaign(levels));
+}
+
+#[test]
+fn portals() {
+    fuzz(Settings::builder().portal_pairs(3).wall_mode(WallMode::Wrap));
+}
+
+#[test]
+fn wandering_food() {
+    fuzz(Settings::builder().food_wander(Some(2)).wall_density(0.1).portal_pairs(2).food_count(3));
+}
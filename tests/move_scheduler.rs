@@ -0,0 +1,66 @@
+// How `MoveScheduler` turns the frame loop's uneven updates into evenly spaced moves: the moves due after
+// each `dt`, for the kinds of update timing the window and the terminal see. Times are binary fractions of
+// a second, so the sums come out exact and the counts don't hinge on rounding.
+
+use copperhead::move_scheduler::{MoveScheduler, MAX_CATCH_UP_MOVES};
+
+const INTERVAL: f64 = 0.125; // 8 moves a second
+const UPDATE: f64 = 1.0 / 64.0; // 64 updates a second, 8 per move
+
+// The moves returned for each of `dts` in turn, from a fresh scheduler
+fn moves(dts: &[f64]) -> Vec<u32> {
+    let mut scheduler = MoveScheduler::new(INTERVAL);
+    dts.iter().map(|&dt| scheduler.tick(dt)).collect()
+}
+
+#[test]
+fn tiny_steps_add_up_to_one_move() {
+    let due = moves(&[UPDATE; 24]);
+    let expected: Vec<u32> = (1..=24).map(|i| u32::from(i % 8 == 0)).collect();
+    assert_eq!(due, expected);
+}
+
+#[test]
+fn no_move_before_the_interval() {
+    assert_eq!(moves(&[0.0, UPDATE, UPDATE, 0.0625]), [0, 0, 0, 0]);
+}
+
+#[test]
+fn exact_intervals_make_one_move_each() {
+    assert_eq!(moves(&[INTERVAL; 5]), [1; 5]);
+}
+
+#[test]
+fn leftover_time_carries_over() {
+    // One and a half intervals is one move, and the next half interval completes the second
+    assert_eq!(moves(&[0.1875, 0.0625, 0.1875, 0.0625]), [1, 1, 1, 1]);
+}
+
+#[test]
+fn spikes_catch_up_on_missed_moves() {
+    assert_eq!(moves(&[UPDATE, 0.25, UPDATE, 0.09375]), [0, 2, 0, 1]);
+    assert_eq!(moves(&[0.375]), [3]);
+}
+
+#[test]
+fn catch_up_is_clamped() {
+    assert_eq!(moves(&[0.5]), [MAX_CATCH_UP_MOVES]);
+    assert_eq!(moves(&[5.0]), [MAX_CATCH_UP_MOVES]);
+}
+
+#[test]
+fn sleep_drops_the_backlog() {
+    // Waking up after a minute makes a few moves, then play goes on at the normal pace, with only the time
+    // towards the next move kept
+    assert_eq!(moves(&[0.0625, 60.03125, UPDATE, 0.0625, INTERVAL]), [0, MAX_CATCH_UP_MOVES, 0, 1, 1]);
+}
+
+#[test]
+fn progress_counts_towards_the_next_move() {
+    let mut scheduler = MoveScheduler::new(INTERVAL);
+    assert_eq!(scheduler.progress(), 0.0);
+    scheduler.tick(INTERVAL / 4.0);
+    assert_eq!(scheduler.progress(), 0.25);
+    scheduler.tick(INTERVAL);
+    assert_eq!(scheduler.progress(), 0.25, "a move starts the count over");
+}
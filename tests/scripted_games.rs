@@ -0,0 +1,99 @@
+// Whole games played the way the window plays them: key presses go through `press_key` and the snake
+// moves once per move interval. The runs are seeded, so every food lands in the same place each time and
+// every game ends the same way, with the same score and length. The autopilot plays one more game with
+// every random feature on, to check that the seed alone decides how it goes.
+
+use copperhead::game::{DeathCause, FoodKind, Game, GameState, Players, Settings};
+use piston_window::Key;
+
+const MAX_MOVES: u32 = 1_000;
+
+// A seeded run from the default spawn (head at (10, 10) heading right, three long). Seed 12 puts the
+// first food at (17, 11), then at (15, 2), (17, 12) and (19, 2).
+fn game() -> Game {
+    let settings = Settings::builder().seed(Some(12)).build().expect("valid settings");
+    Game::new(&settings)
+}
+
+// Start the run with Space and wait out the countdown, then move until the game is over, pressing each of
+// `keys` once the snake has made the given number of moves. Returns how many moves were made.
+fn play(game: &mut Game, keys: &[(u32, Key)]) -> u32 {
+    game.press_key(Key::Space);
+    assert!(game.state == GameState::Running);
+    game.tick_clock(game.settings.countdown as f64);
+
+    let mut next_key = 0;
+    for moves in 0..MAX_MOVES {
+        while let Some(&(at, key)) = keys.get(next_key)
+            && at == moves
+        {
+            game.press_key(key);
+            next_key += 1;
+        }
+        game.tick_clock(game.settings.move_interval);
+        game.update();
+        if game.state == GameState::GameOver {
+            assert_eq!(next_key, keys.len(), "the game ended before every key was pressed");
+            return moves + 1;
+        }
+    }
+    panic!("the game was still going after {} moves", MAX_MOVES);
+}
+
+#[test]
+fn eats_its_way_around_into_the_wall() {
+    let mut game = game();
+    // Right and down to the first food, up to the second, then down past the third into the bottom wall
+    let moves = play(&mut game, &[(7, Key::Down), (8, Key::Left), (10, Key::Up), (19, Key::Right), (21, Key::Down)]);
+
+    assert_eq!(game.score, 3);
+    assert_eq!(game.snake.body().len(), 6);
+    assert_eq!(game.snake.head(), (17, 20));
+    assert_eq!(moves, 39);
+    assert!(game.death_cause == Some(DeathCause::Wall));
+    assert_eq!(game.foods[0].pos, (19, 2));
+}
+
+#[test]
+fn grows_and_turns_back_into_itself() {
+    let mut game = game();
+    // After the second food, right, down and left in turn make a U-turn into the body
+    let moves = play(&mut game, &[(7, Key::Down), (8, Key::Left), (10, Key::Up), (19, Key::Right), (20, Key::Down), (21, Key::Left)]);
+
+    assert_eq!(game.score, 2);
+    assert_eq!(game.snake.body().len(), 5);
+    assert_eq!(game.snake.head(), (15, 3));
+    assert_eq!(moves, 22);
+    assert!(game.death_cause == Some(DeathCause::SelfBite));
+}
+// Everything a seed decides, move by move: where the snakes are, what lies on the board and the scores
+fn autopilot_trace(settings: &Settings) -> Vec<String> {
+    let mut game = Game::new(settings);
+    let mut trace = Vec::new();
+    while game.state != GameState::GameOver && game.moves < MAX_MOVES {
+        let dir = game.autopilot();
+        game.tick(Some(dir));
+        let foods: Vec<(i32, i32)> = game.foods.iter().map(|food| food.pos).collect();
+        let rival = game.rival.as_ref().map(|rival| (rival.snake.head(), rival.score));
+        trace.push(format!("{:?} {} {:?} {:?} {:?} {:?}", game.snake.head(), game.score, rival, foods, game.power_up, game.phase_pickup));
+    }
+    trace
+}
+
+#[test]
+fn a_seed_plays_out_the_same_with_everything_random_switched_on() {
+    let table = vec![(FoodKind::Normal, 6.0), (FoodKind::Bonus, 2.0), (FoodKind::Large, 1.0), (FoodKind::Poison, 1.0)];
+    let settings = Settings::builder()
+        .seed(Some(3))
+        .wall_density(0.1)
+        .food_table(table)
+        .power_ups(true)
+        .players(Players::Computer)
+        .build()
+        .expect("valid settings");
+    let first = autopilot_trace(&settings);
+    assert!(first.len() > 20, "the game lasted {} moves", first.len());
+    assert_eq!(first, autopilot_trace(&settings));
+    let other = autopilot_trace(&Settings { seed: Some(4), ..settings });
+    assert_ne!(first, other);
+}